@@ -0,0 +1,594 @@
+// The simulation rules run on the GPU by `gpu_simulation.rs`.
+//
+// Each invocation updates a single cell, reading the current state and writing the next state,
+// so every cell updates at once, just like the synchronous CPU version.
+// Every cell stores its tile kind, age, fire and fuel: see the `cell` module in `gpu_simulation.rs`.
+
+// The number of tile kinds, and the most transitions that each can make, are set by `gpu_simulation.rs`
+const KIND_COUNT: u32 = #{KIND_COUNT}u;
+const MAX_TRANSITIONS: u32 = #{MAX_TRANSITIONS}u;
+// Burned ground can also return to the kind it was before the fire
+const MAX_OPTIONS: u32 = MAX_TRANSITIONS + 1u;
+
+// These values must match the `cell` module in `gpu_simulation.rs`
+const INERT: u32 = 4294967295u;
+const REMAINING_TICKS_MASK: u32 = 0xFFFFu;
+const INTENSITY_SHIFT: u32 = 16u;
+const PEAK_INTENSITY_SHIFT: u32 = 18u;
+const FUELED_BY_SHIFT: u32 = 20u;
+const NOT_FUELED: u32 = 0xFFu;
+const HAS_DURATION: u32 = 0x10000000u;
+const PRESCRIBED: u32 = 0x20000000u;
+const NO_FIRE: u32 = 0x0FF00000u;
+
+// These values must match the `flags` module in `gpu_simulation.rs`
+const DISPERSAL_LIMITED: u32 = 1u;
+const CANOPY: u32 = 2u;
+
+// These values must match `topology_code` and `neighborhood_code` in `gpu_simulation.rs`
+const ABSORBING: u32 = 0u;
+const REFLECTING: u32 = 1u;
+const TOROIDAL: u32 = 2u;
+
+const VON_NEUMANN: u32 = 0u;
+const MOORE: u32 = 1u;
+const RADIUS: u32 = 2u;
+const HEXAGONAL: u32 = 3u;
+
+// These values must match the `burn_mode` module in `gpu_simulation.rs`
+const BURN_BY_FUEL: u32 = 0u;
+const BURN_UNIFORM: u32 = 1u;
+const BURN_GEOMETRIC: u32 = 2u;
+
+const SMOLDERING: u32 = 0u;
+const BURNING: u32 = 1u;
+const CROWN_FIRE: u32 = 2u;
+
+struct Transition {
+    target_kind: u32,
+    weight: f32,
+}
+
+struct KindParameters {
+    color: vec4<f32>,
+    susceptibility: f32,
+    fuel_accumulation: f32,
+    max_fuel: f32,
+    flags: u32,
+    burn_mode: u32,
+    burn_min_ticks: u32,
+    burn_max_ticks: u32,
+    burnout_probability: f32,
+    transition_count: u32,
+    transitions: array<Transition, MAX_TRANSITIONS>,
+}
+
+struct PostFirePathway {
+    present: u32,
+    survival: f32,
+    transition_count: u32,
+    transitions: array<Transition, MAX_TRANSITIONS>,
+}
+
+struct Parameters {
+    seed: u32,
+    topology: u32,
+    neighborhood: u32,
+    radius: i32,
+    empty_kind: u32,
+    tree_kind: u32,
+    fire_kind: u32,
+    burned_kind: u32,
+    shade_tolerant_kind: u32,
+    shade_intolerant_kind: u32,
+    growth_probability: f32,
+    lightning_probability: f32,
+    moisture_dampening: f32,
+    spread_multiplier: f32,
+    uphill_multiplier: f32,
+    prescribed_multiplier: f32,
+    intensity_multipliers: array<f32, 3>,
+    ignition_half_saturation: f32,
+    consumption_rate: f32,
+    burning_threshold: f32,
+    crown_fire_threshold: f32,
+    isolation_multiplier: f32,
+    shade_tolerant_threshold: f32,
+    exposed_tolerant_multiplier: f32,
+    intolerant_shade_penalty: f32,
+    growth_scaling: f32,
+    lightning_strike_probability: f32,
+    lightning_ignition_multiplier: f32,
+    climate_fire_multiplier: f32,
+    climate_growth_multiplier: f32,
+    north_temperature: f32,
+    south_temperature: f32,
+    kinds: array<KindParameters, KIND_COUNT>,
+    post_fire_pathways: array<PostFirePathway, 3>,
+    // Indexed by the current kind, then the target kind
+    minimum_ages: array<array<u32, KIND_COUNT>, KIND_COUNT>,
+}
+
+@group(0) @binding(0) var current: texture_storage_2d<rgba32uint, read>;
+@group(0) @binding(1) var next: texture_storage_2d<rgba32uint, write>;
+@group(0) @binding(2) var terrain: texture_storage_2d<rgba32float, read>;
+@group(0) @binding(3) var display: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(4) var<storage, read_write> counts: array<atomic<u32>>;
+@group(0) @binding(5) var<storage, read> parameters: Parameters;
+
+// A PCG hash, used as a cheap source of random numbers
+fn hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// A random number between 0 and 1 for the given cell on this tick.
+// Each decision uses its own stream, so that they are independent of each other.
+fn random(location: vec2<i32>, stream: u32) -> f32 {
+    let cell = vec2<u32>(location);
+    let value = hash(cell.x ^ hash(cell.y ^ hash(parameters.seed ^ hash(stream))));
+    return f32(value) / 4294967295.0;
+}
+
+// Mirrors a coordinate about the edge itself, so that -1 maps to 0 and `size` maps to `size - 1`
+fn reflect_coordinate(value: i32, size: i32) -> i32 {
+    let period = ((value % (2 * size)) + 2 * size) % (2 * size);
+    if period < size {
+        return period;
+    }
+    return 2 * size - 1 - period;
+}
+
+// Finds the cell that a location refers to under the map topology, matching `MapTopology::resolve`.
+// Returns false if the location lies beyond an absorbing edge.
+fn resolve(location: ptr<function, vec2<i32>>, size: vec2<i32>) -> bool {
+    if all(*location >= vec2<i32>(0)) && all(*location < size) {
+        return true;
+    }
+
+    switch parameters.topology {
+        case REFLECTING: {
+            *location = vec2<i32>(
+                reflect_coordinate((*location).x, size.x),
+                reflect_coordinate((*location).y, size.y),
+            );
+            return true;
+        }
+        case TOROIDAL: {
+            *location = ((*location % size) + size) % size;
+            return true;
+        }
+        default: {
+            return false;
+        }
+    }
+}
+
+// The largest distance along either axis to any neighbor
+fn neighborhood_reach() -> i32 {
+    if parameters.neighborhood == RADIUS {
+        return max(parameters.radius, 0);
+    }
+    return 1;
+}
+
+// Checks whether the given offset is part of the neighborhood, matching `NeighborhoodKind::neighbors`
+fn is_neighbor_offset(offset: vec2<i32>) -> bool {
+    if all(offset == vec2<i32>(0)) {
+        return false;
+    }
+
+    switch parameters.neighborhood {
+        case VON_NEUMANN: {
+            return abs(offset.x) + abs(offset.y) == 1;
+        }
+        case MOORE: {
+            return max(abs(offset.x), abs(offset.y)) == 1;
+        }
+        case RADIUS: {
+            return dot(offset, offset) <= parameters.radius * parameters.radius;
+        }
+        default: {
+            // Hexagonal neighbors share an edge, which rules out one of the diagonals
+            return max(abs(offset.x), abs(offset.y)) == 1 && offset.x * offset.y != 1;
+        }
+    }
+}
+
+// Reads the cell at the given location, if there is a tile there once the map topology is taken into account.
+// The location is resolved in place, so that other textures can be read at the same cell.
+fn load_cell(location: ptr<function, vec2<i32>>, size: vec2<i32>, cell: ptr<function, vec4<u32>>) -> bool {
+    if !resolve(location, size) {
+        return false;
+    }
+
+    *cell = textureLoad(current, *location);
+    return (*cell).x != INERT;
+}
+
+// Counts the cell, and draws it into the display texture
+fn draw_cell(location: vec2<i32>, size: vec2<i32>, kind: u32) {
+    // Map rows count up from the south, but texture rows count down from the top
+    let texel = vec2<i32>(location.x, size.y - 1 - location.y);
+
+    // Gaps in the map are left transparent, so that the background shows through
+    if kind >= KIND_COUNT {
+        textureStore(display, texel, vec4<f32>(0.0));
+        return;
+    }
+
+    atomicAdd(&counts[kind], 1u);
+    textureStore(display, texel, parameters.kinds[kind].color);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn draw(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let size = vec2<i32>(textureDimensions(current));
+    let location = vec2<i32>(invocation_id.xy);
+    if any(location >= size) {
+        return;
+    }
+
+    draw_cell(location, size, textureLoad(current, location).x);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn step_drossel_schwabl(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let size = vec2<i32>(textureDimensions(current));
+    let location = vec2<i32>(invocation_id.xy);
+    if any(location >= size) {
+        return;
+    }
+
+    let cell = textureLoad(current, location);
+    var next_cell = cell;
+
+    if cell.x == parameters.fire_kind {
+        next_cell.x = parameters.empty_kind;
+    } else if cell.x == parameters.tree_kind {
+        var neighbor_burning = false;
+        let reach = neighborhood_reach();
+        for (var dx = -reach; dx <= reach; dx++) {
+            for (var dy = -reach; dy <= reach; dy++) {
+                var neighbor_location = location + vec2<i32>(dx, dy);
+                var neighbor: vec4<u32>;
+                if is_neighbor_offset(vec2<i32>(dx, dy))
+                    && load_cell(&neighbor_location, size, &neighbor)
+                    && neighbor.x == parameters.fire_kind {
+                    neighbor_burning = true;
+                }
+            }
+        }
+
+        if neighbor_burning || random(location, 0u) < parameters.lightning_probability {
+            next_cell.x = parameters.fire_kind;
+        }
+    } else if cell.x == parameters.empty_kind && random(location, 1u) < parameters.growth_probability {
+        next_cell.x = parameters.tree_kind;
+    }
+
+    textureStore(next, location, next_cell);
+    draw_cell(location, size, next_cell.x);
+}
+
+fn fire_bits(remaining_ticks: u32, intensity: u32, peak_intensity: u32, fueled_by: u32) -> u32 {
+    return min(remaining_ticks, REMAINING_TICKS_MASK)
+        | (intensity << INTENSITY_SHIFT)
+        | (peak_intensity << PEAK_INTENSITY_SHIFT)
+        | (fueled_by << FUELED_BY_SHIFT);
+}
+
+fn intensity_of(fire: u32) -> u32 {
+    return (fire >> INTENSITY_SHIFT) & 3u;
+}
+
+fn peak_intensity_of(fire: u32) -> u32 {
+    return (fire >> PEAK_INTENSITY_SHIFT) & 3u;
+}
+
+fn fueled_by_of(fire: u32) -> u32 {
+    return (fire >> FUELED_BY_SHIFT) & 0xFFu;
+}
+
+// Matches `FireSusceptibility::get` and `FireFuel::ignition_multiplier`
+fn ignition_probability(kind: u32, moisture: f32, fuel: f32) -> f32 {
+    let dryness = max(1.0 - parameters.moisture_dampening * moisture, 0.0);
+    let fuel_load = max(fuel, 0.0);
+    let fuel_multiplier = fuel_load / (fuel_load + max(parameters.ignition_half_saturation, 1e-7));
+    return parameters.kinds[kind].susceptibility * dryness * fuel_multiplier;
+}
+
+// Matches `TemperatureGradient::temperature_at`
+fn temperature(location: vec2<i32>, size: vec2<i32>) -> f32 {
+    var latitude = 0.5;
+    if size.y > 1 {
+        latitude = clamp(f32(location.y) / f32(size.y - 1), 0.0, 1.0);
+    }
+    return max(mix(parameters.south_temperature, parameters.north_temperature, latitude), 0.0);
+}
+
+// Matches `FireFuel::target_intensity`
+fn target_intensity(fuel: f32) -> u32 {
+    if fuel > parameters.crown_fire_threshold {
+        return CROWN_FIRE;
+    } else if fuel > parameters.burning_threshold {
+        return BURNING;
+    }
+    return SMOLDERING;
+}
+
+// Sets up a new fire on a tile of the given kind, matching `FireFuel::ignite` and `BurnDurations::draw`
+fn ignite(location: vec2<i32>, kind: u32, fuel: f32) -> vec4<u32> {
+    let kind_parameters = parameters.kinds[kind];
+    var fire = fire_bits(0u, SMOLDERING, SMOLDERING, kind);
+
+    switch kind_parameters.burn_mode {
+        case BURN_UNIFORM: {
+            let span = kind_parameters.burn_max_ticks - kind_parameters.burn_min_ticks + 1u;
+            let ticks = kind_parameters.burn_min_ticks + min(u32(random(location, 4u) * f32(span)), span - 1u);
+            fire = fire_bits(ticks, SMOLDERING, SMOLDERING, kind) | HAS_DURATION;
+        }
+        case BURN_GEOMETRIC: {
+            let burnout_probability = clamp(kind_parameters.burnout_probability, 1e-7, 1.0);
+            var ticks = 1u;
+            if burnout_probability < 1.0 {
+                let roll = max(random(location, 4u), 1e-7);
+                ticks = u32(min(ceil(log(roll) / log(1.0 - burnout_probability)), f32(REMAINING_TICKS_MASK)));
+            }
+            fire = fire_bits(ticks, SMOLDERING, SMOLDERING, kind) | HAS_DURATION;
+        }
+        default: {}
+    }
+
+    // The tile's fuel is handed over to the fire
+    return vec4<u32>(parameters.fire_kind, 0u, fire, bitcast<u32>(fuel));
+}
+
+// Burns down a fire, matching `update_fire_intensity`
+fn update_fire(cell: vec4<u32>) -> vec4<u32> {
+    var fire = cell.z;
+    var fuel = bitcast<f32>(cell.w);
+
+    // Fires get one last tick to spread after consuming the last of their fuel or time
+    var burned_out = fuel <= 0.0;
+    if (fire & HAS_DURATION) != 0u {
+        let remaining_ticks = fire & REMAINING_TICKS_MASK;
+        burned_out = remaining_ticks == 0u;
+        fire = (fire & ~REMAINING_TICKS_MASK) | (max(remaining_ticks, 1u) - 1u);
+    }
+
+    if burned_out {
+        // The burned ground remembers the fire, until it changes kind again
+        let context = fire_bits(0u, SMOLDERING, peak_intensity_of(fire), fueled_by_of(fire));
+        return vec4<u32>(parameters.burned_kind, cell.y, context, bitcast<u32>(0.0));
+    }
+
+    fuel = max(fuel - parameters.consumption_rate, 0.0);
+    let intensity = intensity_of(fire);
+    let target_level = target_intensity(fuel);
+    var new_intensity = intensity;
+    if target_level > intensity {
+        new_intensity = intensity + 1u;
+    } else if target_level < intensity {
+        new_intensity = intensity - 1u;
+    }
+    let peak_intensity = max(peak_intensity_of(fire), new_intensity);
+    let remaining_ticks = fire & REMAINING_TICKS_MASK;
+    fire = fire_bits(remaining_ticks, new_intensity, peak_intensity, fueled_by_of(fire)) | (fire & (HAS_DURATION | PRESCRIBED));
+
+    return vec4<u32>(cell.x, cell.y, fire, bitcast<u32>(fuel));
+}
+
+// Matches the growth multiplier passed to `TransitionProbabilities::choose_transition` by the `ForestRule`
+fn transition_weight(
+    kind: u32,
+    age: u32,
+    transition: Transition,
+    growth_multiplier: f32,
+    neighbor_kinds: ptr<function, array<u32, 4>>,
+    canopy_density: f32,
+) -> f32 {
+    let target_kind = transition.target_kind;
+    if target_kind == kind {
+        return transition.weight;
+    }
+    if target_kind >= KIND_COUNT || age < parameters.minimum_ages[kind][target_kind] {
+        return 0.0;
+    }
+    // Burning out isn't growth: the climate only changes how readily fires start and spread
+    if kind == parameters.fire_kind {
+        return transition.weight;
+    }
+
+    var weight = transition.weight * growth_multiplier;
+
+    // Seed dispersal: see `SeedDispersal::multiplier`
+    if (parameters.kinds[target_kind].flags & DISPERSAL_LIMITED) != 0u {
+        let word = (*neighbor_kinds)[target_kind / 32u];
+        if (word & (1u << (target_kind % 32u))) == 0u {
+            weight *= parameters.isolation_multiplier;
+        }
+    }
+
+    // Shade succession: see `ShadeSuccession::multiplier`
+    if target_kind == parameters.shade_tolerant_kind && canopy_density < parameters.shade_tolerant_threshold {
+        weight *= parameters.exposed_tolerant_multiplier;
+    } else if target_kind == parameters.shade_intolerant_kind {
+        weight *= 1.0 - clamp(parameters.intolerant_shade_penalty, 0.0, 1.0) * canopy_density;
+    }
+
+    return weight;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn step_forest(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let size = vec2<i32>(textureDimensions(current));
+    let location = vec2<i32>(invocation_id.xy);
+    if any(location >= size) {
+        return;
+    }
+
+    let cell = textureLoad(current, location);
+    let kind = cell.x;
+    if kind >= KIND_COUNT {
+        textureStore(next, location, cell);
+        draw_cell(location, size, kind);
+        return;
+    }
+
+    let age = cell.y;
+    let fire = cell.z;
+    var fuel = bitcast<f32>(cell.w);
+    let terrain_values = textureLoad(terrain, location);
+    let moisture = terrain_values.x;
+    let elevation = terrain_values.y;
+    let fertility = terrain_values.z;
+    let local_temperature = temperature(location, size);
+
+    var next_cell: vec4<u32>;
+    if kind == parameters.fire_kind {
+        next_cell = update_fire(cell);
+    } else {
+        // Fuel builds up on every tile that isn't burning: see `accumulate_fuel`
+        let kind_parameters = parameters.kinds[kind];
+        fuel = max(min(fuel + kind_parameters.fuel_accumulation, kind_parameters.max_fuel), 0.0);
+
+        // Look over the neighborhood once, for both fire spread and seed dispersal
+        let base_probability = ignition_probability(kind, moisture, fuel)
+            * parameters.spread_multiplier
+            * parameters.climate_fire_multiplier
+            * local_temperature;
+        var neighbor_kinds = array<u32, 4>(0u, 0u, 0u, 0u);
+        var caught_fire = false;
+        var roll_index = 0u;
+        let reach = neighborhood_reach();
+        for (var dx = -reach; dx <= reach; dx++) {
+            for (var dy = -reach; dy <= reach; dy++) {
+                var neighbor_location = location + vec2<i32>(dx, dy);
+                var neighbor: vec4<u32>;
+                if !is_neighbor_offset(vec2<i32>(dx, dy))
+                    || !load_cell(&neighbor_location, size, &neighbor) {
+                    continue;
+                }
+
+                neighbor_kinds[neighbor.x / 32u] |= 1u << (neighbor.x % 32u);
+                if neighbor.x != parameters.fire_kind || caught_fire {
+                    continue;
+                }
+
+                // Fire climbs slopes, so tiles above their burning neighbor are more at risk
+                var slope_multiplier = 1.0;
+                if elevation > textureLoad(terrain, neighbor_location).y {
+                    slope_multiplier = parameters.uphill_multiplier;
+                }
+                var prescribed_multiplier = 1.0;
+                if (neighbor.z & PRESCRIBED) != 0u {
+                    prescribed_multiplier = parameters.prescribed_multiplier;
+                }
+
+                let probability = base_probability
+                    * slope_multiplier
+                    * parameters.intensity_multipliers[intensity_of(neighbor.z)]
+                    * prescribed_multiplier;
+                caught_fire = random(location, 8u + roll_index) < probability;
+                roll_index++;
+            }
+        }
+
+        if caught_fire {
+            next_cell = ignite(location, kind, fuel);
+        } else {
+            next_cell = vec4<u32>(kind, age, fire, bitcast<u32>(fuel));
+
+            // Shade is cast by the Moore neighbors, whatever the neighborhood: see `ShadeSuccession::canopy_density`
+            var shaded_neighbors = 0u;
+            for (var dx = -1; dx <= 1; dx++) {
+                for (var dy = -1; dy <= 1; dy++) {
+                    var neighbor_location = location + vec2<i32>(dx, dy);
+                    var neighbor: vec4<u32>;
+                    if (dx != 0 || dy != 0)
+                        && load_cell(&neighbor_location, size, &neighbor)
+                        && (parameters.kinds[neighbor.x].flags & CANOPY) != 0u {
+                        shaded_neighbors++;
+                    }
+                }
+            }
+            let canopy_density = f32(shaded_neighbors) / 8.0;
+            let growth_multiplier = parameters.climate_growth_multiplier
+                * local_temperature
+                * (1.0 + parameters.growth_scaling * (2.0 * fertility - 1.0));
+
+            // Burned ground recovers according to the intensity of the fire that left it: see `TransitionProbabilities::options`
+            var options: array<Transition, MAX_OPTIONS>;
+            var option_count = kind_parameters.transition_count;
+            for (var i = 0u; i < option_count; i++) {
+                options[i] = kind_parameters.transitions[i];
+            }
+            let fueled_by = fueled_by_of(fire);
+            if kind == parameters.burned_kind && fueled_by != NOT_FUELED {
+                let pathway = parameters.post_fire_pathways[peak_intensity_of(fire)];
+                if pathway.present != 0u {
+                    option_count = pathway.transition_count;
+                    for (var i = 0u; i < option_count; i++) {
+                        options[i] = pathway.transitions[i];
+                    }
+                    // Fires painted straight onto the map don't know what they burned, so nothing can survive them
+                    if fueled_by != parameters.fire_kind {
+                        options[option_count] = Transition(fueled_by, pathway.survival);
+                        option_count++;
+                    }
+                }
+            }
+
+            // Choose between the options in proportion to their weights, as `choose_weighted` does
+            var weights: array<f32, MAX_OPTIONS>;
+            var total_weight = 0.0;
+            for (var i = 0u; i < option_count; i++) {
+                weights[i] = transition_weight(kind, age, options[i], growth_multiplier, &neighbor_kinds, canopy_density);
+                total_weight += weights[i];
+            }
+
+            if total_weight > 0.0 {
+                var roll = random(location, 2u) * total_weight;
+                for (var i = 0u; i < option_count; i++) {
+                    if weights[i] <= 0.0 {
+                        continue;
+                    }
+
+                    next_cell.x = options[i].target_kind;
+                    if roll < weights[i] {
+                        break;
+                    }
+                    roll -= weights[i];
+                }
+            }
+
+            // Lightning strikes a few random tiles every tick: see `lightning_strikes`
+            if random(location, 3u) < parameters.lightning_strike_probability
+                && random(location, 5u) < ignition_probability(kind, moisture, fuel)
+                    * parameters.lightning_ignition_multiplier
+                    * parameters.climate_fire_multiplier
+                    * local_temperature {
+                next_cell = ignite(location, kind, fuel);
+            }
+        }
+    }
+
+    // Ages reset whenever a tile changes kind: see `age_tiles`
+    if next_cell.x == kind {
+        next_cell.y = age + 1u;
+    } else {
+        next_cell.y = 0u;
+
+        // Only fires, and the burned ground that they leave behind, remember the fire
+        let keeps_fire = next_cell.x == parameters.fire_kind
+            || (next_cell.x == parameters.burned_kind && kind == parameters.fire_kind);
+        if !keeps_fire {
+            next_cell.z = NO_FIRE;
+        }
+    }
+
+    textureStore(next, location, next_cell);
+    draw_cell(location, size, next_cell.x);
+}
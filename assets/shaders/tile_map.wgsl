@@ -0,0 +1,39 @@
+// Draws every tile on the map with a single quad, for `tile_renderer.rs`.
+//
+// Each fragment works out which tile it lies in, then looks up that tile's color and atlas texture.
+
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+// The width and height of the map in tiles, followed by the number of columns and rows in the tile atlas
+@group(2) @binding(0) var<uniform> grid: vec4<u32>;
+// The packed sRGB color and atlas texture of each tile, in rows from the bottom of the map
+@group(2) @binding(1) var<storage, read> tiles: array<vec2<u32>>;
+@group(2) @binding(2) var atlas_texture: texture_2d<f32>;
+@group(2) @binding(3) var atlas_sampler: sampler;
+
+fn srgb_to_linear(channel: f32) -> f32 {
+    if channel <= 0.04045 {
+        return channel / 12.92;
+    }
+    return pow((channel + 0.055) / 1.055, 2.4);
+}
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let size = grid.xy;
+    let atlas_grid = vec2<f32>(grid.zw);
+
+    // The quad's uvs start from the top of the map, while tile positions count up from the bottom
+    let position = vec2<f32>(mesh.uv.x, 1.0 - mesh.uv.y) * vec2<f32>(size);
+    let tile_position = min(vec2<u32>(position), size - 1u);
+    let tile = tiles[tile_position.y * size.x + tile_position.x];
+
+    let srgb = unpack4x8unorm(tile.x);
+    let color = vec4<f32>(srgb_to_linear(srgb.r), srgb_to_linear(srgb.g), srgb_to_linear(srgb.b), srgb.a);
+
+    let texture = vec2<u32>(tile.y % grid.z, tile.y / grid.z);
+    let within_tile = fract(position);
+    let uv = (vec2<f32>(texture) + vec2<f32>(within_tile.x, 1.0 - within_tile.y)) / atlas_grid;
+
+    return textureSampleLevel(atlas_texture, atlas_sampler, uv, 0.0) * color;
+}
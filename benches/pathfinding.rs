@@ -0,0 +1,100 @@
+//! Measures how long [`TileIndex::pathfind`] takes to route between random pairs of tiles on a generated map.
+//!
+//! Run with `cargo bench --bench pathfinding`.
+//! The map is generated headlessly with the same plugins and settings as the app, at a larger size.
+
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use cellular_automata_demo::SimState;
+use cellular_automata_demo::chunks::ChunkPlugin;
+use cellular_automata_demo::control_flow::ControlFlowPlugin;
+use cellular_automata_demo::heightmap::HeightmapPlugin;
+use cellular_automata_demo::landmasses::LandmassPlugin;
+use cellular_automata_demo::map_generation::{MapGenerationPlugin, MapSeed, MapSize};
+use cellular_automata_demo::map_mask::MapMaskPlugin;
+use cellular_automata_demo::rulesets::Ruleset;
+use cellular_automata_demo::simulation::TileKind;
+use cellular_automata_demo::spatial_index::{
+    GridShape, MovementCosts, Position, TileIndex, TilePlugin,
+};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+/// The width and height of the generated map, in tiles.
+const MAP_SIZE: i32 = 256;
+/// The number of routes searched for on each grid shape.
+const ROUTES: usize = 200;
+
+fn main() {
+    let mut world = generate_map();
+
+    for grid_shape in [GridShape::Square, GridShape::Hexagonal] {
+        world.insert_resource(grid_shape);
+        bench_routes(&world, grid_shape);
+    }
+}
+
+/// Generates a map by running the app's map generation until the simulation is ready to start.
+fn generate_map() -> World {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default()))
+        .add_plugins((
+            ChunkPlugin,
+            ControlFlowPlugin,
+            HeightmapPlugin,
+            LandmassPlugin,
+            MapGenerationPlugin,
+            MapMaskPlugin,
+            TilePlugin,
+        ))
+        .insert_resource(MapSize {
+            width: MAP_SIZE,
+            height: MAP_SIZE,
+        })
+        .insert_resource(MapSeed(0))
+        // The ruleset's own plugin pulls in every simulation, but only its choice of terrain is needed here
+        .init_resource::<Ruleset>()
+        // Masks and heightmaps can be loaded from images, although none are used here
+        .init_asset::<Image>()
+        .init_state::<SimState>();
+
+    while *app.world().resource::<State<SimState>>() == SimState::Generate {
+        app.update();
+    }
+
+    std::mem::take(app.world_mut())
+}
+
+fn bench_routes(world: &World, grid_shape: GridShape) {
+    let tile_index = world.resource::<TileIndex>();
+    let movement_costs = world.resource::<MovementCosts>();
+    let cost = |tile| movement_costs.cost(world.get::<TileKind>(tile)?);
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut random_position = || Position {
+        x: rng.random_range(0..MAP_SIZE),
+        y: rng.random_range(0..MAP_SIZE),
+    };
+
+    let mut total = Duration::ZERO;
+    let mut slowest = Duration::ZERO;
+    let mut routes_found = 0;
+    for _ in 0..ROUTES {
+        let (start, goal) = (random_position(), random_position());
+
+        let started = Instant::now();
+        let path = tile_index.pathfind(start, goal, &grid_shape, cost);
+        let elapsed = started.elapsed();
+
+        total += elapsed;
+        slowest = slowest.max(elapsed);
+        routes_found += usize::from(path.is_some());
+    }
+
+    println!(
+        "{grid_shape:?} grid, {MAP_SIZE}x{MAP_SIZE} tiles: {ROUTES} routes ({routes_found} found) in {total:?}, \
+        averaging {:?} and at most {slowest:?}",
+        total / ROUTES as u32
+    );
+}
@@ -0,0 +1,178 @@
+//! Langton's ant: a famous agent-based cellular automaton.
+//!
+//! Each ant looks at the tile it is standing on: on a white tile it turns right, on a black tile it turns left.
+//! It then flips the color of the tile and steps forward.
+//! After around ten thousand steps of apparent chaos, a single ant builds an endlessly repeating "highway".
+//!
+//! Like the [herbivores](crate::herbivores), ants are just entities with a [`Position`],
+//! which use the [`TileIndex`] to find the tile they are standing on.
+
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+use rand::Rng;
+
+use crate::SimState;
+use crate::control_flow::Simulation;
+use crate::map_generation::{GeneratedMapSize, GenerationStep, MapSeed};
+use crate::rulesets::{Ruleset, ruleset_is};
+use crate::simulation::TileKind;
+use crate::spatial_index::{Position, Tile, TileIndex};
+
+pub struct AntPlugin;
+
+impl Plugin for AntPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Ant>()
+            .init_resource::<AntColony>()
+            .register_type::<AntColony>()
+            .add_systems(OnEnter(SimState::Generate), despawn_ants)
+            .add_systems(
+                OnExit(SimState::Generate),
+                spawn_ants.run_if(ruleset_is(Ruleset::LangtonsAnt)),
+            )
+            .add_systems(
+                Simulation,
+                move_ants.run_if(ruleset_is(Ruleset::LangtonsAnt)),
+            );
+    }
+}
+
+/// An ant that wanders the map, flipping the color of each tile it visits.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ant {
+    /// The direction that the ant is facing, as a unit step in one of the four cardinal directions.
+    pub facing: IVec2,
+}
+
+impl Ant {
+    const COLOR: Color = Color::hsl(0., 0.9, 0.5);
+    const SIZE: f32 = Position::PIXELS_PER_TILE * 0.6;
+
+    fn turn_right(&mut self) {
+        self.facing = IVec2::new(self.facing.y, -self.facing.x);
+    }
+
+    fn turn_left(&mut self) {
+        self.facing = IVec2::new(-self.facing.y, self.facing.x);
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct AntColony {
+    /// The number of ants spawned each time the map is generated.
+    ///
+    /// The first ant always starts in the center of the map, while the rest start at random positions.
+    count: u32,
+}
+
+impl Default for AntColony {
+    fn default() -> Self {
+        Self { count: 1 }
+    }
+}
+
+fn despawn_ants(mut commands: Commands, query: Query<Entity, With<Ant>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[hot]
+fn spawn_ants(
+    ant_colony: Res<AntColony>,
+    map_size: Res<GeneratedMapSize>,
+    map_seed: Res<MapSeed>,
+    mut commands: Commands,
+) {
+    if map_size.width <= 0 || map_size.height <= 0 {
+        return;
+    }
+
+    let mut rng = map_seed.rng(GenerationStep::Ants);
+
+    for i in 0..ant_colony.count {
+        let position = if i == 0 {
+            Position {
+                x: map_size.width / 2,
+                y: map_size.height / 2,
+            }
+        } else {
+            Position {
+                x: rng.random_range(0..map_size.width),
+                y: rng.random_range(0..map_size.height),
+            }
+        };
+
+        let ant = Ant { facing: IVec2::Y };
+
+        commands.spawn((
+            Name::new("Ant"),
+            ant,
+            position,
+            ant_transform(&position, &ant),
+            Sprite {
+                color: Ant::COLOR,
+                // Ants are longer than they are wide, so that you can tell which way they're facing
+                custom_size: Some(Vec2::new(Ant::SIZE * 0.5, Ant::SIZE)),
+                ..Default::default()
+            },
+        ));
+    }
+}
+
+/// Ants are drawn above the tiles that they are standing on, rotated to face the direction they're heading.
+fn ant_transform(position: &Position, ant: &Ant) -> Transform {
+    let mut transform = position.to_transform();
+    transform.translation.z = 2.0;
+    transform.rotation = Quat::from_rotation_z(Vec2::Y.angle_to(ant.facing.as_vec2()));
+    transform
+}
+
+/// Turns, flips and moves every ant, according to the color of the tile it is standing on.
+///
+/// Ants that walk off the edge of the map wrap around to the opposite side.
+#[hot]
+fn move_ants(
+    ant_query: Query<(Entity, &Ant, &Position)>,
+    mut tile_query: Query<&mut TileKind, With<Tile>>,
+    map_size: Res<GeneratedMapSize>,
+    tile_index: Res<TileIndex>,
+    mut commands: Commands,
+) {
+    if map_size.width <= 0 || map_size.height <= 0 {
+        return;
+    }
+
+    for (entity, ant, position) in ant_query.iter() {
+        let mut ant = *ant;
+
+        // Masked maps can have holes in them: ants walk straight across these
+        if let Some(tile_entity) = tile_index.get(position)
+            && let Ok(mut tile_kind) = tile_query.get_mut(tile_entity)
+        {
+            match *tile_kind {
+                TileKind::White => {
+                    ant.turn_right();
+                    *tile_kind = TileKind::Black;
+                }
+                TileKind::Black => {
+                    ant.turn_left();
+                    *tile_kind = TileKind::White;
+                }
+                // Ants don't know what to do with anything else, so just keep walking
+                _ => {}
+            }
+        }
+
+        let destination = Position {
+            x: (position.x + ant.facing.x).rem_euclid(map_size.width),
+            y: (position.y + ant.facing.y).rem_euclid(map_size.height),
+        };
+
+        // `Position` is immutable, so we need to reinsert it to move the ant
+        commands
+            .entity(entity)
+            .insert((ant, destination, ant_transform(&destination, &ant)));
+    }
+}
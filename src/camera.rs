@@ -21,13 +21,19 @@ impl Plugin for CameraPlugin {
     }
 }
 
+/// A marker component for the camera that the map is viewed through.
+///
+/// Other cameras, such as the one that draws the minimap, don't respond to the camera controls.
+#[derive(Component)]
+pub struct MainCamera;
+
 fn spawn_camera(mut commands: Commands) {
-    commands.spawn(Camera2d);
+    commands.spawn((Camera2d, MainCamera));
 }
 
 #[hot]
 fn pan_camera(
-    mut camera: Single<(&mut Transform, &Projection), With<Camera2d>>,
+    mut camera: Single<(&mut Transform, &Projection), With<MainCamera>>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
 ) {
@@ -74,7 +80,7 @@ fn pan_camera(
 
 #[hot]
 fn zoom_camera(
-    mut camera_projection: Single<&mut Projection, With<Camera2d>>,
+    mut camera_projection: Single<&mut Projection, With<MainCamera>>,
     mousewheel_input: Res<AccumulatedMouseScroll>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
 ) {
@@ -115,7 +121,7 @@ fn zoom_camera(
 // easier reuse and robustness to strange setups.
 #[hot]
 fn adjust_camera_to_map_extents(
-    mut camera: Single<(&mut Transform, &mut Projection), With<Camera2d>>,
+    mut camera: Single<(&mut Transform, &mut Projection), With<MainCamera>>,
     tile_query: Query<(&Sprite, &GlobalTransform)>,
     sprite_assets: Res<Assets<Image>>,
 ) {
@@ -145,6 +151,11 @@ fn adjust_camera_to_map_extents(
         upper_right.y = upper_right.y.max(position.y + half_size.y);
     }
 
+    // Masked maps can end up with no tiles at all, leaving nothing to frame
+    if lower_left.x > upper_right.x || lower_left.y > upper_right.y {
+        return;
+    }
+
     let center = (lower_left + upper_right) / 2.0;
     let scale = (upper_right - lower_left).length();
 
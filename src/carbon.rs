@@ -0,0 +1,181 @@
+//! Carbon accounting: how much carbon the landscape is storing, and how much fire has released.
+//!
+//! Each tile holds a [`CarbonStock`], which grows towards a capacity set by its kind:
+//! young meadows hold very little, while old-growth forest holds a great deal.
+//! Fires burn off the stored carbon, releasing it into the atmosphere.
+//! The running total is recorded in the [`CarbonBudget`], and plotted over time in the statistics panel.
+
+use std::collections::VecDeque;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+
+use crate::SimState;
+use crate::control_flow::Simulation;
+use crate::map_generation::{SpawnTiles, insert_tile_components};
+use crate::rulesets::{ForestSystems, Ruleset, ruleset_is};
+use crate::simulation::TileKind;
+use crate::spatial_index::Tile;
+
+pub struct CarbonPlugin;
+
+impl Plugin for CarbonPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CarbonStock>()
+            .init_resource::<CarbonCycle>()
+            .register_type::<CarbonCycle>()
+            .init_resource::<CarbonBudget>()
+            .register_type::<CarbonBudget>()
+            .add_systems(
+                Update,
+                insert_tile_components::<CarbonStock>
+                    .after(SpawnTiles)
+                    .run_if(in_state(SimState::Generate))
+                    .run_if(ruleset_is(Ruleset::Forest)),
+            )
+            .add_systems(
+                OnExit(SimState::Generate),
+                seed_carbon_stocks.run_if(ruleset_is(Ruleset::Forest)),
+            )
+            .add_systems(
+                Simulation,
+                (update_carbon_stocks, record_carbon_budget)
+                    .chain()
+                    .in_set(ForestSystems),
+            );
+    }
+}
+
+/// The amount of carbon stored in a tile's vegetation and soil, in tonnes.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Default)]
+pub struct CarbonStock(pub f32);
+
+/// Controls how carbon accumulates in, and is lost from, each tile.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct CarbonCycle {
+    /// The amount of carbon that a mature tile of each kind stores, in tonnes.
+    ///
+    /// Missing entries store no carbon.
+    capacity: HashMap<TileKind, f32>,
+    /// The fraction of the remaining capacity that is gained each tick.
+    ///
+    /// Growth is fast at first and slows as the tile matures, so carbon accumulates with age.
+    growth_rate: f32,
+    /// The fraction of any carbon above capacity that decomposes each tick,
+    /// such as the dead wood left behind when a forest dies.
+    decay_rate: f32,
+    /// The fraction of the stored carbon that is released each tick while a tile is burning.
+    combustion_rate: f32,
+}
+
+impl CarbonCycle {
+    /// Returns the carbon capacity of the given kind of tile.
+    fn capacity(&self, tile_kind: &TileKind) -> f32 {
+        self.capacity.get(tile_kind).copied().unwrap_or_default()
+    }
+}
+
+impl Default for CarbonCycle {
+    fn default() -> Self {
+        let mut capacity = HashMap::new();
+        capacity.insert(TileKind::Meadow, 5.0);
+        capacity.insert(TileKind::Shrubland, 20.0);
+        capacity.insert(TileKind::ShadeIntolerantForest, 100.0);
+        capacity.insert(TileKind::ShadeTolerantForest, 150.0);
+        capacity.insert(TileKind::DeadForest, 60.0);
+        capacity.insert(TileKind::Burned, 2.0);
+        capacity.insert(TileKind::InvasiveGrass, 4.0);
+        capacity.insert(TileKind::Shore, 1.0);
+
+        Self {
+            capacity,
+            growth_rate: 0.05,
+            decay_rate: 0.02,
+            combustion_rate: 0.5,
+        }
+    }
+}
+
+/// The carbon stored across the whole map, and how it has changed since the map was generated.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct CarbonBudget {
+    /// The total carbon currently stored in every tile, in tonnes.
+    pub total: f32,
+    /// The total carbon released by fires since the map was generated, in tonnes.
+    pub released_by_fire: f32,
+    /// The total stored carbon at each of the most recent ticks, oldest first.
+    pub history: VecDeque<f32>,
+    /// The maximum number of ticks kept in the [`history`](Self::history).
+    pub history_length: usize,
+}
+
+impl Default for CarbonBudget {
+    fn default() -> Self {
+        Self {
+            total: 0.0,
+            released_by_fire: 0.0,
+            history: VecDeque::new(),
+            history_length: 60,
+        }
+    }
+}
+
+/// Starts every tile off fully stocked, as if the landscape had been growing undisturbed for a long time.
+#[hot]
+fn seed_carbon_stocks(
+    mut tile_query: Query<(&TileKind, &mut CarbonStock), With<Tile>>,
+    carbon_cycle: Res<CarbonCycle>,
+    mut carbon_budget: ResMut<CarbonBudget>,
+) {
+    let mut total = 0.0;
+    for (tile_kind, mut carbon_stock) in tile_query.iter_mut() {
+        carbon_stock.0 = carbon_cycle.capacity(tile_kind);
+        total += carbon_stock.0;
+    }
+
+    carbon_budget.total = total;
+    carbon_budget.released_by_fire = 0.0;
+    carbon_budget.history.clear();
+}
+
+/// Grows or decays the carbon stock of each tile towards its capacity, burning off carbon from fires.
+#[hot]
+fn update_carbon_stocks(
+    mut tile_query: Query<(&TileKind, &mut CarbonStock), With<Tile>>,
+    carbon_cycle: Res<CarbonCycle>,
+    mut carbon_budget: ResMut<CarbonBudget>,
+) {
+    for (tile_kind, mut carbon_stock) in tile_query.iter_mut() {
+        if *tile_kind == TileKind::Fire {
+            let released = carbon_stock.0 * carbon_cycle.combustion_rate.clamp(0.0, 1.0);
+            carbon_stock.0 -= released;
+            carbon_budget.released_by_fire += released;
+            continue;
+        }
+
+        let capacity = carbon_cycle.capacity(tile_kind);
+        let rate = if carbon_stock.0 < capacity {
+            carbon_cycle.growth_rate
+        } else {
+            carbon_cycle.decay_rate
+        };
+
+        carbon_stock.0 += rate.clamp(0.0, 1.0) * (capacity - carbon_stock.0);
+    }
+}
+
+fn record_carbon_budget(
+    tile_query: Query<&CarbonStock, With<Tile>>,
+    mut carbon_budget: ResMut<CarbonBudget>,
+) {
+    let total = tile_query.iter().map(|carbon_stock| carbon_stock.0).sum();
+    carbon_budget.total = total;
+
+    carbon_budget.history.push_back(total);
+    while carbon_budget.history.len() > carbon_budget.history_length {
+        carbon_budget.history.pop_front();
+    }
+}
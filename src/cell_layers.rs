@@ -0,0 +1,565 @@
+//! Named layers of per-tile data, stored in dense grids.
+//!
+//! Adding a new per-tile field normally means adding a new component, spawning it on every tile,
+//! and writing a bespoke system to visualize it.
+//! Instead, simulation code can register a named [`CellLayer`] in the [`CellLayers`] resource,
+//! then read and write it by [`Position`].
+//! Every layer can be drawn on the map using the [`LayerOverlay`](crate::graphics::LayerOverlay).
+//!
+//! A few of the built-in per-tile components are mirrored into layers, so that they can be inspected the same way.
+//! Mirroring works in both directions: values set on those layers are copied back into the components,
+//! so rules can read and write whichever is more convenient.
+//! There are also a few derived layers, such as `fire_distance`, which are only computed while they are being overlaid.
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::SimState;
+use crate::control_flow::run_simulation;
+use crate::graphics::LayerOverlay;
+use crate::map_generation::{GeneratedMapSize, record_generated_map_size};
+use crate::sandpile::SandGrains;
+use crate::simulation::{
+    Age, Elevation, Fertility, FireSusceptibility, FuelLoad, Moisture, TileKind,
+};
+use crate::spatial_index::{GridIndex, GridShape, Position, Tile, TileIndex};
+
+pub struct CellLayersPlugin;
+
+impl Plugin for CellLayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CellLayers>()
+            .register_type::<CellLayers>()
+            .add_systems(Startup, add_derived_layers)
+            .add_systems(
+                OnEnter(SimState::Generate),
+                resize_cell_layers.after(record_generated_map_size),
+            )
+            .add_systems(
+                Update,
+                (write_back_component_layers, mirror_component_layers)
+                    .chain()
+                    .after(run_simulation),
+            )
+            .add_systems(
+                Update,
+                update_fire_distance_layer
+                    .after(run_simulation)
+                    .run_if(overlaying_layer(FIRE_DISTANCE_LAYER)),
+            )
+            .add_systems(
+                Update,
+                update_water_distance_layer
+                    .after(run_simulation)
+                    .run_if(overlaying_layer(WATER_DISTANCE_LAYER)),
+            )
+            .add_systems(
+                Update,
+                update_susceptibility_layer
+                    .after(run_simulation)
+                    .run_if(overlaying_layer(SUSCEPTIBILITY_LAYER)),
+            );
+    }
+}
+
+/// The values stored in a [`CellLayer`], one for each tile in the same column-by-column order as the [`GridIndex`].
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub enum LayerData {
+    F32(Vec<f32>),
+    U8(Vec<u8>),
+}
+
+/// A single named field, with one value per tile.
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct CellLayer {
+    data: LayerData,
+    /// The value that every tile starts with when the map is generated.
+    default: f32,
+    /// The range of values mapped onto the overlay's color ramp.
+    pub display_range: (f32, f32),
+    /// The indices changed through [`CellLayers::set`] since they were last copied back into their components.
+    #[reflect(ignore)]
+    written: Vec<usize>,
+}
+
+impl CellLayer {
+    /// Resets every value in the layer to its default, resizing it to fit the given number of tiles.
+    fn reset(&mut self, tiles: usize) {
+        self.written.clear();
+        match &mut self.data {
+            LayerData::F32(values) => {
+                values.clear();
+                values.resize(tiles, self.default);
+            }
+            LayerData::U8(values) => {
+                values.clear();
+                values.resize(tiles, self.default as u8);
+            }
+        }
+    }
+
+    /// Returns the value at the given index, converted to an `f32`.
+    fn read(&self, index: usize) -> Option<f32> {
+        match &self.data {
+            LayerData::F32(values) => values.get(index).copied(),
+            LayerData::U8(values) => values.get(index).map(|&value| value as f32),
+        }
+    }
+
+    /// Stores a value at the given index, rounding and saturating it for `u8` layers.
+    ///
+    /// Returns whether the stored value changed.
+    fn write(&mut self, index: usize, value: f32) -> bool {
+        match &mut self.data {
+            LayerData::F32(values) => values.get_mut(index).is_some_and(|slot| {
+                let changed = *slot != value;
+                *slot = value;
+                changed
+            }),
+            LayerData::U8(values) => values.get_mut(index).is_some_and(|slot| {
+                let value = value.round().clamp(0.0, u8::MAX as f32) as u8;
+                let changed = *slot != value;
+                *slot = value;
+                changed
+            }),
+        }
+    }
+}
+
+/// All of the named [`CellLayer`]s, sized to match the map.
+///
+/// Values are looked up by [`Position`]: positions outside of the map return `None`, and writes to them are ignored.
+#[derive(Resource, Reflect, Debug, Default)]
+#[reflect(Resource)]
+pub struct CellLayers {
+    width: i32,
+    height: i32,
+    layers: HashMap<String, CellLayer>,
+}
+
+impl CellLayers {
+    /// Adds a new layer of `f32` values, replacing any existing layer with the same name.
+    pub fn add_f32_layer(
+        &mut self,
+        name: impl Into<String>,
+        default: f32,
+        display_range: (f32, f32),
+    ) {
+        self.add_layer(
+            name.into(),
+            LayerData::F32(Vec::new()),
+            default,
+            display_range,
+        );
+    }
+
+    /// Adds a new layer of `u8` values, replacing any existing layer with the same name.
+    pub fn add_u8_layer(
+        &mut self,
+        name: impl Into<String>,
+        default: u8,
+        display_range: (f32, f32),
+    ) {
+        self.add_layer(
+            name.into(),
+            LayerData::U8(Vec::new()),
+            default as f32,
+            display_range,
+        );
+    }
+
+    fn add_layer(
+        &mut self,
+        name: String,
+        data: LayerData,
+        default: f32,
+        display_range: (f32, f32),
+    ) {
+        let mut layer = CellLayer {
+            data,
+            default,
+            display_range,
+            written: Vec::new(),
+        };
+        layer.reset(self.tile_count());
+        self.layers.insert(name, layer);
+    }
+
+    /// Returns the layer with the given name, if it exists.
+    pub fn layer(&self, name: &str) -> Option<&CellLayer> {
+        self.layers.get(name)
+    }
+
+    /// Returns the names of every layer, in alphabetical order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.layers.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns the value of the given layer at the given position, converted to an `f32`.
+    pub fn get(&self, name: &str, position: &Position) -> Option<f32> {
+        let index = self.index(position)?;
+        self.layers.get(name)?.read(index)
+    }
+
+    /// Sets the value of the given layer at the given position.
+    ///
+    /// Values written to `u8` layers are rounded and clamped to fit.
+    /// Setting a tile to the value it already has does nothing.
+    pub fn set(&mut self, name: &str, position: &Position, value: f32) {
+        let Some(index) = self.index(position) else {
+            return;
+        };
+
+        if let Some(layer) = self.layers.get_mut(name)
+            && layer.write(index, value)
+        {
+            layer.written.push(index);
+        }
+    }
+
+    /// Sets the value of the given layer at the given position, without it being copied back into a component.
+    fn mirror(&mut self, name: &str, position: &Position, value: f32) {
+        let Some(index) = self.index(position) else {
+            return;
+        };
+
+        if let Some(layer) = self.layers.get_mut(name) {
+            layer.write(index, value);
+        }
+    }
+
+    /// Returns the position and current value of every tile set on the given layer since this was last called.
+    fn take_written(&mut self, name: &str) -> Vec<(Position, f32)> {
+        let height = self.height;
+        let Some(layer) = self.layers.get_mut(name) else {
+            return Vec::new();
+        };
+
+        let mut written = std::mem::take(&mut layer.written);
+        written.sort_unstable();
+        written.dedup();
+        written
+            .into_iter()
+            .filter_map(|index| {
+                let position = GridIndex::flat_position(index, height);
+                Some((position, layer.read(index)?))
+            })
+            .collect()
+    }
+
+    fn tile_count(&self) -> usize {
+        (self.width.max(0) * self.height.max(0)) as usize
+    }
+
+    fn index(&self, position: &Position) -> Option<usize> {
+        GridIndex::flat_index(position, self.width, self.height)
+    }
+}
+
+/// Resizes every layer to match the new map, resetting all values to their defaults.
+fn resize_cell_layers(map_size: Res<GeneratedMapSize>, mut cell_layers: ResMut<CellLayers>) {
+    cell_layers.width = map_size.width;
+    cell_layers.height = map_size.height;
+
+    let tile_count = cell_layers.tile_count();
+    for layer in cell_layers.layers.values_mut() {
+        layer.reset(tile_count);
+    }
+}
+
+/// The layers that mirror a built-in per-tile component, along with the range of values they display.
+const MIRRORED_LAYERS: [(&str, (f32, f32)); 6] = [
+    ("moisture", (0.0, 1.0)),
+    ("elevation", (0.0, 1.0)),
+    ("fertility", (0.0, 1.0)),
+    // The fuel load of the densest stands of dead trees
+    ("fuel", (0.0, 12.0)),
+    // Sand piles topple long before they could overflow a byte
+    ("sand", (0.0, SandGrains::TOPPLING_THRESHOLD as f32)),
+    // Long enough for a meadow to grow into an old forest
+    ("age", (0.0, 100.0)),
+];
+
+/// Copies values set on the mirrored layers back into their components.
+///
+/// This runs just before [`mirror_component_layers`], so a value set on a layer wins over
+/// a change made to the component during the same frame.
+#[allow(clippy::type_complexity)]
+fn write_back_component_layers(
+    mut tile_query: Query<
+        (
+            Option<&mut Moisture>,
+            Option<&mut Elevation>,
+            Option<&mut Fertility>,
+            Option<&mut FuelLoad>,
+            Option<&mut SandGrains>,
+            Option<&mut Age>,
+        ),
+        With<Tile>,
+    >,
+    tile_index: Res<TileIndex>,
+    mut cell_layers: ResMut<CellLayers>,
+) {
+    for (name, _) in MIRRORED_LAYERS {
+        for (position, value) in cell_layers.take_written(name) {
+            let Some(entity) = tile_index.get(&position) else {
+                continue;
+            };
+            let Ok((moisture, elevation, fertility, fuel_load, sand_grains, age)) =
+                tile_query.get_mut(entity)
+            else {
+                continue;
+            };
+
+            // Tiles only have the components used by the active ruleset, so the rest are skipped
+            match (
+                name,
+                moisture,
+                elevation,
+                fertility,
+                fuel_load,
+                sand_grains,
+                age,
+            ) {
+                ("moisture", Some(mut moisture), ..) => moisture.0 = value,
+                ("elevation", _, Some(mut elevation), ..) => elevation.0 = value,
+                ("fertility", _, _, Some(mut fertility), ..) => fertility.0 = value,
+                ("fuel", _, _, _, Some(mut fuel_load), ..) => fuel_load.0 = value,
+                ("sand", .., Some(mut sand_grains), _) => sand_grains.0 = value.round() as u32,
+                ("age", .., Some(mut age)) => age.0 = value.round() as u32,
+                _ => {}
+            }
+        }
+    }
+
+    // Nothing else needs to know which tiles were set on the remaining layers
+    for layer in cell_layers.layers.values_mut() {
+        layer.written.clear();
+    }
+}
+
+/// Copies the built-in per-tile components into layers of the same name, so that they can be overlaid.
+#[allow(clippy::type_complexity)]
+fn mirror_component_layers(
+    tile_query: Query<
+        (
+            &Position,
+            Option<Ref<Moisture>>,
+            Option<Ref<Elevation>>,
+            Option<Ref<Fertility>>,
+            Option<Ref<FuelLoad>>,
+            Option<Ref<SandGrains>>,
+            Option<Ref<Age>>,
+        ),
+        With<Tile>,
+    >,
+    mut cell_layers: ResMut<CellLayers>,
+) {
+    for (name, display_range) in MIRRORED_LAYERS {
+        if cell_layers.layer(name).is_some() {
+            continue;
+        }
+
+        if name == "sand" {
+            cell_layers.add_u8_layer(name, 0, display_range);
+        } else {
+            cell_layers.add_f32_layer(name, 0.0, display_range);
+        }
+    }
+
+    for (position, moisture, elevation, fertility, fuel_load, sand_grains, age) in tile_query.iter()
+    {
+        if let Some(moisture) = moisture.filter(Ref::is_changed) {
+            cell_layers.mirror("moisture", position, moisture.0);
+        }
+        if let Some(elevation) = elevation.filter(Ref::is_changed) {
+            cell_layers.mirror("elevation", position, elevation.0);
+        }
+        if let Some(fertility) = fertility.filter(Ref::is_changed) {
+            cell_layers.mirror("fertility", position, fertility.0);
+        }
+        if let Some(fuel_load) = fuel_load.filter(Ref::is_changed) {
+            cell_layers.mirror("fuel", position, fuel_load.0);
+        }
+        if let Some(sand_grains) = sand_grains.filter(Ref::is_changed) {
+            cell_layers.mirror("sand", position, sand_grains.0 as f32);
+        }
+        if let Some(age) = age.filter(Ref::is_changed) {
+            cell_layers.mirror("age", position, age.0 as f32);
+        }
+    }
+}
+
+/// The name of the layer storing how many tiles away the nearest fire is.
+pub const FIRE_DISTANCE_LAYER: &str = "fire_distance";
+
+/// The name of the layer storing how many tiles away the nearest water is.
+pub const WATER_DISTANCE_LAYER: &str = "water_distance";
+
+/// The name of the layer storing how easily each tile catches fire, relative to the base fire susceptibility.
+pub const SUSCEPTIBILITY_LAYER: &str = "susceptibility";
+
+/// Tiles further than this from every source are all given this distance.
+const MAX_DISTANCE: i32 = 32;
+
+fn add_derived_layers(mut cell_layers: ResMut<CellLayers>) {
+    for name in [FIRE_DISTANCE_LAYER, WATER_DISTANCE_LAYER] {
+        cell_layers.add_u8_layer(name, MAX_DISTANCE as u8, (0.0, MAX_DISTANCE as f32));
+    }
+
+    // Dead forests are the most flammable kind of tile, at twice the base susceptibility
+    cell_layers.add_f32_layer(SUSCEPTIBILITY_LAYER, 0.0, (0.0, 2.0));
+}
+
+/// A run condition that checks whether the named layer is currently shown by the [`LayerOverlay`].
+fn overlaying_layer(name: &'static str) -> impl Fn(Res<LayerOverlay>) -> bool {
+    move |layer_overlay: Res<LayerOverlay>| layer_overlay.layer.as_deref() == Some(name)
+}
+
+/// Fills the `fire_distance` layer with the distance from each tile to the nearest fire.
+///
+/// Computing this touches every tile, so it is only done while the layer is being overlaid,
+/// and only when the map has changed.
+fn update_fire_distance_layer(
+    changed_tiles: Query<(), (With<Tile>, Changed<TileKind>)>,
+    tile_query: Query<(&Position, &TileKind), With<Tile>>,
+    tile_index: Res<TileIndex>,
+    grid_shape: Res<GridShape>,
+    layer_overlay: Res<LayerOverlay>,
+    mut cell_layers: ResMut<CellLayers>,
+) {
+    // The layer needs filling in as soon as the overlay is switched on, even if nothing has changed
+    if changed_tiles.is_empty() && !layer_overlay.is_changed() {
+        return;
+    }
+
+    fill_distance_layer(
+        FIRE_DISTANCE_LAYER,
+        TileKind::Fire,
+        &tile_query,
+        &tile_index,
+        &grid_shape,
+        &mut cell_layers,
+    );
+}
+
+/// Fills the `water_distance` layer with the distance from each tile to the nearest water.
+///
+/// Like [`update_fire_distance_layer`], this only runs while the layer is being overlaid.
+fn update_water_distance_layer(
+    changed_tiles: Query<(), (With<Tile>, Changed<TileKind>)>,
+    tile_query: Query<(&Position, &TileKind), With<Tile>>,
+    tile_index: Res<TileIndex>,
+    grid_shape: Res<GridShape>,
+    layer_overlay: Res<LayerOverlay>,
+    mut cell_layers: ResMut<CellLayers>,
+) {
+    if changed_tiles.is_empty() && !layer_overlay.is_changed() {
+        return;
+    }
+
+    fill_distance_layer(
+        WATER_DISTANCE_LAYER,
+        TileKind::Water,
+        &tile_query,
+        &tile_index,
+        &grid_shape,
+        &mut cell_layers,
+    );
+}
+
+/// Stores the distance from each tile to the nearest tile of the given kind in the named layer.
+fn fill_distance_layer(
+    name: &str,
+    source_kind: TileKind,
+    tile_query: &Query<(&Position, &TileKind), With<Tile>>,
+    tile_index: &TileIndex,
+    grid_shape: &GridShape,
+    cell_layers: &mut CellLayers,
+) {
+    let distances = tile_index.distance_field(grid_shape, MAX_DISTANCE, |entity| {
+        tile_query
+            .get(entity)
+            .is_ok_and(|(_, tile_kind)| *tile_kind == source_kind)
+    });
+
+    for (position, _) in tile_query.iter() {
+        let distance = distances.get(position).copied().unwrap_or(MAX_DISTANCE);
+        cell_layers.set(name, position, distance as f32);
+    }
+}
+
+/// Fills the `susceptibility` layer with how easily each tile would catch fire, taking its moisture into account.
+///
+/// Values are relative to the base susceptibility, so that the overlay stays readable as it is tuned.
+#[allow(clippy::type_complexity)]
+fn update_susceptibility_layer(
+    tile_query: Query<(&Position, Ref<TileKind>, Ref<Moisture>), With<Tile>>,
+    fire_susceptibility: Res<FireSusceptibility>,
+    layer_overlay: Res<LayerOverlay>,
+    mut cell_layers: ResMut<CellLayers>,
+) {
+    let refresh_all = layer_overlay.is_changed() || fire_susceptibility.is_changed();
+    let base_susceptibility = fire_susceptibility.base_susceptibility;
+
+    for (position, tile_kind, moisture) in tile_query.iter() {
+        if !refresh_all && !tile_kind.is_changed() && !moisture.is_changed() {
+            continue;
+        }
+
+        let susceptibility = if base_susceptibility > 0.0 {
+            fire_susceptibility.get(&tile_kind, &moisture) / base_susceptibility
+        } else {
+            0.0
+        };
+        cell_layers.set(SUSCEPTIBILITY_LAYER, position, susceptibility as f32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell_layers(width: i32, height: i32) -> CellLayers {
+        let mut cell_layers = CellLayers {
+            width,
+            height,
+            ..default()
+        };
+        cell_layers.add_f32_layer("moisture", 0.0, (0.0, 1.0));
+        cell_layers
+    }
+
+    #[test]
+    fn layers_are_laid_out_like_the_grid_index() {
+        let cell_layers = cell_layers(3, 2);
+
+        for x in 0..3 {
+            for y in 0..2 {
+                let position = Position { x, y };
+                let index = cell_layers.index(&position);
+
+                assert_eq!(index, GridIndex::flat_index(&position, 3, 2));
+                assert_eq!(GridIndex::flat_position(index.unwrap(), 2), position);
+            }
+        }
+        assert_eq!(cell_layers.index(&Position { x: 3, y: 0 }), None);
+    }
+
+    #[test]
+    fn setting_an_unchanged_value_is_not_written_back() {
+        let mut cell_layers = cell_layers(3, 2);
+        let position = Position { x: 2, y: 1 };
+
+        for _ in 0..3 {
+            cell_layers.set("moisture", &position, 0.5);
+        }
+        cell_layers.set("moisture", &Position { x: 0, y: 1 }, 0.0);
+
+        assert_eq!(cell_layers.take_written("moisture"), vec![(position, 0.5)]);
+        cell_layers.set("moisture", &position, 0.5);
+        assert!(cell_layers.take_written("moisture").is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! Counts how many tiles of each kind are on the map, for the statistics panel.
+//!
+//! The [`TileCensus`] is retaken once each simulation tick has run, and whenever a new map has been generated,
+//! so that it always matches what is drawn on screen.
+//! A history of recent censuses is kept too, which is plotted by the [`PopulationChart`](crate::population_chart::PopulationChart).
+
+use std::collections::VecDeque;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::SimState;
+use crate::control_flow::{Simulation, run_simulation};
+use crate::simulation::TileKind;
+use crate::spatial_index::Tile;
+
+pub struct CensusPlugin;
+
+impl Plugin for CensusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileCensus>()
+            .register_type::<TileCensus>()
+            .add_systems(OnEnter(SimState::Generate), clear_tile_census)
+            .add_systems(OnExit(SimState::Generate), take_tile_census)
+            .add_systems(Simulation, note_simulation_tick)
+            // Tiles change kind through commands, which are only applied once the whole simulation tick has run
+            .add_systems(
+                Update,
+                take_tile_census
+                    .after(run_simulation)
+                    .run_if(tile_census_due),
+            );
+    }
+}
+
+/// The number of tiles of each kind on the map, as of the most recent simulation tick.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct TileCensus {
+    counts: HashMap<TileKind, u32>,
+    total: u32,
+    /// The counts at each of the most recent ticks since the map was generated, oldest first.
+    history: VecDeque<HashMap<TileKind, u32>>,
+    /// The maximum number of ticks kept in the history.
+    pub history_length: usize,
+    /// Whether a simulation tick has run since the census was last taken.
+    tick_ran: bool,
+}
+
+impl Default for TileCensus {
+    fn default() -> Self {
+        Self {
+            counts: HashMap::default(),
+            total: 0,
+            history: VecDeque::new(),
+            history_length: 500,
+            tick_ran: false,
+        }
+    }
+}
+
+impl TileCensus {
+    /// Returns the number of tiles of the given kind.
+    pub fn count(&self, tile_kind: &TileKind) -> u32 {
+        self.counts.get(tile_kind).copied().unwrap_or_default()
+    }
+
+    /// Returns the fraction of the map covered by tiles of the given kind, in the range of 0.0 to 1.0.
+    pub fn fraction(&self, tile_kind: &TileKind) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+
+        self.count(tile_kind) as f32 / self.total as f32
+    }
+
+    /// Returns the counts of each kind of tile at each of the most recent ticks, oldest first.
+    pub fn history(&self) -> &VecDeque<HashMap<TileKind, u32>> {
+        &self.history
+    }
+}
+
+/// A run condition that checks whether a simulation tick has run since the census was last taken.
+fn tile_census_due(tile_census: Res<TileCensus>) -> bool {
+    tile_census.tick_ran
+}
+
+fn note_simulation_tick(mut tile_census: ResMut<TileCensus>) {
+    // The census itself hasn't changed yet, so there's no need to redraw anything that shows it
+    tile_census.bypass_change_detection().tick_ran = true;
+}
+
+/// Empties the census while the map is generated, since the tiles it counted are gone.
+fn clear_tile_census(mut tile_census: ResMut<TileCensus>) {
+    *tile_census = TileCensus {
+        history_length: tile_census.history_length,
+        ..default()
+    };
+}
+
+fn take_tile_census(tile_query: Query<&TileKind, With<Tile>>, mut tile_census: ResMut<TileCensus>) {
+    let mut counts: HashMap<TileKind, u32> = HashMap::default();
+    for tile_kind in tile_query.iter() {
+        *counts.entry(*tile_kind).or_default() += 1;
+    }
+
+    tile_census.total = counts.values().sum();
+    tile_census.history.push_back(counts.clone());
+    while tile_census.history.len() > tile_census.history_length {
+        tile_census.history.pop_front();
+    }
+    tile_census.counts = counts;
+    tile_census.tick_ran = false;
+}
@@ -0,0 +1,175 @@
+//! Groups tiles into square chunks, to keep very large maps manageable.
+//!
+//! Each tile is spawned as a child of the [`Chunk`] that contains it.
+//! This lets us work with whole blocks of tiles at once:
+//! chunks outside of the camera's view are hidden, despawning a chunk despawns all of its tiles,
+//! and each chunk tracks whether any of its tiles changed this frame.
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::view::VisibilitySystems;
+
+use crate::camera::MainCamera;
+use crate::control_flow::run_simulation;
+use crate::level_of_detail::LevelOfDetail;
+use crate::simulation::TileKind;
+use crate::spatial_index::{GridShape, Position, Tile};
+
+pub struct ChunkPlugin;
+
+impl Plugin for ChunkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkIndex>()
+            .add_systems(Update, mark_dirty_chunks.after(run_simulation))
+            .add_systems(
+                PostUpdate,
+                cull_chunks.before(VisibilitySystems::VisibilityPropagate),
+            )
+            .add_systems(Last, clear_dirty_chunks);
+    }
+}
+
+/// A square block of tiles, which are spawned as its children.
+///
+/// Chunks sit at the origin, so the transforms of their tiles are the same as if they had no parent.
+#[derive(Component, Debug)]
+#[require(Transform, Visibility)]
+pub struct Chunk {
+    /// The position of this chunk, counted in chunks rather than tiles.
+    pub coordinates: IVec2,
+    /// Whether the kind of any tile in this chunk changed during this frame.
+    ///
+    /// This is set after the simulation runs, and cleared at the end of the frame.
+    pub dirty: bool,
+}
+
+impl Chunk {
+    /// The width and height of each chunk, in tiles.
+    pub const SIZE: i32 = 32;
+
+    /// Returns the coordinates of the chunk that contains the given tile.
+    pub fn coordinates_of(position: &Position) -> IVec2 {
+        IVec2::new(
+            position.x.div_euclid(Self::SIZE),
+            position.y.div_euclid(Self::SIZE),
+        )
+    }
+
+    /// Returns the axis-aligned bounding box of this chunk's tiles, in world space.
+    fn bounds(&self, grid_shape: &GridShape) -> Rect {
+        let min = (self.coordinates * Self::SIZE).as_vec2() - 0.5;
+        let max = min + Self::SIZE as f32;
+
+        // Hexagonal grids skew each chunk into a rhombus, so we need to check every corner
+        let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+            .map(|corner| grid_shape.grid_to_world(corner));
+        let bounds = corners.iter().fold(
+            Rect::from_corners(corners[0], corners[0]),
+            |bounds, corner| bounds.union_point(*corner),
+        );
+
+        // Leave room for tiles that are drawn taller than they are spaced
+        bounds.inflate(Position::PIXELS_PER_TILE)
+    }
+}
+
+/// Looks up the chunk entity at each set of chunk coordinates.
+#[derive(Resource, Default)]
+struct ChunkIndex {
+    chunks: HashMap<IVec2, Entity>,
+}
+
+/// Returns the chunk that the tile at the given position belongs to, spawning it if it doesn't exist yet.
+pub fn chunk_entity(world: &mut World, position: &Position) -> Entity {
+    let coordinates = Chunk::coordinates_of(position);
+    if let Some(&entity) = world.resource::<ChunkIndex>().chunks.get(&coordinates) {
+        return entity;
+    }
+
+    let entity = world
+        .spawn((
+            Name::new(format!("Chunk ({}, {})", coordinates.x, coordinates.y)),
+            Chunk {
+                coordinates,
+                dirty: false,
+            },
+        ))
+        .id();
+    world
+        .resource_mut::<ChunkIndex>()
+        .chunks
+        .insert(coordinates, entity);
+
+    entity
+}
+
+/// Despawns every chunk, along with all of the tiles inside of them.
+pub fn despawn_chunks(world: &mut World) {
+    let chunks = std::mem::take(&mut world.resource_mut::<ChunkIndex>().chunks);
+
+    for entity in chunks.into_values() {
+        world.despawn(entity);
+    }
+}
+
+/// Marks each chunk containing a tile whose kind changed as dirty.
+fn mark_dirty_chunks(
+    tile_query: Query<&ChildOf, (With<Tile>, Changed<TileKind>)>,
+    mut chunk_query: Query<&mut Chunk>,
+) {
+    for child_of in tile_query.iter() {
+        if let Ok(mut chunk) = chunk_query.get_mut(child_of.parent()) {
+            chunk.dirty = true;
+        }
+    }
+}
+
+fn clear_dirty_chunks(mut chunk_query: Query<&mut Chunk>) {
+    for mut chunk in chunk_query.iter_mut() {
+        chunk.dirty = false;
+    }
+}
+
+/// Hides every chunk that lies entirely outside of the camera's view.
+///
+/// Hidden chunks hide all of their tiles, so the renderer can skip them without checking each tile separately.
+/// Every chunk is hidden while the [`LevelOfDetail`] draws a downsampled copy of the map instead.
+///
+/// Hiding a chunk hides it from every camera, so other views of the map, such as the minimap,
+/// draw their own [`DownsampledMap`](crate::level_of_detail::DownsampledMap) rather than the tiles.
+fn cull_chunks(
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_shape: Res<GridShape>,
+    level_of_detail: Res<LevelOfDetail>,
+    mut chunk_query: Query<(&Chunk, &mut Visibility)>,
+) {
+    if level_of_detail.active() {
+        for (_, mut visibility) in chunk_query.iter_mut() {
+            visibility.set_if_neq(Visibility::Hidden);
+        }
+        return;
+    }
+
+    let (camera, camera_transform) = *camera;
+    let Some(viewport) = camera.logical_viewport_rect() else {
+        return;
+    };
+
+    let Ok(corner) = camera.viewport_to_world_2d(camera_transform, viewport.min) else {
+        return;
+    };
+    let Ok(opposite_corner) = camera.viewport_to_world_2d(camera_transform, viewport.max) else {
+        return;
+    };
+    let view = Rect::from_corners(corner, opposite_corner);
+
+    for (chunk, mut visibility) in chunk_query.iter_mut() {
+        let new_visibility = if chunk.bounds(&grid_shape).intersect(view).is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+
+        visibility.set_if_neq(new_visibility);
+    }
+}
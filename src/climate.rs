@@ -0,0 +1,462 @@
+//! Climate and weather that vary over the course of the simulation.
+//!
+//! Seasons change the rates of fire and regrowth over time,
+//! giving the simulation a rhythm that a constant set of parameters can't produce,
+//! while droughts add year-to-year variation on top of that rhythm.
+//! A temperature gradient from north to south adds spatial variation as well.
+//! Wind carries embers from burning tiles, letting fires jump over gaps in the fuel.
+
+use bevy::ecs::system::SystemParam;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+use rand::{Rng, RngCore};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::SimState;
+use crate::control_flow::Simulation;
+use crate::disturbances::{DisturbanceKind, DisturbanceStarted, schedule_disturbances};
+use crate::map_generation::{GeneratedMapSize, MapSize};
+use crate::rulesets::{ForestClimateSystems, ForestSystems};
+use crate::simulation::TileKind;
+use crate::spatial_index::Position;
+use crate::tile_rng::{TileRng, TileRngStream, advance_tile_rng};
+
+pub struct ClimatePlugin;
+
+impl Plugin for ClimatePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SeasonKind>()
+            .init_resource::<Season>()
+            .register_type::<Season>()
+            .init_resource::<Drought>()
+            .register_type::<Drought>()
+            .init_resource::<Wind>()
+            .register_type::<Wind>()
+            .init_resource::<TemperatureGradient>()
+            .register_type::<TemperatureGradient>()
+            .init_resource::<Windstorm>()
+            .register_type::<Windstorm>()
+            .add_systems(
+                Simulation,
+                (
+                    (advance_season, update_drought).in_set(ForestClimateSystems),
+                    windstorm.in_set(ForestSystems),
+                )
+                    .after(schedule_disturbances)
+                    .after(advance_tile_rng),
+            )
+            .add_systems(OnEnter(SimState::Generate), (reset_season, reset_drought));
+    }
+}
+
+#[derive(Reflect, PartialEq, Eq, Hash, Debug, Clone, Copy, Default, EnumIter)]
+pub enum SeasonKind {
+    #[default]
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl SeasonKind {
+    /// The season that follows this one.
+    pub fn next(&self) -> SeasonKind {
+        use SeasonKind::*;
+
+        match self {
+            Spring => Summer,
+            Summer => Autumn,
+            Autumn => Winter,
+            Winter => Spring,
+        }
+    }
+}
+
+/// The current season, which advances every [`Season::ticks_per_season`] simulation ticks.
+///
+/// Each season scales the base fire susceptibility and the rate of succession,
+/// so summers burn hotter and springs regrow faster.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Season {
+    /// The season that the simulation is currently in.
+    pub kind: SeasonKind,
+    /// The number of simulation ticks that each season lasts.
+    ticks_per_season: u32,
+    /// The number of simulation ticks that have elapsed in the current season.
+    ticks_elapsed: u32,
+    /// The multiplier applied to the fire susceptibility of every tile during each season.
+    fire_multipliers: HashMap<SeasonKind, f64>,
+    /// The multiplier applied to the probability of each succession transition during each season.
+    growth_multipliers: HashMap<SeasonKind, f32>,
+}
+
+impl Season {
+    /// Returns the multiplier applied to fire susceptibility in the current season.
+    ///
+    /// If the season is not found, it returns 1.0.
+    pub fn fire_multiplier(&self) -> f64 {
+        self.fire_multipliers
+            .get(&self.kind)
+            .cloned()
+            .unwrap_or(1.0)
+    }
+
+    /// Returns the multiplier applied to succession probabilities in the current season.
+    ///
+    /// If the season is not found, it returns 1.0.
+    pub fn growth_multiplier(&self) -> f32 {
+        self.growth_multipliers
+            .get(&self.kind)
+            .cloned()
+            .unwrap_or(1.0)
+    }
+
+    /// The number of simulation ticks in a full cycle of the seasons.
+    pub fn ticks_per_year(&self) -> u32 {
+        self.ticks_per_season * SeasonKind::iter().count() as u32
+    }
+}
+
+impl Default for Season {
+    fn default() -> Self {
+        let mut fire_multipliers = HashMap::new();
+        fire_multipliers.insert(SeasonKind::Spring, 0.5);
+        fire_multipliers.insert(SeasonKind::Summer, 3.0);
+        fire_multipliers.insert(SeasonKind::Autumn, 1.0);
+        fire_multipliers.insert(SeasonKind::Winter, 0.1);
+
+        let mut growth_multipliers = HashMap::new();
+        growth_multipliers.insert(SeasonKind::Spring, 2.0);
+        growth_multipliers.insert(SeasonKind::Summer, 1.0);
+        growth_multipliers.insert(SeasonKind::Autumn, 0.5);
+        growth_multipliers.insert(SeasonKind::Winter, 0.1);
+
+        Self {
+            kind: SeasonKind::default(),
+            ticks_per_season: 10,
+            ticks_elapsed: 0,
+            fire_multipliers,
+            growth_multipliers,
+        }
+    }
+}
+
+/// Tracks multi-tick droughts, which raise flammability and suppress succession while active.
+///
+/// Droughts are started by the [`DisturbanceScheduler`](crate::disturbances::DisturbanceScheduler),
+/// and last for a random number of ticks.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Drought {
+    /// The number of ticks remaining in the current drought, or zero if there is no drought.
+    ticks_remaining: u32,
+    /// The shortest possible drought, in ticks.
+    min_duration: u32,
+    /// The longest possible drought, in ticks.
+    max_duration: u32,
+    /// The multiplier applied to the fire susceptibility of every tile during a drought.
+    fire_multiplier: f64,
+    /// The multiplier applied to the probability of each succession transition during a drought.
+    growth_multiplier: f32,
+}
+
+impl Drought {
+    /// Returns true if a drought is currently underway.
+    pub fn is_active(&self) -> bool {
+        self.ticks_remaining > 0
+    }
+
+    /// Returns the number of ticks remaining in the current drought.
+    pub fn ticks_remaining(&self) -> u32 {
+        self.ticks_remaining
+    }
+
+    /// Returns the multiplier applied to fire susceptibility, which is 1.0 outside of a drought.
+    pub fn fire_multiplier(&self) -> f64 {
+        if self.is_active() {
+            self.fire_multiplier
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the multiplier applied to succession probabilities, which is 1.0 outside of a drought.
+    pub fn growth_multiplier(&self) -> f32 {
+        if self.is_active() {
+            self.growth_multiplier
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Default for Drought {
+    fn default() -> Self {
+        Self {
+            ticks_remaining: 0,
+            min_duration: 5,
+            max_duration: 20,
+            fire_multiplier: 4.0,
+            growth_multiplier: 0.1,
+        }
+    }
+}
+
+/// The prevailing wind, which blows embers from burning tiles downwind.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Wind {
+    /// The direction that the wind is blowing towards.
+    ///
+    /// This does not need to be normalized.
+    pub direction: Vec2,
+    /// The strength of the wind, where 1.0 is a typical breeze and 0.0 is perfectly still air.
+    pub speed: f32,
+}
+
+impl Wind {
+    /// Returns the position `distance` tiles downwind of `position`, rounded to the nearest tile.
+    ///
+    /// If the wind has no direction, the original position is returned.
+    pub fn downwind(&self, position: &Position, distance: f32) -> Position {
+        let offset = (self.direction.normalize_or_zero() * distance).round();
+
+        Position {
+            x: position.x + offset.x as i32,
+            y: position.y + offset.y as i32,
+        }
+    }
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            direction: Vec2::new(1.0, 0.0),
+            speed: 1.0,
+        }
+    }
+}
+
+/// A north-south temperature gradient, which makes the top of the map colder than the bottom.
+///
+/// Colder tiles regrow more slowly and are less likely to burn, producing visible bands of vegetation.
+/// Temperatures are relative: at 1.0, succession and fire proceed at their usual rates,
+/// and lower values slow both down proportionally.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct TemperatureGradient {
+    /// The relative temperature of the top row of the map.
+    pub north_temperature: f32,
+    /// The relative temperature of the bottom row of the map.
+    pub south_temperature: f32,
+}
+
+impl TemperatureGradient {
+    /// Returns the relative temperature at the given position, interpolating linearly between the top and bottom rows.
+    pub fn temperature_at(&self, position: &Position, map_size: &MapSize) -> f32 {
+        let latitude = if map_size.height > 1 {
+            (position.y as f32 / (map_size.height - 1) as f32).clamp(0.0, 1.0)
+        } else {
+            0.5
+        };
+
+        self.south_temperature
+            .lerp(self.north_temperature, latitude)
+            .max(0.0)
+    }
+}
+
+impl Default for TemperatureGradient {
+    fn default() -> Self {
+        Self {
+            north_temperature: 0.5,
+            south_temperature: 1.0,
+        }
+    }
+}
+
+/// Controls windstorms, which blow down the trees across a noise-shaped region of the map.
+///
+/// Windstorms are started by the [`DisturbanceScheduler`](crate::disturbances::DisturbanceScheduler).
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct Windstorm {
+    /// The tile kinds whose trees can be blown down.
+    vulnerable_kinds: Vec<TileKind>,
+    /// The approximate fraction of the map swept by each storm, in the range of 0.0 to 1.0.
+    coverage: f32,
+    /// The typical size of the region swept by the storm, in tiles.
+    storm_period: f32,
+    /// The probability that the trees in each vulnerable tile within the storm are blown down,
+    /// turning it into [`TileKind::DeadForest`].
+    blowdown_probability: f64,
+}
+
+impl Default for Windstorm {
+    fn default() -> Self {
+        Self {
+            // Tall, mature trees catch the most wind
+            vulnerable_kinds: vec![
+                TileKind::ShadeIntolerantForest,
+                TileKind::ShadeTolerantForest,
+            ],
+            coverage: 0.2,
+            storm_period: 10.0,
+            blowdown_probability: 0.3,
+        }
+    }
+}
+
+/// The combined effect of the current climate on the simulation.
+///
+/// Simulation systems should use this rather than reading each climate resource directly,
+/// so that new climate effects automatically apply everywhere.
+#[derive(SystemParam)]
+pub struct ClimateConditions<'w> {
+    season: Res<'w, Season>,
+    drought: Res<'w, Drought>,
+    temperature_gradient: Res<'w, TemperatureGradient>,
+    map_size: Res<'w, GeneratedMapSize>,
+}
+
+impl ClimateConditions<'_> {
+    /// The multiplier applied to the fire susceptibility of the tile at the given position.
+    pub fn fire_multiplier(&self, position: &Position) -> f64 {
+        self.uniform_fire_multiplier()
+            * self
+                .temperature_gradient
+                .temperature_at(position, &self.map_size) as f64
+    }
+
+    /// The part of [`ClimateConditions::fire_multiplier`] that is the same for every tile on the map.
+    pub fn uniform_fire_multiplier(&self) -> f64 {
+        self.season.fire_multiplier() * self.drought.fire_multiplier()
+    }
+
+    /// Like [`ClimateConditions::fire_multiplier`], for code that reads from the [`World`] directly.
+    pub fn fire_multiplier_in(world: &World, position: &Position) -> f64 {
+        world.resource::<Season>().fire_multiplier()
+            * world.resource::<Drought>().fire_multiplier()
+            * world
+                .resource::<TemperatureGradient>()
+                .temperature_at(position, world.resource::<GeneratedMapSize>()) as f64
+    }
+
+    /// The multiplier applied to the probability of each succession transition for the tile at the given position.
+    pub fn growth_multiplier(&self, position: &Position) -> f32 {
+        self.uniform_growth_multiplier()
+            * self
+                .temperature_gradient
+                .temperature_at(position, &self.map_size)
+    }
+
+    /// The part of [`ClimateConditions::growth_multiplier`] that is the same for every tile on the map.
+    pub fn uniform_growth_multiplier(&self) -> f32 {
+        self.season.growth_multiplier() * self.drought.growth_multiplier()
+    }
+
+    /// The temperature gradient that scales both multipliers, which varies across the map.
+    pub fn temperature_gradient(&self) -> &TemperatureGradient {
+        &self.temperature_gradient
+    }
+
+    /// Like [`ClimateConditions::growth_multiplier`], for code that reads from the [`World`] directly.
+    pub fn growth_multiplier_in(world: &World, position: &Position) -> f32 {
+        world.resource::<Season>().growth_multiplier()
+            * world.resource::<Drought>().growth_multiplier()
+            * world
+                .resource::<TemperatureGradient>()
+                .temperature_at(position, world.resource::<GeneratedMapSize>())
+    }
+}
+
+fn advance_season(mut season: ResMut<Season>) {
+    season.ticks_elapsed += 1;
+
+    if season.ticks_elapsed >= season.ticks_per_season {
+        season.ticks_elapsed = 0;
+        season.kind = season.kind.next();
+        info!("The season has changed to {:?}.", season.kind);
+    }
+}
+
+fn reset_season(mut season: ResMut<Season>) {
+    season.kind = SeasonKind::default();
+    season.ticks_elapsed = 0;
+}
+
+fn update_drought(
+    mut drought: ResMut<Drought>,
+    mut event_reader: EventReader<DisturbanceStarted>,
+    tile_rng: Res<TileRng>,
+) {
+    let drought_started = event_reader
+        .read()
+        .any(|event| event.kind == DisturbanceKind::Drought);
+
+    if drought.is_active() {
+        drought.ticks_remaining -= 1;
+
+        if !drought.is_active() {
+            info!("The drought has ended.");
+        }
+    } else if drought_started {
+        let max_duration = drought.max_duration.max(drought.min_duration);
+        drought.ticks_remaining = tile_rng
+            .for_map(TileRngStream::Drought)
+            .random_range(drought.min_duration..=max_duration);
+        info!(
+            "A drought has begun, and will last for {} ticks.",
+            drought.ticks_remaining
+        );
+    }
+}
+
+#[hot]
+fn windstorm(
+    mut tile_query: Query<(&Position, &mut TileKind)>,
+    windstorm: Res<Windstorm>,
+    mut event_reader: EventReader<DisturbanceStarted>,
+    tile_rng: Res<TileRng>,
+) {
+    use noiz::prelude::*;
+
+    // Shared between storms, so that two storms on the same tick don't land in the same place
+    let mut rng = tile_rng.for_map(TileRngStream::Windstorm);
+    for _ in event_reader
+        .read()
+        .filter(|event| event.kind == DisturbanceKind::Windstorm)
+    {
+        let mut noise = Noise::<(
+            MixCellGradients<OrthoGrid, Smoothstep, QuickGradients>,
+            SNormToUNorm,
+        )>::default();
+        noise.set_period(windstorm.storm_period);
+        noise.set_seed(rng.next_u32());
+
+        let mut blown_down = 0;
+        for (&position, mut tile_kind) in tile_query.iter_mut() {
+            let converted_position = Vec2::new(position.x as f32, position.y as f32);
+            let noise_value: f32 = noise.sample(converted_position);
+
+            if noise_value < windstorm.coverage
+                && windstorm.vulnerable_kinds.contains(&tile_kind)
+                && tile_rng
+                    .for_tile(&position, TileRngStream::Windstorm)
+                    .random_bool(windstorm.blowdown_probability.clamp(0.0, 1.0))
+            {
+                *tile_kind = TileKind::DeadForest;
+                blown_down += 1;
+            }
+        }
+
+        info!("A windstorm has blown down the trees in {blown_down} tiles.");
+    }
+}
+
+fn reset_drought(mut drought: ResMut<Drought>) {
+    drought.ticks_remaining = 0;
+}
@@ -0,0 +1,229 @@
+//! An optional state-and-transition model, where each tile holds a mix of plant functional types.
+//!
+//! By default, every tile is exactly one [`TileKind`], and succession jumps between kinds all at once.
+//! When [`CompositionSuccession::enabled`] is set, vegetated tiles instead track the fraction of their area
+//! covered by grasses, shrubs, shade-intolerant trees and shade-tolerant trees.
+//! Succession gradually shifts these fractions, and the tile's kind is always set to its dominant type.
+//! Disturbances still act on the tile's kind as usual, and clear the slate back to bare grassland.
+
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+
+use crate::SimState;
+use crate::climate::ClimateConditions;
+use crate::control_flow::Simulation;
+use crate::rulesets::{ForestSystems, Ruleset, ruleset_is};
+use crate::simulation::TileKind;
+use crate::spatial_index::{Position, Tile};
+
+pub struct CompositionPlugin;
+
+impl Plugin for CompositionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Composition>()
+            .init_resource::<CompositionSuccession>()
+            .register_type::<CompositionSuccession>()
+            .add_systems(
+                OnExit(SimState::Generate),
+                seed_compositions
+                    .run_if(ruleset_is(Ruleset::Forest))
+                    .run_if(composition_enabled),
+            )
+            .add_systems(Simulation, shift_compositions.in_set(ForestSystems))
+            .add_systems(
+                Update,
+                regenerate_when_toggled.run_if(resource_changed::<CompositionSuccession>),
+            );
+    }
+}
+
+/// The fraction of a tile covered by each plant functional type.
+///
+/// The fractions always sum to 1.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq)]
+pub struct Composition {
+    pub grass: f32,
+    pub shrub: f32,
+    pub shade_intolerant: f32,
+    pub shade_tolerant: f32,
+}
+
+impl Default for Composition {
+    /// Bare ground is quickly colonized by grasses.
+    fn default() -> Self {
+        Self {
+            grass: 1.0,
+            shrub: 0.0,
+            shade_intolerant: 0.0,
+            shade_tolerant: 0.0,
+        }
+    }
+}
+
+impl Composition {
+    /// The tile kinds that are determined by the composition of a tile, in successional order.
+    ///
+    /// Tiles of any other kind have been disturbed, and their composition is reset.
+    pub const DOMINANT_KINDS: [TileKind; 4] = [
+        TileKind::Meadow,
+        TileKind::Shrubland,
+        TileKind::ShadeIntolerantForest,
+        TileKind::ShadeTolerantForest,
+    ];
+
+    /// Returns a plausible composition for a tile that is currently of the given kind.
+    pub fn typical_of(tile_kind: &TileKind) -> Self {
+        let (grass, shrub, shade_intolerant, shade_tolerant) = match tile_kind {
+            TileKind::Shrubland => (0.3, 0.7, 0.0, 0.0),
+            TileKind::ShadeIntolerantForest => (0.0, 0.3, 0.7, 0.0),
+            TileKind::ShadeTolerantForest => (0.0, 0.0, 0.3, 0.7),
+            _ => return Self::default(),
+        };
+
+        Self {
+            grass,
+            shrub,
+            shade_intolerant,
+            shade_tolerant,
+        }
+    }
+
+    /// Returns the tile kind of the type that covers the most area.
+    ///
+    /// Ties are broken in favor of the later successional stage.
+    pub fn dominant_kind(&self) -> TileKind {
+        let fractions = [
+            self.grass,
+            self.shrub,
+            self.shade_intolerant,
+            self.shade_tolerant,
+        ];
+
+        let mut dominant = 0;
+        for (i, fraction) in fractions.iter().enumerate() {
+            if *fraction >= fractions[dominant] {
+                dominant = i;
+            }
+        }
+
+        Self::DOMINANT_KINDS[dominant]
+    }
+}
+
+/// Controls how quickly each plant functional type spreads into the one before it.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct CompositionSuccession {
+    /// Whether vegetated tiles track their [`Composition`], rather than switching kinds all at once.
+    ///
+    /// Changing this regenerates the map.
+    pub enabled: bool,
+    /// The rate at which shrubs replace grasses.
+    shrub_rate: f32,
+    /// The rate at which shade-intolerant trees replace shrubs.
+    shade_intolerant_rate: f32,
+    /// The rate at which shade-tolerant trees replace shade-intolerant trees, growing up in their shade.
+    shade_tolerant_rate: f32,
+    /// The share of each type's growth that comes from seeds blown in from elsewhere,
+    /// allowing a type to establish in a tile where it is entirely absent.
+    colonization: f32,
+}
+
+impl Default for CompositionSuccession {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            shrub_rate: 0.1,
+            shade_intolerant_rate: 0.05,
+            shade_tolerant_rate: 0.02,
+            colonization: 0.05,
+        }
+    }
+}
+
+impl CompositionSuccession {
+    /// Moves the composition one tick further along the successional sequence.
+    ///
+    /// Each type spreads into the area occupied by the type before it,
+    /// in proportion to how much of the tile it already covers.
+    fn shift(&self, composition: &mut Composition, growth_multiplier: f32) {
+        let transfer = |rate: f32, from: f32, to: f32| {
+            (rate * growth_multiplier * from * (to + self.colonization)).clamp(0.0, from)
+        };
+
+        let to_shrub = transfer(self.shrub_rate, composition.grass, composition.shrub);
+        let to_shade_intolerant = transfer(
+            self.shade_intolerant_rate,
+            composition.shrub,
+            composition.shade_intolerant,
+        );
+        let to_shade_tolerant = transfer(
+            self.shade_tolerant_rate,
+            composition.shade_intolerant,
+            composition.shade_tolerant,
+        );
+
+        composition.grass -= to_shrub;
+        composition.shrub += to_shrub - to_shade_intolerant;
+        composition.shade_intolerant += to_shade_intolerant - to_shade_tolerant;
+        composition.shade_tolerant += to_shade_tolerant;
+    }
+}
+
+fn composition_enabled(composition_succession: Res<CompositionSuccession>) -> bool {
+    composition_succession.enabled
+}
+
+/// Gives every tile a composition matching its generated kind.
+#[hot]
+fn seed_compositions(tile_query: Query<(Entity, &TileKind), With<Tile>>, mut commands: Commands) {
+    for (entity, tile_kind) in tile_query.iter() {
+        commands
+            .entity(entity)
+            .insert(Composition::typical_of(tile_kind));
+    }
+}
+
+/// Advances the composition of every undisturbed tile, and updates its kind to match the dominant type.
+#[hot]
+fn shift_compositions(
+    mut tile_query: Query<(&Position, &mut TileKind, &mut Composition), With<Tile>>,
+    composition_succession: Res<CompositionSuccession>,
+    climate: ClimateConditions,
+) {
+    for (position, mut tile_kind, mut composition) in tile_query.iter_mut() {
+        if !Composition::DOMINANT_KINDS.contains(&tile_kind) {
+            composition.set_if_neq(Composition::default());
+            continue;
+        }
+
+        // Something else (such as the brush) has overwritten the kind of this tile
+        if tile_kind.is_changed() && composition.dominant_kind() != *tile_kind {
+            *composition = Composition::typical_of(&tile_kind);
+        }
+
+        composition_succession.shift(&mut composition, climate.growth_multiplier(position));
+        tile_kind.set_if_neq(composition.dominant_kind());
+    }
+}
+
+/// Tiles need to be given (or stripped of) their compositions, so start over.
+fn regenerate_when_toggled(
+    composition_succession: Res<CompositionSuccession>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    // The resource counts as changed when it is first added, but the map is already being generated
+    if composition_succession.is_added() {
+        return;
+    }
+
+    info!(
+        "Composition succession {}, regenerating map",
+        if composition_succession.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    next_state.set(SimState::Generate);
+}
@@ -21,6 +21,8 @@ impl Plugin for ControlFlowPlugin {
             .add_event::<SetSimulationTimestep>()
             .insert_resource(SimulationStepTime(Duration::from_millis(1000)))
             .register_type::<SimulationStepTime>()
+            .init_resource::<SetupPhase>()
+            .register_type::<SetupPhase>()
             .add_systems(
                 Update,
                 run_simulation
@@ -41,9 +43,38 @@ impl Plugin for ControlFlowPlugin {
 }
 
 /// The amount of real world time that each simulation step should take.
+///
+/// A duration of zero runs one step every frame, which is as fast as the simulation can go.
+/// Change this by sending a [`SetSimulationTimestep`] event.
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
-struct SimulationStepTime(Duration);
+pub struct SimulationStepTime(Duration);
+
+impl SimulationStepTime {
+    /// The amount of real world time that each simulation step should take.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+/// Controls whether the simulation waits in [`SimState::Setup`] after each map is generated.
+///
+/// This gives the user a chance to paint an exact starting configuration before pressing Start,
+/// rather than racing the simulation as it runs.
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct SetupPhase {
+    pub enabled: bool,
+}
+
+/// The state to enter once map generation has finished.
+pub fn state_after_generation(setup_phase: &SetupPhase) -> SimState {
+    if setup_phase.enabled {
+        SimState::Setup
+    } else {
+        SimState::Run
+    }
+}
 
 /// A custom run condition to control whether or not the simulation is ready to run.
 ///
@@ -0,0 +1,219 @@
+//! A purely cosmetic day/night cycle, which darkens the map at night so that fires glow against it.
+//!
+//! Time of day advances with each simulation tick, so pausing the simulation also stops the sun.
+//! When following the seasons, summer days are longer and brighter, while winter nights draw in early.
+//! Nothing here affects the simulation itself.
+
+use bevy::prelude::*;
+
+use crate::SimState;
+use crate::climate::{Season, SeasonKind};
+use crate::control_flow::Simulation;
+use crate::simulation::FireState;
+use crate::spatial_index::GridShape;
+use crate::tile_appearance::Palette;
+
+pub struct DayNightPlugin;
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DayNightCycle>()
+            .register_type::<DayNightCycle>()
+            .add_systems(Startup, spawn_night_overlay)
+            .add_systems(Simulation, advance_time_of_day)
+            .add_systems(OnEnter(SimState::Generate), reset_time_of_day)
+            .add_systems(
+                Update,
+                (
+                    fade_night_overlay,
+                    attach_fire_glows,
+                    detach_fire_glows,
+                    update_fire_glows,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Controls the cycle of day and night, which tints the whole map as time passes.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct DayNightCycle {
+    /// Whether the map darkens at night at all.
+    pub enabled: bool,
+    /// The number of simulation ticks in each full day.
+    pub ticks_per_day: u32,
+    /// The number of simulation ticks that have elapsed since midnight.
+    pub ticks_elapsed: u32,
+    /// Whether the length of the day changes with the [`Season`].
+    pub follow_seasons: bool,
+    /// The color that the map is tinted at night.
+    pub night_tint: Color,
+    /// How strongly the map is tinted in the middle of the night, from 0.0 to 1.0.
+    pub max_darkness: f32,
+}
+
+impl Default for DayNightCycle {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ticks_per_day: 24,
+            ticks_elapsed: 0,
+            follow_seasons: true,
+            night_tint: Color::srgb(0.02, 0.03, 0.12),
+            max_darkness: 0.7,
+        }
+    }
+}
+
+impl DayNightCycle {
+    /// How far through the day it is, from 0.0 at midnight to 0.5 at noon.
+    pub fn time_of_day(&self) -> f32 {
+        self.ticks_elapsed as f32 / self.ticks_per_day.max(1) as f32
+    }
+
+    /// How dark it currently is, from 0.0 in full daylight to [`DayNightCycle::max_darkness`].
+    pub fn darkness(&self, season: &Season) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+
+        // Longer days push sunrise earlier and sunset later, which is the same as raising the sun
+        let day_lengthening = match season.kind {
+            _ if !self.follow_seasons => 0.0,
+            SeasonKind::Spring | SeasonKind::Autumn => 0.0,
+            SeasonKind::Summer => 0.25,
+            SeasonKind::Winter => -0.25,
+        };
+        let sun_height = -(self.time_of_day() * std::f32::consts::TAU).cos() + day_lengthening;
+
+        // Twilight fades in just before sunset, and out just after sunrise
+        let daylight = ((sun_height + 0.2) / 0.4).clamp(0.0, 1.0);
+        self.max_darkness * (1.0 - daylight)
+    }
+}
+
+fn advance_time_of_day(mut day_night_cycle: ResMut<DayNightCycle>) {
+    day_night_cycle.ticks_elapsed =
+        (day_night_cycle.ticks_elapsed + 1) % day_night_cycle.ticks_per_day.max(1);
+}
+
+/// Each new map starts at dawn.
+fn reset_time_of_day(mut day_night_cycle: ResMut<DayNightCycle>) {
+    day_night_cycle.ticks_elapsed = day_night_cycle.ticks_per_day / 4;
+}
+
+/// Tints everything drawn beneath it, fading in and out as night falls and the day breaks.
+#[derive(Component)]
+struct NightOverlay;
+
+impl NightOverlay {
+    /// The width and height of the overlay, in pixels, which is enough to cover the largest maps.
+    const SIZE: f32 = 1.0e6;
+    /// How quickly the overlay catches up with the time of day, as a fraction of the gap per second.
+    const FADE_RATE: f32 = 2.0;
+    /// How dark it needs to be before fires start to glow.
+    const GLOW_DARKNESS: f32 = 0.05;
+}
+
+fn spawn_night_overlay(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Night overlay"),
+        NightOverlay,
+        Sprite {
+            color: Color::NONE,
+            custom_size: Some(Vec2::splat(NightOverlay::SIZE)),
+            ..Default::default()
+        },
+        // Tint the tiles and any overlays on them, but leave glowing fires and embers bright
+        Transform::from_xyz(0.0, 0.0, 1.75),
+    ));
+}
+
+/// Smoothly moves the overlay towards the darkness for the current time of day,
+/// since time only advances once per simulation tick.
+fn fade_night_overlay(
+    mut overlay_sprite: Single<&mut Sprite, With<NightOverlay>>,
+    day_night_cycle: Res<DayNightCycle>,
+    season: Res<Season>,
+    time: Res<Time>,
+) {
+    let target = day_night_cycle.darkness(&season);
+    let alpha = overlay_sprite.color.alpha();
+    let fade = 1.0 - (-NightOverlay::FADE_RATE * time.delta_secs()).exp();
+
+    overlay_sprite.color = day_night_cycle
+        .night_tint
+        .with_alpha(alpha.lerp(target, fade));
+}
+
+/// Points to the glow drawn over a burning tile at night, which is a child of the tile.
+#[derive(Component)]
+struct FireGlow(Entity);
+
+/// Draws a glow over each burning tile once it gets dark.
+fn attach_fire_glows(
+    overlay_sprite: Single<&Sprite, With<NightOverlay>>,
+    tile_query: Query<Entity, (With<FireState>, Without<FireGlow>)>,
+    grid_shape: Res<GridShape>,
+    mut commands: Commands,
+) {
+    if overlay_sprite.color.alpha() < NightOverlay::GLOW_DARKNESS {
+        return;
+    }
+
+    for entity in tile_query.iter() {
+        let glow = commands
+            .spawn((
+                Name::new("Fire glow"),
+                Sprite {
+                    color: Color::NONE,
+                    custom_size: Some(grid_shape.tile_size()),
+                    ..Default::default()
+                },
+                // Tiles sit at a depth of zero, so this lands just above the night overlay
+                Transform::from_xyz(0.0, 0.0, 1.85),
+                ChildOf(entity),
+            ))
+            .id();
+
+        commands.entity(entity).insert(FireGlow(glow));
+    }
+}
+
+/// Removes the glow from tiles once their fire goes out, or once the sun comes back up.
+fn detach_fire_glows(
+    overlay_sprite: Single<&Sprite, With<NightOverlay>>,
+    tile_query: Query<(Entity, &FireGlow, Has<FireState>)>,
+    mut commands: Commands,
+) {
+    let daytime = overlay_sprite.color.alpha() < NightOverlay::GLOW_DARKNESS;
+
+    for (entity, fire_glow, burning) in tile_query.iter() {
+        if burning && !daytime {
+            continue;
+        }
+
+        commands.entity(fire_glow.0).despawn();
+        commands.entity(entity).remove::<FireGlow>();
+    }
+}
+
+/// Brightens each glow as the night darkens, so that fires shine through at their full color.
+fn update_fire_glows(
+    overlay_sprite: Single<&Sprite, With<NightOverlay>>,
+    tile_query: Query<(&FireGlow, &FireState)>,
+    mut glow_query: Query<&mut Sprite, Without<NightOverlay>>,
+    palette: Res<Palette>,
+) {
+    let darkness = overlay_sprite.color.alpha();
+
+    for (fire_glow, fire_state) in tile_query.iter() {
+        if let Ok(mut sprite) = glow_query.get_mut(fire_glow.0) {
+            sprite.color = palette
+                .fire_color(fire_state.intensity)
+                .lighter(0.1)
+                .with_alpha(darkness);
+        }
+    }
+}
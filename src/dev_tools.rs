@@ -11,9 +11,30 @@ use clap::Parser;
 
 use crate::{
     SimState,
+    cell_layers::CellLayers,
+    census::TileCensus,
+    composition::CompositionSuccession,
     control_flow::{
-        PauseSimulation, ResetSimulation, SetSimulationTimestep, StepSimulation, UnpauseSimulation,
+        PauseSimulation, ResetSimulation, SetSimulationTimestep, SetupPhase, StepSimulation,
+        UnpauseSimulation,
     },
+    elementary::ElementaryRule,
+    fire_tracking::{FireExtinguished, FireMerged, FireStarted, FireTracker},
+    gpu_simulation::SimulationBackend,
+    graphics::{ChangeHighlight, LayerOverlay, OverlayMode},
+    grid_overlay::GridOverlay,
+    heightmap::Heightmap,
+    landmasses::LandmassConstraint,
+    map_export::ExportMap,
+    map_generation::{MapPreset, MapSeed, RegenerateMap, RerollMap, TerrainGenerator},
+    map_mask::MapMask,
+    painting::PaintTiles,
+    prescribed_burns::StartPrescribedBurn,
+    recorder::{Recorder, RecordingFormat},
+    rulesets::{LifeRule, Ruleset},
+    screenshots::TakeScreenshot,
+    simulation::{LightningStruck, TileKind},
+    spatial_index::{GridShape, IndexAudit, MovementCosts, Position, TileIndex},
 };
 
 pub struct DevToolsPlugin;
@@ -38,10 +59,80 @@ impl Plugin for DevToolsPlugin {
         // The duplication between the various commands and events is intentional,
         // as it allows us to easily trigger the same logic via alternative means.
         app.add_console_command::<ResetCommand, _>(reset_command)
+            .add_console_command::<RegenerateCommand, _>(regenerate_command)
+            .add_console_command::<RerollCommand, _>(reroll_command)
+            .add_console_command::<SetSeedCommand, _>(set_seed_command)
+            .add_console_command::<PresetCommand, _>(preset_command)
+            .add_console_command::<GeneratorCommand, _>(generator_command)
+            .add_console_command::<HeightmapCommand, _>(heightmap_command)
+            .add_console_command::<MaskCommand, _>(mask_command)
+            .add_console_command::<LandmassesCommand, _>(landmasses_command)
+            .add_console_command::<ExportMapCommand, _>(export_map_command)
+            .add_console_command::<ScreenshotCommand, _>(screenshot_command)
+            .add_console_command::<RecordCommand, _>(record_command)
             .add_console_command::<PauseCommand, _>(pause_command)
             .add_console_command::<UnpauseCommand, _>(unpause_command)
             .add_console_command::<StepCommand, _>(step_command)
-            .add_console_command::<SetTimestepCommand, _>(set_timestep_command);
+            .add_console_command::<SetupCommand, _>(setup_command)
+            .add_console_command::<SetTimestepCommand, _>(set_timestep_command)
+            .add_console_command::<FirebreakCommand, _>(firebreak_command)
+            .add_console_command::<BurnCommand, _>(burn_command)
+            .add_console_command::<RouteCommand, _>(route_command)
+            .add_console_command::<RulesetCommand, _>(ruleset_command)
+            .add_console_command::<GridCommand, _>(grid_command)
+            .add_console_command::<BackendCommand, _>(backend_command)
+            .add_console_command::<AuditIndexCommand, _>(audit_index_command)
+            .add_console_command::<ElementaryRuleCommand, _>(elementary_rule_command)
+            .add_console_command::<OverlayCommand, _>(overlay_command)
+            .add_console_command::<OverlayModeCommand, _>(overlay_mode_command)
+            .add_console_command::<GridLinesCommand, _>(grid_lines_command)
+            .add_console_command::<HighlightChangesCommand, _>(highlight_changes_command)
+            .add_console_command::<SetRuleCommand, _>(set_rule_command)
+            .add_console_command::<FiresCommand, _>(fires_command)
+            .add_console_command::<CompositionCommand, _>(composition_command)
+            .add_console_command::<CensusHistoryCommand, _>(census_history_command);
+
+        app.add_systems(Update, (log_lightning_strikes, log_fires));
+    }
+}
+
+/// Logs every lightning strike, which is handy when tuning ignition rates.
+///
+/// These are logged at the debug level to avoid flooding the console.
+fn log_lightning_strikes(mut event_reader: EventReader<LightningStruck>) {
+    for event in event_reader.read() {
+        debug!(
+            "Lightning struck {:?} at ({}, {}). Ignited: {}",
+            event.entity, event.position.x, event.position.y, event.ignited
+        );
+    }
+}
+
+/// Logs the start and end of every tracked fire, and whenever two fires merge.
+fn log_fires(
+    mut started_reader: EventReader<FireStarted>,
+    mut merged_reader: EventReader<FireMerged>,
+    mut extinguished_reader: EventReader<FireExtinguished>,
+) {
+    for event in started_reader.read() {
+        debug!(
+            "Fire {} started at ({}, {}).",
+            event.id.0, event.origin.x, event.origin.y
+        );
+    }
+
+    for event in merged_reader.read() {
+        debug!(
+            "Fire {} merged into fire {}, bringing {} burned tiles with it.",
+            event.id.0, event.into.0, event.area_burned
+        );
+    }
+
+    for event in extinguished_reader.read() {
+        info!(
+            "Fire {} has burned out after {} ticks, burning {} tiles.",
+            event.id.0, event.duration, event.area_burned
+        );
     }
 }
 
@@ -59,6 +150,240 @@ fn reset_command(
     }
 }
 
+/// Regenerates the map from the current seed, producing identical terrain.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "regenerate")]
+struct RegenerateCommand;
+
+fn regenerate_command(
+    mut console_command: ConsoleCommand<RegenerateCommand>,
+    mut event_writer: EventWriter<RegenerateMap>,
+) {
+    if console_command.take().is_some() {
+        event_writer.write(RegenerateMap);
+    }
+}
+
+/// Generates a brand new map from a random seed.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "reroll")]
+struct RerollCommand;
+
+fn reroll_command(
+    mut console_command: ConsoleCommand<RerollCommand>,
+    mut event_writer: EventWriter<RerollMap>,
+) {
+    if console_command.take().is_some() {
+        event_writer.write(RerollMap);
+    }
+}
+
+/// Regenerates the map from the given seed.
+///
+/// Maps generated from the same seed with the same settings are identical.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "set_seed")]
+struct SetSeedCommand {
+    seed: u64,
+}
+
+fn set_seed_command(
+    mut console_command: ConsoleCommand<SetSeedCommand>,
+    mut map_seed: ResMut<MapSeed>,
+    mut event_writer: EventWriter<RegenerateMap>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        // The map is regenerated even if the seed is the same
+        map_seed.0 = command.seed;
+        event_writer.write(RegenerateMap);
+    }
+}
+
+/// Switches to a named set of map generation settings, such as `archipelago` or `plains`, regenerating the map.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "preset")]
+struct PresetCommand {
+    #[arg(value_enum)]
+    preset: MapPreset,
+}
+
+fn preset_command(
+    mut console_command: ConsoleCommand<PresetCommand>,
+    mut map_preset: ResMut<MapPreset>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        // Always marks the preset as changed, so reselecting it undoes any tweaks
+        *map_preset = command.preset;
+    }
+}
+
+/// Switches to a different terrain generator, such as `noise` or `voronoi`, regenerating the map.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "generator")]
+struct GeneratorCommand {
+    #[arg(value_enum)]
+    generator: TerrainGenerator,
+}
+
+fn generator_command(
+    mut console_command: ConsoleCommand<GeneratorCommand>,
+    mut terrain_generator: ResMut<TerrainGenerator>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        terrain_generator.set_if_neq(command.generator);
+    }
+}
+
+/// Regenerates the map using the elevations from a heightmap image in the `assets` folder.
+///
+/// Leave out the path to go back to generating elevation from noise.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "heightmap")]
+struct HeightmapCommand {
+    path: Option<String>,
+    /// Resize the map to match the image, rather than stretching the image to fit the map.
+    #[arg(long)]
+    resize: bool,
+}
+
+fn heightmap_command(
+    mut console_command: ConsoleCommand<HeightmapCommand>,
+    mut heightmap: ResMut<Heightmap>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        heightmap.path = command.path;
+        heightmap.resize_map = command.resize;
+    }
+}
+
+/// Restricts the map to a shape, regenerating the map.
+///
+/// The shape can be `rectangle`, `ellipse`, or the path to a mask image in the `assets` folder.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "mask")]
+struct MaskCommand {
+    shape: String,
+}
+
+fn mask_command(mut console_command: ConsoleCommand<MaskCommand>, mut map_mask: ResMut<MapMask>) {
+    if let Some(Ok(command)) = console_command.take() {
+        let new_mask = match command.shape.as_str() {
+            "rectangle" => MapMask::Rectangle,
+            "ellipse" | "circle" => MapMask::Ellipse,
+            path => MapMask::Image(path.to_string()),
+        };
+
+        // Always marks the mask as changed, so that edited mask images are reloaded
+        *map_mask = new_mask;
+    }
+}
+
+/// Constrains how the land of generated maps is connected, then regenerates the map.
+///
+/// Use `any` to leave the land as generated, `single` for one connected landmass,
+/// or a number for at least that many separate islands.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "landmasses")]
+struct LandmassesCommand {
+    constraint: String,
+}
+
+fn landmasses_command(
+    mut console_command: ConsoleCommand<LandmassesCommand>,
+    mut landmass_constraint: ResMut<LandmassConstraint>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        let new_constraint = match command.constraint.as_str() {
+            "any" => LandmassConstraint::Unconstrained,
+            "single" => LandmassConstraint::SingleLandmass,
+            count => match count.parse() {
+                Ok(minimum) => LandmassConstraint::MinimumIslands(minimum),
+                Err(_) => {
+                    console_command.reply_failed(format!(
+                        "Unknown constraint {count}. Use `any`, `single` or a number of islands"
+                    ));
+                    return;
+                }
+            },
+        };
+
+        *landmass_constraint = new_constraint;
+    }
+}
+
+/// Saves the current map to the `exports` folder, as both a RON file and a PNG image.
+///
+/// The file name defaults to one based on the map seed.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "export_map")]
+struct ExportMapCommand {
+    name: Option<String>,
+}
+
+fn export_map_command(
+    mut console_command: ConsoleCommand<ExportMapCommand>,
+    mut event_writer: EventWriter<ExportMap>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        event_writer.write(ExportMap { name: command.name });
+    }
+}
+
+/// Saves a screenshot of the simulation, without the GUI, to the `screenshots` folder.
+///
+/// The file name defaults to one based on the current time.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "screenshot")]
+struct ScreenshotCommand {
+    name: Option<String>,
+}
+
+fn screenshot_command(
+    mut console_command: ConsoleCommand<ScreenshotCommand>,
+    mut event_writer: EventWriter<TakeScreenshot>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        event_writer.write(TakeScreenshot { name: command.name });
+    }
+}
+
+/// Starts recording a timelapse of the simulation to the `recordings` folder, or stops and saves the current one.
+///
+/// Use `--format gif` to save a single looping GIF rather than a sequence of PNG images,
+/// and `--every` to set how many simulation ticks pass between each frame.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "record")]
+struct RecordCommand {
+    #[arg(long, value_enum)]
+    format: Option<RecordingFormat>,
+    #[arg(long)]
+    every: Option<u32>,
+}
+
+fn record_command(
+    mut console_command: ConsoleCommand<RecordCommand>,
+    mut recorder: ResMut<Recorder>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        if let Some(format) = command.format {
+            recorder.format = format;
+        }
+        if let Some(every) = command.every {
+            recorder.ticks_per_frame = every.max(1);
+        }
+
+        recorder.recording = !recorder.recording;
+        if recorder.recording {
+            console_command.reply(format!(
+                "Recording a {:?} every {} ticks",
+                recorder.format, recorder.ticks_per_frame
+            ));
+        } else {
+            console_command.reply("Stopped recording");
+        }
+    }
+}
+
 /// Pauses the simulation.
 #[derive(Parser, ConsoleCommand)]
 #[command(name = "pause")]
@@ -104,7 +429,7 @@ fn step_command(
                 // If the simulation is paused.
                 event_writer.write(StepSimulation);
             }
-            SimState::Run | SimState::Generate => {
+            SimState::Run | SimState::Generate | SimState::Setup => {
                 // If the simulation is running, we need to pause it first, then step it.
                 // Otherwise it won't be perceived as a step by the user.
                 next_state.set(SimState::Paused);
@@ -114,6 +439,24 @@ fn step_command(
     }
 }
 
+/// Pauses in a setup phase after each map is generated, so that the starting conditions can be painted.
+///
+/// Use `unpause` (or the Start button) to begin the simulation.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "setup")]
+struct SetupCommand {
+    enabled: bool,
+}
+
+fn setup_command(
+    mut console_command: ConsoleCommand<SetupCommand>,
+    mut setup_phase: ResMut<SetupPhase>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        setup_phase.enabled = command.enabled;
+    }
+}
+
 /// Sets the simulation timestep to a specific value in milliseconds.
 ///
 /// Lower values will make the simulation run faster, while higher values will slow it down.
@@ -134,3 +477,391 @@ fn set_timestep_command(
         });
     }
 }
+
+/// Paints a rectangle of firebreak tiles, starting at the given position.
+///
+/// The width and height default to a single tile.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "firebreak")]
+struct FirebreakCommand {
+    x: i32,
+    y: i32,
+    #[arg(default_value_t = 1)]
+    width: i32,
+    #[arg(default_value_t = 1)]
+    height: i32,
+}
+
+fn firebreak_command(
+    mut console_command: ConsoleCommand<FirebreakCommand>,
+    mut event_writer: EventWriter<PaintTiles>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        event_writer.write(PaintTiles {
+            min: Position {
+                x: command.x,
+                y: command.y,
+            },
+            max: Position {
+                x: command.x + command.width - 1,
+                y: command.y + command.height - 1,
+            },
+            kind: TileKind::Firebreak,
+        });
+    }
+}
+
+/// Measures the cheapest overland route between two tiles, using the current [`MovementCosts`].
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "route")]
+struct RouteCommand {
+    start_x: i32,
+    start_y: i32,
+    goal_x: i32,
+    goal_y: i32,
+}
+
+fn route_command(
+    mut console_command: ConsoleCommand<RouteCommand>,
+    tile_query: Query<&TileKind>,
+    tile_index: Res<TileIndex>,
+    grid_shape: Res<GridShape>,
+    movement_costs: Res<MovementCosts>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        let cost = |tile| {
+            let tile_kind = tile_query.get(tile).ok()?;
+            movement_costs.cost(tile_kind)
+        };
+
+        let start = Position {
+            x: command.start_x,
+            y: command.start_y,
+        };
+        let goal = Position {
+            x: command.goal_x,
+            y: command.goal_y,
+        };
+
+        match tile_index.pathfind(start, goal, &grid_shape, cost) {
+            Some(path) => {
+                let total_cost: f32 = path
+                    .iter()
+                    .filter_map(|position| tile_index.get(position))
+                    .filter_map(cost)
+                    .sum();
+                console_command.reply(format!(
+                    "Route found: {} steps, with a total cost of {total_cost:.1}",
+                    path.len()
+                ));
+            }
+            None => console_command.reply_failed("No route between those tiles"),
+        }
+    }
+}
+
+/// Lights a prescribed burn in a rectangular region, starting at the given position.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "burn")]
+struct BurnCommand {
+    x: i32,
+    y: i32,
+    #[arg(default_value_t = 1)]
+    width: i32,
+    #[arg(default_value_t = 1)]
+    height: i32,
+}
+
+fn burn_command(
+    mut console_command: ConsoleCommand<BurnCommand>,
+    mut event_writer: EventWriter<StartPrescribedBurn>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        event_writer.write(StartPrescribedBurn {
+            min: Position {
+                x: command.x,
+                y: command.y,
+            },
+            max: Position {
+                x: command.x + command.width - 1,
+                y: command.y + command.height - 1,
+            },
+        });
+    }
+}
+
+/// Switches to a different ruleset, such as `game-of-life`, regenerating the map.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "ruleset")]
+struct RulesetCommand {
+    #[arg(value_enum)]
+    ruleset: Ruleset,
+}
+
+fn ruleset_command(
+    mut console_command: ConsoleCommand<RulesetCommand>,
+    mut ruleset: ResMut<Ruleset>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        ruleset.set_if_neq(command.ruleset);
+    }
+}
+
+/// Switches between `square` and `hexagonal` tiles, regenerating the map.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "grid")]
+struct GridCommand {
+    #[arg(value_enum)]
+    shape: GridShape,
+}
+
+fn grid_command(
+    mut console_command: ConsoleCommand<GridCommand>,
+    mut grid_shape: ResMut<GridShape>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        grid_shape.set_if_neq(command.shape);
+    }
+}
+
+/// Switches between running the simulation on the `cpu` or the `gpu`.
+///
+/// Only the forest and Drossel-Schwabl rulesets on square grids can run on the GPU.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "backend")]
+struct BackendCommand {
+    #[arg(value_enum)]
+    backend: SimulationBackend,
+}
+
+fn backend_command(
+    mut console_command: ConsoleCommand<BackendCommand>,
+    mut backend: ResMut<SimulationBackend>,
+    ruleset: Res<Ruleset>,
+    grid_shape: Res<GridShape>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        backend.set_if_neq(command.backend);
+
+        if command.backend == SimulationBackend::Gpu
+            && !SimulationBackend::gpu_supports(&ruleset, &grid_shape)
+        {
+            console_command.reply(format!(
+                "The {ruleset:?} ruleset on {grid_shape:?} grids isn't supported on the GPU, so it will keep running on the CPU"
+            ));
+        }
+    }
+}
+
+/// Checks the tile index against the world at the end of every frame, logging any discrepancies.
+///
+/// This is slow on large maps, so only turn it on while hunting for bugs.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "audit_index")]
+struct AuditIndexCommand {
+    enabled: bool,
+}
+
+fn audit_index_command(
+    mut console_command: ConsoleCommand<AuditIndexCommand>,
+    mut index_audit: ResMut<IndexAudit>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        index_audit.enabled = command.enabled;
+    }
+}
+
+/// Sets the rule number (0-255) used by the elementary cellular automaton, such as `30` or `110`.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "elementary_rule")]
+struct ElementaryRuleCommand {
+    number: u8,
+}
+
+fn elementary_rule_command(
+    mut console_command: ConsoleCommand<ElementaryRuleCommand>,
+    mut elementary_rule: ResMut<ElementaryRule>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        elementary_rule.set_if_neq(ElementaryRule {
+            number: command.number,
+        });
+    }
+}
+
+/// Colors the map by the named cell layer, such as `moisture` or `elevation`.
+///
+/// Call with no arguments to turn the overlay off and list the available layers.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "overlay")]
+struct OverlayCommand {
+    layer: Option<String>,
+}
+
+fn overlay_command(
+    mut console_command: ConsoleCommand<OverlayCommand>,
+    mut layer_overlay: ResMut<LayerOverlay>,
+    cell_layers: Res<CellLayers>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        match command.layer {
+            Some(layer) if cell_layers.layer(&layer).is_none() => {
+                console_command.reply_failed(format!(
+                    "Unknown layer {layer}. Available layers: {}",
+                    cell_layers.names().join(", ")
+                ));
+            }
+            Some(layer) => layer_overlay.layer = Some(layer),
+            None => {
+                layer_overlay.layer = None;
+                console_command.reply(format!(
+                    "Overlay off. Available layers: {}",
+                    cell_layers.names().join(", ")
+                ));
+            }
+        }
+    }
+}
+
+/// Shows a heatmap of a commonly used layer, such as `susceptibility` or `distance-to-water`.
+///
+/// Use `none` to turn the overlay off.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "overlay_mode")]
+struct OverlayModeCommand {
+    #[arg(value_enum)]
+    mode: OverlayMode,
+}
+
+fn overlay_mode_command(
+    mut console_command: ConsoleCommand<OverlayModeCommand>,
+    mut overlay_mode: ResMut<OverlayMode>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        *overlay_mode = command.mode;
+    }
+}
+
+/// Toggles the grid lines, along with the coordinates of each tile when zoomed in.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "grid_lines")]
+struct GridLinesCommand;
+
+fn grid_lines_command(
+    mut console_command: ConsoleCommand<GridLinesCommand>,
+    mut grid_overlay: ResMut<GridOverlay>,
+) {
+    if console_command.take().is_some() {
+        grid_overlay.enabled = !grid_overlay.enabled;
+    }
+}
+
+/// Toggles outlining every tile whose kind changed in the most recent simulation step.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "highlight_changes")]
+struct HighlightChangesCommand;
+
+fn highlight_changes_command(
+    mut console_command: ConsoleCommand<HighlightChangesCommand>,
+    mut change_highlight: ResMut<ChangeHighlight>,
+) {
+    if console_command.take().is_some() {
+        change_highlight.enabled = !change_highlight.enabled;
+    }
+}
+
+/// Runs any life-like cellular automaton, given a rule string such as `B36/S23`.
+///
+/// This switches to the Game of Life ruleset if it is not already active.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "set_rule")]
+struct SetRuleCommand {
+    rule: LifeRule,
+}
+
+fn set_rule_command(
+    mut console_command: ConsoleCommand<SetRuleCommand>,
+    mut life_rule: ResMut<LifeRule>,
+    mut ruleset: ResMut<Ruleset>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        life_rule.set_if_neq(command.rule);
+        ruleset.set_if_neq(Ruleset::GameOfLife);
+    }
+}
+
+/// Summarizes the fires that are burning, and those that have burned out since the map was generated.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "fires")]
+struct FiresCommand;
+
+fn fires_command(
+    mut console_command: ConsoleCommand<FiresCommand>,
+    fire_tracker: Res<FireTracker>,
+) {
+    if let Some(Ok(FiresCommand)) = console_command.take() {
+        let tick = fire_tracker.tick();
+
+        let mut active_fires: Vec<_> = fire_tracker.active_fires().collect();
+        active_fires.sort_unstable_by_key(|record| record.id);
+        for record in &active_fires {
+            console_command.reply(format!(
+                "Fire {}: started at ({}, {}) on tick {}, burning for {} ticks across {} tiles",
+                record.id.0,
+                record.origin.x,
+                record.origin.y,
+                record.start_tick,
+                record.duration(tick),
+                record.area_burned
+            ));
+        }
+
+        let extinguished_fires = fire_tracker.extinguished_fires();
+        let largest_fire = extinguished_fires
+            .iter()
+            .max_by_key(|record| record.area_burned);
+        console_command.reply(format!(
+            "{} active fires, {} recently burned out. Largest recent burned out fire: {} tiles",
+            active_fires.len(),
+            extinguished_fires.len(),
+            largest_fire.map_or(0, |record| record.area_burned)
+        ));
+    }
+}
+
+/// Switches between whole-tile succession and the gradual sub-tile composition model.
+///
+/// This regenerates the map.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "composition")]
+struct CompositionCommand {
+    enabled: bool,
+}
+
+fn composition_command(
+    mut console_command: ConsoleCommand<CompositionCommand>,
+    mut composition_succession: ResMut<CompositionSuccession>,
+) {
+    // Only flag the resource as changed if the setting actually changed, since that regenerates the map
+    if let Some(Ok(command)) = console_command.take()
+        && composition_succession.enabled != command.enabled
+    {
+        composition_succession.enabled = command.enabled;
+    }
+}
+
+/// Sets how many ticks of tile counts are kept for the population chart.
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "census_history")]
+struct CensusHistoryCommand {
+    ticks: usize,
+}
+
+fn census_history_command(
+    mut console_command: ConsoleCommand<CensusHistoryCommand>,
+    mut tile_census: ResMut<TileCensus>,
+) {
+    if let Some(Ok(command)) = console_command.take() {
+        tile_census.history_length = command.ticks;
+    }
+}
@@ -0,0 +1,114 @@
+//! Schedules the large, landscape-scale disturbances that strike the map at random.
+//!
+//! Rather than each disturbance rolling its own dice every tick,
+//! the [`DisturbanceScheduler`] draws every kind of disturbance from a single table of annual probabilities,
+//! and sends a [`DisturbanceStarted`] event for the systems responsible for each kind to act on.
+//! This keeps the frequency of every disturbance in one place, where it's easy to compare and tune.
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use rand::Rng;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::climate::Season;
+use crate::control_flow::Simulation;
+use crate::rulesets::ForestClimateSystems;
+use crate::tile_rng::{TileRng, TileRngStream, advance_tile_rng};
+
+pub struct DisturbancePlugin;
+
+impl Plugin for DisturbancePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DisturbanceKind>()
+            .init_resource::<DisturbanceScheduler>()
+            .register_type::<DisturbanceScheduler>()
+            .add_event::<DisturbanceStarted>()
+            .add_systems(
+                Simulation,
+                schedule_disturbances
+                    .after(advance_tile_rng)
+                    .in_set(ForestClimateSystems),
+            );
+    }
+}
+
+/// The kinds of disturbance that are drawn by the [`DisturbanceScheduler`].
+#[derive(Reflect, PartialEq, Eq, Hash, Debug, Clone, Copy, EnumIter)]
+pub enum DisturbanceKind {
+    /// Strong winds that blow down trees across a swath of the map.
+    Windstorm,
+    /// A prolonged dry spell, which raises flammability and suppresses succession.
+    Drought,
+    /// A rainstorm that puts out fires and soaks the ground.
+    HeavyRain,
+    /// A fresh outbreak of bark beetles in mature forest.
+    InsectOutbreak,
+}
+
+/// Controls how often each kind of disturbance strikes the map.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct DisturbanceScheduler {
+    /// The probability that each kind of disturbance begins at least once over the course of a year.
+    ///
+    /// Missing entries never occur.
+    annual_probabilities: HashMap<DisturbanceKind, f64>,
+}
+
+impl DisturbanceScheduler {
+    /// Returns the probability that the given kind of disturbance begins on any single tick,
+    /// given the number of ticks in a year.
+    pub fn tick_probability(&self, kind: &DisturbanceKind, ticks_per_year: u32) -> f64 {
+        let annual_probability = self
+            .annual_probabilities
+            .get(kind)
+            .cloned()
+            .unwrap_or_default()
+            .clamp(0.0, 1.0);
+
+        // The chance of at least one event in a year is 1 - (1 - p)^n, so we solve for p
+        1.0 - (1.0 - annual_probability).powf(1.0 / ticks_per_year.max(1) as f64)
+    }
+}
+
+impl Default for DisturbanceScheduler {
+    fn default() -> Self {
+        let mut annual_probabilities = HashMap::new();
+        annual_probabilities.insert(DisturbanceKind::Windstorm, 0.3);
+        annual_probabilities.insert(DisturbanceKind::Drought, 0.55);
+        annual_probabilities.insert(DisturbanceKind::HeavyRain, 0.87);
+        annual_probabilities.insert(DisturbanceKind::InsectOutbreak, 0.5);
+
+        Self {
+            annual_probabilities,
+        }
+    }
+}
+
+/// An event that is sent whenever the [`DisturbanceScheduler`] decides that a disturbance begins.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisturbanceStarted {
+    pub kind: DisturbanceKind,
+}
+
+/// Rolls for each kind of disturbance, sending a [`DisturbanceStarted`] event for each one that begins.
+///
+/// Systems that respond to these events should be ordered after this system,
+/// so that they see the events sent on the same tick.
+pub fn schedule_disturbances(
+    scheduler: Res<DisturbanceScheduler>,
+    season: Res<Season>,
+    tile_rng: Res<TileRng>,
+    mut event_writer: EventWriter<DisturbanceStarted>,
+) {
+    let ticks_per_year = season.ticks_per_year();
+    let mut rng = tile_rng.for_map(TileRngStream::DisturbanceSchedule);
+
+    for kind in DisturbanceKind::iter() {
+        let probability = scheduler.tick_probability(&kind, ticks_per_year);
+        if rng.random_bool(probability) {
+            event_writer.write(DisturbanceStarted { kind });
+        }
+    }
+}
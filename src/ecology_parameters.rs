@@ -0,0 +1,207 @@
+//! Loads the ecological parameters of the forest simulation from a RON asset file.
+//!
+//! Editing `assets/forest.ecology.ron` is much faster than recompiling whenever a table needs tweaking.
+//! When Bevy's `file_watcher` feature is enabled (`cargo run --features bevy/file_watcher`),
+//! saving the file updates the parameters of the running simulation without regenerating the map.
+//!
+//! Every field in the file is optional: anything that is left out keeps its hard-coded default.
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::map_generation::{InitialWeights, apply_map_preset};
+use crate::rulesets::Ruleset;
+use crate::simulation::{FireSusceptibility, TileKind, TransitionProbabilities};
+
+pub struct EcologyParametersPlugin;
+
+impl Plugin for EcologyParametersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<EcologyParameters>()
+            .init_asset_loader::<EcologyParametersLoader>()
+            .add_systems(Startup, load_ecology_parameters)
+            .add_systems(
+                Update,
+                // Choosing a map preset takes priority over the initial weights in the file
+                apply_ecology_parameters.before(apply_map_preset),
+            );
+    }
+}
+
+/// Overrides for the hard-coded ecological parameters, as read from a `.ecology.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct EcologyParameters {
+    /// Replaces the [`TransitionProbabilities`] of each listed tile kind.
+    pub transition_probabilities: HashMap<TileKind, Vec<(TileKind, f32)>>,
+    /// Replaces the minimum ages of the listed transitions, keyed by the current and target tile kinds.
+    pub minimum_ages: Vec<((TileKind, TileKind), u32)>,
+    /// Replaces the listed fields of the [`FireSusceptibility`].
+    pub fire_susceptibility: FireSusceptibilityOverrides,
+    /// Replaces the [`InitialWeights`] used by the forest ruleset.
+    ///
+    /// These only take effect the next time the map is generated,
+    /// and are replaced by the weights of the [`MapPreset`](crate::map_generation::MapPreset)
+    /// whenever the preset or the ruleset changes.
+    pub initial_weights: Option<Vec<(TileKind, f32)>>,
+}
+
+/// Overrides for the fields of [`FireSusceptibility`].
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct FireSusceptibilityOverrides {
+    pub base_susceptibility: Option<f64>,
+    pub tile_susceptibility: HashMap<TileKind, f64>,
+    pub moisture_dampening: Option<f64>,
+}
+
+#[derive(Default)]
+struct EcologyParametersLoader;
+
+impl AssetLoader for EcologyParametersLoader {
+    type Asset = EcologyParameters;
+    type Settings = ();
+    type Error = EcologyParametersLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ecology.ron"]
+    }
+}
+
+/// The ways in which loading an [`EcologyParameters`] file can fail.
+#[derive(Debug)]
+enum EcologyParametersLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl std::fmt::Display for EcologyParametersLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EcologyParametersLoaderError::Io(error) => {
+                write!(f, "could not read ecology parameters: {error}")
+            }
+            EcologyParametersLoaderError::Ron(error) => {
+                write!(f, "could not parse ecology parameters: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EcologyParametersLoaderError {}
+
+impl From<std::io::Error> for EcologyParametersLoaderError {
+    fn from(error: std::io::Error) -> Self {
+        EcologyParametersLoaderError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for EcologyParametersLoaderError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        EcologyParametersLoaderError::Ron(error)
+    }
+}
+
+/// Keeps the ecology parameters loaded, so that they can be hot-reloaded.
+#[derive(Resource)]
+struct EcologyParametersHandle(Handle<EcologyParameters>);
+
+fn load_ecology_parameters(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(EcologyParametersHandle(
+        asset_server.load("forest.ecology.ron"),
+    ));
+}
+
+/// Resets the ecological parameters to their defaults, then applies the overrides from the asset file.
+///
+/// This only runs when the file is (re)loaded,
+/// so changes made while the app is running (such as in the inspector) are kept until the file is edited again.
+fn apply_ecology_parameters(
+    mut asset_events: EventReader<AssetEvent<EcologyParameters>>,
+    handle: Option<Res<EcologyParametersHandle>>,
+    ecology_parameters: Res<Assets<EcologyParameters>>,
+    ruleset: Res<Ruleset>,
+    mut transition_probabilities: ResMut<TransitionProbabilities>,
+    mut fire_susceptibility: ResMut<FireSusceptibility>,
+    mut initial_weights: ResMut<InitialWeights>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    let mut first_load = false;
+    let mut asset_changed = false;
+    for event in asset_events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } if *id == handle.0.id() => {
+                first_load = true;
+                asset_changed = true;
+            }
+            AssetEvent::Modified { id } if *id == handle.0.id() => asset_changed = true,
+            _ => {}
+        }
+    }
+
+    if !asset_changed {
+        return;
+    }
+
+    let Some(parameters) = ecology_parameters.get(&handle.0) else {
+        return;
+    };
+
+    info!("Applying ecology parameters");
+
+    *transition_probabilities = TransitionProbabilities::default();
+    for (tile_kind, probabilities) in &parameters.transition_probabilities {
+        transition_probabilities
+            .probabilities
+            .insert(*tile_kind, probabilities.clone());
+    }
+    for (transition, minimum_age) in &parameters.minimum_ages {
+        transition_probabilities
+            .minimum_ages
+            .insert(*transition, *minimum_age);
+    }
+
+    *fire_susceptibility = FireSusceptibility::default();
+    let overrides = &parameters.fire_susceptibility;
+    if let Some(base_susceptibility) = overrides.base_susceptibility {
+        fire_susceptibility.base_susceptibility = base_susceptibility;
+    }
+    for (tile_kind, susceptibility) in &overrides.tile_susceptibility {
+        fire_susceptibility
+            .tile_susceptibility
+            .insert(*tile_kind, *susceptibility);
+    }
+    if let Some(moisture_dampening) = overrides.moisture_dampening {
+        fire_susceptibility.moisture_dampening = moisture_dampening;
+    }
+
+    if *ruleset == Ruleset::Forest
+        && let Some(weights) = &parameters.initial_weights
+    {
+        // The first map is generated before the file finishes loading, so it needs to be regenerated.
+        // Changing the initial weights normally regenerates the map,
+        // but later tweaks to the file shouldn't throw away the current run.
+        if first_load {
+            initial_weights.weights = weights.clone();
+        } else {
+            initial_weights.bypass_change_detection().weights = weights.clone();
+        }
+    }
+}
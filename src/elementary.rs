@@ -0,0 +1,163 @@
+//! Elementary cellular automata, such as the famous Rule 30 and Rule 110.
+//!
+//! These are one-dimensional automata: each generation is a single row of cells,
+//! and each cell's next state depends only on itself and its left and right neighbors.
+//! The map is used as a space-time diagram: the first generation is the top row,
+//! and each tick fills in the row below it with the next generation.
+
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+
+use crate::SimState;
+use crate::control_flow::Simulation;
+use crate::map_generation::{GeneratedMapSize, MapSize};
+use crate::rulesets::{Ruleset, ruleset_is};
+use crate::simulation::TileKind;
+use crate::spatial_index::{Position, Tile, TileIndex};
+
+pub struct ElementaryPlugin;
+
+impl Plugin for ElementaryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ElementaryRule>()
+            .register_type::<ElementaryRule>()
+            .init_resource::<ElementaryGeneration>()
+            .register_type::<ElementaryGeneration>()
+            .add_systems(
+                OnExit(SimState::Generate),
+                seed_first_generation.run_if(ruleset_is(Ruleset::Elementary)),
+            )
+            .add_systems(
+                Simulation,
+                advance_generation.run_if(ruleset_is(Ruleset::Elementary)),
+            )
+            .add_systems(
+                Update,
+                regenerate_when_rule_changes
+                    .run_if(resource_changed::<ElementaryRule>)
+                    .run_if(ruleset_is(Ruleset::Elementary)),
+            );
+    }
+}
+
+/// The Wolfram code of the elementary cellular automaton to run.
+///
+/// Each of the eight possible neighborhoods (left, center, right) is read as a three-bit number,
+/// and the corresponding bit of the rule number determines whether the cell below is alive.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct ElementaryRule {
+    pub number: u8,
+}
+
+impl Default for ElementaryRule {
+    fn default() -> Self {
+        // Rule 110 is Turing complete, and produces a lovely mix of order and chaos
+        Self { number: 110 }
+    }
+}
+
+impl ElementaryRule {
+    /// Returns whether a cell with the given neighborhood is alive in the next generation.
+    pub fn next(&self, left: bool, center: bool, right: bool) -> bool {
+        let pattern = (left as u8) << 2 | (center as u8) << 1 | right as u8;
+        (self.number >> pattern) & 1 == 1
+    }
+}
+
+/// The number of generations that have been computed since the map was generated.
+///
+/// Generation 0 is the top row of the map.
+#[derive(Resource, Reflect, Debug, Default)]
+#[reflect(Resource)]
+struct ElementaryGeneration(i32);
+
+impl ElementaryGeneration {
+    /// The row of the map that holds the most recent generation.
+    fn row(&self, map_size: &MapSize) -> i32 {
+        map_size.height - 1 - self.0
+    }
+}
+
+/// Starts the diagram off with a single live cell in the middle of the top row.
+fn seed_first_generation(
+    mut generation: ResMut<ElementaryGeneration>,
+    map_size: Res<GeneratedMapSize>,
+    tile_index: Res<TileIndex>,
+    mut tile_query: Query<&mut TileKind, With<Tile>>,
+) {
+    generation.0 = 0;
+
+    let center = Position {
+        x: map_size.width / 2,
+        y: generation.row(&map_size),
+    };
+
+    if let Some(entity) = tile_index.get(&center)
+        && let Ok(mut tile_kind) = tile_query.get_mut(entity)
+    {
+        *tile_kind = TileKind::Alive;
+    }
+}
+
+/// Computes the next generation from the most recent row, and writes it into the row below.
+///
+/// The left and right edges of the map wrap around.
+/// Once the bottom of the map is reached, the diagram is complete and nothing more happens.
+#[hot]
+fn advance_generation(
+    rule: Res<ElementaryRule>,
+    mut generation: ResMut<ElementaryGeneration>,
+    map_size: Res<GeneratedMapSize>,
+    tile_index: Res<TileIndex>,
+    mut tile_query: Query<&mut TileKind, With<Tile>>,
+) {
+    let current_row = generation.row(&map_size);
+    if current_row <= 0 || map_size.width <= 0 {
+        return;
+    }
+
+    let current_generation: Vec<bool> = (0..map_size.width)
+        .map(|x| {
+            tile_index
+                .get(&Position { x, y: current_row })
+                .and_then(|entity| tile_query.get(entity).ok())
+                .is_some_and(|tile_kind| *tile_kind == TileKind::Alive)
+        })
+        .collect();
+
+    for x in 0..map_size.width {
+        let left = current_generation[(x - 1).rem_euclid(map_size.width) as usize];
+        let center = current_generation[x as usize];
+        let right = current_generation[(x + 1).rem_euclid(map_size.width) as usize];
+
+        let Some(entity) = tile_index.get(&Position {
+            x,
+            y: current_row - 1,
+        }) else {
+            continue;
+        };
+
+        if let Ok(mut tile_kind) = tile_query.get_mut(entity) {
+            *tile_kind = if rule.next(left, center, right) {
+                TileKind::Alive
+            } else {
+                TileKind::Dead
+            };
+        }
+    }
+
+    generation.0 += 1;
+}
+
+/// The old diagram doesn't make sense under a new rule, so start over.
+fn regenerate_when_rule_changes(
+    rule: Res<ElementaryRule>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    info!(
+        "Elementary rule changed to {}, regenerating map",
+        rule.number
+    );
+    next_state.set(SimState::Generate);
+}
@@ -0,0 +1,276 @@
+//! Purely visual effects that make fires stand out, such as glowing embers rising from burning tiles.
+//!
+//! Burning tiles also give off puffs of smoke, which drift with the [`Wind`] and slowly dissipate.
+//! Where a fire has been burning for a while, the smoke builds up into a plume that shows which way the wind is blowing.
+//!
+//! None of this affects the simulation: effects draw their random numbers from their own generator,
+//! so turning them on or off never changes how a fire spreads.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::climate::Wind;
+use crate::simulation::{FireIntensity, FireState};
+use crate::spatial_index::Position;
+
+pub struct FireEffectsPlugin;
+
+impl Plugin for FireEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                attach_fire_emitters,
+                detach_fire_emitters,
+                emit_embers,
+                animate_embers,
+                emit_smoke,
+                drift_smoke,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Spawns [`Ember`]s and [`SmokePuff`]s from a tile while it is on fire.
+///
+/// This is added to each tile when it catches fire, and removed once the fire goes out.
+#[derive(Component, Default)]
+struct FireEmitter {
+    /// The fraction of an ember that is waiting to be spawned, carried over between frames.
+    pending: f32,
+    /// The fraction of a puff of smoke that is waiting to be spawned, carried over between frames.
+    pending_smoke: f32,
+}
+
+impl FireIntensity {
+    /// How many embers rise from a tile burning at this intensity each second.
+    fn ember_rate(&self) -> f32 {
+        match self {
+            FireIntensity::Smoldering => 1.0,
+            FireIntensity::Burning => 4.0,
+            FireIntensity::CrownFire => 10.0,
+        }
+    }
+
+    /// How many puffs of smoke rise from a tile burning at this intensity each second.
+    ///
+    /// Smoldering fires burn inefficiently, so they are nearly as smoky as much hotter ones.
+    fn smoke_rate(&self) -> f32 {
+        match self {
+            FireIntensity::Smoldering => 0.6,
+            FireIntensity::Burning => 0.8,
+            FireIntensity::CrownFire => 1.2,
+        }
+    }
+}
+
+/// A small glowing particle that drifts upwards from a burning tile, fading and shrinking as it goes.
+#[derive(Component)]
+struct Ember {
+    /// How fast the ember is moving, in pixels per second.
+    velocity: Vec2,
+    /// The color the ember started out with.
+    color: Color,
+    lifetime: Timer,
+}
+
+impl Ember {
+    /// The most embers that can exist at once, to keep large fires cheap to draw.
+    const MAX_EMBERS: usize = 2000;
+    /// How long each ember lasts, in seconds.
+    const LIFETIME: f32 = 1.2;
+    /// The width and height of a freshly spawned ember, in pixels.
+    const SIZE: f32 = 4.0;
+}
+
+fn attach_fire_emitters(
+    tile_query: Query<Entity, (With<FireState>, Without<FireEmitter>)>,
+    mut commands: Commands,
+) {
+    for entity in tile_query.iter() {
+        commands.entity(entity).insert(FireEmitter::default());
+    }
+}
+
+fn detach_fire_emitters(
+    tile_query: Query<Entity, (With<FireEmitter>, Without<FireState>)>,
+    mut commands: Commands,
+) {
+    for entity in tile_query.iter() {
+        commands.entity(entity).remove::<FireEmitter>();
+    }
+}
+
+/// Spawns embers from each burning tile, with more intense fires throwing off more of them.
+fn emit_embers(
+    mut emitter_query: Query<(
+        &mut FireEmitter,
+        &FireState,
+        &GlobalTransform,
+        &InheritedVisibility,
+    )>,
+    ember_query: Query<(), With<Ember>>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let mut rng = rand::rng();
+    let mut embers = ember_query.iter().count();
+
+    for (mut emitter, fire_state, transform, visibility) in emitter_query.iter_mut() {
+        emitter.pending += fire_state.intensity.ember_rate() * time.delta_secs();
+
+        // Tiles in hidden chunks still keep time, but there's no point drawing their embers
+        if !visibility.get() {
+            emitter.pending = emitter.pending.fract();
+            continue;
+        }
+
+        while emitter.pending >= 1.0 && embers < Ember::MAX_EMBERS {
+            emitter.pending -= 1.0;
+            embers += 1;
+
+            let half_tile = Position::PIXELS_PER_TILE / 2.0;
+            let offset = Vec2::new(
+                rng.random_range(-half_tile..half_tile),
+                rng.random_range(-half_tile..half_tile),
+            );
+            let velocity = Vec2::new(rng.random_range(-8.0..8.0), rng.random_range(16.0..40.0));
+            let color = fire_state.intensity.color().lighter(0.2);
+
+            // Draw the embers above the tiles and any overlays on them
+            let translation = transform.translation() + offset.extend(2.0);
+
+            commands.spawn((
+                Name::new("Ember"),
+                Ember {
+                    velocity,
+                    color,
+                    lifetime: Timer::from_seconds(Ember::LIFETIME, TimerMode::Once),
+                },
+                Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(Ember::SIZE)),
+                    ..Default::default()
+                },
+                Transform::from_translation(translation),
+            ));
+        }
+    }
+}
+
+fn animate_embers(
+    mut ember_query: Query<(Entity, &mut Ember, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, mut ember, mut transform, mut sprite) in ember_query.iter_mut() {
+        ember.lifetime.tick(time.delta());
+
+        if ember.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let remaining = ember.lifetime.fraction_remaining();
+        transform.translation += (ember.velocity * time.delta_secs()).extend(0.0);
+        transform.scale = Vec3::splat(remaining);
+        sprite.color = ember.color.with_alpha(remaining);
+    }
+}
+
+/// A translucent puff of smoke, which is carried along by the [`Wind`] while it spreads out and fades away.
+#[derive(Component)]
+struct SmokePuff {
+    lifetime: Timer,
+}
+
+impl SmokePuff {
+    /// The most puffs of smoke that can exist at once.
+    const MAX_PUFFS: usize = 1500;
+    /// How long each puff lasts, in seconds.
+    const LIFETIME: f32 = 6.0;
+    /// The width and height of a freshly spawned puff, in pixels.
+    const SIZE: f32 = 24.0;
+    /// How much larger each puff grows by the time it disappears.
+    const GROWTH: f32 = 2.5;
+    /// How far a puff drifts each second in a wind of speed 1.0, in pixels.
+    const DRIFT_SPEED: f32 = 24.0;
+    /// The color of a fresh puff of smoke.
+    const COLOR: Color = Color::srgba(0.35, 0.35, 0.35, 0.35);
+}
+
+fn emit_smoke(
+    mut emitter_query: Query<(
+        &mut FireEmitter,
+        &FireState,
+        &GlobalTransform,
+        &InheritedVisibility,
+    )>,
+    smoke_query: Query<(), With<SmokePuff>>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let mut rng = rand::rng();
+    let mut puffs = smoke_query.iter().count();
+
+    for (mut emitter, fire_state, transform, visibility) in emitter_query.iter_mut() {
+        emitter.pending_smoke += fire_state.intensity.smoke_rate() * time.delta_secs();
+
+        if !visibility.get() {
+            emitter.pending_smoke = emitter.pending_smoke.fract();
+            continue;
+        }
+
+        while emitter.pending_smoke >= 1.0 && puffs < SmokePuff::MAX_PUFFS {
+            emitter.pending_smoke -= 1.0;
+            puffs += 1;
+
+            let half_tile = Position::PIXELS_PER_TILE / 2.0;
+            let offset = Vec2::new(
+                rng.random_range(-half_tile..half_tile),
+                rng.random_range(-half_tile..half_tile),
+            );
+
+            // Smoke hangs above the embers
+            let translation = transform.translation() + offset.extend(3.0);
+
+            commands.spawn((
+                Name::new("Smoke"),
+                SmokePuff {
+                    lifetime: Timer::from_seconds(SmokePuff::LIFETIME, TimerMode::Once),
+                },
+                Sprite {
+                    color: SmokePuff::COLOR,
+                    custom_size: Some(Vec2::splat(SmokePuff::SIZE)),
+                    ..Default::default()
+                },
+                Transform::from_translation(translation),
+            ));
+        }
+    }
+}
+
+/// Carries each puff of smoke downwind, growing and fading it until it dissipates.
+fn drift_smoke(
+    mut smoke_query: Query<(Entity, &mut SmokePuff, &mut Transform, &mut Sprite)>,
+    wind: Res<Wind>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let drift = wind.direction.normalize_or_zero() * wind.speed * SmokePuff::DRIFT_SPEED;
+
+    for (entity, mut puff, mut transform, mut sprite) in smoke_query.iter_mut() {
+        puff.lifetime.tick(time.delta());
+
+        if puff.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let remaining = puff.lifetime.fraction_remaining();
+        transform.translation += (drift * time.delta_secs()).extend(0.0);
+        transform.scale = Vec3::splat(1.0.lerp(SmokePuff::GROWTH, puff.lifetime.fraction()));
+        sprite.color = SmokePuff::COLOR.with_alpha(SmokePuff::COLOR.alpha() * remaining);
+    }
+}
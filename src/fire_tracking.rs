@@ -0,0 +1,299 @@
+//! Tracks individual fires from ignition until they burn out.
+//!
+//! Each contiguous patch of burning tiles is treated as a single fire, and given a unique [`FireId`].
+//! The [`FireTracker`] keeps a record of where and when each fire started and how much ground it covered,
+//! which is the foundation for fire statistics and exporting data for further analysis.
+
+use std::collections::VecDeque;
+
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+
+use crate::SimState;
+use crate::control_flow::Simulation;
+use crate::rulesets::{ForestSystems, Ruleset, ruleset_is};
+use crate::simulation::TileKind;
+use crate::spatial_index::{Position, Tile, TileIndex};
+
+pub struct FireTrackingPlugin;
+
+impl Plugin for FireTrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FireTracker>()
+            .register_type::<FireTracker>()
+            .register_type::<FireId>()
+            .add_event::<FireStarted>()
+            .add_event::<FireExtinguished>()
+            .add_event::<FireMerged>()
+            .add_systems(OnEnter(SimState::Generate), reset_fire_tracker)
+            .add_systems(
+                Simulation,
+                track_fires
+                    .after(ForestSystems)
+                    .run_if(ruleset_is(Ruleset::Forest)),
+            );
+    }
+}
+
+/// A unique identifier for a single fire, shared by every tile that it is currently burning.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FireId(pub u32);
+
+/// Everything we know about a single fire.
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct FireRecord {
+    pub id: FireId,
+    /// The simulation tick on which the fire was first seen.
+    pub start_tick: u64,
+    /// The first tile that was seen burning.
+    pub origin: Position,
+    /// The total number of tiles that this fire has burned, including those still burning.
+    pub area_burned: u32,
+    /// The simulation tick on which the last of the fire went out, or `None` if it is still burning.
+    pub end_tick: Option<u64>,
+}
+
+impl FireRecord {
+    /// The number of ticks that the fire burned for, or has burned for so far if it is still active.
+    pub fn duration(&self, current_tick: u64) -> u64 {
+        self.end_tick.unwrap_or(current_tick) - self.start_tick
+    }
+}
+
+/// Keeps a record of the active and most recently extinguished fires since the map was generated.
+///
+/// When two fires burn into each other, they merge: the older fire keeps burning,
+/// and absorbs the area burned by the younger one.
+/// A fire that splits into several patches keeps the same ID for all of them.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct FireTracker {
+    /// The number of simulation ticks that have been tracked since the map was generated.
+    tick: u64,
+    /// The ID that will be given to the next new fire.
+    next_id: u32,
+    /// The fires that are currently burning.
+    active: HashMap<FireId, FireRecord>,
+    /// The most recent fires to have burned out, in the order that they went out.
+    extinguished: VecDeque<FireRecord>,
+    /// The maximum number of burned out fires that are remembered.
+    pub extinguished_length: usize,
+}
+
+impl Default for FireTracker {
+    fn default() -> Self {
+        Self {
+            tick: 0,
+            next_id: 0,
+            active: HashMap::default(),
+            extinguished: VecDeque::new(),
+            extinguished_length: 1000,
+        }
+    }
+}
+
+impl FireTracker {
+    /// The number of simulation ticks that have been tracked since the map was generated.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns the records of every fire that is currently burning.
+    pub fn active_fires(&self) -> impl Iterator<Item = &FireRecord> {
+        self.active.values()
+    }
+
+    /// Returns the records of the most recent fires to have burned out, in the order that they went out.
+    pub fn extinguished_fires(&self) -> &VecDeque<FireRecord> {
+        &self.extinguished
+    }
+
+    fn start_fire(&mut self, origin: Position) -> FireId {
+        let id = FireId(self.next_id);
+        self.next_id += 1;
+
+        self.active.insert(
+            id,
+            FireRecord {
+                id,
+                start_tick: self.tick,
+                origin,
+                area_burned: 0,
+                end_tick: None,
+            },
+        );
+
+        id
+    }
+}
+
+/// Sent when a new fire is first seen burning.
+#[derive(Event, Debug)]
+pub struct FireStarted {
+    pub id: FireId,
+    /// The first tile that was seen burning.
+    pub origin: Position,
+}
+
+/// Sent when the last tile of a fire stops burning.
+#[derive(Event, Debug)]
+pub struct FireExtinguished {
+    pub id: FireId,
+    /// The total number of tiles that the fire burned.
+    pub area_burned: u32,
+    /// The number of ticks that the fire burned for.
+    pub duration: u64,
+}
+
+/// Sent when a fire burns into an older fire, and is absorbed by it.
+///
+/// The merged fire never sends a [`FireExtinguished`] event: its area is counted towards the fire it merged into.
+#[derive(Event, Debug)]
+pub struct FireMerged {
+    /// The younger fire, which no longer exists.
+    pub id: FireId,
+    /// The older fire, which carries on burning.
+    pub into: FireId,
+    /// The number of tiles that the younger fire had burned before it merged.
+    pub area_burned: u32,
+}
+
+fn reset_fire_tracker(mut fire_tracker: ResMut<FireTracker>) {
+    *fire_tracker = FireTracker {
+        extinguished_length: fire_tracker.extinguished_length,
+        ..default()
+    };
+}
+
+/// Groups burning tiles into contiguous fires, updating the [`FireTracker`] and the [`FireId`] of each tile.
+///
+/// Tiles are considered to be part of the same fire if they touch, including diagonally.
+#[hot]
+fn track_fires(
+    tile_query: Query<(Entity, &Position, &TileKind, Option<&FireId>), With<Tile>>,
+    tile_index: Res<TileIndex>,
+    mut fire_tracker: ResMut<FireTracker>,
+    mut fire_started: EventWriter<FireStarted>,
+    mut fire_extinguished: EventWriter<FireExtinguished>,
+    mut fire_merged: EventWriter<FireMerged>,
+    mut commands: Commands,
+) {
+    fire_tracker.tick += 1;
+
+    let mut burning: HashMap<Position, (Entity, Option<FireId>)> = HashMap::new();
+    for (entity, position, tile_kind, fire_id) in tile_query.iter() {
+        if *tile_kind == TileKind::Fire {
+            burning.insert(*position, (entity, fire_id.copied()));
+        } else if fire_id.is_some() {
+            commands.entity(entity).remove::<FireId>();
+        }
+    }
+
+    let mut visited: HashSet<Position> = HashSet::new();
+    let mut still_burning: HashSet<FireId> = HashSet::new();
+    // Fires that merged into an older fire earlier this tick, so that their other patches follow along
+    let mut merged_into: HashMap<FireId, FireId> = HashMap::new();
+
+    // Visiting the tiles in order ensures that new fires are numbered consistently
+    let mut positions: Vec<Position> = burning.keys().copied().collect();
+    positions.sort_unstable_by_key(|position| (position.y, position.x));
+
+    for start in positions {
+        if !visited.insert(start) {
+            continue;
+        }
+
+        // Flood fill outwards to find every tile in this patch of fire
+        let mut patch = vec![start];
+        let mut frontier = VecDeque::from([start]);
+        while let Some(position) = frontier.pop_front() {
+            for neighbor in position.moore_neighbors() {
+                if let Some(neighbor) = tile_index.resolve(&neighbor)
+                    && burning.contains_key(&neighbor)
+                    && visited.insert(neighbor)
+                {
+                    patch.push(neighbor);
+                    frontier.push_back(neighbor);
+                }
+            }
+        }
+
+        let mut existing_ids: Vec<FireId> = patch
+            .iter()
+            .filter_map(|position| burning[position].1)
+            .map(|id| merged_into.get(&id).copied().unwrap_or(id))
+            .filter(|id| fire_tracker.active.contains_key(id))
+            .collect();
+        existing_ids.sort_unstable();
+        existing_ids.dedup();
+
+        let id = match existing_ids.split_first() {
+            Some((&oldest, merged)) => {
+                for merged_id in merged {
+                    if let Some(merged_record) = fire_tracker.active.remove(merged_id) {
+                        let record = fire_tracker.active.get_mut(&oldest).unwrap();
+                        record.area_burned += merged_record.area_burned;
+                        record.start_tick = record.start_tick.min(merged_record.start_tick);
+
+                        fire_merged.write(FireMerged {
+                            id: *merged_id,
+                            into: oldest,
+                            area_burned: merged_record.area_burned,
+                        });
+                    }
+                    merged_into.insert(*merged_id, oldest);
+                    for target in merged_into.values_mut() {
+                        if target == merged_id {
+                            *target = oldest;
+                        }
+                    }
+                }
+                oldest
+            }
+            None => {
+                let id = fire_tracker.start_fire(start);
+                fire_started.write(FireStarted { id, origin: start });
+                id
+            }
+        };
+
+        for position in &patch {
+            let (entity, fire_id) = burning[position];
+            if fire_id != Some(id) {
+                commands.entity(entity).insert(id);
+            }
+            if fire_id.is_none() {
+                fire_tracker.active.get_mut(&id).unwrap().area_burned += 1;
+            }
+        }
+
+        still_burning.insert(id);
+    }
+
+    let tick = fire_tracker.tick;
+    let burnt_out: Vec<FireId> = fire_tracker
+        .active
+        .keys()
+        .filter(|id| !still_burning.contains(*id))
+        .copied()
+        .collect();
+
+    for id in burnt_out {
+        let Some(mut record) = fire_tracker.active.remove(&id) else {
+            continue;
+        };
+        record.end_tick = Some(tick);
+
+        fire_extinguished.write(FireExtinguished {
+            id,
+            area_burned: record.area_burned,
+            duration: record.duration(tick),
+        });
+        fire_tracker.extinguished.push_back(record);
+    }
+
+    while fire_tracker.extinguished.len() > fire_tracker.extinguished_length {
+        fire_tracker.extinguished.pop_front();
+    }
+}
@@ -0,0 +1,226 @@
+//! Firefighters: agents that rush towards the nearest fire and put it out.
+//!
+//! Crews are stationed at fixed bases, and spawn there each time the map is generated.
+//! Every tick, each firefighter finds the cheapest route to the nearest burning tile using the [`TileIndex`],
+//! weighing the terrain by its [`MovementCosts`], and extinguishes the fire when they arrive, leaving behind burned ground.
+//! Along the way, they can also hose down any fires close enough to see.
+
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+
+use crate::SimState;
+use crate::control_flow::Simulation;
+use crate::map_generation::GeneratedMapSize;
+use crate::rulesets::{ForestSystems, Ruleset, ruleset_is};
+use crate::simulation::TileKind;
+use crate::spatial_index::{GridShape, MovementCosts, Position, Tile, TileIndex};
+
+pub struct FirefighterPlugin;
+
+impl Plugin for FirefighterPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Firefighter>()
+            .init_resource::<FirefighterCrews>()
+            .register_type::<FirefighterCrews>()
+            .add_systems(OnEnter(SimState::Generate), despawn_firefighters)
+            .add_systems(
+                OnExit(SimState::Generate),
+                spawn_firefighters.run_if(ruleset_is(Ruleset::Forest)),
+            )
+            .add_systems(Simulation, fight_fires.in_set(ForestSystems));
+    }
+}
+
+/// A marker component for firefighting agents.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Firefighter;
+
+impl Firefighter {
+    const COLOR: Color = Color::hsl(50., 1.0, 0.5);
+    const SIZE: f32 = Position::PIXELS_PER_TILE * 0.5;
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct FirefighterCrews {
+    /// The positions of the bases that firefighters are dispatched from.
+    ///
+    /// Bases outside of the map are skipped.
+    bases: Vec<Position>,
+    /// The number of firefighters spawned at each base.
+    crew_size: u32,
+    /// The number of tiles that each firefighter can move every tick.
+    moves_per_tick: usize,
+    /// The tile kinds that firefighters cannot cross.
+    impassable_kinds: Vec<TileKind>,
+    /// How far away firefighters can put out fires with their hoses, in tiles.
+    ///
+    /// Firefighters can only hose down fires that they can see: see [`FirefighterCrews::view_blocking_kinds`].
+    hose_range: i32,
+    /// The tile kinds that firefighters cannot see through.
+    view_blocking_kinds: Vec<TileKind>,
+}
+
+impl Default for FirefighterCrews {
+    fn default() -> Self {
+        Self {
+            bases: vec![Position { x: 12, y: 12 }, Position { x: 37, y: 37 }],
+            crew_size: 2,
+            moves_per_tick: 2,
+            impassable_kinds: vec![TileKind::Water, TileKind::Fire],
+            hose_range: 2,
+            view_blocking_kinds: vec![
+                TileKind::ShadeIntolerantForest,
+                TileKind::ShadeTolerantForest,
+                TileKind::DeadForest,
+            ],
+        }
+    }
+}
+
+fn despawn_firefighters(mut commands: Commands, query: Query<Entity, With<Firefighter>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[hot]
+fn spawn_firefighters(
+    firefighter_crews: Res<FirefighterCrews>,
+    map_size: Res<GeneratedMapSize>,
+    mut commands: Commands,
+) {
+    for &base in &firefighter_crews.bases {
+        if base.x < 0 || base.y < 0 || base.x >= map_size.width || base.y >= map_size.height {
+            warn!(
+                "Firefighter base at ({}, {}) is outside of the map, and will be skipped.",
+                base.x, base.y
+            );
+            continue;
+        }
+
+        for _ in 0..firefighter_crews.crew_size {
+            commands.spawn((
+                Name::new("Firefighter"),
+                Firefighter,
+                base,
+                firefighter_transform(&base),
+                Sprite {
+                    color: Firefighter::COLOR,
+                    custom_size: Some(Vec2::splat(Firefighter::SIZE)),
+                    ..Default::default()
+                },
+            ));
+        }
+    }
+}
+
+/// Firefighters are drawn above the tiles that they are standing on.
+fn firefighter_transform(position: &Position) -> Transform {
+    let mut transform = position.to_transform();
+    transform.translation.z = 2.0;
+    transform
+}
+
+/// Moves each firefighter along the cheapest path towards the nearest fire,
+/// putting it out if they reach it this tick.
+///
+/// Fires are tried from nearest to furthest, and firefighters head for the first one they can reach.
+/// Once they have moved, firefighters also hose down every fire that they can see within their [`FirefighterCrews::hose_range`].
+///
+/// Firefighters with no reachable fire stay where they are.
+// PERF: each fire that can't be reached costs a search of every tile the firefighter can reach.
+#[hot]
+#[allow(clippy::too_many_arguments)]
+fn fight_fires(
+    firefighter_query: Query<(Entity, &Position), With<Firefighter>>,
+    mut tile_query: Query<(Entity, &Position, &mut TileKind), With<Tile>>,
+    firefighter_crews: Res<FirefighterCrews>,
+    movement_costs: Res<MovementCosts>,
+    grid_shape: Res<GridShape>,
+    tile_index: Res<TileIndex>,
+    mut commands: Commands,
+) {
+    let tile_kind_is = |tile_query: &Query<(Entity, &Position, &mut TileKind), With<Tile>>,
+                        tile: Entity,
+                        kinds: &[TileKind]| {
+        tile_query
+            .get(tile)
+            .is_ok_and(|(_, _, tile_kind)| kinds.contains(tile_kind))
+    };
+
+    let mut fires: Vec<(Entity, Position)> = tile_query
+        .iter()
+        .filter(|(_, _, tile_kind)| **tile_kind == TileKind::Fire)
+        .map(|(tile, position, _)| (tile, *position))
+        .collect();
+
+    for (entity, position) in firefighter_query.iter() {
+        // Earlier firefighters may have already put some of the fires out
+        fires.retain(|(fire, _)| tile_kind_is(&tile_query, *fire, &[TileKind::Fire]));
+        let mut nearest_fires = fires.clone();
+        nearest_fires.sort_by_key(|(_, fire_position)| {
+            tile_index.distance(position, fire_position, &grid_shape)
+        });
+
+        // Firefighters can step onto the fire they are heading for, even though they can't cross other fires
+        let route_to = |fire: Entity, fire_position: Position| {
+            tile_index.pathfind(*position, fire_position, &grid_shape, |tile| {
+                if tile == fire {
+                    return Some(1.0);
+                }
+
+                let (_, _, tile_kind) = tile_query.get(tile).ok()?;
+                if firefighter_crews.impassable_kinds.contains(tile_kind) {
+                    return None;
+                }
+                movement_costs.cost(tile_kind)
+            })
+        };
+        let Some(path) = nearest_fires
+            .into_iter()
+            .find_map(|(fire, fire_position)| route_to(fire, fire_position))
+        else {
+            continue;
+        };
+
+        let steps = path.len().min(firefighter_crews.moves_per_tick);
+        // Firefighters standing on a fire (for example, one started by lightning) put it out immediately
+        let destination = if steps == 0 {
+            *position
+        } else {
+            path[steps - 1]
+        };
+
+        if steps == path.len()
+            && let Some(tile) = tile_index.get(&destination)
+            && let Ok((_, _, mut tile_kind)) = tile_query.get_mut(tile)
+        {
+            *tile_kind = TileKind::Burned;
+        }
+
+        let hosed_fires: Vec<Entity> = tile_index
+            .get_many(destination.neighbors_within_radius(firefighter_crews.hose_range))
+            .filter(|(_, tile)| tile_kind_is(&tile_query, *tile, &[TileKind::Fire]))
+            .filter(|(fire_position, _)| {
+                tile_index.visible(&destination, fire_position, |tile| {
+                    tile_kind_is(&tile_query, tile, &firefighter_crews.view_blocking_kinds)
+                })
+            })
+            .map(|(_, tile)| tile)
+            .collect();
+
+        for tile in hosed_fires {
+            if let Ok((_, _, mut tile_kind)) = tile_query.get_mut(tile) {
+                *tile_kind = TileKind::Burned;
+            }
+        }
+
+        if destination != *position {
+            // `Position` is immutable, so we need to reinsert it to move the firefighter
+            commands
+                .entity(entity)
+                .insert((destination, firefighter_transform(&destination)));
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Human land management: periodically logging patches of mature forest.
+//!
+//! Clear-cuts reset succession and break up continuous stands of fuel,
+//! which makes this a handy lever for land-management teaching scenarios.
+
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+use rand::seq::IndexedRandom;
+
+use crate::SimState;
+use crate::control_flow::Simulation;
+use crate::rulesets::ForestSystems;
+use crate::simulation::TileKind;
+use crate::spatial_index::{Position, TileIndex};
+use crate::tile_rng::{TileRng, TileRngStream, advance_tile_rng};
+
+pub struct ForestryPlugin;
+
+impl Plugin for ForestryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Logging>()
+            .register_type::<Logging>()
+            .add_systems(
+                Simulation,
+                log_forest_patches
+                    .after(advance_tile_rng)
+                    .in_set(ForestSystems),
+            )
+            .add_systems(OnEnter(SimState::Generate), reset_logging);
+    }
+}
+
+/// Controls how often, and how much, mature forest is logged.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct Logging {
+    /// Whether or not logging is currently taking place.
+    enabled: bool,
+    /// The number of simulation ticks between each harvest.
+    interval_ticks: u32,
+    /// The number of ticks since the last harvest.
+    ticks_since_harvest: u32,
+    /// The width of each clear-cut patch, in tiles.
+    patch_width: i32,
+    /// The height of each clear-cut patch, in tiles.
+    patch_height: i32,
+    /// The tile kinds that are considered mature enough to be worth logging.
+    harvested_kinds: Vec<TileKind>,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_ticks: 20,
+            ticks_since_harvest: 0,
+            patch_width: 4,
+            patch_height: 3,
+            harvested_kinds: vec![
+                TileKind::ShadeIntolerantForest,
+                TileKind::ShadeTolerantForest,
+            ],
+        }
+    }
+}
+
+/// Every [`Logging::interval_ticks`] ticks, clears a rectangular patch of mature forest back to meadow.
+///
+/// Patches are centered on a randomly chosen mature forest tile,
+/// and only mature forest within the patch is cleared.
+#[hot]
+fn log_forest_patches(
+    mut tile_query: Query<(&Position, &mut TileKind)>,
+    mut logging: ResMut<Logging>,
+    tile_index: Res<TileIndex>,
+    tile_rng: Res<TileRng>,
+) {
+    if !logging.enabled {
+        return;
+    }
+
+    logging.ticks_since_harvest += 1;
+    if logging.ticks_since_harvest < logging.interval_ticks {
+        return;
+    }
+    logging.ticks_since_harvest = 0;
+
+    let mut mature_forest: Vec<Position> = tile_query
+        .iter()
+        .filter(|(_, tile_kind)| logging.harvested_kinds.contains(tile_kind))
+        .map(|(position, _)| *position)
+        .collect();
+    // Sorted by position, so that the same patch is chosen no matter what order the tiles are iterated in
+    mature_forest.sort_unstable_by_key(|position| (position.y, position.x));
+
+    let mut rng = tile_rng.for_map(TileRngStream::Logging);
+    let Some(center) = mature_forest.choose(&mut rng) else {
+        return;
+    };
+
+    let min = Position {
+        x: center.x - logging.patch_width / 2,
+        y: center.y - logging.patch_height / 2,
+    };
+    let max = Position {
+        x: min.x + logging.patch_width - 1,
+        y: min.y + logging.patch_height - 1,
+    };
+    let mut cleared_tiles = 0;
+
+    for (_, entity) in tile_index.iter_rect(min, max) {
+        let Ok((_, mut tile_kind)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+
+        if logging.harvested_kinds.contains(&tile_kind) {
+            *tile_kind = TileKind::Meadow;
+            cleared_tiles += 1;
+        }
+    }
+
+    info!(
+        "Logged {cleared_tiles} tiles of mature forest around ({}, {}).",
+        center.x, center.y
+    );
+}
+
+fn reset_logging(mut logging: ResMut<Logging>) {
+    logging.ticks_since_harvest = 0;
+}
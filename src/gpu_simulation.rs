@@ -0,0 +1,1146 @@
+//! An optional backend that runs the simulation in a compute shader, for enormous maps.
+//!
+//! While the [`SimulationBackend::Gpu`] is active, the grid lives in a pair of storage textures on the GPU.
+//! Each simulation tick, a compute shader applies the rules to every cell at once,
+//! and draws the result straight into a texture that is shown over the map.
+//! The tile entities are left untouched in the meantime: only the cell counts in [`GpuStatistics`]
+//! are read back every frame, and the whole grid is only read back when switching back to the CPU.
+//!
+//! The [`Ruleset::Forest`] and the [`Ruleset::DrosselSchwabl`] rulesets have been ported to the GPU, for square grids.
+//! Neighbors are found using the same [`NeighborhoodKind`] and [`MapTopology`] as the [`TileIndex`](crate::spatial_index::TileIndex),
+//! so tiles on the edge of the map see exactly the same neighbors on either backend.
+//!
+//! For the forest, the shader covers fire spread (see [`FireSusceptibility`], [`FireSpread`] and [`FireFuel`]),
+//! [`BurnDurations`], succession (see [`TransitionProbabilities`], [`SeedDispersal`], [`ShadeSuccession`] and [`SoilFertility`]),
+//! [`Lightning`] and the climate. A few things are simplified along the way:
+//! - Cells always update synchronously, whatever the [`SimulationUpdateMode`](crate::simulation::SimulationUpdateMode).
+//! - Lightning strikes each cell with the same small probability, rather than striking an exact number of cells.
+//! - [`Moisture`], [`Elevation`] and [`Fertility`] stay as they were when the grid was uploaded.
+//! - The rest of the [`ForestSystems`], such as spot fires, rainfall and herbivores, are paused until the grid is read back.
+
+use std::borrow::Cow;
+
+use bevy::ecs::system::SystemParam;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::gpu_readback::{Readback, ReadbackComplete};
+use bevy::render::graph::CameraDriverLabel;
+use bevy::render::render_asset::{RenderAssetUsages, RenderAssets};
+use bevy::render::render_graph::{self, RenderGraph, RenderLabel};
+use bevy::render::render_resource::binding_types::{
+    storage_buffer, storage_buffer_read_only, texture_storage_2d,
+};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderContext, RenderDevice, RenderQueue};
+use bevy::render::storage::{GpuShaderStorageBuffer, ShaderStorageBuffer};
+use bevy::render::texture::GpuImage;
+use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderSet};
+use clap::ValueEnum;
+use strum::{EnumCount, IntoEnumIterator};
+
+use crate::SimState;
+use crate::climate::ClimateConditions;
+use crate::control_flow::Simulation;
+use crate::map_generation::GeneratedMapSize;
+use crate::rulesets::{ForestFireProbabilities, ForestSystems, Ruleset};
+use crate::simulation::{
+    Age, BurnDuration, BurnDurationDistribution, BurnDurations, DisturbanceContext, Elevation,
+    Fertility, FireFuel, FireIntensity, FireSpread, FireState, FireSusceptibility, FuelLoad,
+    Lightning, Moisture, PrescribedFire, SeedDispersal, ShadeSuccession, SoilFertility, TileKind,
+    TransitionProbabilities,
+};
+use crate::spatial_index::{GridShape, MapTopology, NeighborhoodKind, Position};
+use crate::tile_appearance::TileAppearance;
+use parameters::{
+    GpuKindParameters, GpuPostFirePathway, GpuSimulationParameters, GpuTransition, MAX_TRANSITIONS,
+};
+
+/// The compute shader that runs the simulation, in the `assets` folder.
+const SHADER_ASSET_PATH: &str = "shaders/gpu_simulation.wgsl";
+
+/// The width and height of each workgroup, which must match the shader.
+const WORKGROUP_SIZE: u32 = 8;
+
+pub struct GpuSimulationPlugin;
+
+impl Plugin for GpuSimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationBackend>()
+            .register_type::<SimulationBackend>()
+            .init_resource::<GpuStatistics>()
+            .register_type::<GpuStatistics>()
+            // The forest systems work on the tile entities, which are stale while the grid is on the GPU
+            .configure_sets(Simulation, ForestSystems.run_if(not(gpu_backend_active)))
+            .add_systems(OnEnter(SimState::Generate), discard_gpu_grid)
+            .add_systems(
+                Update,
+                (
+                    upload_grid_to_gpu.run_if(gpu_backend_active),
+                    download_grid_from_gpu.run_if(not(gpu_backend_active)),
+                    update_gpu_parameters,
+                )
+                    .run_if(not(in_state(SimState::Generate))),
+            )
+            .add_systems(Simulation, request_gpu_step.run_if(gpu_backend_active));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .add_systems(ExtractSchedule, extract_gpu_grid)
+            .add_systems(
+                Render,
+                prepare_gpu_simulation_bind_group
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .run_if(resource_exists::<GpuGrid>),
+            );
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        render_graph.add_node(GpuSimulationLabel, GpuSimulationNode::default());
+        render_graph.add_node_edge(GpuSimulationLabel, CameraDriverLabel);
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<GpuSimulationPipeline>();
+        }
+    }
+}
+
+/// Where the simulation rules are run.
+#[derive(Resource, Reflect, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum SimulationBackend {
+    /// Each tile entity is updated by the ECS, which supports every ruleset.
+    #[default]
+    Cpu,
+    /// The grid is simulated in a compute shader, which is much faster for large maps.
+    ///
+    /// Only the [`Ruleset::Forest`] and [`Ruleset::DrosselSchwabl`] rulesets on square grids are supported:
+    /// anything else falls back to the CPU.
+    Gpu,
+}
+
+impl SimulationBackend {
+    /// Checks whether the GPU backend supports the given ruleset and grid shape.
+    pub fn gpu_supports(ruleset: &Ruleset, grid_shape: &GridShape) -> bool {
+        matches!(ruleset, Ruleset::Forest | Ruleset::DrosselSchwabl)
+            && *grid_shape == GridShape::Square
+    }
+}
+
+/// A run condition that checks whether the simulation is currently running on the GPU.
+pub fn gpu_backend_active(
+    backend: Res<SimulationBackend>,
+    ruleset: Res<Ruleset>,
+    grid_shape: Res<GridShape>,
+) -> bool {
+    *backend == SimulationBackend::Gpu && SimulationBackend::gpu_supports(&ruleset, &grid_shape)
+}
+
+/// The number of cells of each kind on the GPU, as of the most recent readback.
+///
+/// Kinds with no cells at all are left out.
+#[derive(Resource, Reflect, Debug, Default, PartialEq)]
+#[reflect(Resource)]
+pub struct GpuStatistics {
+    pub counts: HashMap<TileKind, u32>,
+}
+
+/// How each tile is stored in the GPU grid.
+///
+/// Every cell holds four numbers: the index of its [`TileKind`], its [`Age`],
+/// its fire (packed using the constants below) and the bits of its fuel as an `f32`.
+/// Fires store their remaining [`FireState::fuel`], while every other tile stores its [`FuelLoad`].
+///
+/// These values must match the constants in the shader.
+mod cell {
+    /// Anything that isn't a tile, such as gaps in the map mask, which never changes.
+    pub const INERT: u32 = u32::MAX;
+    /// The remaining ticks of the fire's [`BurnDuration`](crate::simulation::BurnDuration) are kept in the lowest bits.
+    pub const REMAINING_TICKS_MASK: u32 = 0xFFFF;
+    pub const INTENSITY_SHIFT: u32 = 16;
+    pub const PEAK_INTENSITY_SHIFT: u32 = 18;
+    pub const FUELED_BY_SHIFT: u32 = 20;
+    /// Stored in place of the kind that fueled the fire, for tiles that aren't burning and haven't just burned.
+    pub const NOT_FUELED: u32 = 0xFF;
+    /// Set for fires whose remaining ticks are counting down, rather than burning until their fuel runs out.
+    pub const HAS_DURATION: u32 = 1 << 28;
+    /// Set for fires that are a [`PrescribedFire`](crate::simulation::PrescribedFire).
+    pub const PRESCRIBED: u32 = 1 << 29;
+    /// The fire of a tile that has never burned.
+    pub const NO_FIRE: u32 = NOT_FUELED << FUELED_BY_SHIFT;
+}
+
+fn decode_kind(value: u32) -> Option<TileKind> {
+    TileKind::iter().nth(value as usize)
+}
+
+fn decode_intensity(value: u32) -> FireIntensity {
+    FireIntensity::iter()
+        .nth((value & 3) as usize)
+        .unwrap_or_default()
+}
+
+/// Packs the fire of a tile, or the fire that it was burned by, into the third channel of its cell.
+fn encode_fire(
+    fire_state: Option<&FireState>,
+    burn_duration: Option<&BurnDuration>,
+    prescribed: bool,
+    disturbance: Option<&DisturbanceContext>,
+) -> u32 {
+    let pack = |remaining_ticks: u32,
+                intensity: FireIntensity,
+                peak_intensity: FireIntensity,
+                fueled_by: TileKind| {
+        remaining_ticks.min(cell::REMAINING_TICKS_MASK)
+            | (intensity as u32) << cell::INTENSITY_SHIFT
+            | (peak_intensity as u32) << cell::PEAK_INTENSITY_SHIFT
+            | (fueled_by as u32) << cell::FUELED_BY_SHIFT
+    };
+
+    if let Some(fire_state) = fire_state {
+        let mut fire = pack(
+            burn_duration.map_or(0, |burn_duration| burn_duration.remaining_ticks),
+            fire_state.intensity,
+            fire_state.peak_intensity,
+            fire_state.fueled_by,
+        );
+        if burn_duration.is_some() {
+            fire |= cell::HAS_DURATION;
+        }
+        if prescribed {
+            fire |= cell::PRESCRIBED;
+        }
+        fire
+    } else if let Some(DisturbanceContext::Fire {
+        peak_intensity,
+        burned_kind,
+    }) = disturbance
+    {
+        pack(0, FireIntensity::Smoldering, *peak_intensity, *burned_kind)
+    } else {
+        cell::NO_FIRE
+    }
+}
+
+/// How fires on each kind of tile decide how long to burn for, which must match the constants in the shader.
+mod burn_mode {
+    /// Burn until the fuel runs out.
+    pub const BY_FUEL: u32 = 0;
+    /// Burn for a number of ticks chosen uniformly between a minimum and maximum.
+    pub const UNIFORM: u32 = 1;
+    /// Burn out with the same probability every tick.
+    pub const GEOMETRIC: u32 = 2;
+}
+
+/// Properties of each tile kind that the shader needs, which must match the constants in the shader.
+mod flags {
+    /// See [`SeedDispersal::dispersal_limited`](crate::simulation::SeedDispersal::dispersal_limited).
+    pub const DISPERSAL_LIMITED: u32 = 1;
+    /// See [`ShadeSuccession::canopy_kinds`](crate::simulation::ShadeSuccession::canopy_kinds).
+    pub const CANOPY: u32 = 2;
+}
+
+/// Encodes the map topology for the shader.
+fn topology_code(map_topology: &MapTopology) -> u32 {
+    match map_topology {
+        MapTopology::Absorbing => 0,
+        MapTopology::Reflecting => 1,
+        MapTopology::Toroidal => 2,
+    }
+}
+
+/// Encodes the neighborhood for the shader, along with its radius.
+fn neighborhood_code(neighborhood: &NeighborhoodKind) -> (u32, i32) {
+    match neighborhood {
+        NeighborhoodKind::VonNeumann => (0, 1),
+        NeighborhoodKind::Moore => (1, 1),
+        NeighborhoodKind::Radius(radius) => (2, *radius),
+        NeighborhoodKind::Hexagonal => (3, 1),
+    }
+}
+
+/// The GPU resources holding the grid, which only exist while the GPU backend is running.
+#[derive(Resource, Clone)]
+struct GpuGrid {
+    /// The current state of every cell, followed by a scratch texture that the next state is written into.
+    cells: [Handle<Image>; 2],
+    /// The moisture, elevation and fertility of every cell, which the shader only reads.
+    terrain: Handle<Image>,
+    /// The colors of each cell, drawn over the map.
+    display: Handle<Image>,
+    /// How many cells of each kind there are, counted by the shader.
+    counts: Handle<ShaderStorageBuffer>,
+    /// The width and height of the grid, in cells.
+    size: UVec2,
+    /// The ruleset that the grid was uploaded for, which picks the shader entry point.
+    ruleset: Ruleset,
+    /// The number of simulation ticks that have been requested so far.
+    tick: u32,
+    parameters: GpuSimulationParameters,
+}
+
+// `ShaderType` generates layout checks that are never called, which trips the dead code lint
+#[allow(dead_code)]
+mod parameters {
+    use bevy::math::Vec4;
+    use bevy::render::render_resource::ShaderType;
+    use strum::EnumCount;
+
+    use crate::simulation::TileKind;
+
+    /// The most transitions that each tile kind (or post-fire pathway) can have on the GPU.
+    ///
+    /// Any further transitions are ignored.
+    pub const MAX_TRANSITIONS: usize = 6;
+
+    #[derive(ShaderType, Debug, Clone, Copy, Default)]
+    pub struct GpuTransition {
+        pub target_kind: u32,
+        pub weight: f32,
+    }
+
+    /// Everything the shader needs to know about a single tile kind.
+    #[derive(ShaderType, Debug, Clone, Copy, Default)]
+    pub struct GpuKindParameters {
+        pub color: Vec4,
+        /// The fire susceptibility, already scaled by the base susceptibility.
+        pub susceptibility: f32,
+        pub fuel_accumulation: f32,
+        pub max_fuel: f32,
+        /// A combination of the values in the `flags` module.
+        pub flags: u32,
+        /// One of the values in the `burn_mode` module.
+        pub burn_mode: u32,
+        pub burn_min_ticks: u32,
+        pub burn_max_ticks: u32,
+        pub burnout_probability: f32,
+        pub transition_count: u32,
+        pub transitions: [GpuTransition; MAX_TRANSITIONS],
+    }
+
+    #[derive(ShaderType, Debug, Clone, Copy, Default)]
+    pub struct GpuPostFirePathway {
+        /// Zero if there is no pathway for this intensity, so burned ground uses its usual transitions.
+        pub present: u32,
+        pub survival: f32,
+        pub transition_count: u32,
+        pub transitions: [GpuTransition; MAX_TRANSITIONS],
+    }
+
+    /// The values passed to the shader in a storage buffer, in the same order as the `Parameters` in the shader.
+    #[derive(ShaderType, Debug, Clone, Copy, Default)]
+    pub struct GpuSimulationParameters {
+        /// Changes every tick, so that each tick draws different random numbers.
+        pub seed: u32,
+        pub topology: u32,
+        pub neighborhood: u32,
+        pub radius: i32,
+        pub empty_kind: u32,
+        pub tree_kind: u32,
+        pub fire_kind: u32,
+        pub burned_kind: u32,
+        pub shade_tolerant_kind: u32,
+        pub shade_intolerant_kind: u32,
+        pub growth_probability: f32,
+        pub lightning_probability: f32,
+        pub moisture_dampening: f32,
+        pub spread_multiplier: f32,
+        pub uphill_multiplier: f32,
+        pub prescribed_multiplier: f32,
+        /// Indexed by the fire intensity.
+        pub intensity_multipliers: [f32; 3],
+        pub ignition_half_saturation: f32,
+        pub consumption_rate: f32,
+        pub burning_threshold: f32,
+        pub crown_fire_threshold: f32,
+        pub isolation_multiplier: f32,
+        pub shade_tolerant_threshold: f32,
+        pub exposed_tolerant_multiplier: f32,
+        pub intolerant_shade_penalty: f32,
+        pub growth_scaling: f32,
+        /// The probability that lightning strikes each cell on a given tick.
+        pub lightning_strike_probability: f32,
+        pub lightning_ignition_multiplier: f32,
+        /// The climate's fire multiplier, before the temperature of each tile is taken into account.
+        pub climate_fire_multiplier: f32,
+        /// The climate's growth multiplier, before the temperature of each tile is taken into account.
+        pub climate_growth_multiplier: f32,
+        pub north_temperature: f32,
+        pub south_temperature: f32,
+        /// Indexed by the tile kind.
+        pub kinds: [GpuKindParameters; TileKind::COUNT],
+        /// Indexed by the peak intensity of the fire.
+        pub post_fire_pathways: [GpuPostFirePathway; 3],
+        /// Indexed by the current tile kind, then the target tile kind.
+        pub minimum_ages: [[u32; TileKind::COUNT]; TileKind::COUNT],
+    }
+}
+
+/// A marker component for the entities that only exist while the [`GpuGrid`] does,
+/// such as the sprite that shows it and the readback of the [`GpuStatistics`].
+#[derive(Component)]
+struct GpuGridEntity;
+
+/// Copies the tiles into a new grid on the GPU, and starts showing it over the map.
+#[allow(clippy::type_complexity)]
+fn upload_grid_to_gpu(
+    gpu_grid: Option<Res<GpuGrid>>,
+    tile_query: Query<(
+        &Position,
+        &TileKind,
+        Option<&Age>,
+        Option<&FuelLoad>,
+        Option<&FireState>,
+        Option<&BurnDuration>,
+        Has<PrescribedFire>,
+        Option<&DisturbanceContext>,
+        Option<&Moisture>,
+        Option<&Elevation>,
+        Option<&Fertility>,
+    )>,
+    map_size: Res<GeneratedMapSize>,
+    ruleset: Res<Ruleset>,
+    mut images: ResMut<Assets<Image>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut commands: Commands,
+) {
+    if gpu_grid.is_some() {
+        return;
+    }
+
+    let size = UVec2::new(map_size.width.max(1) as u32, map_size.height.max(1) as u32);
+    let extent = Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+    };
+
+    let mut cells = vec![[cell::INERT, 0, cell::NO_FIRE, 0]; (size.x * size.y) as usize];
+    let mut terrain = vec![[0.0f32; 4]; cells.len()];
+    for (
+        position,
+        tile_kind,
+        age,
+        fuel_load,
+        fire_state,
+        burn_duration,
+        prescribed,
+        disturbance,
+        moisture,
+        elevation,
+        fertility,
+    ) in tile_query.iter()
+    {
+        let (Ok(x), Ok(y)) = (u32::try_from(position.x), u32::try_from(position.y)) else {
+            continue;
+        };
+        if x >= size.x || y >= size.y {
+            continue;
+        }
+
+        let fuel = match fire_state {
+            Some(fire_state) => fire_state.fuel,
+            None => fuel_load.map_or(0.0, |fuel_load| fuel_load.0),
+        };
+
+        let index = (y * size.x + x) as usize;
+        cells[index] = [
+            *tile_kind as u32,
+            age.map_or(0, |age| age.0),
+            encode_fire(fire_state, burn_duration, prescribed, disturbance),
+            fuel.to_bits(),
+        ];
+        terrain[index] = [
+            moisture.map_or(0.0, |moisture| moisture.0),
+            elevation.map_or(0.0, |elevation| elevation.0),
+            fertility.map_or(0.0, |fertility| fertility.0),
+            0.0,
+        ];
+    }
+
+    let storage_image = |data: Vec<u8>, format: TextureFormat| {
+        let mut image = Image::new(
+            extent,
+            TextureDimension::D2,
+            data,
+            format,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.texture_descriptor.usage |= TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC;
+        image
+    };
+
+    let current = storage_image(
+        cells
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect(),
+        TextureFormat::Rgba32Uint,
+    );
+    let terrain = storage_image(
+        terrain
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect(),
+        TextureFormat::Rgba32Float,
+    );
+
+    let mut next = Image::new_uninit(
+        extent,
+        TextureDimension::D2,
+        TextureFormat::Rgba32Uint,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    next.texture_descriptor.usage |= TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC;
+
+    let mut display = Image::new_uninit(
+        extent,
+        TextureDimension::D2,
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    display.texture_descriptor.usage |= TextureUsages::STORAGE_BINDING;
+    display.sampler = bevy::image::ImageSampler::nearest();
+
+    let mut counts = ShaderStorageBuffer::from(vec![0u32; TileKind::COUNT]);
+    counts.buffer_description.usage |= BufferUsages::COPY_SRC | BufferUsages::COPY_DST;
+
+    let display = images.add(display);
+    let counts = buffers.add(counts);
+
+    // The sprite covers the map exactly, with the center of each texel over the center of each tile
+    let map_pixels = size.as_vec2() * Position::PIXELS_PER_TILE;
+    let center = (map_pixels - Position::PIXELS_PER_TILE) / 2.0;
+    commands.spawn((
+        Name::new("GPU grid"),
+        GpuGridEntity,
+        Sprite {
+            image: display.clone(),
+            custom_size: Some(map_pixels),
+            ..Default::default()
+        },
+        // Draw above the tiles, which are no longer kept up to date
+        Transform::from_translation(center.extend(1.5)),
+    ));
+
+    commands
+        .spawn((
+            Name::new("GPU statistics readback"),
+            GpuGridEntity,
+            Readback::buffer(counts.clone()),
+        ))
+        .observe(read_gpu_statistics);
+
+    commands.insert_resource(GpuGrid {
+        cells: [images.add(current), images.add(next)],
+        terrain: images.add(terrain),
+        display,
+        counts,
+        size,
+        ruleset: *ruleset,
+        tick: 0,
+        parameters: GpuSimulationParameters::default(),
+    });
+
+    info!("Running the simulation on the GPU");
+}
+
+fn read_gpu_statistics(trigger: Trigger<ReadbackComplete>, mut statistics: ResMut<GpuStatistics>) {
+    let counts: Vec<u32> = trigger.event().to_shader_type();
+
+    statistics.set_if_neq(GpuStatistics {
+        counts: TileKind::iter()
+            .zip(counts)
+            .filter(|(_, count)| *count > 0)
+            .collect(),
+    });
+}
+
+/// Stops simulating on the GPU, reading the grid back into the tiles so that the CPU can pick up where it left off.
+fn download_grid_from_gpu(gpu_grid: Option<Res<GpuGrid>>, mut commands: Commands) {
+    let Some(gpu_grid) = gpu_grid else {
+        return;
+    };
+
+    commands
+        .spawn((
+            Name::new("GPU grid readback"),
+            Readback::texture(gpu_grid.cells[0].clone()),
+        ))
+        .observe(copy_grid_into_tiles);
+
+    commands.run_system_cached(discard_gpu_grid);
+    info!("Running the simulation on the CPU");
+}
+
+/// Writes the grid read back from the GPU into the tiles, then stops reading it back.
+#[allow(clippy::type_complexity)]
+fn copy_grid_into_tiles(
+    trigger: Trigger<ReadbackComplete>,
+    mut tile_query: Query<(
+        Entity,
+        &Position,
+        &mut TileKind,
+        Option<&mut Age>,
+        Option<&mut FuelLoad>,
+    )>,
+    map_size: Res<GeneratedMapSize>,
+    mut commands: Commands,
+) {
+    commands.entity(trigger.target()).despawn();
+
+    let bytes = &trigger.event().0;
+    let height = map_size.height.max(1) as usize;
+    // Each row of the texture is padded out to meet the GPU's alignment requirements
+    let row_stride = bytes.len() / height;
+    let texel_size = 4 * size_of::<u32>();
+
+    for (entity, position, mut tile_kind, age, fuel_load) in tile_query.iter_mut() {
+        let (Ok(x), Ok(y)) = (usize::try_from(position.x), usize::try_from(position.y)) else {
+            continue;
+        };
+
+        let offset = y * row_stride + x * texel_size;
+        let Some(texel) = bytes.get(offset..offset + texel_size) else {
+            continue;
+        };
+        let [kind, cell_age, fire, fuel] = core::array::from_fn(|i| {
+            u32::from_le_bytes([
+                texel[4 * i],
+                texel[4 * i + 1],
+                texel[4 * i + 2],
+                texel[4 * i + 3],
+            ])
+        });
+
+        let Some(new_kind) = decode_kind(kind) else {
+            continue;
+        };
+        tile_kind.set_if_neq(new_kind);
+        if let Some(mut age) = age {
+            age.set_if_neq(Age(cell_age));
+        }
+
+        let fuel = f32::from_bits(fuel);
+        let intensity = decode_intensity(fire >> cell::INTENSITY_SHIFT);
+        let peak_intensity = decode_intensity(fire >> cell::PEAK_INTENSITY_SHIFT);
+        let fueled_by = decode_kind((fire >> cell::FUELED_BY_SHIFT) & cell::NOT_FUELED);
+
+        let mut entity_commands = commands.entity(entity);
+        if new_kind == TileKind::Fire
+            && let Some(fueled_by) = fueled_by
+        {
+            if let Some(mut fuel_load) = fuel_load {
+                fuel_load.set_if_neq(FuelLoad(0.0));
+            }
+
+            entity_commands
+                .insert(FireState {
+                    intensity,
+                    peak_intensity,
+                    fuel,
+                    fueled_by,
+                })
+                .remove::<DisturbanceContext>();
+
+            if fire & cell::HAS_DURATION != 0 {
+                entity_commands.insert(BurnDuration {
+                    remaining_ticks: fire & cell::REMAINING_TICKS_MASK,
+                });
+            } else {
+                entity_commands.remove::<BurnDuration>();
+            }
+
+            if fire & cell::PRESCRIBED != 0 {
+                entity_commands.insert(PrescribedFire);
+            } else {
+                entity_commands.remove::<PrescribedFire>();
+            }
+        } else {
+            if let Some(mut fuel_load) = fuel_load {
+                fuel_load.set_if_neq(FuelLoad(fuel));
+            }
+
+            entity_commands.remove::<(FireState, BurnDuration, PrescribedFire)>();
+            match fueled_by {
+                Some(burned_kind) if new_kind == TileKind::Burned => {
+                    entity_commands.insert(DisturbanceContext::Fire {
+                        peak_intensity,
+                        burned_kind,
+                    });
+                }
+                _ => {
+                    entity_commands.remove::<DisturbanceContext>();
+                }
+            }
+        }
+    }
+}
+
+/// Throws away the GPU grid without reading it back, such as when the map is regenerated.
+fn discard_gpu_grid(entity_query: Query<Entity, With<GpuGridEntity>>, mut commands: Commands) {
+    for entity in entity_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    commands.remove_resource::<GpuGrid>();
+}
+
+/// The parameters of the forest simulation, as read by [`update_gpu_parameters`].
+#[derive(SystemParam)]
+struct ForestParameters<'w> {
+    fire_susceptibility: Res<'w, FireSusceptibility>,
+    fire_spread: Res<'w, FireSpread>,
+    fire_fuel: Res<'w, FireFuel>,
+    burn_durations: Res<'w, BurnDurations>,
+    transition_probabilities: Res<'w, TransitionProbabilities>,
+    seed_dispersal: Res<'w, SeedDispersal>,
+    shade_succession: Res<'w, ShadeSuccession>,
+    soil_fertility: Res<'w, SoilFertility>,
+    lightning: Res<'w, Lightning>,
+    climate: ClimateConditions<'w>,
+}
+
+/// Copies up to [`MAX_TRANSITIONS`] transitions into the fixed-size array that the shader expects.
+fn gpu_transitions(transitions: &[(TileKind, f32)]) -> (u32, [GpuTransition; MAX_TRANSITIONS]) {
+    if transitions.len() > MAX_TRANSITIONS {
+        warn_once!(
+            "Only the first {MAX_TRANSITIONS} transitions of each tile kind are used on the GPU"
+        );
+    }
+
+    let mut gpu_transitions = [GpuTransition::default(); MAX_TRANSITIONS];
+    for (gpu_transition, (target_kind, weight)) in gpu_transitions.iter_mut().zip(transitions) {
+        *gpu_transition = GpuTransition {
+            target_kind: *target_kind as u32,
+            weight: *weight,
+        };
+    }
+
+    (
+        transitions.len().min(MAX_TRANSITIONS) as u32,
+        gpu_transitions,
+    )
+}
+
+/// Copies the parameters of the rules into the shader's parameters.
+///
+/// This is cheap enough that it's simply redone every frame, so that the GPU picks up every change to the rules.
+fn update_gpu_parameters(
+    gpu_grid: Option<ResMut<GpuGrid>>,
+    probabilities: Res<ForestFireProbabilities>,
+    map_topology: Res<MapTopology>,
+    neighborhood: Res<NeighborhoodKind>,
+    map_size: Res<GeneratedMapSize>,
+    tile_appearance: Res<TileAppearance>,
+    forest: ForestParameters,
+) {
+    let Some(mut gpu_grid) = gpu_grid else {
+        return;
+    };
+
+    let mut kinds = [GpuKindParameters::default(); TileKind::COUNT];
+    let mut minimum_ages = [[0; TileKind::COUNT]; TileKind::COUNT];
+    for tile_kind in TileKind::iter() {
+        let (burn_mode, burn_min_ticks, burn_max_ticks, burnout_probability) =
+            match forest.burn_durations.distributions.get(&tile_kind) {
+                None => (burn_mode::BY_FUEL, 0, 0, 0.0),
+                Some(BurnDurationDistribution::Fixed(ticks)) => {
+                    (burn_mode::UNIFORM, *ticks, *ticks, 0.0)
+                }
+                Some(BurnDurationDistribution::Uniform { min, max }) => {
+                    (burn_mode::UNIFORM, *min, (*max).max(*min), 0.0)
+                }
+                Some(BurnDurationDistribution::Geometric {
+                    burnout_probability,
+                }) => (burn_mode::GEOMETRIC, 0, 0, *burnout_probability as f32),
+            };
+
+        let mut kind_flags = 0;
+        if forest.seed_dispersal.dispersal_limited.contains(&tile_kind) {
+            kind_flags |= flags::DISPERSAL_LIMITED;
+        }
+        if forest.shade_succession.canopy_kinds.contains(&tile_kind) {
+            kind_flags |= flags::CANOPY;
+        }
+
+        let (transition_count, transitions) = gpu_transitions(
+            forest
+                .transition_probabilities
+                .probabilities
+                .get(&tile_kind)
+                .map_or(&[], Vec::as_slice),
+        );
+
+        let fire_susceptibility = &forest.fire_susceptibility;
+        kinds[tile_kind as usize] = GpuKindParameters {
+            color: tile_appearance.color(&tile_kind).to_linear().to_vec4(),
+            susceptibility: (fire_susceptibility
+                .tile_susceptibility
+                .get(&tile_kind)
+                .cloned()
+                .unwrap_or(0.0)
+                * fire_susceptibility.base_susceptibility) as f32,
+            fuel_accumulation: forest
+                .fire_fuel
+                .accumulation_rates
+                .get(&tile_kind)
+                .cloned()
+                .unwrap_or(0.0),
+            max_fuel: forest
+                .fire_fuel
+                .max_fuel_loads
+                .get(&tile_kind)
+                .cloned()
+                .unwrap_or(0.0),
+            flags: kind_flags,
+            burn_mode,
+            burn_min_ticks,
+            burn_max_ticks,
+            burnout_probability,
+            transition_count,
+            transitions,
+        };
+    }
+
+    for (&(from, to), &minimum_age) in forest.transition_probabilities.minimum_ages.iter() {
+        minimum_ages[from as usize][to as usize] = minimum_age;
+    }
+
+    let mut post_fire_pathways = [GpuPostFirePathway::default(); 3];
+    let mut intensity_multipliers = [0.0; 3];
+    for intensity in FireIntensity::iter() {
+        intensity_multipliers[intensity as usize] =
+            forest.fire_spread.intensity_multiplier(&intensity) as f32;
+
+        if let Some(pathway) = forest
+            .transition_probabilities
+            .post_fire_pathways
+            .get(&intensity)
+        {
+            let (transition_count, transitions) = gpu_transitions(&pathway.probabilities);
+            post_fire_pathways[intensity as usize] = GpuPostFirePathway {
+                present: 1,
+                survival: pathway.survival,
+                transition_count,
+                transitions,
+            };
+        }
+    }
+
+    let (neighborhood, radius) = neighborhood_code(&neighborhood);
+    let tile_count = (map_size.width.max(1) * map_size.height.max(1)) as f32;
+    let temperature_gradient = forest.climate.temperature_gradient();
+
+    gpu_grid.parameters = GpuSimulationParameters {
+        seed: gpu_grid.parameters.seed,
+        topology: topology_code(&map_topology),
+        neighborhood,
+        radius,
+        empty_kind: TileKind::Empty as u32,
+        tree_kind: TileKind::Tree as u32,
+        fire_kind: TileKind::Fire as u32,
+        burned_kind: TileKind::Burned as u32,
+        shade_tolerant_kind: TileKind::ShadeTolerantForest as u32,
+        shade_intolerant_kind: TileKind::ShadeIntolerantForest as u32,
+        growth_probability: probabilities.growth_probability.clamp(0.0, 1.0),
+        lightning_probability: probabilities.lightning_probability.clamp(0.0, 1.0),
+        moisture_dampening: forest.fire_susceptibility.moisture_dampening as f32,
+        spread_multiplier: forest.fire_spread.spread_multiplier as f32,
+        uphill_multiplier: forest.fire_spread.uphill_multiplier as f32,
+        prescribed_multiplier: forest.fire_spread.prescribed_multiplier(true) as f32,
+        intensity_multipliers,
+        ignition_half_saturation: forest.fire_fuel.ignition_half_saturation,
+        consumption_rate: forest.fire_fuel.consumption_rate,
+        burning_threshold: forest.fire_fuel.burning_threshold,
+        crown_fire_threshold: forest.fire_fuel.crown_fire_threshold,
+        isolation_multiplier: forest.seed_dispersal.isolation_multiplier,
+        shade_tolerant_threshold: forest.shade_succession.shade_tolerant_threshold,
+        exposed_tolerant_multiplier: forest.shade_succession.exposed_tolerant_multiplier,
+        intolerant_shade_penalty: forest.shade_succession.intolerant_shade_penalty,
+        growth_scaling: forest.soil_fertility.growth_scaling,
+        // Spread the strikes evenly over the map, rather than striking an exact number of tiles
+        lightning_strike_probability: (forest.lightning.strikes_per_tick as f32 / tile_count)
+            .min(1.0),
+        lightning_ignition_multiplier: forest.lightning.ignition_multiplier as f32,
+        climate_fire_multiplier: forest.climate.uniform_fire_multiplier() as f32,
+        climate_growth_multiplier: forest.climate.uniform_growth_multiplier(),
+        north_temperature: temperature_gradient.north_temperature,
+        south_temperature: temperature_gradient.south_temperature,
+        kinds,
+        post_fire_pathways,
+        minimum_ages,
+    };
+}
+
+/// Asks the GPU to advance the grid by one tick.
+///
+/// The GPU runs at most one tick per frame, so very short timesteps will run slower than requested.
+fn request_gpu_step(gpu_grid: Option<ResMut<GpuGrid>>) {
+    if let Some(mut gpu_grid) = gpu_grid {
+        gpu_grid.tick = gpu_grid.tick.wrapping_add(1);
+        gpu_grid.parameters.seed = gpu_grid.tick;
+    }
+}
+
+/// Mirrors the [`GpuGrid`] into the render world, removing it again once the GPU backend stops.
+fn extract_gpu_grid(gpu_grid: Extract<Option<Res<GpuGrid>>>, mut commands: Commands) {
+    match gpu_grid.as_ref() {
+        Some(gpu_grid) => commands.insert_resource(GpuGrid::clone(gpu_grid)),
+        None => commands.remove_resource::<GpuGrid>(),
+    }
+}
+
+#[derive(Resource)]
+struct GpuSimulationPipeline {
+    layout: BindGroupLayout,
+    /// Draws the grid without changing it, used when it is first uploaded.
+    draw_pipeline: CachedComputePipelineId,
+    /// Advances the grid by one tick of the [`Ruleset::DrosselSchwabl`], then draws it.
+    drossel_schwabl_pipeline: CachedComputePipelineId,
+    /// Advances the grid by one tick of the [`Ruleset::Forest`], then draws it.
+    forest_pipeline: CachedComputePipelineId,
+}
+
+impl GpuSimulationPipeline {
+    /// The pipeline that advances a grid running the given ruleset.
+    fn step_pipeline(&self, ruleset: Ruleset) -> CachedComputePipelineId {
+        match ruleset {
+            Ruleset::DrosselSchwabl => self.drossel_schwabl_pipeline,
+            _ => self.forest_pipeline,
+        }
+    }
+}
+
+impl FromWorld for GpuSimulationPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "GpuSimulation",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    texture_storage_2d(TextureFormat::Rgba32Uint, StorageTextureAccess::ReadOnly),
+                    texture_storage_2d(TextureFormat::Rgba32Uint, StorageTextureAccess::WriteOnly),
+                    texture_storage_2d(TextureFormat::Rgba32Float, StorageTextureAccess::ReadOnly),
+                    texture_storage_2d(TextureFormat::Rgba8Unorm, StorageTextureAccess::WriteOnly),
+                    storage_buffer::<Vec<u32>>(false),
+                    storage_buffer_read_only::<GpuSimulationParameters>(false),
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let queue_pipeline = |entry_point: &'static str| {
+            pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(format!("GPU simulation {entry_point}").into()),
+                layout: vec![layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs: vec![
+                    ShaderDefVal::UInt("KIND_COUNT".into(), TileKind::COUNT as u32),
+                    ShaderDefVal::UInt("MAX_TRANSITIONS".into(), MAX_TRANSITIONS as u32),
+                ],
+                entry_point: Cow::from(entry_point),
+                zero_initialize_workgroup_memory: false,
+            })
+        };
+
+        let draw_pipeline = queue_pipeline("draw");
+        let drossel_schwabl_pipeline = queue_pipeline("step_drossel_schwabl");
+        let forest_pipeline = queue_pipeline("step_forest");
+
+        Self {
+            layout,
+            draw_pipeline,
+            drossel_schwabl_pipeline,
+            forest_pipeline,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct GpuSimulationBindGroup {
+    bind_group: BindGroup,
+    /// The grid that this bind group was created for, so that a stale bind group is never used for a new grid.
+    grid: AssetId<Image>,
+}
+
+fn prepare_gpu_simulation_bind_group(
+    gpu_grid: Res<GpuGrid>,
+    pipeline: Res<GpuSimulationPipeline>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    buffers: Res<RenderAssets<GpuShaderStorageBuffer>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut commands: Commands,
+) {
+    // The textures take a frame or two to be uploaded
+    let (Some(current), Some(next), Some(terrain), Some(display), Some(counts)) = (
+        gpu_images.get(&gpu_grid.cells[0]),
+        gpu_images.get(&gpu_grid.cells[1]),
+        gpu_images.get(&gpu_grid.terrain),
+        gpu_images.get(&gpu_grid.display),
+        buffers.get(&gpu_grid.counts),
+    ) else {
+        return;
+    };
+
+    let mut parameters = StorageBuffer::from(gpu_grid.parameters);
+    parameters.write_buffer(&render_device, &render_queue);
+
+    let bind_group = render_device.create_bind_group(
+        "GpuSimulation",
+        &pipeline.layout,
+        &BindGroupEntries::sequential((
+            &current.texture_view,
+            &next.texture_view,
+            &terrain.texture_view,
+            &display.texture_view,
+            counts.buffer.as_entire_buffer_binding(),
+            &parameters,
+        )),
+    );
+
+    commands.insert_resource(GpuSimulationBindGroup {
+        bind_group,
+        grid: gpu_grid.cells[0].id(),
+    });
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct GpuSimulationLabel;
+
+/// The work that the [`GpuSimulationNode`] needs to do this frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GpuPass {
+    Draw,
+    Step,
+}
+
+/// Runs the compute shader whenever a new grid is uploaded or a tick is requested.
+#[derive(Default)]
+struct GpuSimulationNode {
+    pass: Option<GpuPass>,
+    /// The grid and tick that were last run, if any.
+    last_run: Option<(AssetId<Image>, u32)>,
+}
+
+impl render_graph::Node for GpuSimulationNode {
+    fn update(&mut self, world: &mut World) {
+        self.pass = None;
+
+        let (Some(gpu_grid), Some(bind_group)) = (
+            world.get_resource::<GpuGrid>(),
+            world.get_resource::<GpuSimulationBindGroup>(),
+        ) else {
+            self.last_run = None;
+            return;
+        };
+
+        let grid = gpu_grid.cells[0].id();
+        if bind_group.grid != grid {
+            return;
+        }
+
+        let pipeline = world.resource::<GpuSimulationPipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        for pipeline_id in [
+            pipeline.draw_pipeline,
+            pipeline.step_pipeline(gpu_grid.ruleset),
+        ] {
+            match pipeline_cache.get_compute_pipeline_state(pipeline_id) {
+                CachedPipelineState::Ok(_) => {}
+                CachedPipelineState::Err(error) => {
+                    error_once!("Failed to compile {SHADER_ASSET_PATH}: {error}");
+                    return;
+                }
+                _ => return,
+            }
+        }
+
+        self.pass = match self.last_run {
+            Some((last_grid, _)) if last_grid != grid => Some(GpuPass::Draw),
+            None => Some(GpuPass::Draw),
+            Some((_, last_tick)) if last_tick != gpu_grid.tick => Some(GpuPass::Step),
+            Some(_) => None,
+        };
+        self.last_run = Some((grid, gpu_grid.tick));
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(pass) = self.pass else {
+            return Ok(());
+        };
+
+        let (Some(gpu_grid), Some(bind_group)) = (
+            world.get_resource::<GpuGrid>(),
+            world.get_resource::<GpuSimulationBindGroup>(),
+        ) else {
+            return Ok(());
+        };
+
+        let pipeline = world.resource::<GpuSimulationPipeline>();
+        let pipeline_id = match pass {
+            GpuPass::Draw => pipeline.draw_pipeline,
+            GpuPass::Step => pipeline.step_pipeline(gpu_grid.ruleset),
+        };
+        let Some(compute_pipeline) = world
+            .resource::<PipelineCache>()
+            .get_compute_pipeline(pipeline_id)
+        else {
+            return Ok(());
+        };
+
+        let gpu_images = world.resource::<RenderAssets<GpuImage>>();
+        let buffers = world.resource::<RenderAssets<GpuShaderStorageBuffer>>();
+        let (Some(current), Some(next), Some(counts)) = (
+            gpu_images.get(&gpu_grid.cells[0]),
+            gpu_images.get(&gpu_grid.cells[1]),
+            buffers.get(&gpu_grid.counts),
+        ) else {
+            return Ok(());
+        };
+
+        let encoder = render_context.command_encoder();
+        // The shader adds to the counts, so they need to start from zero
+        encoder.clear_buffer(&counts.buffer, 0, None);
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
+            compute_pass.set_bind_group(0, &bind_group.bind_group, &[]);
+            compute_pass.set_pipeline(compute_pipeline);
+            compute_pass.dispatch_workgroups(
+                gpu_grid.size.x.div_ceil(WORKGROUP_SIZE),
+                gpu_grid.size.y.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+
+        // The next state becomes the current state, ready for the following tick and any readbacks
+        if pass == GpuPass::Step {
+            encoder.copy_texture_to_texture(
+                next.texture.as_image_copy(),
+                current.texture.as_image_copy(),
+                Extent3d {
+                    width: gpu_grid.size.x,
+                    height: gpu_grid.size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
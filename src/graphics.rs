@@ -1,49 +1,1024 @@
 //! Renders the graphics for the simulation.
 
-use bevy::{platform::collections::HashMap, prelude::*};
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use clap::ValueEnum;
 use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 
-use crate::control_flow::run_simulation;
-use crate::simulation::TileKind;
+use crate::SimState;
+use crate::cell_layers::{
+    CellLayers, FIRE_DISTANCE_LAYER, SUSCEPTIBILITY_LAYER, WATER_DISTANCE_LAYER,
+};
+use crate::control_flow::{Simulation, run_simulation};
+use crate::hillshade::HillshadeMap;
+use crate::outbreaks::Infested;
+use crate::reaction_diffusion::Concentrations;
+use crate::rulesets::{Ruleset, ruleset_is};
+use crate::sandpile::SandGrains;
+use crate::simulation::{Fertility, FireIntensity, FireState, RainStarted, TileKind};
+use crate::spatial_index::{GridLayer, GridShape, Position, Tile, TileIndex};
+use crate::tile_appearance::{Palette, TileAppearance};
 
 pub struct GraphicsPlugin;
 
 impl Plugin for GraphicsPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<TileImages>()
-            .add_systems(Update, update_tile_graphics.after(run_simulation));
+            .init_resource::<FertilityOverlay>()
+            .register_type::<FertilityOverlay>()
+            .init_resource::<LayerOverlay>()
+            .register_type::<LayerOverlay>()
+            .init_resource::<OverlayMode>()
+            .register_type::<OverlayMode>()
+            .init_resource::<ChangeHighlight>()
+            .register_type::<ChangeHighlight>()
+            .add_systems(Simulation, note_simulation_step)
+            .add_systems(
+                Update,
+                (
+                    collect_changed_tiles.after(run_simulation),
+                    draw_change_highlights.run_if(change_highlight_enabled),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    begin_transition_animations,
+                    update_tile_edges,
+                    update_tile_graphics,
+                )
+                    .chain()
+                    .after(run_simulation),
+            )
+            .add_systems(Update, update_tile_shapes)
+            .add_systems(
+                Update,
+                animate_tile_frames
+                    .after(update_tile_graphics)
+                    .after(update_tile_shapes),
+            )
+            .add_systems(
+                Update,
+                update_concentration_graphics
+                    .after(update_tile_graphics)
+                    .run_if(ruleset_is(Ruleset::ReactionDiffusion)),
+            )
+            .add_systems(
+                Update,
+                update_sand_graphics
+                    .after(update_tile_graphics)
+                    .run_if(ruleset_is(Ruleset::Sandpile)),
+            )
+            .add_systems(
+                Update,
+                select_overlay_layer.run_if(resource_changed::<OverlayMode>),
+            )
+            .add_systems(
+                Update,
+                update_layer_overlay
+                    .after(select_overlay_layer)
+                    .after(update_tile_graphics)
+                    .after(update_concentration_graphics)
+                    .after(update_sand_graphics),
+            )
+            .add_systems(
+                Update,
+                tween_transition_animations.after(update_layer_overlay),
+            )
+            .add_systems(Update, (spawn_rain_overlay, fade_rain_overlay))
+            .add_systems(OnEnter(SimState::Generate), despawn_rain_overlays);
     }
 }
 
-#[derive(Resource, Deref)]
+/// The generated textures used to draw tiles.
+///
+/// Their colors come from the [`TileAppearance`].
+#[derive(Resource)]
 struct TileImages {
-    colors: HashMap<TileKind, Color>,
+    /// The texture atlas used to draw square tiles.
+    square: TileAtlas,
+    /// The texture atlas used to draw tiles on a [`GridShape::Hexagonal`] grid.
+    hexagon: TileAtlas,
 }
 
 impl FromWorld for TileImages {
-    fn from_world(_world: &mut World) -> Self {
-        let mut colors = HashMap::new();
+    fn from_world(world: &mut World) -> Self {
+        let square = TileAtlas::new(world, GridShape::Square);
+        let hexagon = TileAtlas::new(world, GridShape::Hexagonal);
 
-        for variant in TileKind::iter() {
-            colors.insert(variant, variant.color());
+        Self { square, hexagon }
+    }
+}
+
+impl TileImages {
+    /// The texture atlas that matches the given [`GridShape`].
+    fn atlas(&self, grid_shape: &GridShape) -> &TileAtlas {
+        match grid_shape {
+            GridShape::Square => &self.square,
+            GridShape::Hexagonal => &self.hexagon,
+        }
+    }
+}
+
+/// A single image containing a texture for every [`TileKind`], laid out side by side.
+///
+/// Each row of the atlas holds one frame of animation, for kinds that are animated by the [`TileAppearance`].
+/// The rows are repeated for each of the [`TileEdges`] variants, which shade the sides of a tile that border another kind.
+/// Textures are drawn in shades of light gray, so that they can be tinted by each tile's color.
+/// They are generated when the app starts, so no art assets are needed.
+struct TileAtlas {
+    image: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+}
+
+impl TileAtlas {
+    /// The width of each texture in the atlas, in pixels.
+    const TEXTURE_WIDTH: u32 = 32;
+    /// The number of frames of animation drawn for every kind.
+    const FRAMES: u32 = 4;
+
+    fn new(world: &mut World, grid_shape: GridShape) -> Self {
+        let size = grid_shape.tile_size() * Self::TEXTURE_WIDTH as f32 / Position::PIXELS_PER_TILE;
+        let texture_size = UVec2::new(Self::TEXTURE_WIDTH, size.y.round() as u32);
+        let kinds = TileKind::iter().count() as u32;
+
+        let image =
+            world
+                .resource_mut::<Assets<Image>>()
+                .add(atlas_image(grid_shape, texture_size, kinds));
+        let layout =
+            world
+                .resource_mut::<Assets<TextureAtlasLayout>>()
+                .add(TextureAtlasLayout::from_grid(
+                    texture_size,
+                    kinds,
+                    Self::FRAMES * TileEdges::VARIANTS,
+                    None,
+                    None,
+                ));
+
+        Self { image, layout }
+    }
+
+    /// Returns the part of the atlas used to draw the given kind of tile, with the given edges.
+    fn texture(
+        &self,
+        tile_kind: &TileKind,
+        tile_appearance: &TileAppearance,
+        edges: TileEdges,
+    ) -> TextureAtlas {
+        TextureAtlas {
+            layout: self.layout.clone(),
+            index: frame_index(tile_kind, tile_appearance, 0, edges),
+        }
+    }
+}
+
+/// The position of each kind's own texture within a [`TileAtlas`].
+fn atlas_index(tile_kind: &TileKind) -> usize {
+    TileKind::iter()
+        .position(|kind| kind == *tile_kind)
+        .unwrap_or_default()
+}
+
+/// The texture used to draw the given frame of animation for the given kind of tile, with the given edges.
+///
+/// Frames past the end of the atlas wrap back around to the start.
+fn frame_index(
+    tile_kind: &TileKind,
+    tile_appearance: &TileAppearance,
+    frame: u32,
+    edges: TileEdges,
+) -> usize {
+    let row = (edges.variant() * TileAtlas::FRAMES + frame % TileAtlas::FRAMES) as usize;
+    row * TileKind::iter().count() + texture_index(tile_kind, tile_appearance)
+}
+
+/// The texture used to draw the given kind of tile, as chosen by the [`TileAppearance`].
+///
+/// Indexes past the end of the atlas fall back to the kind's own texture.
+fn texture_index(tile_kind: &TileKind, tile_appearance: &TileAppearance) -> usize {
+    tile_appearance
+        .texture_index(tile_kind)
+        .filter(|&index| index < TileKind::iter().count())
+        .unwrap_or_else(|| atlas_index(tile_kind))
+}
+
+/// Draws the texture for every kind of tile side by side, with each frame of animation in its own row.
+///
+/// Textures for hexagonal grids are clipped to a pointy-topped hexagon, leaving the corners transparent.
+/// Only square grids are autotiled, so each [`TileEdges`] variant of a hexagonal texture looks the same.
+fn atlas_image(grid_shape: GridShape, texture_size: UVec2, kinds: u32) -> Image {
+    let width = texture_size.x * kinds;
+    let height = texture_size.y * TileAtlas::FRAMES * TileEdges::VARIANTS;
+    let size = texture_size.as_vec2();
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+
+    for row in 0..height {
+        for column in 0..width {
+            let kind = TileKind::iter()
+                .nth((column / texture_size.x) as usize)
+                .unwrap_or(TileKind::Meadow);
+            let frame = row / texture_size.y % TileAtlas::FRAMES;
+            let edges = TileEdges((row / texture_size.y / TileAtlas::FRAMES) as u8);
+            let pixel = UVec2::new(column % texture_size.x, row % texture_size.y);
+
+            let offset = (pixel.as_vec2() + 0.5 - size / 2.0).abs();
+            let (inside, shade) = match grid_shape {
+                GridShape::Square => (true, edge_shade(pixel, texture_size, edges)),
+                GridShape::Hexagonal => (offset.y <= size.y / 2.0 - offset.x / 3.0_f32.sqrt(), 1.0),
+            };
+
+            let brightness =
+                (texture_brightness(kind, pixel, texture_size, frame) * shade * 255.0) as u8;
+            let alpha = if inside { 255 } else { 0 };
+
+            data.extend_from_slice(&[brightness, brightness, brightness, alpha]);
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    // Smoothing would blend the edges of each texture with its neighbors in the atlas
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+/// The brightness of a single pixel of the texture for the given kind of tile, between 0 and 1.
+///
+/// Each family of tile kinds gets a simple procedural pattern, kept subtle so that the colors still read clearly.
+/// Fire and water change from frame to frame, while every other kind looks the same in each frame.
+fn texture_brightness(tile_kind: TileKind, pixel: UVec2, texture_size: UVec2, frame: u32) -> f32 {
+    use TileKind::*;
+
+    let uv = (pixel.as_vec2() + 0.5) / texture_size.as_vec2();
+    // How far through the animation this frame is, as an angle
+    let phase = frame as f32 / TileAtlas::FRAMES as f32 * std::f32::consts::TAU;
+    // A cheap, repeatable hash of the pixel coordinates, between 0 and 1
+    let noise = {
+        let hash = (pixel.x.wrapping_mul(73_856_093) ^ pixel.y.wrapping_mul(19_349_663))
+            .wrapping_mul(2_654_435_761);
+        (hash >> 16) as f32 / u16::MAX as f32
+    };
+
+    match tile_kind {
+        // Scattered blades of grass
+        Meadow | InvasiveGrass | Shrubland | Burned => 0.85 + 0.15 * noise,
+        // Clumps of tree crowns, shaded on their lower edge
+        ShadeIntolerantForest | ShadeTolerantForest | DeadForest | Tree => {
+            let crowns = [
+                Vec2::new(0.3, 0.3),
+                Vec2::new(0.72, 0.4),
+                Vec2::new(0.45, 0.75),
+            ];
+            let nearest = crowns
+                .iter()
+                .map(|crown| uv.distance(*crown))
+                .fold(f32::INFINITY, f32::min);
+            (1.0 - nearest * 0.8).clamp(0.7, 1.0) - 0.05 * noise
+        }
+        // Gentle ripples, which roll across the tile over the course of the animation
+        Water => 0.85 + 0.15 * (uv.y * 18.0 + (uv.x * 6.0).sin() + phase / 2.0).sin().abs(),
+        // Flames licking upwards from the base of the tile, swaying from side to side
+        Fire => {
+            let sway = 0.1 * (uv.x * 12.0 + phase).sin();
+            0.7 + 0.3 * (uv.y + sway).clamp(0.0, 1.0) + 0.1 * noise
+        }
+        // Sand is smooth, with the odd pebble
+        Shore => {
+            if noise > 0.95 {
+                0.8
+            } else {
+                0.97
+            }
+        }
+        // Every other ruleset uses flat cells, so that patterns are easy to read
+        _ => 1.0,
+    }
+}
+
+/// How much each pixel of a square texture is darkened by the edges of the tile that border a different kind of tile.
+///
+/// Bordering sides fade into a darker rim, and the corner between two bordering sides is rounded off,
+/// so that the boundaries between kinds of tile follow smooth outlines rather than hard steps.
+fn edge_shade(pixel: UVec2, texture_size: UVec2, edges: TileEdges) -> f32 {
+    /// How far the rim reaches into the tile, in pixels.
+    const RIM_WIDTH: f32 = 6.0;
+    /// The radius of each rounded corner, in pixels.
+    const CORNER_RADIUS: f32 = 10.0;
+    /// How much darker the very edge of the rim is than the middle of the tile.
+    const RIM_DARKNESS: f32 = 0.35;
+
+    // Pixels are counted from the top of the texture, which is the north side of the tile
+    let point = pixel.as_vec2() + 0.5;
+    let size = texture_size.as_vec2();
+    let sides = [
+        (TileEdges::NORTH, point.y),
+        (TileEdges::SOUTH, size.y - point.y),
+        (TileEdges::EAST, size.x - point.x),
+        (TileEdges::WEST, point.x),
+    ];
+
+    // How far the pixel is inside the rounded outline formed by the bordering sides
+    let mut distance = f32::INFINITY;
+    for (side, side_distance) in sides {
+        if edges.borders(side) {
+            distance = distance.min(side_distance);
+        }
+    }
+    for (vertical, horizontal) in [(0, 2), (0, 3), (1, 2), (1, 3)] {
+        let (vertical_side, vertical_distance) = sides[vertical];
+        let (horizontal_side, horizontal_distance) = sides[horizontal];
+        if edges.borders(vertical_side)
+            && edges.borders(horizontal_side)
+            && vertical_distance < CORNER_RADIUS
+            && horizontal_distance < CORNER_RADIUS
+        {
+            let from_center = Vec2::new(
+                CORNER_RADIUS - horizontal_distance,
+                CORNER_RADIUS - vertical_distance,
+            );
+            distance = distance.min(CORNER_RADIUS - from_center.length());
+        }
+    }
+
+    let fade = (distance / RIM_WIDTH).clamp(0.0, 1.0);
+    1.0 - RIM_DARKNESS * (1.0 - fade)
+}
+
+/// Which sides of a tile border a different kind of tile, used to pick its variant in the [`TileAtlas`].
+///
+/// Each side is a single bit, so together they form one of sixteen variants:
+/// the cardinal subset of the usual "blob" autotiling patterns.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct TileEdges(u8);
+
+impl TileEdges {
+    /// The number of distinct combinations of edges.
+    const VARIANTS: u32 = 16;
+
+    // These match the order of `Position::cardinal_neighbors`
+    const NORTH: u8 = 1 << 0;
+    const SOUTH: u8 = 1 << 1;
+    const EAST: u8 = 1 << 2;
+    const WEST: u8 = 1 << 3;
+
+    /// Whether the given side borders a different kind of tile.
+    fn borders(&self, side: u8) -> bool {
+        self.0 & side != 0
+    }
+
+    /// The variant of each texture in the [`TileAtlas`] drawn with these edges.
+    fn variant(&self) -> u32 {
+        u32::from(self.0) % Self::VARIANTS
+    }
+}
+
+/// Works out which sides of each tile border a different kind of tile.
+///
+/// This is only recomputed for tiles whose neighborhood changed: a tile that changed kind, and each of its neighbors.
+/// Hexagonal grids aren't autotiled, so their tiles never have any edges.
+fn update_tile_edges(
+    changed_query: Query<&Position, (With<Tile>, Changed<TileKind>)>,
+    mut tile_query: Query<(Entity, &Position, &TileKind, Option<&mut TileEdges>), With<Tile>>,
+    tile_index: Res<TileIndex>,
+    grid_shape: Res<GridShape>,
+    mut commands: Commands,
+) {
+    let entities: Vec<Entity> = if grid_shape.is_changed() {
+        tile_query.iter().map(|(entity, ..)| entity).collect()
+    } else {
+        let mut entities: Vec<Entity> = changed_query
+            .iter()
+            .flat_map(|position| {
+                std::iter::once(*position)
+                    .chain(position.cardinal_neighbors())
+                    .filter_map(|position| tile_index.get(&position))
+            })
+            .collect();
+        entities.sort_unstable();
+        entities.dedup();
+        entities
+    };
+
+    for entity in entities {
+        let Ok((_, position, tile_kind, _)) = tile_query.get(entity) else {
+            continue;
+        };
+
+        let mut edges = TileEdges::default();
+        if *grid_shape == GridShape::Square {
+            for (bit, neighbor) in position.cardinal_neighbors().iter().enumerate() {
+                // The edge of the map counts as more of the same
+                let borders = tile_index
+                    .get(neighbor)
+                    .and_then(|neighbor| tile_query.get(neighbor).ok())
+                    .is_some_and(|(_, _, neighbor_kind, _)| neighbor_kind != tile_kind);
+                if borders {
+                    edges.0 |= 1 << bit;
+                }
+            }
+        }
+
+        match tile_query.get_mut(entity) {
+            Ok((.., Some(mut tile_edges))) => {
+                tile_edges.set_if_neq(edges);
+            }
+            Ok((.., None)) => {
+                commands.entity(entity).insert(edges);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Swaps each tile's sprite to match the current [`GridShape`], using the matching [`TileAtlas`].
+fn update_tile_shapes(
+    grid_shape: Res<GridShape>,
+    tile_images: Res<TileImages>,
+    tile_appearance: Res<TileAppearance>,
+    mut tile_query: Query<(Ref<Tile>, &TileKind, Option<&TileEdges>, &mut Sprite)>,
+) {
+    for (tile, tile_kind, edges, mut sprite) in tile_query.iter_mut() {
+        if !grid_shape.is_changed() && !tile.is_added() {
+            continue;
+        }
+
+        let atlas = tile_images.atlas(&grid_shape);
+        sprite.image = atlas.image.clone();
+        sprite.texture_atlas = Some(atlas.texture(
+            tile_kind,
+            &tile_appearance,
+            edges.copied().unwrap_or_default(),
+        ));
+        sprite.custom_size = Some(grid_shape.tile_size());
+    }
+}
+
+/// Steps every animated tile on to the current frame of its [`FrameAnimation`](crate::tile_appearance::FrameAnimation).
+///
+/// All tiles of the same kind share a frame, so this only touches the tiles of kinds whose frame just changed.
+fn animate_tile_frames(
+    mut tile_query: Query<(&TileKind, Option<&TileEdges>, &mut Sprite), With<Tile>>,
+    tile_appearance: Res<TileAppearance>,
+    time: Res<Time>,
+    mut shown_frames: Local<HashMap<TileKind, u32>>,
+) {
+    let elapsed = time.elapsed_secs();
+    let mut changed_kinds = Vec::new();
+
+    for tile_kind in TileKind::iter() {
+        let frame = tile_appearance
+            .animation(&tile_kind)
+            .map_or(0, |animation| animation.frame_at(elapsed));
+
+        // Changing the appearance resets every tile's texture, so each one needs its frame restoring
+        if shown_frames.insert(tile_kind, frame) != Some(frame) || tile_appearance.is_changed() {
+            changed_kinds.push(tile_kind);
+        }
+    }
+
+    if changed_kinds.is_empty() {
+        return;
+    }
+
+    for (tile_kind, edges, mut sprite) in tile_query.iter_mut() {
+        if !changed_kinds.contains(tile_kind) {
+            continue;
+        }
+
+        let index = frame_index(
+            tile_kind,
+            &tile_appearance,
+            shown_frames[tile_kind],
+            edges.copied().unwrap_or_default(),
+        );
+        if let Some(texture_atlas) = sprite.texture_atlas.as_mut()
+            && texture_atlas.index != index
+        {
+            texture_atlas.index = index;
+        }
+    }
+}
+
+/// When enabled, tiles are colored by their [`Fertility`] rather than their kind.
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct FertilityOverlay {
+    pub enabled: bool,
+}
+
+/// When enabled, every tile whose kind changed in the most recent simulation step is outlined.
+///
+/// This makes slow succession visible at a glance, and is handy for checking that a rule change does what it should.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct ChangeHighlight {
+    pub enabled: bool,
+    /// The color of the outlines.
+    pub color: Color,
+    /// Whether a simulation step has run since the changed tiles were last collected.
+    step_ran: bool,
+    /// The tiles whose kind changed in the most recent simulation step.
+    #[reflect(ignore)]
+    changed: Vec<Position>,
+}
+
+impl Default for ChangeHighlight {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: Color::srgb(1.0, 0.2, 0.9),
+            step_ran: false,
+            changed: Vec::new(),
+        }
+    }
+}
+
+/// A run condition that checks whether tiles that changed are being highlighted.
+fn change_highlight_enabled(change_highlight: Res<ChangeHighlight>) -> bool {
+    change_highlight.enabled
+}
+
+fn note_simulation_step(mut change_highlight: ResMut<ChangeHighlight>) {
+    if change_highlight.enabled {
+        change_highlight.step_ran = true;
+    }
+}
+
+/// Replaces the highlighted tiles with those whose kind changed, once each simulation step has run.
+///
+/// This runs every frame, even while highlighting is off,
+/// so that turning it on doesn't pick up every change made in the meantime.
+fn collect_changed_tiles(
+    tile_query: Query<&Position, (With<Tile>, Changed<TileKind>)>,
+    mut change_highlight: ResMut<ChangeHighlight>,
+) {
+    if !change_highlight.enabled {
+        change_highlight.bypass_change_detection().changed.clear();
+        return;
+    }
+
+    if !change_highlight.step_ran {
+        return;
+    }
+
+    change_highlight.step_ran = false;
+    change_highlight.changed = tile_query.iter().copied().collect();
+}
+
+fn draw_change_highlights(
+    change_highlight: Res<ChangeHighlight>,
+    grid_shape: Res<GridShape>,
+    mut gizmos: Gizmos,
+) {
+    // Outlines are drawn slightly inside each tile, so that neighboring outlines don't merge
+    let tile_size = grid_shape.tile_size() * 0.85;
+    let hexagon = RegularPolygon::new(tile_size.y / 2.0, 6);
+
+    for position in &change_highlight.changed {
+        let center = grid_shape.world_position(position);
+        match *grid_shape {
+            GridShape::Square => gizmos.rect_2d(center, tile_size, change_highlight.color),
+            GridShape::Hexagonal => {
+                gizmos.primitive_2d(&hexagon, center, change_highlight.color);
+            }
         }
+    }
+}
+
+/// When set, tiles are colored by the value of the named [`CellLayer`](crate::cell_layers::CellLayer),
+/// taking priority over every other coloring.
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct LayerOverlay {
+    pub layer: Option<String>,
+}
 
-        Self { colors }
+/// A quick way to pick one of the most useful layers to show with the [`LayerOverlay`].
+///
+/// Each mode draws a heatmap of a single per-tile value, which is handy for debugging and teaching.
+#[derive(Resource, Reflect, ValueEnum, EnumIter, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum OverlayMode {
+    /// Tiles are drawn normally.
+    #[default]
+    None,
+    /// How easily each tile would catch fire, taking its moisture into account.
+    Susceptibility,
+    /// How many ticks each tile has spent as its current kind.
+    Age,
+    Moisture,
+    Elevation,
+    /// How much fuel each tile would give a fire.
+    Fuel,
+    /// How many tiles away the nearest water is.
+    DistanceToWater,
+    /// How many tiles away the nearest fire is.
+    DistanceToFire,
+}
+
+impl OverlayMode {
+    /// The name of the [`CellLayer`](crate::cell_layers::CellLayer) that this mode shows.
+    pub fn layer_name(&self) -> Option<&'static str> {
+        match self {
+            OverlayMode::None => None,
+            OverlayMode::Susceptibility => Some(SUSCEPTIBILITY_LAYER),
+            OverlayMode::Age => Some("age"),
+            OverlayMode::Moisture => Some("moisture"),
+            OverlayMode::Elevation => Some("elevation"),
+            OverlayMode::Fuel => Some("fuel"),
+            OverlayMode::DistanceToWater => Some(WATER_DISTANCE_LAYER),
+            OverlayMode::DistanceToFire => Some(FIRE_DISTANCE_LAYER),
+        }
+    }
+
+    /// The mode after this one, wrapping back around to [`OverlayMode::None`].
+    pub fn next(&self) -> OverlayMode {
+        OverlayMode::iter()
+            .cycle()
+            .skip_while(|mode| mode != self)
+            .nth(1)
+            .unwrap_or_default()
     }
 }
 
+/// Shows the layer chosen by the [`OverlayMode`].
+fn select_overlay_layer(overlay_mode: Res<OverlayMode>, mut layer_overlay: ResMut<LayerOverlay>) {
+    // The resource counts as changed when it is first added, but there's nothing to show yet
+    if overlay_mode.is_added() {
+        return;
+    }
+
+    layer_overlay.layer = overlay_mode.layer_name().map(str::to_string);
+}
+
+/// Infested tiles are tinted a sickly yellow-brown, so that outbreaks can be spotted before the trees die.
+const INFESTED_COLOR: Color = Color::hsl(55., 0.4, 0.3);
+
+/// Recolors each tile whenever anything that affects its appearance changes.
+///
+/// Each tile's texture is picked from the [`TileAtlas`] to match its kind and its [`TileEdges`].
+/// By default, tiles are colored by their kind, as set in the [`TileAppearance`], with burning tiles colored by their [`FireIntensity`]
+/// and infested tiles tinted to make outbreaks visible.
+/// Apart from fires, which give off their own light, these colors are then shaded by the [`HillshadeMap`].
+/// When the [`FertilityOverlay`] is enabled, tiles are instead colored by their fertility.
+#[allow(clippy::type_complexity)]
 fn update_tile_graphics(
-    mut tile_query: Query<(&mut Sprite, &TileKind), Changed<TileKind>>,
-    tile_materials: ResMut<TileImages>,
+    mut tile_query: Query<(
+        &mut Sprite,
+        &Position,
+        Ref<TileKind>,
+        Option<Ref<FireState>>,
+        Option<Ref<Infested>>,
+        Ref<Fertility>,
+        Option<Ref<TileEdges>>,
+    )>,
+    tile_appearance: Res<TileAppearance>,
+    palette: Res<Palette>,
+    hillshade_map: Res<HillshadeMap>,
+    fertility_overlay: Res<FertilityOverlay>,
+    layer_overlay: Res<LayerOverlay>,
 ) {
-    for (mut sprite, succession_state) in tile_query.iter_mut() {
-        let Some(new_color) = tile_materials.get(succession_state) else {
-            warn_once!("Tile graphics not found for {succession_state:?}");
+    // Turning off the layer overlay needs to restore the normal colors
+    let overlay_changed = fertility_overlay.is_changed() || layer_overlay.is_changed();
+    let appearance_changed = tile_appearance.is_changed();
+    let shading_changed = hillshade_map.is_changed();
+
+    for (mut sprite, position, tile_kind, fire_state, infested, fertility, edges) in
+        tile_query.iter_mut()
+    {
+        let edges_changed = edges.as_ref().is_some_and(|edges| edges.is_changed());
+        let needs_update = overlay_changed
+            || edges_changed
+            || appearance_changed
+            || shading_changed
+            || tile_kind.is_changed()
+            || fire_state
+                .as_ref()
+                .is_some_and(|fire_state| fire_state.is_changed())
+            || infested
+                .as_ref()
+                .is_some_and(|infested| infested.is_added())
+            || (fertility_overlay.enabled && fertility.is_changed());
+
+        if !needs_update {
+            continue;
+        }
+
+        if (tile_kind.is_changed() || appearance_changed || edges_changed)
+            && let Some(texture_atlas) = sprite.texture_atlas.as_mut()
+        {
+            let edges = edges.as_deref().copied().unwrap_or_default();
+            texture_atlas.index = frame_index(&tile_kind, &tile_appearance, 0, edges);
+        }
 
+        if fertility_overlay.enabled {
+            sprite.color = fertility_color(fertility.0, *palette);
             continue;
+        }
+
+        // Fire state and infestations are cleaned up on the following tick,
+        // so we need to check that they still apply to this kind of tile
+        if let Some(fire_state) = fire_state.filter(|_| *tile_kind == TileKind::Fire) {
+            sprite.color = palette.fire_color(fire_state.intensity);
+        } else if infested.is_some() && *tile_kind != TileKind::DeadForest {
+            sprite.color = hillshade_map.shade(INFESTED_COLOR, position);
+        } else {
+            sprite.color = hillshade_map.shade(tile_appearance.color(&tile_kind), position);
+        }
+    }
+}
+
+/// A color ramp from barren brown to rich green, used to visualize fertility.
+///
+/// Other palettes swap the green for a blue, or stretch the ramp from black to white.
+fn fertility_color(fertility: f32, palette: Palette) -> Color {
+    let fertility = fertility.clamp(0.0, 1.0);
+    match palette {
+        Palette::Default => Color::hsl(30.0.lerp(110.0, fertility), 0.6, 0.15.lerp(0.5, fertility)),
+        Palette::Deuteranopia | Palette::Protanopia => {
+            Color::hsl(30.0.lerp(200.0, fertility), 0.6, 0.15.lerp(0.6, fertility))
+        }
+        Palette::HighContrast => Color::hsl(100., 0.6, 0.02.lerp(0.95, fertility)),
+    }
+}
+
+/// Colors each tile by the concentration of `v`, for the [`Ruleset::ReactionDiffusion`] ruleset.
+///
+/// This runs after [`update_tile_graphics`], so that it overrides the plain color of the tile's kind.
+fn update_concentration_graphics(
+    mut tile_query: Query<(&mut Sprite, Ref<Concentrations>, Ref<TileKind>)>,
+    layer_overlay: Res<LayerOverlay>,
+) {
+    for (mut sprite, concentrations, tile_kind) in tile_query.iter_mut() {
+        if concentrations.is_changed() || tile_kind.is_changed() || layer_overlay.is_changed() {
+            sprite.color = concentration_color(concentrations.v);
+        }
+    }
+}
+
+/// A color ramp from deep blue through teal to pale yellow, used to visualize chemical concentrations.
+fn concentration_color(concentration: f32) -> Color {
+    // In practice, v rarely climbs much above 0.4, so stretch the ramp to make the most of it
+    let concentration = (concentration * 2.5).clamp(0.0, 1.0);
+    Color::hsl(
+        230.0.lerp(60.0, concentration),
+        0.7,
+        0.1.lerp(0.8, concentration),
+    )
+}
+
+/// Colors each tile by the number of [`SandGrains`] piled on it, for the [`Ruleset::Sandpile`] ruleset.
+///
+/// Like [`update_concentration_graphics`], this overrides the plain color of the tile's kind.
+fn update_sand_graphics(
+    mut tile_query: Query<(&mut Sprite, Ref<SandGrains>, Ref<TileKind>)>,
+    layer_overlay: Res<LayerOverlay>,
+) {
+    for (mut sprite, sand_grains, tile_kind) in tile_query.iter_mut() {
+        if sand_grains.is_changed() || tile_kind.is_changed() || layer_overlay.is_changed() {
+            sprite.color = sand_color(sand_grains.0);
+        }
+    }
+}
+
+/// A color ramp from dark to bright sand, with piles that are about to topple drawn in red.
+fn sand_color(grains: u32) -> Color {
+    if grains >= SandGrains::TOPPLING_THRESHOLD {
+        return Color::hsl(0., 0.8, 0.5);
+    }
+
+    let fullness = grains as f32 / (SandGrains::TOPPLING_THRESHOLD - 1) as f32;
+    Color::hsl(40., 0.6, 0.1.lerp(0.75, fullness))
+}
+
+/// Colors every tile by the value of the layer selected in the [`LayerOverlay`].
+///
+/// When the overlay is turned off, the other graphics systems restore the normal colors.
+fn update_layer_overlay(
+    layer_overlay: Res<LayerOverlay>,
+    cell_layers: Res<CellLayers>,
+    palette: Res<Palette>,
+    mut tile_query: Query<(&mut Sprite, &Position), With<Tile>>,
+) {
+    let Some(name) = &layer_overlay.layer else {
+        return;
+    };
+
+    if !layer_overlay.is_changed() && !cell_layers.is_changed() && !palette.is_changed() {
+        return;
+    }
+
+    let Some(layer) = cell_layers.layer(name) else {
+        warn_once!("Cannot overlay the {name} layer, as it does not exist");
+        return;
+    };
+
+    let (min, max) = layer.display_range;
+    for (mut sprite, position) in tile_query.iter_mut() {
+        let value = cell_layers.get(name, position).unwrap_or(min);
+        let fraction = if max > min {
+            (value - min) / (max - min)
+        } else {
+            0.0
         };
+        sprite.color = layer_color(fraction, *palette);
+    }
+}
+
+/// A perceptually-ordered color ramp from dark purple to bright yellow, used to visualize arbitrary layers.
+///
+/// This is already readable without red or green, but the [`Palette::HighContrast`] stretches it to span nearly black to nearly white.
+fn layer_color(fraction: f32, palette: Palette) -> Color {
+    let fraction = fraction.clamp(0.0, 1.0);
+    let lightness = match palette {
+        Palette::HighContrast => 0.03.lerp(0.9, fraction),
+        _ => 0.15.lerp(0.7, fraction),
+    };
+    Color::hsl(280.0.lerp(60.0, fraction), 0.7, lightness)
+}
+
+/// A translucent overlay drawn above tiles that are being rained on, which fades out over time.
+#[derive(Component)]
+struct RainOverlay(Timer);
+
+impl RainOverlay {
+    /// How long the overlay takes to fade out, in seconds.
+    const DURATION: f32 = 1.5;
+    /// The color of the overlay when it first appears.
+    const COLOR: Color = Color::srgba(0.4, 0.6, 1.0, 0.5);
+}
+
+/// Draws a [`RainOverlay`] over each tile that starts being rained on.
+///
+/// Overlays live on their own [`GridLayer`], so tiles that are rained on again simply have their overlay refreshed.
+fn spawn_rain_overlay(
+    mut event_reader: EventReader<RainStarted>,
+    tile_query: Query<(&Position, &Transform, &Sprite), Without<RainOverlay>>,
+    mut overlay_query: Query<(&mut RainOverlay, &mut Sprite)>,
+    tile_index: Res<TileIndex>,
+    mut commands: Commands,
+) {
+    for event in event_reader.read() {
+        for &tile_entity in &event.tiles {
+            let Ok((position, tile_transform, tile_sprite)) = tile_query.get(tile_entity) else {
+                continue;
+            };
+
+            if let Some(overlay_entity) = tile_index.get_in_layer(GridLayer::Overlay, position)
+                && let Ok((mut overlay, mut sprite)) = overlay_query.get_mut(overlay_entity)
+            {
+                overlay.0.reset();
+                sprite.color = RainOverlay::COLOR;
+                continue;
+            }
 
-        sprite.color = new_color.clone();
+            // Draw the overlay just above the tile itself
+            let transform = tile_transform.with_translation(tile_transform.translation + Vec3::Z);
+
+            commands.spawn((
+                Name::new("Rain overlay"),
+                RainOverlay(Timer::from_seconds(RainOverlay::DURATION, TimerMode::Once)),
+                *position,
+                GridLayer::Overlay,
+                Sprite {
+                    color: RainOverlay::COLOR,
+                    // Match the shape of the tile underneath
+                    image: tile_sprite.image.clone(),
+                    texture_atlas: tile_sprite.texture_atlas.clone(),
+                    custom_size: tile_sprite.custom_size,
+                    ..Default::default()
+                },
+                transform,
+            ));
+        }
+    }
+}
+
+fn fade_rain_overlay(
+    mut overlay_query: Query<(Entity, &mut RainOverlay, &mut Sprite)>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, mut overlay, mut sprite) in overlay_query.iter_mut() {
+        overlay.0.tick(time.delta());
+
+        if overlay.0.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            let alpha = RainOverlay::COLOR.alpha() * overlay.0.fraction_remaining();
+            sprite.color = RainOverlay::COLOR.with_alpha(alpha);
+        }
+    }
+}
+
+/// Clears away any overlays that are still fading when the map is regenerated, since the tiles beneath them are gone.
+fn despawn_rain_overlays(overlay_query: Query<Entity, With<RainOverlay>>, mut commands: Commands) {
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Fades a tile from its old color to its new one after its kind changes,
+/// so that large waves of transitions are easy to follow.
+#[derive(Component)]
+struct TransitionAnimation {
+    /// The color the tile was drawn in when its kind changed.
+    from: Color,
+    /// The color the tile is fading towards.
+    to: Color,
+    /// The color the tile was drawn in during the last frame.
+    ///
+    /// If the sprite's color no longer matches this, another system has recolored the tile,
+    /// so the fade heads for that new color instead.
+    shown: Color,
+    timer: Timer,
+}
+
+impl TransitionAnimation {
+    /// How long the fade takes, in seconds.
+    const DURATION: f32 = 0.3;
+}
+
+/// Starts a [`TransitionAnimation`] for each tile whose kind just changed.
+///
+/// This runs before the tile is recolored, so that we can capture the color it was drawn in.
+/// Tiles that change again partway through a fade start over from whatever color they are currently drawn in.
+fn begin_transition_animations(
+    tile_query: Query<(Entity, Ref<TileKind>, &Sprite), Changed<TileKind>>,
+    mut commands: Commands,
+) {
+    for (entity, tile_kind, sprite) in tile_query.iter() {
+        // Freshly spawned tiles have nothing to fade from
+        if tile_kind.is_added() {
+            continue;
+        }
+
+        commands.entity(entity).insert(TransitionAnimation {
+            from: sprite.color,
+            to: sprite.color,
+            shown: sprite.color,
+            timer: Timer::from_seconds(TransitionAnimation::DURATION, TimerMode::Once),
+        });
+    }
+}
+
+/// Blends the color of each animating tile from its old color towards its new one.
+///
+/// This runs after every other system that colors tiles, and picks up whatever color they chose as the target.
+fn tween_transition_animations(
+    mut tile_query: Query<(Entity, &mut TransitionAnimation, &mut Sprite)>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (entity, mut animation, mut sprite) in tile_query.iter_mut() {
+        animation.timer.tick(time.delta());
+
+        if sprite.color != animation.shown {
+            animation.to = sprite.color;
+        }
+
+        if animation.timer.finished() {
+            sprite.color = animation.to;
+            commands.entity(entity).remove::<TransitionAnimation>();
+        } else {
+            // Blending in Oklab keeps the brightness even partway through the fade
+            let shown: Color = Oklaba::from(animation.from)
+                .mix(&Oklaba::from(animation.to), animation.timer.fraction())
+                .into();
+            sprite.color = shown;
+            animation.shown = shown;
+        }
+    }
+}
+
+impl FireIntensity {
+    /// The color associated with this fire intensity.
+    ///
+    /// Hotter fires are brighter and more yellow.
+    pub fn color(&self) -> Color {
+        use FireIntensity::*;
+
+        match self {
+            Smoldering => Color::hsl(10., 0.6, 0.35),
+            Burning => Color::hsl(20., 0.8, 0.5),
+            CrownFire => Color::hsl(45., 1.0, 0.6),
+        }
     }
 }
 
@@ -61,6 +1036,23 @@ impl TileKind {
             ShadeTolerantForest => Color::hsl(84., 0.2, 0.2),
             Water => Color::hsl(210., 0.5, 0.5),
             Fire => Color::hsl(20., 0.8, 0.5),
+            Burned => Color::hsl(30., 0.1, 0.15),
+            DeadForest => Color::hsl(30., 0.3, 0.35),
+            InvasiveGrass => Color::hsl(50., 0.7, 0.65),
+            Firebreak => Color::hsl(35., 0.25, 0.6),
+            Shore => Color::hsl(45., 0.55, 0.78),
+            Alive => Color::hsl(60., 0.3, 0.9),
+            Dead => Color::hsl(230., 0.2, 0.12),
+            Empty => Color::hsl(0., 0.0, 0.08),
+            Conductor => Color::hsl(40., 0.9, 0.5),
+            ElectronHead => Color::hsl(210., 1.0, 0.6),
+            ElectronTail => Color::hsl(0., 0.9, 0.55),
+            White => Color::hsl(0., 0.0, 0.95),
+            Black => Color::hsl(0., 0.0, 0.05),
+            Tree => Color::hsl(120., 0.5, 0.3),
+            Rock => Color::hsl(0., 0.75, 0.55),
+            Paper => Color::hsl(210., 0.75, 0.55),
+            Scissors => Color::hsl(50., 0.85, 0.55),
         }
     }
 }
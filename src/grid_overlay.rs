@@ -0,0 +1,231 @@
+//! Draws the lines between tiles, and labels each tile with its coordinates once zoomed in far enough.
+//!
+//! This is invaluable when debugging which tiles count as neighbors, or explaining how the grid is addressed,
+//! particularly on hexagonal grids, whose axial coordinates are easy to mix up.
+//! Toggle it with the button in the GUI, or the `grid_lines` console command.
+
+use bevy::prelude::*;
+
+use crate::camera::MainCamera;
+use crate::map_generation::{GeneratedMapSize, MapSize};
+use crate::spatial_index::{GridShape, Position};
+
+pub struct GridOverlayPlugin;
+
+impl Plugin for GridOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GridOverlay>()
+            .register_type::<GridOverlay>()
+            .add_systems(
+                Update,
+                (
+                    draw_grid_lines.run_if(grid_overlay_enabled),
+                    update_coordinate_labels,
+                ),
+            );
+    }
+}
+
+/// Controls the overlay of grid lines and tile coordinates.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct GridOverlay {
+    /// Whether the overlay is shown.
+    pub enabled: bool,
+    /// The color of the lines between tiles.
+    pub line_color: Color,
+    /// Grid lines are only drawn once each tile is at least this many pixels across, as they would cover the map otherwise.
+    pub min_line_pixels: f32,
+    /// Coordinates are only shown once each tile is at least this many pixels across, so that they have room to fit.
+    pub min_label_pixels: f32,
+}
+
+impl Default for GridOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            line_color: Color::srgba(1.0, 1.0, 1.0, 0.3),
+            min_line_pixels: 6.0,
+            min_label_pixels: 48.0,
+        }
+    }
+}
+
+/// A run condition that checks whether the grid overlay is shown.
+fn grid_overlay_enabled(grid_overlay: Res<GridOverlay>) -> bool {
+    grid_overlay.enabled
+}
+
+/// The tiles on the map that the main camera can currently see, along with how large each tile appears on screen.
+struct VisibleTiles {
+    /// The width of each tile on screen, in pixels.
+    tile_pixels: f32,
+    /// The lowest coordinates of any visible tile.
+    min: IVec2,
+    /// The highest coordinates of any visible tile.
+    max: IVec2,
+}
+
+impl VisibleTiles {
+    /// Finds the tiles in view of the given camera, or `None` if none of the map is in view.
+    fn new(
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        grid_shape: &GridShape,
+        map_size: &MapSize,
+    ) -> Option<Self> {
+        let viewport = camera.logical_viewport_rect()?;
+        let corner = camera
+            .viewport_to_world_2d(camera_transform, viewport.min)
+            .ok()?;
+        let opposite_corner = camera
+            .viewport_to_world_2d(camera_transform, viewport.max)
+            .ok()?;
+        let view = Rect::from_corners(corner, opposite_corner);
+        let tile_pixels =
+            viewport.width() / view.width().max(f32::EPSILON) * Position::PIXELS_PER_TILE;
+
+        // Hexagonal grids skew the view into a rhombus, so we need to check every corner
+        let corners = [
+            view.min,
+            Vec2::new(view.max.x, view.min.y),
+            view.max,
+            Vec2::new(view.min.x, view.max.y),
+        ]
+        .map(|corner| grid_shape.world_to_grid(corner));
+        let bounds = corners.iter().fold(
+            Rect::from_corners(corners[0], corners[0]),
+            |bounds, corner| bounds.union_point(*corner),
+        );
+
+        let min = bounds.min.round().as_ivec2().max(IVec2::ZERO);
+        let max = bounds
+            .max
+            .round()
+            .as_ivec2()
+            .min(IVec2::new(map_size.width, map_size.height) - 1);
+        if min.cmpgt(max).any() {
+            return None;
+        }
+
+        Some(Self {
+            tile_pixels,
+            min,
+            max,
+        })
+    }
+}
+
+fn draw_grid_lines(
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_overlay: Res<GridOverlay>,
+    grid_shape: Res<GridShape>,
+    map_size: Res<GeneratedMapSize>,
+    mut gizmos: Gizmos,
+) {
+    let (camera, camera_transform) = *camera;
+    let Some(visible) = VisibleTiles::new(camera, camera_transform, &grid_shape, &map_size) else {
+        return;
+    };
+    if visible.tile_pixels < grid_overlay.min_line_pixels {
+        return;
+    }
+
+    match *grid_shape {
+        GridShape::Square => {
+            // Lines run along the edges of the tiles, half a tile either side of their centers
+            let min = visible.min.as_vec2() - 0.5;
+            let max = visible.max.as_vec2() + 0.5;
+
+            for x in visible.min.x..=visible.max.x + 1 {
+                let x = x as f32 - 0.5;
+                gizmos.line_2d(
+                    grid_shape.grid_to_world(Vec2::new(x, min.y)),
+                    grid_shape.grid_to_world(Vec2::new(x, max.y)),
+                    grid_overlay.line_color,
+                );
+            }
+
+            for y in visible.min.y..=visible.max.y + 1 {
+                let y = y as f32 - 0.5;
+                gizmos.line_2d(
+                    grid_shape.grid_to_world(Vec2::new(min.x, y)),
+                    grid_shape.grid_to_world(Vec2::new(max.x, y)),
+                    grid_overlay.line_color,
+                );
+            }
+        }
+        GridShape::Hexagonal => {
+            // Hexagons are pointy-topped, matching the vertex that regular polygons start from
+            let hexagon = RegularPolygon::new(grid_shape.tile_size().y / 2.0, 6);
+
+            for y in visible.min.y..=visible.max.y {
+                for x in visible.min.x..=visible.max.x {
+                    let center = grid_shape.world_position(&Position { x, y });
+                    gizmos.primitive_2d(&hexagon, center, grid_overlay.line_color);
+                }
+            }
+        }
+    }
+}
+
+/// A label showing the coordinates of the tile beneath it.
+///
+/// Labels are reused as the camera moves, with any that aren't needed hidden.
+#[derive(Component)]
+struct CoordinateLabel;
+
+impl CoordinateLabel {
+    /// The size of the text, in world units, which is scaled along with the map as the camera zooms.
+    const FONT_SIZE: f32 = 9.0;
+}
+
+/// Labels every visible tile with its coordinates, once the camera is zoomed in far enough.
+fn update_coordinate_labels(
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_overlay: Res<GridOverlay>,
+    grid_shape: Res<GridShape>,
+    map_size: Res<GeneratedMapSize>,
+    mut label_query: Query<(&mut Text2d, &mut Transform, &mut Visibility), With<CoordinateLabel>>,
+    mut commands: Commands,
+) {
+    let (camera, camera_transform) = *camera;
+    let visible =
+        VisibleTiles::new(camera, camera_transform, &grid_shape, &map_size).filter(|visible| {
+            grid_overlay.enabled && visible.tile_pixels >= grid_overlay.min_label_pixels
+        });
+
+    let mut positions = visible.into_iter().flat_map(|visible| {
+        (visible.min.y..=visible.max.y)
+            .flat_map(move |y| (visible.min.x..=visible.max.x).map(move |x| Position { x, y }))
+    });
+
+    for (mut text, mut transform, mut visibility) in label_query.iter_mut() {
+        let Some(position) = positions.next() else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+
+        let label = format!("{},{}", position.x, position.y);
+        if text.0 != label {
+            text.0 = label;
+        }
+        // Draw the labels above the tiles and everything drawn on top of them
+        let translation = grid_shape.world_position(&position).extend(3.0);
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+        visibility.set_if_neq(Visibility::Inherited);
+    }
+
+    // Spawn more labels for whatever tiles are left over
+    for position in positions {
+        commands.spawn((
+            Name::new("Coordinate label"),
+            CoordinateLabel,
+            Text2d::new(format!("{},{}", position.x, position.y)),
+            TextFont::from_font_size(CoordinateLabel::FONT_SIZE),
+            Transform::from_translation(grid_shape.world_position(&position).extend(3.0)),
+        ));
+    }
+}
@@ -0,0 +1,2001 @@
+//! The graphical user interface, laid out around the simulation.
+//!
+//! Controls live in the left panel, while statistics about the running simulation are shown in the right panel.
+
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+use strum::IntoEnumIterator;
+
+use crate::SimState;
+use crate::carbon::CarbonBudget;
+use crate::census::TileCensus;
+use crate::climate::{Drought, Season};
+use crate::control_flow::{
+    PauseSimulation, ResetSimulation, SetSimulationTimestep, SimulationStepTime, StepSimulation,
+    UnpauseSimulation,
+};
+use crate::graphics::{FertilityOverlay, OverlayMode};
+use crate::grid_overlay::GridOverlay;
+use crate::map_export::ExportMap;
+use crate::map_generation::{
+    GenerationPhase, GenerationProgress, MapPreset, MapSeed, MapSize, NoisePeriod, RegenerateMap,
+    RerollMap, WaterThreshold,
+};
+use crate::minimap::{MinimapImage, MinimapViewRectangle, MinimapWidget};
+use crate::painting::Brush;
+use crate::population_chart::PopulationChart;
+use crate::prescribed_burns::PrescribedBurnTool;
+use crate::rulesets::{ForestFireProbabilities, Ruleset};
+use crate::simulation::{FireSpread, FireSusceptibility, TileKind};
+use crate::tile_appearance::{Palette, TileAppearance};
+
+pub struct GuiPlugin;
+
+impl Plugin for GuiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapSettingsDraft>()
+            .add_systems(Startup, spawn_gui)
+            .add_systems(
+                Update,
+                (
+                    update_seed_text.run_if(resource_changed::<MapSeed>),
+                    update_generation_progress.run_if(on_event::<GenerationProgress>),
+                    update_season_text.run_if(resource_changed::<Season>),
+                    update_drought_text.run_if(resource_changed::<Drought>),
+                    (
+                        update_census_display.run_if(
+                            resource_changed::<TileCensus>.or(resource_changed::<TileAppearance>),
+                        ),
+                        update_carbon_display.run_if(resource_changed::<CarbonBudget>),
+                        press_population_chart_buttons,
+                        update_population_chart_controls
+                            .run_if(resource_changed::<PopulationChart>),
+                    ),
+                    toggle_brush,
+                    update_brush_buttons.run_if(resource_changed::<Brush>),
+                    show_brush_buttons_for_ruleset.run_if(resource_changed::<Ruleset>),
+                    show_ruleset_controls.run_if(resource_changed::<Ruleset>),
+                    (
+                        toggle_fertility_overlay,
+                        update_fertility_overlay_button
+                            .run_if(resource_changed::<FertilityOverlay>),
+                    ),
+                    (
+                        cycle_overlay_mode,
+                        update_overlay_mode_button.run_if(resource_changed::<OverlayMode>),
+                    ),
+                    (
+                        toggle_grid_overlay,
+                        update_grid_overlay_button.run_if(resource_changed::<GridOverlay>),
+                    ),
+                    (
+                        toggle_palette_options,
+                        select_palette,
+                        update_palette_dropdown.run_if(resource_changed::<Palette>),
+                    ),
+                    (
+                        select_map_preset,
+                        update_map_preset_buttons.run_if(resource_changed::<MapPreset>),
+                    ),
+                    (
+                        sync_map_settings_draft,
+                        press_map_setting_buttons,
+                        regenerate_on_click,
+                        update_map_settings_display,
+                    )
+                        .chain(),
+                    (
+                        start_on_click,
+                        show_start_button.run_if(state_changed::<SimState>),
+                        press_playback_buttons,
+                        style_playback_buttons,
+                        press_speed_buttons,
+                        update_speed_display.run_if(resource_changed::<SimulationStepTime>),
+                    ),
+                    export_map_on_click,
+                    (
+                        toggle_prescribed_burn_tool,
+                        update_prescribed_burn_button
+                            .run_if(resource_changed::<PrescribedBurnTool>),
+                        toggle_parameters_section,
+                    ),
+                    (
+                        drag_sliders,
+                        apply_slider_values,
+                        sync_sliders.run_if(
+                            resource_changed::<ForestFireProbabilities>
+                                .or(resource_changed::<MapSettingsDraft>)
+                                .or(resource_changed::<FireSusceptibility>)
+                                .or(resource_changed::<FireSpread>),
+                        ),
+                        update_slider_display,
+                    )
+                        .chain(),
+                ),
+            );
+    }
+}
+
+const PANEL_WIDTH: Val = Val::Px(220.0);
+const PANEL_BACKGROUND: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+const BUTTON_BACKGROUND: Color = Color::srgb(0.25, 0.25, 0.25);
+const ACTIVE_BUTTON_BACKGROUND: Color = Color::srgb(0.35, 0.5, 0.3);
+const HOVERED_BUTTON_BACKGROUND: Color = Color::srgb(0.35, 0.35, 0.35);
+const PRESSED_BUTTON_BACKGROUND: Color = Color::srgb(0.45, 0.45, 0.45);
+const DISABLED_BUTTON_BACKGROUND: Color = Color::srgb(0.15, 0.15, 0.15);
+const DISABLED_TEXT: Color = Color::srgb(0.5, 0.5, 0.5);
+const SLIDER_FILL: Color = Color::srgb(0.35, 0.5, 0.3);
+const CHART_BACKGROUND: Color = Color::srgb(0.05, 0.05, 0.05);
+const CARBON_CHART_BAR: Color = Color::srgb(0.3, 0.6, 0.4);
+/// The number of ticks of history shown in the carbon chart.
+const CARBON_CHART_BARS: usize = 60;
+
+fn spawn_gui(
+    minimap_image: Res<MinimapImage>,
+    population_chart: Res<PopulationChart>,
+    mut commands: Commands,
+) {
+    commands
+        .spawn((
+            Name::new("GUI"),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::SpaceBetween,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            spawn_left_panel(parent);
+            spawn_right_panel(parent, &minimap_image, &population_chart);
+        });
+}
+
+fn panel_node() -> Node {
+    Node {
+        width: PANEL_WIDTH,
+        height: Val::Percent(100.0),
+        flex_direction: FlexDirection::Column,
+        padding: UiRect::all(Val::Px(8.0)),
+        row_gap: Val::Px(4.0),
+        ..default()
+    }
+}
+
+fn button_node() -> Node {
+    Node {
+        padding: UiRect::all(Val::Px(6.0)),
+        justify_content: JustifyContent::Center,
+        ..default()
+    }
+}
+
+fn spawn_left_panel(parent: &mut ChildSpawnerCommands) {
+    parent
+        .spawn((
+            Name::new("Left panel"),
+            panel_node(),
+            BackgroundColor(PANEL_BACKGROUND),
+            // Tracking interactions lets us avoid painting onto the map through the panel
+            Interaction::default(),
+        ))
+        .with_children(|panel| {
+            panel
+                .spawn((
+                    Name::new("Playback controls"),
+                    Node {
+                        column_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                ))
+                .with_children(|controls| {
+                    for playback_button in [
+                        PlaybackButton::PlayPause,
+                        PlaybackButton::Step,
+                        PlaybackButton::Reset,
+                    ] {
+                        controls
+                            .spawn((
+                                Name::new(format!("{playback_button:?} button")),
+                                Button,
+                                playback_button,
+                                Node {
+                                    flex_grow: 1.0,
+                                    ..button_node()
+                                },
+                                BackgroundColor(BUTTON_BACKGROUND),
+                            ))
+                            .with_child(Text::new(playback_button.label(&SimState::default())));
+                    }
+                });
+
+            panel
+                .spawn((
+                    Name::new("Speed controls"),
+                    Node {
+                        column_gap: Val::Px(4.0),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                ))
+                .with_children(|controls| {
+                    for speed_button in [SpeedButton::Slower, SpeedButton::Faster] {
+                        controls
+                            .spawn((
+                                Name::new(format!("{speed_button:?} button")),
+                                Button,
+                                speed_button,
+                                button_node(),
+                                BackgroundColor(BUTTON_BACKGROUND),
+                            ))
+                            .with_child(Text::new(speed_button.label()));
+                    }
+
+                    controls.spawn((
+                        SpeedText,
+                        Text::new(""),
+                        Node {
+                            flex_grow: 1.0,
+                            ..default()
+                        },
+                    ));
+
+                    controls
+                        .spawn((
+                            Name::new("Max speed button"),
+                            Button,
+                            SpeedButton::Max,
+                            button_node(),
+                            BackgroundColor(BUTTON_BACKGROUND),
+                        ))
+                        .with_child(Text::new(SpeedButton::Max.label()));
+                });
+
+            // Only shown during the setup phase, while the simulation waits for the user
+            panel
+                .spawn((
+                    Name::new("Start button"),
+                    Button,
+                    StartButton,
+                    Node {
+                        display: Display::None,
+                        ..button_node()
+                    },
+                    BackgroundColor(ACTIVE_BUTTON_BACKGROUND),
+                ))
+                .with_child(Text::new("Start"));
+
+            // Each ruleset has its own set of brushes: the irrelevant ones are hidden
+            let mut paintable_kinds: Vec<TileKind> = Vec::new();
+            for ruleset in Ruleset::iter() {
+                for kind in ruleset.paintable_kinds() {
+                    if !paintable_kinds.contains(&kind) {
+                        paintable_kinds.push(kind);
+                    }
+                }
+            }
+
+            for kind in paintable_kinds {
+                panel
+                    .spawn((
+                        Name::new(format!("{kind:?} brush button")),
+                        Button,
+                        BrushButton(kind),
+                        button_node(),
+                        BackgroundColor(BUTTON_BACKGROUND),
+                    ))
+                    .with_child(Text::new(format!("{kind:?} brush")));
+            }
+
+            panel
+                .spawn((
+                    Name::new("Fertility overlay button"),
+                    Button,
+                    FertilityOverlayButton,
+                    button_node(),
+                    BackgroundColor(BUTTON_BACKGROUND),
+                ))
+                .with_child(Text::new("Fertility overlay"));
+
+            panel
+                .spawn((
+                    Name::new("Overlay mode button"),
+                    Button,
+                    OverlayModeButton,
+                    button_node(),
+                    BackgroundColor(BUTTON_BACKGROUND),
+                ))
+                .with_child(Text::new(format!("Overlay: {:?}", OverlayMode::default())));
+
+            panel
+                .spawn((
+                    Name::new("Grid lines button"),
+                    Button,
+                    GridOverlayButton,
+                    button_node(),
+                    BackgroundColor(BUTTON_BACKGROUND),
+                ))
+                .with_child(Text::new("Grid lines"));
+
+            panel
+                .spawn((
+                    Name::new("Palette dropdown button"),
+                    Button,
+                    PaletteDropdownButton,
+                    button_node(),
+                    BackgroundColor(BUTTON_BACKGROUND),
+                ))
+                .with_child(Text::new(format!("Palette: {:?}", Palette::default())));
+
+            panel
+                .spawn((
+                    Name::new("Palette options"),
+                    PaletteOptions,
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.0),
+                        padding: UiRect::left(Val::Px(12.0)),
+                        display: Display::None,
+                        ..default()
+                    },
+                ))
+                .with_children(|options| {
+                    for palette in Palette::iter() {
+                        options
+                            .spawn((
+                                Name::new(format!("{palette:?} palette button")),
+                                Button,
+                                PaletteOptionButton(palette),
+                                button_node(),
+                                BackgroundColor(BUTTON_BACKGROUND),
+                            ))
+                            .with_child(Text::new(format!("{palette:?}")));
+                    }
+                });
+
+            panel.spawn(Text::new("Map presets"));
+
+            for preset in MapPreset::iter() {
+                panel
+                    .spawn((
+                        Name::new(format!("{preset:?} preset button")),
+                        Button,
+                        MapPresetButton(preset),
+                        button_node(),
+                        BackgroundColor(BUTTON_BACKGROUND),
+                    ))
+                    .with_child(Text::new(format!("{preset:?}")));
+            }
+
+            panel.spawn(Text::new("Map settings"));
+
+            for (setting, buttons) in [
+                (
+                    MapSettingText::Width,
+                    &[
+                        MapSettingButton::Width(-MapSettingsDraft::SIZE_STEP),
+                        MapSettingButton::Width(MapSettingsDraft::SIZE_STEP),
+                    ][..],
+                ),
+                (
+                    MapSettingText::Height,
+                    &[
+                        MapSettingButton::Height(-MapSettingsDraft::SIZE_STEP),
+                        MapSettingButton::Height(MapSettingsDraft::SIZE_STEP),
+                    ][..],
+                ),
+                (MapSettingText::Seed, &[MapSettingButton::RandomSeed][..]),
+            ] {
+                panel
+                    .spawn((
+                        Name::new(format!("{setting:?} setting")),
+                        Node {
+                            column_gap: Val::Px(4.0),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                    ))
+                    .with_children(|row| {
+                        row.spawn((
+                            setting,
+                            Text::new(""),
+                            Node {
+                                flex_grow: 1.0,
+                                ..default()
+                            },
+                        ));
+
+                        for &button in buttons {
+                            row.spawn((
+                                Name::new(format!("{button:?} button")),
+                                Button,
+                                button,
+                                button_node(),
+                                BackgroundColor(BUTTON_BACKGROUND),
+                            ))
+                            .with_child(Text::new(button.label()));
+                        }
+                    });
+            }
+
+            spawn_slider(panel, SliderParameter::WaterThreshold, 0.0, 1.0);
+            spawn_slider(panel, SliderParameter::NoisePeriod, 4.0, 64.0);
+
+            panel
+                .spawn((
+                    Name::new("Regenerate map button"),
+                    Button,
+                    RegenerateButton::ApplySettings,
+                    button_node(),
+                    BackgroundColor(BUTTON_BACKGROUND),
+                ))
+                .with_child(Text::new("Regenerate"));
+
+            panel
+                .spawn((
+                    Name::new("New random map button"),
+                    Button,
+                    RegenerateButton::NewSeed,
+                    button_node(),
+                    BackgroundColor(BUTTON_BACKGROUND),
+                ))
+                .with_child(Text::new("New random map"));
+
+            panel
+                .spawn((
+                    Name::new("Export map button"),
+                    Button,
+                    ExportMapButton,
+                    button_node(),
+                    BackgroundColor(BUTTON_BACKGROUND),
+                ))
+                .with_child(Text::new("Export map"));
+
+            panel
+                .spawn((
+                    Name::new("Forest controls"),
+                    RulesetControls(Ruleset::Forest),
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                ))
+                .with_children(|controls| {
+                    controls
+                        .spawn((
+                            Name::new("Prescribed burn button"),
+                            Button,
+                            PrescribedBurnButton,
+                            button_node(),
+                            BackgroundColor(BUTTON_BACKGROUND),
+                        ))
+                        .with_child(Text::new("Prescribed burn"));
+
+                    controls
+                        .spawn((
+                            Name::new("Parameters button"),
+                            Button,
+                            ParametersButton,
+                            button_node(),
+                            BackgroundColor(BUTTON_BACKGROUND),
+                        ))
+                        .with_child(Text::new("Parameters"));
+
+                    // Collapsed by default, since the sliders take up most of the panel
+                    controls
+                        .spawn((
+                            Name::new("Parameters"),
+                            ParametersSection,
+                            Node {
+                                flex_direction: FlexDirection::Column,
+                                row_gap: Val::Px(4.0),
+                                display: Display::None,
+                                ..default()
+                            },
+                        ))
+                        .with_children(|parameters| {
+                            spawn_slider(
+                                parameters,
+                                SliderParameter::BaseSusceptibility,
+                                0.0,
+                                0.01,
+                            );
+                            spawn_slider(
+                                parameters,
+                                SliderParameter::SpreadMultiplier,
+                                0.0,
+                                5000.0,
+                            );
+
+                            // Kinds that can never burn, such as water, are left out
+                            let default_susceptibility = FireSusceptibility::default();
+                            for kind in TileKind::iter() {
+                                if default_susceptibility
+                                    .tile_susceptibility
+                                    .get(&kind)
+                                    .is_some_and(|&susceptibility| susceptibility > 0.0)
+                                {
+                                    spawn_slider(
+                                        parameters,
+                                        SliderParameter::TileSusceptibility(kind),
+                                        0.0,
+                                        3.0,
+                                    );
+                                }
+                            }
+                        });
+                });
+
+            panel
+                .spawn((
+                    Name::new("Drossel-Schwabl controls"),
+                    RulesetControls(Ruleset::DrosselSchwabl),
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.0),
+                        display: Display::None,
+                        ..default()
+                    },
+                ))
+                .with_children(|controls| {
+                    spawn_slider(controls, SliderParameter::GrowthProbability, 0.0, 0.2);
+                    spawn_slider(controls, SliderParameter::LightningProbability, 0.0, 0.002);
+                });
+        });
+}
+
+fn spawn_right_panel(
+    parent: &mut ChildSpawnerCommands,
+    minimap_image: &MinimapImage,
+    population_chart: &PopulationChart,
+) {
+    parent
+        .spawn((
+            Name::new("Right panel"),
+            panel_node(),
+            BackgroundColor(PANEL_BACKGROUND),
+            Interaction::default(),
+        ))
+        .with_children(|panel| {
+            panel.spawn(Text::new("Statistics"));
+            panel
+                .spawn((
+                    Name::new("Tile census"),
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(2.0),
+                        ..default()
+                    },
+                ))
+                .with_children(|census| {
+                    // Kinds that aren't on the map are hidden, which leaves just those used by the current ruleset
+                    for kind in TileKind::iter() {
+                        census
+                            .spawn((
+                                Name::new(format!("{kind:?} census row")),
+                                CensusRow(kind),
+                                Node {
+                                    column_gap: Val::Px(4.0),
+                                    align_items: AlignItems::Center,
+                                    display: Display::None,
+                                    ..default()
+                                },
+                            ))
+                            .with_children(|row| {
+                                row.spawn((
+                                    CensusSwatch,
+                                    Node {
+                                        width: Val::Px(12.0),
+                                        height: Val::Px(12.0),
+                                        flex_shrink: 0.0,
+                                        ..default()
+                                    },
+                                ));
+                                row.spawn((
+                                    CensusText,
+                                    Text::new(""),
+                                    TextFont::from_font_size(14.0),
+                                ));
+                            });
+                    }
+                });
+            panel.spawn((
+                PopulationChartText,
+                Text::new(""),
+                TextFont::from_font_size(14.0),
+            ));
+            panel.spawn((
+                Name::new("Population chart"),
+                Node {
+                    width: Val::Percent(100.0),
+                    aspect_ratio: Some(
+                        PopulationChart::WIDTH as f32 / PopulationChart::HEIGHT as f32,
+                    ),
+                    ..default()
+                },
+                ImageNode::new(population_chart.image.clone()),
+            ));
+            panel
+                .spawn((
+                    Name::new("Population chart controls"),
+                    Node {
+                        column_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                ))
+                .with_children(|controls| {
+                    for chart_button in [
+                        PopulationChartButton::Pause,
+                        PopulationChartButton::ZoomOut,
+                        PopulationChartButton::ZoomIn,
+                    ] {
+                        controls
+                            .spawn((
+                                Name::new(format!("Population chart {chart_button:?} button")),
+                                Button,
+                                chart_button,
+                                Node {
+                                    flex_grow: 1.0,
+                                    ..button_node()
+                                },
+                                BackgroundColor(BUTTON_BACKGROUND),
+                            ))
+                            .with_child(Text::new(chart_button.label(population_chart)));
+                    }
+                });
+            panel
+                .spawn((
+                    Name::new("Minimap"),
+                    MinimapWidget,
+                    Node {
+                        width: Val::Percent(100.0),
+                        aspect_ratio: Some(1.0),
+                        overflow: Overflow::clip(),
+                        ..default()
+                    },
+                    ImageNode::new(minimap_image.0.clone()),
+                    Interaction::default(),
+                    RelativeCursorPosition::default(),
+                ))
+                .with_child((
+                    MinimapViewRectangle,
+                    Node {
+                        position_type: PositionType::Absolute,
+                        border: UiRect::all(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BorderColor(Color::WHITE),
+                ));
+            panel
+                .spawn((
+                    Name::new("Generation progress"),
+                    GenerationProgressDisplay,
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                ))
+                .with_children(|progress| {
+                    progress.spawn((Text::new("Generating map..."), GenerationProgressText));
+                    progress
+                        .spawn((
+                            Node {
+                                width: Val::Percent(100.0),
+                                height: Val::Px(8.0),
+                                ..default()
+                            },
+                            BackgroundColor(CHART_BACKGROUND),
+                        ))
+                        .with_child((
+                            GenerationProgressFill,
+                            Node {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            BackgroundColor(SLIDER_FILL),
+                        ));
+                });
+            panel.spawn((Text::new(""), SeedText));
+            panel.spawn((Text::new(""), SeasonText));
+            panel.spawn((Text::new(""), DroughtText));
+            panel.spawn((Text::new(""), CarbonText));
+            panel
+                .spawn((
+                    Name::new("Carbon chart"),
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(60.0),
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::FlexEnd,
+                        ..default()
+                    },
+                    BackgroundColor(CHART_BACKGROUND),
+                ))
+                .with_children(|chart| {
+                    for index in 0..CARBON_CHART_BARS {
+                        chart.spawn((
+                            CarbonChartBar(index),
+                            Node {
+                                flex_grow: 1.0,
+                                height: Val::Percent(0.0),
+                                ..default()
+                            },
+                            BackgroundColor(CARBON_CHART_BAR),
+                        ));
+                    }
+                });
+        });
+}
+
+/// A marker component for the text that displays the current [`MapSeed`].
+#[derive(Component)]
+struct SeedText;
+
+fn update_seed_text(map_seed: Res<MapSeed>, mut seed_text: Single<&mut Text, With<SeedText>>) {
+    seed_text.0 = format!("Seed: {}", map_seed.0);
+}
+
+/// A marker component for the progress bar shown while the map is being generated.
+#[derive(Component)]
+struct GenerationProgressDisplay;
+
+/// A marker component for the text describing the current phase of map generation.
+#[derive(Component)]
+struct GenerationProgressText;
+
+/// A marker component for the filled part of the map generation progress bar.
+#[derive(Component)]
+struct GenerationProgressFill;
+
+fn update_generation_progress(
+    mut event_reader: EventReader<GenerationProgress>,
+    mut display_node: Single<&mut Node, With<GenerationProgressDisplay>>,
+    mut fill_node: Single<
+        &mut Node,
+        (
+            With<GenerationProgressFill>,
+            Without<GenerationProgressDisplay>,
+        ),
+    >,
+    mut progress_text: Single<&mut Text, With<GenerationProgressText>>,
+) {
+    let Some(progress) = event_reader.read().last() else {
+        return;
+    };
+
+    display_node.display = if progress.phase == GenerationPhase::Done {
+        Display::None
+    } else {
+        Display::Flex
+    };
+    fill_node.width = Val::Percent(progress.fraction * 100.0);
+    progress_text.0 = match progress.phase {
+        GenerationPhase::SpawningTiles => "Spawning tiles...".to_string(),
+        GenerationPhase::ShapingTerrain(terrain_phase) => {
+            format!("{}...", terrain_phase.description())
+        }
+        GenerationPhase::Done => "Map generated".to_string(),
+    };
+}
+
+/// A marker component for the text that displays the current season.
+#[derive(Component)]
+struct SeasonText;
+
+fn update_season_text(season: Res<Season>, mut season_text: Single<&mut Text, With<SeasonText>>) {
+    season_text.0 = format!("Season: {:?}", season.kind);
+}
+
+/// A marker component for the text that indicates whether a drought is underway.
+#[derive(Component)]
+struct DroughtText;
+
+fn update_drought_text(
+    drought: Res<Drought>,
+    mut drought_text: Single<(&mut Text, &mut TextColor), With<DroughtText>>,
+) {
+    let (text, text_color) = &mut *drought_text;
+
+    if drought.is_active() {
+        text.0 = format!("Drought! ({} ticks remaining)", drought.ticks_remaining());
+        text_color.0 = Color::hsl(35., 0.9, 0.6);
+    } else {
+        text.0 = "No drought".to_string();
+        text_color.0 = Color::WHITE;
+    }
+}
+
+/// A row of the tile census, showing how many tiles of the given kind are on the map.
+#[derive(Component)]
+struct CensusRow(TileKind);
+
+/// A marker component for the square of color that shows which kind of tile a [`CensusRow`] counts.
+#[derive(Component)]
+struct CensusSwatch;
+
+/// A marker component for the text of a [`CensusRow`].
+#[derive(Component)]
+struct CensusText;
+
+fn update_census_display(
+    tile_census: Res<TileCensus>,
+    tile_appearance: Res<TileAppearance>,
+    mut row_query: Query<(&CensusRow, &Children, &mut Node)>,
+    mut swatch_query: Query<&mut BackgroundColor, With<CensusSwatch>>,
+    mut text_query: Query<&mut Text, With<CensusText>>,
+) {
+    for (row, children, mut node) in row_query.iter_mut() {
+        let count = tile_census.count(&row.0);
+        node.display = if count > 0 {
+            Display::Flex
+        } else {
+            Display::None
+        };
+
+        for &child in children {
+            if let Ok(mut swatch_color) = swatch_query.get_mut(child) {
+                swatch_color.0 = tile_appearance.color(&row.0);
+            }
+
+            if let Ok(mut text) = text_query.get_mut(child) {
+                text.0 = format!(
+                    "{:?}: {count} ({:.1}%)",
+                    row.0,
+                    tile_census.fraction(&row.0) * 100.0
+                );
+            }
+        }
+    }
+}
+
+/// A marker component for the text that describes what the [`PopulationChart`] is showing.
+#[derive(Component)]
+struct PopulationChartText;
+
+/// A button that controls which ticks the [`PopulationChart`] shows.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum PopulationChartButton {
+    /// Stops the chart from following the simulation, or resumes following it.
+    Pause,
+    /// Shows more ticks.
+    ZoomOut,
+    /// Shows fewer ticks.
+    ZoomIn,
+}
+
+impl PopulationChartButton {
+    fn label(&self, population_chart: &PopulationChart) -> &'static str {
+        match self {
+            PopulationChartButton::Pause if population_chart.paused() => "Resume",
+            PopulationChartButton::Pause => "Pause",
+            PopulationChartButton::ZoomOut => "-",
+            PopulationChartButton::ZoomIn => "+",
+        }
+    }
+}
+
+fn press_population_chart_buttons(
+    button_query: Query<(&Interaction, &PopulationChartButton), Changed<Interaction>>,
+    tile_census: Res<TileCensus>,
+    mut population_chart: ResMut<PopulationChart>,
+) {
+    for (interaction, chart_button) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match chart_button {
+            PopulationChartButton::Pause => population_chart.toggle_paused(&tile_census),
+            PopulationChartButton::ZoomOut => population_chart.zoom_out(&tile_census),
+            PopulationChartButton::ZoomIn => population_chart.zoom_in(),
+        }
+    }
+}
+
+fn update_population_chart_controls(
+    population_chart: Res<PopulationChart>,
+    mut chart_text: Single<&mut Text, With<PopulationChartText>>,
+    mut button_query: Query<(&PopulationChartButton, &Children, &mut BackgroundColor)>,
+    mut label_query: Query<&mut Text, Without<PopulationChartText>>,
+) {
+    chart_text.0 = format!(
+        "Population over the last {} ticks{}",
+        population_chart.visible_ticks,
+        if population_chart.paused() {
+            " (paused)"
+        } else {
+            ""
+        }
+    );
+
+    for (chart_button, children, mut background_color) in button_query.iter_mut() {
+        background_color.0 =
+            if *chart_button == PopulationChartButton::Pause && population_chart.paused() {
+                ACTIVE_BUTTON_BACKGROUND
+            } else {
+                BUTTON_BACKGROUND
+            };
+
+        for &child in children {
+            if let Ok(mut label) = label_query.get_mut(child) {
+                label.0 = chart_button.label(&population_chart).to_string();
+            }
+        }
+    }
+}
+
+/// A marker component for the text that displays the carbon stored across the map.
+#[derive(Component)]
+struct CarbonText;
+
+/// One bar of the chart of total stored carbon over time.
+///
+/// Bars are numbered from left to right, and the rightmost bar shows the most recent tick.
+#[derive(Component)]
+struct CarbonChartBar(usize);
+
+fn update_carbon_display(
+    carbon_budget: Res<CarbonBudget>,
+    mut carbon_text: Single<&mut Text, With<CarbonText>>,
+    mut bar_query: Query<(&CarbonChartBar, &mut Node)>,
+) {
+    carbon_text.0 = format!(
+        "Carbon stored: {:.0} t\nReleased by fire: {:.0} t",
+        carbon_budget.total, carbon_budget.released_by_fire
+    );
+
+    // Zoom in on the range of values in view, since the total only changes by a few percent at a time
+    let min_total = carbon_budget
+        .history
+        .iter()
+        .copied()
+        .fold(f32::INFINITY, f32::min);
+    let max_total = carbon_budget.history.iter().copied().fold(0.0, f32::max);
+    let history_start = CARBON_CHART_BARS.saturating_sub(carbon_budget.history.len());
+    let skipped = carbon_budget
+        .history
+        .len()
+        .saturating_sub(CARBON_CHART_BARS);
+
+    for (bar, mut node) in bar_query.iter_mut() {
+        let total = bar
+            .0
+            .checked_sub(history_start)
+            .and_then(|offset| carbon_budget.history.get(skipped + offset));
+
+        node.height = match total {
+            Some(total) if max_total > min_total => {
+                Val::Percent(10.0 + 90.0 * (total - min_total) / (max_total - min_total))
+            }
+            Some(_) => Val::Percent(100.0),
+            None => Val::Percent(0.0),
+        };
+    }
+}
+
+/// A button that toggles painting the given tile kind with the mouse.
+#[derive(Component)]
+struct BrushButton(TileKind);
+
+fn toggle_brush(
+    button_query: Query<(&Interaction, &BrushButton), Changed<Interaction>>,
+    mut brush: ResMut<Brush>,
+    mut prescribed_burn_tool: ResMut<PrescribedBurnTool>,
+) {
+    for (interaction, brush_button) in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            brush.kind = if brush.kind == Some(brush_button.0) {
+                None
+            } else {
+                Some(brush_button.0)
+            };
+
+            // Dragging on the map can either paint or select a burn, but not both
+            if brush.kind.is_some() {
+                prescribed_burn_tool.active = false;
+            }
+        }
+    }
+}
+
+fn update_brush_buttons(
+    brush: Res<Brush>,
+    mut button_query: Query<(&BrushButton, &mut BackgroundColor)>,
+) {
+    for (brush_button, mut button_color) in button_query.iter_mut() {
+        button_color.0 = if brush.kind == Some(brush_button.0) {
+            ACTIVE_BUTTON_BACKGROUND
+        } else {
+            BUTTON_BACKGROUND
+        };
+    }
+}
+
+/// A button that regenerates the map using the given [`MapPreset`].
+#[derive(Component)]
+struct MapPresetButton(MapPreset);
+
+fn select_map_preset(
+    button_query: Query<(&Interaction, &MapPresetButton), Changed<Interaction>>,
+    mut map_preset: ResMut<MapPreset>,
+) {
+    for (interaction, preset_button) in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            // Always marks the preset as changed, so reselecting it undoes any tweaks
+            *map_preset = preset_button.0;
+        }
+    }
+}
+
+fn update_map_preset_buttons(
+    map_preset: Res<MapPreset>,
+    mut button_query: Query<(&MapPresetButton, &mut BackgroundColor)>,
+) {
+    for (preset_button, mut button_color) in button_query.iter_mut() {
+        button_color.0 = if *map_preset == preset_button.0 {
+            ACTIVE_BUTTON_BACKGROUND
+        } else {
+            BUTTON_BACKGROUND
+        };
+    }
+}
+
+fn show_brush_buttons_for_ruleset(
+    ruleset: Res<Ruleset>,
+    mut button_query: Query<(&BrushButton, &mut Node)>,
+) {
+    let paintable_kinds = ruleset.paintable_kinds();
+
+    for (brush_button, mut node) in button_query.iter_mut() {
+        node.display = if paintable_kinds.contains(&brush_button.0) {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+}
+
+/// A marker component for the button that toggles the fertility overlay.
+#[derive(Component)]
+struct FertilityOverlayButton;
+
+fn toggle_fertility_overlay(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<FertilityOverlayButton>)>,
+    mut fertility_overlay: ResMut<FertilityOverlay>,
+) {
+    for interaction in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            fertility_overlay.enabled = !fertility_overlay.enabled;
+        }
+    }
+}
+
+fn update_fertility_overlay_button(
+    fertility_overlay: Res<FertilityOverlay>,
+    mut button_color: Single<&mut BackgroundColor, With<FertilityOverlayButton>>,
+) {
+    button_color.0 = if fertility_overlay.enabled {
+        ACTIVE_BUTTON_BACKGROUND
+    } else {
+        BUTTON_BACKGROUND
+    };
+}
+
+/// A marker component for the button that cycles through the [`OverlayMode`]s.
+#[derive(Component)]
+struct OverlayModeButton;
+
+fn cycle_overlay_mode(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<OverlayModeButton>)>,
+    mut overlay_mode: ResMut<OverlayMode>,
+) {
+    for interaction in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            *overlay_mode = overlay_mode.next();
+        }
+    }
+}
+
+fn update_overlay_mode_button(
+    overlay_mode: Res<OverlayMode>,
+    mut button_query: Query<(&Children, &mut BackgroundColor), With<OverlayModeButton>>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (children, mut background_color) in button_query.iter_mut() {
+        background_color.0 = if *overlay_mode == OverlayMode::None {
+            BUTTON_BACKGROUND
+        } else {
+            ACTIVE_BUTTON_BACKGROUND
+        };
+
+        for &child in children {
+            if let Ok(mut text) = text_query.get_mut(child) {
+                text.0 = format!("Overlay: {:?}", *overlay_mode);
+            }
+        }
+    }
+}
+
+/// A marker component for the button that toggles the [`GridOverlay`].
+#[derive(Component)]
+struct GridOverlayButton;
+
+fn toggle_grid_overlay(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<GridOverlayButton>)>,
+    mut grid_overlay: ResMut<GridOverlay>,
+) {
+    for interaction in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            grid_overlay.enabled = !grid_overlay.enabled;
+        }
+    }
+}
+
+fn update_grid_overlay_button(
+    grid_overlay: Res<GridOverlay>,
+    mut button_color: Single<&mut BackgroundColor, With<GridOverlayButton>>,
+) {
+    button_color.0 = if grid_overlay.enabled {
+        ACTIVE_BUTTON_BACKGROUND
+    } else {
+        BUTTON_BACKGROUND
+    };
+}
+
+/// A marker component for the button that opens and closes the list of [`Palette`]s.
+#[derive(Component)]
+struct PaletteDropdownButton;
+
+/// A marker component for the list of [`PaletteOptionButton`]s, which is hidden until the dropdown is opened.
+#[derive(Component)]
+struct PaletteOptions;
+
+/// A button in the palette dropdown that switches to the given [`Palette`].
+#[derive(Component)]
+struct PaletteOptionButton(Palette);
+
+fn toggle_palette_options(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<PaletteDropdownButton>)>,
+    mut options_node: Single<&mut Node, With<PaletteOptions>>,
+) {
+    for interaction in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            options_node.display = match options_node.display {
+                Display::None => Display::Flex,
+                _ => Display::None,
+            };
+        }
+    }
+}
+
+/// Switches to the chosen palette, closing the dropdown.
+fn select_palette(
+    button_query: Query<(&Interaction, &PaletteOptionButton), Changed<Interaction>>,
+    mut options_node: Single<&mut Node, With<PaletteOptions>>,
+    mut palette: ResMut<Palette>,
+) {
+    for (interaction, option_button) in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            palette.set_if_neq(option_button.0);
+            options_node.display = Display::None;
+        }
+    }
+}
+
+fn update_palette_dropdown(
+    palette: Res<Palette>,
+    dropdown_children: Single<&Children, With<PaletteDropdownButton>>,
+    mut option_query: Query<(&PaletteOptionButton, &mut BackgroundColor)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for &child in *dropdown_children {
+        if let Ok(mut text) = text_query.get_mut(child) {
+            text.0 = format!("Palette: {:?}", *palette);
+        }
+    }
+
+    for (option_button, mut button_color) in option_query.iter_mut() {
+        button_color.0 = if *palette == option_button.0 {
+            ACTIVE_BUTTON_BACKGROUND
+        } else {
+            BUTTON_BACKGROUND
+        };
+    }
+}
+
+/// A button that regenerates the map, either with the edited map settings or from a new random seed.
+#[derive(Component, PartialEq, Eq)]
+enum RegenerateButton {
+    /// Regenerates the map using the settings in the [`MapSettingsDraft`].
+    ApplySettings,
+    NewSeed,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn regenerate_on_click(
+    button_query: Query<(&Interaction, &RegenerateButton), Changed<Interaction>>,
+    map_settings_draft: Res<MapSettingsDraft>,
+    mut map_size: ResMut<MapSize>,
+    mut map_seed: ResMut<MapSeed>,
+    mut water_threshold: ResMut<WaterThreshold>,
+    mut noise_period: ResMut<NoisePeriod>,
+    mut regenerate_writer: EventWriter<RegenerateMap>,
+    mut reroll_writer: EventWriter<RerollMap>,
+) {
+    for (interaction, regenerate_button) in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            match regenerate_button {
+                RegenerateButton::ApplySettings => {
+                    if map_size.width != map_settings_draft.width
+                        || map_size.height != map_settings_draft.height
+                    {
+                        map_size.width = map_settings_draft.width;
+                        map_size.height = map_settings_draft.height;
+                    }
+                    if map_seed.0 != map_settings_draft.seed {
+                        map_seed.0 = map_settings_draft.seed;
+                    }
+                    if water_threshold.0 != map_settings_draft.water_threshold {
+                        water_threshold.0 = map_settings_draft.water_threshold;
+                    }
+                    if noise_period.0 != map_settings_draft.noise_period {
+                        noise_period.0 = map_settings_draft.noise_period;
+                    }
+
+                    // These settings don't regenerate the map by themselves
+                    regenerate_writer.write(RegenerateMap);
+                }
+                RegenerateButton::NewSeed => {
+                    reroll_writer.write(RerollMap);
+                }
+            }
+        }
+    }
+}
+
+/// Map generation settings that have been edited in the GUI, but not yet applied.
+///
+/// Applying each edit straight away would regenerate the map on every click or drag of a slider,
+/// so instead they are collected here until the Regenerate button is pressed.
+/// Changes made by other means, such as presets or the inspector, are copied in as they happen,
+/// and wait for the Regenerate button just like edits made here.
+#[derive(Resource, Debug, Default, PartialEq)]
+struct MapSettingsDraft {
+    width: i32,
+    height: i32,
+    seed: u64,
+    water_threshold: f32,
+    noise_period: f32,
+}
+
+impl MapSettingsDraft {
+    /// How many tiles the width and height buttons change the map size by.
+    const SIZE_STEP: i32 = 10;
+    /// The smallest width or height that the map can be given.
+    const MIN_SIZE: i32 = 10;
+    /// The largest width or height that the map can be given.
+    ///
+    /// Chunked generation spreads the work of spawning tiles over many frames, so even maps this large stay responsive.
+    const MAX_SIZE: i32 = 2048;
+
+    /// Whether these settings differ from the ones that the current map was generated with.
+    fn differs_from(
+        &self,
+        map_size: &MapSize,
+        map_seed: &MapSeed,
+        water_threshold: &WaterThreshold,
+        noise_period: &NoisePeriod,
+    ) -> bool {
+        self.width != map_size.width
+            || self.height != map_size.height
+            || self.seed != map_seed.0
+            || self.water_threshold != water_threshold.0
+            || self.noise_period != noise_period.0
+    }
+}
+
+/// Copies any map generation settings that were changed outside of the GUI into the [`MapSettingsDraft`].
+///
+/// Only the settings that actually changed are copied, so that other edits in progress aren't lost.
+fn sync_map_settings_draft(
+    map_size: Res<MapSize>,
+    map_seed: Res<MapSeed>,
+    water_threshold: Res<WaterThreshold>,
+    noise_period: Res<NoisePeriod>,
+    mut map_settings_draft: ResMut<MapSettingsDraft>,
+) {
+    if map_size.is_changed() {
+        map_settings_draft.width = map_size.width;
+        map_settings_draft.height = map_size.height;
+    }
+
+    if map_seed.is_changed() {
+        map_settings_draft.seed = map_seed.0;
+    }
+
+    if water_threshold.is_changed() {
+        map_settings_draft.water_threshold = water_threshold.0;
+    }
+
+    if noise_period.is_changed() {
+        map_settings_draft.noise_period = noise_period.0;
+    }
+}
+
+/// A button that edits one of the settings in the [`MapSettingsDraft`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum MapSettingButton {
+    /// Changes the width of the map by the given number of tiles.
+    Width(i32),
+    /// Changes the height of the map by the given number of tiles.
+    Height(i32),
+    /// Picks a new seed at random.
+    RandomSeed,
+}
+
+impl MapSettingButton {
+    fn label(&self) -> &'static str {
+        match self {
+            MapSettingButton::Width(change) | MapSettingButton::Height(change) if *change < 0 => {
+                "-"
+            }
+            MapSettingButton::Width(_) | MapSettingButton::Height(_) => "+",
+            MapSettingButton::RandomSeed => "Random",
+        }
+    }
+}
+
+/// The text that shows one of the settings in the [`MapSettingsDraft`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum MapSettingText {
+    Width,
+    Height,
+    Seed,
+}
+
+fn press_map_setting_buttons(
+    button_query: Query<(&Interaction, &MapSettingButton), Changed<Interaction>>,
+    mut map_settings_draft: ResMut<MapSettingsDraft>,
+) {
+    for (interaction, map_setting_button) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match map_setting_button {
+            MapSettingButton::Width(change) => {
+                map_settings_draft.width = (map_settings_draft.width + change)
+                    .clamp(MapSettingsDraft::MIN_SIZE, MapSettingsDraft::MAX_SIZE);
+            }
+            MapSettingButton::Height(change) => {
+                map_settings_draft.height = (map_settings_draft.height + change)
+                    .clamp(MapSettingsDraft::MIN_SIZE, MapSettingsDraft::MAX_SIZE);
+            }
+            MapSettingButton::RandomSeed => {
+                map_settings_draft.seed = MapSeed::default().0;
+            }
+        }
+    }
+}
+
+/// Shows the settings in the [`MapSettingsDraft`], and highlights the Regenerate button while any of them are waiting to be applied.
+fn update_map_settings_display(
+    map_settings_draft: Res<MapSettingsDraft>,
+    map_size: Res<MapSize>,
+    map_seed: Res<MapSeed>,
+    water_threshold: Res<WaterThreshold>,
+    noise_period: Res<NoisePeriod>,
+    mut text_query: Query<(&MapSettingText, &mut Text)>,
+    mut button_query: Query<(&RegenerateButton, &mut BackgroundColor)>,
+) {
+    if map_settings_draft.is_changed() {
+        for (setting, mut text) in text_query.iter_mut() {
+            text.0 = match setting {
+                MapSettingText::Width => format!("Width: {}", map_settings_draft.width),
+                MapSettingText::Height => format!("Height: {}", map_settings_draft.height),
+                MapSettingText::Seed => format!("Seed: {}", map_settings_draft.seed),
+            };
+        }
+    }
+
+    let pending =
+        map_settings_draft.differs_from(&map_size, &map_seed, &water_threshold, &noise_period);
+    for (regenerate_button, mut background_color) in button_query.iter_mut() {
+        let new_background = if *regenerate_button == RegenerateButton::ApplySettings && pending {
+            ACTIVE_BUTTON_BACKGROUND
+        } else {
+            BUTTON_BACKGROUND
+        };
+        if background_color.0 != new_background {
+            background_color.0 = new_background;
+        }
+    }
+}
+
+/// A marker component for the button that starts the simulation at the end of the setup phase.
+#[derive(Component)]
+struct StartButton;
+
+fn start_on_click(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<StartButton>)>,
+    mut event_writer: EventWriter<UnpauseSimulation>,
+) {
+    for interaction in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            event_writer.write(UnpauseSimulation);
+        }
+    }
+}
+
+fn show_start_button(
+    state: Res<State<SimState>>,
+    mut button_node: Single<&mut Node, With<StartButton>>,
+) {
+    button_node.display = if *state.get() == SimState::Setup {
+        Display::Flex
+    } else {
+        Display::None
+    };
+}
+
+/// A button that controls the running simulation.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum PlaybackButton {
+    /// Pauses the simulation while it is running, and resumes it while it is paused.
+    PlayPause,
+    /// Advances the simulation by a single tick, while it is paused.
+    Step,
+    /// Throws the current map away and generates a new one.
+    Reset,
+}
+
+impl PlaybackButton {
+    /// Whether this button does anything in the given state.
+    ///
+    /// While the map is being generated there is nothing to control,
+    /// and during the setup phase the simulation is started with the Start button instead.
+    fn enabled(&self, state: &SimState) -> bool {
+        match self {
+            PlaybackButton::PlayPause => matches!(state, SimState::Run | SimState::Paused),
+            PlaybackButton::Step => *state == SimState::Paused,
+            PlaybackButton::Reset => *state != SimState::Generate,
+        }
+    }
+
+    /// The text shown on this button in the given state.
+    fn label(&self, state: &SimState) -> &'static str {
+        match self {
+            PlaybackButton::PlayPause if *state == SimState::Paused => "Play",
+            PlaybackButton::PlayPause => "Pause",
+            PlaybackButton::Step => "Step",
+            PlaybackButton::Reset => "Reset",
+        }
+    }
+}
+
+fn press_playback_buttons(
+    button_query: Query<(&Interaction, &PlaybackButton), Changed<Interaction>>,
+    state: Res<State<SimState>>,
+    mut pause_writer: EventWriter<PauseSimulation>,
+    mut unpause_writer: EventWriter<UnpauseSimulation>,
+    mut step_writer: EventWriter<StepSimulation>,
+    mut reset_writer: EventWriter<ResetSimulation>,
+) {
+    for (interaction, playback_button) in button_query.iter() {
+        if *interaction != Interaction::Pressed || !playback_button.enabled(state.get()) {
+            continue;
+        }
+
+        match playback_button {
+            PlaybackButton::PlayPause if *state.get() == SimState::Paused => {
+                unpause_writer.write(UnpauseSimulation);
+            }
+            PlaybackButton::PlayPause => {
+                pause_writer.write(PauseSimulation);
+            }
+            PlaybackButton::Step => {
+                step_writer.write(StepSimulation);
+            }
+            PlaybackButton::Reset => {
+                reset_writer.write(ResetSimulation);
+            }
+        }
+    }
+}
+
+/// Relabels the playback buttons to match the state of the simulation,
+/// dimming any that can't be used and highlighting the rest as they are hovered and pressed.
+fn style_playback_buttons(
+    state: Res<State<SimState>>,
+    mut button_query: Query<(
+        &Interaction,
+        &PlaybackButton,
+        &Children,
+        &mut BackgroundColor,
+    )>,
+    mut text_query: Query<(&mut Text, &mut TextColor)>,
+) {
+    for (interaction, playback_button, children, mut background_color) in button_query.iter_mut() {
+        let enabled = playback_button.enabled(state.get());
+
+        let new_background = match interaction {
+            _ if !enabled => DISABLED_BUTTON_BACKGROUND,
+            Interaction::Pressed => PRESSED_BUTTON_BACKGROUND,
+            Interaction::Hovered => HOVERED_BUTTON_BACKGROUND,
+            Interaction::None => BUTTON_BACKGROUND,
+        };
+        if background_color.0 != new_background {
+            background_color.0 = new_background;
+        }
+
+        for &child in children {
+            if let Ok((mut text, mut text_color)) = text_query.get_mut(child) {
+                let label = playback_button.label(state.get());
+                if text.0 != label {
+                    text.0 = label.to_string();
+                }
+
+                let new_color = if enabled { Color::WHITE } else { DISABLED_TEXT };
+                if text_color.0 != new_color {
+                    text_color.0 = new_color;
+                }
+            }
+        }
+    }
+}
+
+/// A button that changes how quickly the simulation runs, by sending [`SetSimulationTimestep`] events.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum SpeedButton {
+    /// Moves to the next longest step time.
+    Slower,
+    /// Moves to the next shortest step time, and then to max speed.
+    Faster,
+    /// Runs one step every frame.
+    Max,
+}
+
+impl SpeedButton {
+    /// The step times that the speed buttons move between, in milliseconds, from slowest to fastest.
+    ///
+    /// Max speed comes after the last of these.
+    const STEP_MILLISECONDS: [u64; 7] = [2000, 1000, 500, 250, 100, 50, 20];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SpeedButton::Slower => "-",
+            SpeedButton::Faster => "+",
+            SpeedButton::Max => "Max",
+        }
+    }
+
+    /// The step time that pressing this button moves to from the current one, in milliseconds.
+    fn next_milliseconds(&self, current: u64) -> u64 {
+        match self {
+            SpeedButton::Slower => Self::STEP_MILLISECONDS
+                .into_iter()
+                .rev()
+                .find(|&milliseconds| milliseconds > current)
+                .unwrap_or(current),
+            SpeedButton::Faster => Self::STEP_MILLISECONDS
+                .into_iter()
+                .find(|&milliseconds| milliseconds < current)
+                .unwrap_or(0),
+            SpeedButton::Max => 0,
+        }
+    }
+}
+
+/// A marker component for the text that shows how quickly the simulation runs.
+#[derive(Component)]
+struct SpeedText;
+
+fn press_speed_buttons(
+    button_query: Query<(&Interaction, &SpeedButton), Changed<Interaction>>,
+    simulation_step_time: Res<SimulationStepTime>,
+    mut event_writer: EventWriter<SetSimulationTimestep>,
+) {
+    let current = simulation_step_time.duration().as_millis() as u64;
+    for (interaction, speed_button) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let milliseconds = speed_button.next_milliseconds(current);
+        if milliseconds != current {
+            event_writer.write(SetSimulationTimestep { milliseconds });
+        }
+    }
+}
+
+/// Shows the current step time, and highlights the max speed button while it is in use.
+fn update_speed_display(
+    simulation_step_time: Res<SimulationStepTime>,
+    mut speed_text: Single<&mut Text, With<SpeedText>>,
+    mut button_query: Query<(&SpeedButton, &mut BackgroundColor)>,
+) {
+    let milliseconds = simulation_step_time.duration().as_millis();
+    speed_text.0 = if milliseconds == 0 {
+        "Every frame".to_string()
+    } else {
+        format!("{milliseconds} ms")
+    };
+
+    for (speed_button, mut background_color) in button_query.iter_mut() {
+        background_color.0 = if *speed_button == SpeedButton::Max && milliseconds == 0 {
+            ACTIVE_BUTTON_BACKGROUND
+        } else {
+            BUTTON_BACKGROUND
+        };
+    }
+}
+
+/// A marker component for the button that exports the current map.
+#[derive(Component)]
+struct ExportMapButton;
+
+fn export_map_on_click(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<ExportMapButton>)>,
+    mut event_writer: EventWriter<ExportMap>,
+) {
+    for interaction in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            event_writer.write(ExportMap::default());
+        }
+    }
+}
+
+/// A marker component for the button that toggles the [`PrescribedBurnTool`].
+#[derive(Component)]
+struct PrescribedBurnButton;
+
+fn toggle_prescribed_burn_tool(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<PrescribedBurnButton>)>,
+    mut prescribed_burn_tool: ResMut<PrescribedBurnTool>,
+    mut brush: ResMut<Brush>,
+) {
+    for interaction in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            prescribed_burn_tool.active = !prescribed_burn_tool.active;
+            if prescribed_burn_tool.active {
+                brush.kind = None;
+            }
+        }
+    }
+}
+
+fn update_prescribed_burn_button(
+    prescribed_burn_tool: Res<PrescribedBurnTool>,
+    mut button_color: Single<&mut BackgroundColor, With<PrescribedBurnButton>>,
+) {
+    button_color.0 = if prescribed_burn_tool.active {
+        ACTIVE_BUTTON_BACKGROUND
+    } else {
+        BUTTON_BACKGROUND
+    };
+}
+
+/// A marker component for the button that shows and hides the [`ParametersSection`].
+#[derive(Component)]
+struct ParametersButton;
+
+/// A marker component for the collapsible section of sliders that tune how fires start and spread in the forest ruleset.
+#[derive(Component)]
+struct ParametersSection;
+
+#[allow(clippy::type_complexity)]
+fn toggle_parameters_section(
+    mut button_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<ParametersButton>),
+    >,
+    mut section_node: Single<&mut Node, With<ParametersSection>>,
+) {
+    for (interaction, mut background_color) in button_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            let open = section_node.display == Display::None;
+            section_node.display = if open { Display::Flex } else { Display::None };
+            background_color.0 = if open {
+                ACTIVE_BUTTON_BACKGROUND
+            } else {
+                BUTTON_BACKGROUND
+            };
+        }
+    }
+}
+
+/// A group of controls that are only shown while the given ruleset is active.
+#[derive(Component)]
+struct RulesetControls(Ruleset);
+
+fn show_ruleset_controls(
+    ruleset: Res<Ruleset>,
+    mut controls_query: Query<(&RulesetControls, &mut Node)>,
+) {
+    for (controls, mut node) in controls_query.iter_mut() {
+        node.display = if controls.0 == *ruleset {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+}
+
+/// A horizontal slider, which can be clicked or dragged to set a value between `min` and `max`.
+#[derive(Component, Debug)]
+struct Slider {
+    min: f32,
+    max: f32,
+    value: f32,
+}
+
+impl Slider {
+    /// How far along the slider the current value is, from 0 to 1.
+    fn fraction(&self) -> f32 {
+        if self.max > self.min {
+            ((self.value - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The parameter that a [`Slider`] controls.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum SliderParameter {
+    GrowthProbability,
+    LightningProbability,
+    WaterThreshold,
+    NoisePeriod,
+    BaseSusceptibility,
+    SpreadMultiplier,
+    /// The fire susceptibility of a single tile kind.
+    TileSusceptibility(TileKind),
+}
+
+impl SliderParameter {
+    fn label(&self) -> String {
+        match self {
+            SliderParameter::GrowthProbability => "Growth (p)".to_string(),
+            SliderParameter::LightningProbability => "Lightning (f)".to_string(),
+            SliderParameter::WaterThreshold => "Water".to_string(),
+            SliderParameter::NoisePeriod => "Noise period".to_string(),
+            SliderParameter::BaseSusceptibility => "Susceptibility".to_string(),
+            SliderParameter::SpreadMultiplier => "Spread".to_string(),
+            SliderParameter::TileSusceptibility(kind) => format!("{kind:?}"),
+        }
+    }
+
+    /// The number of decimal places that the value of this parameter is shown with.
+    fn decimals(&self) -> usize {
+        match self {
+            SliderParameter::GrowthProbability | SliderParameter::LightningProbability => 4,
+            SliderParameter::WaterThreshold => 2,
+            SliderParameter::NoisePeriod => 1,
+            SliderParameter::BaseSusceptibility => 4,
+            SliderParameter::SpreadMultiplier => 0,
+            SliderParameter::TileSusceptibility(_) => 3,
+        }
+    }
+}
+
+/// A marker component for the colored bar that shows how far along its parent [`Slider`] is.
+#[derive(Component)]
+struct SliderFill;
+
+/// A marker component for the text that shows the value of its parent [`Slider`].
+#[derive(Component)]
+struct SliderLabel;
+
+fn spawn_slider(parent: &mut ChildSpawnerCommands, parameter: SliderParameter, min: f32, max: f32) {
+    parent
+        .spawn((
+            Name::new(format!("{} slider", parameter.label())),
+            Slider {
+                min,
+                max,
+                value: min,
+            },
+            parameter,
+            Node {
+                height: Val::Px(24.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_BACKGROUND),
+            Interaction::default(),
+            RelativeCursorPosition::default(),
+        ))
+        .with_children(|slider| {
+            slider.spawn((
+                SliderFill,
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(0.0),
+                    height: Val::Percent(100.0),
+                    width: Val::Percent(0.0),
+                    ..default()
+                },
+                BackgroundColor(SLIDER_FILL),
+            ));
+            slider.spawn((Text::new(""), SliderLabel));
+        });
+}
+
+/// Moves sliders to wherever they are being pressed.
+fn drag_sliders(mut slider_query: Query<(&Interaction, &RelativeCursorPosition, &mut Slider)>) {
+    for (interaction, relative_cursor_position, mut slider) in slider_query.iter_mut() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let Some(cursor_position) = relative_cursor_position.normalized else {
+            continue;
+        };
+
+        let value = slider
+            .min
+            .lerp(slider.max, cursor_position.x.clamp(0.0, 1.0));
+        if slider.value != value {
+            slider.value = value;
+        }
+    }
+}
+
+/// Writes the values of any sliders that have been moved into the parameters they control.
+fn apply_slider_values(
+    slider_query: Query<(Ref<Slider>, &SliderParameter), Changed<Slider>>,
+    mut forest_fire_probabilities: ResMut<ForestFireProbabilities>,
+    mut map_settings_draft: ResMut<MapSettingsDraft>,
+    mut fire_susceptibility: ResMut<FireSusceptibility>,
+    mut fire_spread: ResMut<FireSpread>,
+) {
+    for (slider, parameter) in slider_query.iter() {
+        // Newly spawned sliders haven't been moved to match their parameters yet
+        if slider.is_added() {
+            continue;
+        }
+
+        let value = slider.value;
+        match parameter {
+            SliderParameter::GrowthProbability => set_slider_parameter(
+                &mut forest_fire_probabilities,
+                |probabilities| Some(&mut probabilities.growth_probability),
+                value,
+            ),
+            SliderParameter::LightningProbability => set_slider_parameter(
+                &mut forest_fire_probabilities,
+                |probabilities| Some(&mut probabilities.lightning_probability),
+                value,
+            ),
+            SliderParameter::WaterThreshold => set_slider_parameter(
+                &mut map_settings_draft,
+                |draft| Some(&mut draft.water_threshold),
+                value,
+            ),
+            SliderParameter::NoisePeriod => set_slider_parameter(
+                &mut map_settings_draft,
+                |draft| Some(&mut draft.noise_period),
+                value,
+            ),
+            SliderParameter::BaseSusceptibility => set_slider_parameter(
+                &mut fire_susceptibility,
+                |susceptibility| Some(&mut susceptibility.base_susceptibility),
+                value,
+            ),
+            SliderParameter::SpreadMultiplier => set_slider_parameter(
+                &mut fire_spread,
+                |spread| Some(&mut spread.spread_multiplier),
+                value,
+            ),
+            SliderParameter::TileSusceptibility(kind) => set_slider_parameter(
+                &mut fire_susceptibility,
+                // Kinds without an entry are left alone, rather than being given one by the slider
+                |susceptibility| susceptibility.tile_susceptibility.get_mut(kind),
+                value,
+            ),
+        }
+    }
+}
+
+/// Sets one of a resource's parameters to the value of its slider,
+/// unless the two already match to within the slider's `f32` precision.
+///
+/// Many parameters are stored as `f64`, which can't survive a round trip through the slider:
+/// writing the slider's value back would replace `1e-3` with `0.0010000000474974513`.
+/// The resource is only marked as changed when the parameter is actually written,
+/// and parameters that `parameter` can't find are skipped.
+fn set_slider_parameter<R: Resource, T: Copy + Into<f64> + From<f32>>(
+    resource: &mut ResMut<R>,
+    parameter: impl FnOnce(&mut R) -> Option<&mut T>,
+    value: f32,
+) {
+    let Some(target) = parameter(resource.bypass_change_detection()) else {
+        return;
+    };
+    if matches_slider(*target, value) {
+        return;
+    }
+
+    *target = T::from(value);
+    resource.set_changed();
+}
+
+/// Whether a parameter already holds the slider's value, to within the slider's `f32` precision.
+fn matches_slider(parameter: impl Into<f64>, value: f32) -> bool {
+    parameter.into() as f32 == value
+}
+
+/// Moves sliders to match their parameters, when those are changed by other means, such as the inspector.
+fn sync_sliders(
+    forest_fire_probabilities: Res<ForestFireProbabilities>,
+    map_settings_draft: Res<MapSettingsDraft>,
+    fire_susceptibility: Res<FireSusceptibility>,
+    fire_spread: Res<FireSpread>,
+    mut slider_query: Query<(&mut Slider, &SliderParameter)>,
+) {
+    for (mut slider, parameter) in slider_query.iter_mut() {
+        let value = match parameter {
+            SliderParameter::GrowthProbability => forest_fire_probabilities.growth_probability,
+            SliderParameter::LightningProbability => {
+                forest_fire_probabilities.lightning_probability
+            }
+            SliderParameter::WaterThreshold => map_settings_draft.water_threshold,
+            SliderParameter::NoisePeriod => map_settings_draft.noise_period,
+            SliderParameter::BaseSusceptibility => fire_susceptibility.base_susceptibility as f32,
+            SliderParameter::SpreadMultiplier => fire_spread.spread_multiplier as f32,
+            SliderParameter::TileSusceptibility(kind) => fire_susceptibility
+                .tile_susceptibility
+                .get(kind)
+                .copied()
+                .unwrap_or(0.0) as f32,
+        };
+
+        if slider.value != value {
+            slider.value = value;
+        }
+    }
+}
+
+fn update_slider_display(
+    slider_query: Query<(&Slider, &SliderParameter, &Children), Changed<Slider>>,
+    mut fill_query: Query<&mut Node, With<SliderFill>>,
+    mut label_query: Query<&mut Text, With<SliderLabel>>,
+) {
+    for (slider, parameter, children) in slider_query.iter() {
+        for &child in children {
+            if let Ok(mut fill_node) = fill_query.get_mut(child) {
+                fill_node.width = Val::Percent(slider.fraction() * 100.0);
+            }
+
+            if let Ok(mut label) = label_query.get_mut(child) {
+                label.0 = format!(
+                    "{}: {:.*}",
+                    parameter.label(),
+                    parameter.decimals(),
+                    slider.value
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters_match_their_slider_despite_rounding() {
+        // `1e-3` has no exact `f32` representation, so the slider holds a slightly different value
+        assert!(matches_slider(1e-3_f64, 1e-3_f32));
+        assert_ne!(1e-3_f32 as f64, 1e-3_f64);
+        assert!(matches_slider(0.25_f32, 0.25_f32));
+
+        assert!(!matches_slider(1e-3_f64, 2e-3_f32));
+        assert!(!matches_slider(0.0_f64, f32::EPSILON));
+    }
+}
@@ -0,0 +1,150 @@
+//! Loads a grayscale heightmap image to use as the elevation layer of the map, instead of noise.
+//!
+//! Set the [`Heightmap::path`] (or use the `heightmap` console command) to an image in the `assets` folder,
+//! such as a digital elevation model exported from a GIS tool.
+//! Brighter pixels are higher: tiles below the [`Heightmap::sea_level`] become water,
+//! and the rest of the map is generated as usual.
+//! 8-bit and 16-bit grayscale images are both supported, and color images are converted to grayscale.
+
+use bevy::image::ImageLoaderSettings;
+use bevy::prelude::*;
+
+use crate::SimState;
+use crate::map_generation::MapSize;
+use crate::map_images::{ImageLayer, newly_loaded_image};
+use crate::spatial_index::Position;
+
+pub struct HeightmapPlugin;
+
+impl Plugin for HeightmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Heightmap>()
+            .register_type::<Heightmap>()
+            .add_systems(
+                Update,
+                (
+                    load_heightmap.run_if(resource_changed::<Heightmap>),
+                    apply_loaded_heightmap,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Controls whether the elevation of the map is read from a heightmap image.
+///
+/// Changing these settings regenerates the map, once the image has loaded.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Heightmap {
+    /// The path to the heightmap image, relative to the `assets` folder.
+    ///
+    /// If this is `None`, elevation is generated from noise instead.
+    pub path: Option<String>,
+    /// The elevation below which tiles become water, in the range of 0.0 to 1.0.
+    pub sea_level: f32,
+    /// If true, the [`MapSize`] is changed to match the size of the image, with one tile per pixel.
+    ///
+    /// Otherwise, the image is stretched to fit the current map size.
+    pub resize_map: bool,
+}
+
+impl Default for Heightmap {
+    fn default() -> Self {
+        Self {
+            path: None,
+            sea_level: 0.3,
+            resize_map: false,
+        }
+    }
+}
+
+/// The image currently being loaded (or already loaded) for the [`Heightmap`].
+#[derive(Resource)]
+struct HeightmapHandle(Handle<Image>);
+
+/// The elevations read from the [`Heightmap`] image, ready to be used during map generation.
+///
+/// Each elevation is the brightness of a pixel, in the range of 0.0 to 1.0.
+/// This resource only exists while a heightmap is in use.
+#[derive(Resource, Debug)]
+pub struct LoadedHeightmap(ImageLayer<f32>);
+
+impl LoadedHeightmap {
+    /// Reads the brightness of every pixel of the image.
+    fn from_image(image: &Image) -> Self {
+        Self(ImageLayer::from_image(image, |color| {
+            (color.red + color.green + color.blue) / 3.0
+        }))
+    }
+
+    /// Returns the elevation of the tile at the given position, stretching the image to cover the whole map.
+    pub fn elevation_at(&self, position: &Position, map_size: &MapSize) -> f32 {
+        self.0.sample(position, map_size).unwrap_or(0.0)
+    }
+}
+
+/// Starts loading the heightmap image, or stops using it if the path has been cleared.
+fn load_heightmap(
+    heightmap: Res<Heightmap>,
+    asset_server: Res<AssetServer>,
+    mut next_state: ResMut<NextState<SimState>>,
+    mut commands: Commands,
+) {
+    let Some(path) = &heightmap.path else {
+        // The resource counts as changed when it is first added, but there's nothing to undo yet
+        if !heightmap.is_added() {
+            info!("Heightmap cleared, regenerating map");
+            commands.remove_resource::<HeightmapHandle>();
+            commands.remove_resource::<LoadedHeightmap>();
+            next_state.set(SimState::Generate);
+        }
+        return;
+    };
+
+    // Heightmaps store heights, not colors, so they shouldn't be gamma corrected
+    let handle =
+        asset_server.load_with_settings(path.clone(), |settings: &mut ImageLoaderSettings| {
+            settings.is_srgb = false;
+        });
+    commands.insert_resource(HeightmapHandle(handle));
+}
+
+/// Reads the heightmap image once it has loaded (or been modified on disk), then regenerates the map with it.
+fn apply_loaded_heightmap(
+    mut asset_events: EventReader<AssetEvent<Image>>,
+    handle: Option<Res<HeightmapHandle>>,
+    images: Res<Assets<Image>>,
+    heightmap: Res<Heightmap>,
+    mut map_size: ResMut<MapSize>,
+    mut next_state: ResMut<NextState<SimState>>,
+    mut commands: Commands,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    let Some(image) =
+        newly_loaded_image(&mut asset_events, &handle.0, handle.is_changed(), &images)
+    else {
+        return;
+    };
+
+    let loaded_heightmap = LoadedHeightmap::from_image(image);
+    info!(
+        "Loaded a {}x{} heightmap, regenerating map",
+        loaded_heightmap.0.width, loaded_heightmap.0.height
+    );
+
+    if heightmap.resize_map {
+        let width = loaded_heightmap.0.width as i32;
+        let height = loaded_heightmap.0.height as i32;
+        if map_size.width != width || map_size.height != height {
+            map_size.width = width;
+            map_size.height = height;
+        }
+    }
+
+    commands.insert_resource(loaded_heightmap);
+    next_state.set(SimState::Generate);
+}
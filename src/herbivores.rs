@@ -0,0 +1,251 @@
+//! Grazing herbivores: a simple agent-based model living on top of the cellular automaton.
+//!
+//! Each herbivore wanders the map at random, eating back any young growth on the tile it stands on.
+//! This shows how agent-based and cellular models can be mixed in the same [`Simulation`] schedule:
+//! agents are just entities with a [`Position`], and use the [`TileIndex`] to find the tiles around them.
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::SimState;
+use crate::control_flow::Simulation;
+use crate::map_generation::{GenerationStep, MapSeed};
+use crate::rulesets::{ForestSystems, Ruleset, ruleset_is};
+use crate::simulation::TileKind;
+use crate::spatial_index::{Position, Tile, TileIndex};
+use crate::tile_rng::{TileRng, TileRngStream, advance_tile_rng};
+
+pub struct HerbivorePlugin;
+
+impl Plugin for HerbivorePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Herbivore>()
+            .init_resource::<HerbivorePopulation>()
+            .register_type::<HerbivorePopulation>()
+            .init_resource::<Grazing>()
+            .register_type::<Grazing>()
+            .add_systems(OnEnter(SimState::Generate), despawn_herbivores)
+            .add_systems(
+                OnExit(SimState::Generate),
+                spawn_herbivores.run_if(ruleset_is(Ruleset::Forest)),
+            )
+            .add_systems(
+                Simulation,
+                (move_herbivores, graze)
+                    .chain()
+                    .after(advance_tile_rng)
+                    .in_set(ForestSystems),
+            );
+    }
+}
+
+/// A marker component for grazing animals that wander the map.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Herbivore;
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct HerbivorePopulation {
+    /// The number of herbivores spawned on dry land each time the map is generated.
+    count: u32,
+}
+
+impl Default for HerbivorePopulation {
+    fn default() -> Self {
+        Self { count: 20 }
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct Grazing {
+    /// The probability that a herbivore eats back the vegetation on its tile each tick.
+    grazing_probability: f64,
+    /// The kind that each tile kind is reverted to when grazed.
+    ///
+    /// Missing entries indicate that the tile kind cannot be grazed.
+    grazed_kinds: HashMap<TileKind, TileKind>,
+    /// The tile kinds that herbivores refuse to walk onto.
+    impassable_kinds: Vec<TileKind>,
+}
+
+impl Default for Grazing {
+    fn default() -> Self {
+        let mut grazed_kinds = HashMap::new();
+        grazed_kinds.insert(TileKind::Shrubland, TileKind::Meadow);
+        grazed_kinds.insert(TileKind::ShadeIntolerantForest, TileKind::Shrubland);
+
+        Self {
+            grazing_probability: 0.5,
+            grazed_kinds,
+            impassable_kinds: vec![TileKind::Water, TileKind::Fire],
+        }
+    }
+}
+
+impl Herbivore {
+    const COLOR: Color = Color::hsl(30., 0.6, 0.25);
+    const SIZE: f32 = Position::PIXELS_PER_TILE * 0.5;
+}
+
+fn despawn_herbivores(mut commands: Commands, query: Query<Entity, With<Herbivore>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[hot]
+fn spawn_herbivores(
+    tile_query: Query<(&Position, &TileKind), With<Tile>>,
+    herbivore_population: Res<HerbivorePopulation>,
+    grazing: Res<Grazing>,
+    map_seed: Res<MapSeed>,
+    mut commands: Commands,
+) {
+    let mut rng = map_seed.rng(GenerationStep::Herbivores);
+    let habitable_positions: Vec<Position> = tile_query
+        .iter()
+        .filter(|(_, tile_kind)| !grazing.impassable_kinds.contains(tile_kind))
+        .map(|(position, _)| *position)
+        .collect();
+
+    for _ in 0..herbivore_population.count {
+        let Some(&position) = habitable_positions.choose(&mut rng) else {
+            warn!("No habitable tiles found: no herbivores will be spawned.");
+            return;
+        };
+
+        commands.spawn((
+            Name::new("Herbivore"),
+            Herbivore,
+            position,
+            herbivore_transform(&position),
+            Sprite {
+                color: Herbivore::COLOR,
+                custom_size: Some(Vec2::splat(Herbivore::SIZE)),
+                ..Default::default()
+            },
+        ));
+    }
+}
+
+/// Herbivores are drawn above the tiles that they are standing on.
+fn herbivore_transform(position: &Position) -> Transform {
+    let mut transform = position.to_transform();
+    transform.translation.z = 2.0;
+    transform
+}
+
+/// Moves each herbivore one step in a random cardinal direction, if that tile is passable.
+///
+/// Steps off the edge of the map follow the [`MapTopology`](crate::spatial_index::MapTopology),
+/// so herbivores can wander across the seam of a wrapping map, but never off of an absorbing one.
+#[hot]
+fn move_herbivores(
+    herbivore_query: Query<(Entity, &Position), With<Herbivore>>,
+    tile_query: Query<&TileKind, With<Tile>>,
+    grazing: Res<Grazing>,
+    tile_index: Res<TileIndex>,
+    tile_rng: Res<TileRng>,
+    mut commands: Commands,
+) {
+    // Herbivores take turns in order of position, so that the same map seed always moves them the same way.
+    // Herbivores sharing a tile are interchangeable, so it doesn't matter which of them goes first.
+    let mut herbivores: Vec<(Entity, Position)> = herbivore_query
+        .iter()
+        .map(|(entity, position)| (entity, *position))
+        .collect();
+    herbivores.sort_unstable_by_key(|(_, position)| (position.y, position.x));
+
+    let mut rng = tile_rng.for_map(TileRngStream::HerbivoreMove);
+    for (entity, position) in herbivores {
+        let candidates: Vec<Position> = position
+            .cardinal_neighbors()
+            .iter()
+            .filter_map(|neighbor| tile_index.resolve(neighbor))
+            .collect();
+        let Some(&destination) = candidates.choose(&mut rng) else {
+            continue;
+        };
+
+        let Some(destination_entity) = tile_index.get(&destination) else {
+            continue;
+        };
+
+        let Ok(destination_kind) = tile_query.get(destination_entity) else {
+            continue;
+        };
+
+        if grazing.impassable_kinds.contains(destination_kind) {
+            continue;
+        }
+
+        // `Position` is immutable, so we need to reinsert it to move the herbivore
+        commands
+            .entity(entity)
+            .insert((destination, herbivore_transform(&destination)));
+    }
+}
+
+#[hot]
+fn graze(
+    herbivore_query: Query<&Position, With<Herbivore>>,
+    mut tile_query: Query<&mut TileKind, With<Tile>>,
+    grazing: Res<Grazing>,
+    tile_index: Res<TileIndex>,
+    tile_rng: Res<TileRng>,
+) {
+    for position in herbivore_query.iter() {
+        let Some(tile_entity) = tile_index.get(position) else {
+            continue;
+        };
+
+        let Ok(mut tile_kind) = tile_query.get_mut(tile_entity) else {
+            continue;
+        };
+
+        let Some(&grazed_kind) = grazing.grazed_kinds.get(&*tile_kind) else {
+            continue;
+        };
+
+        let mut rng = tile_rng.for_tile(position, TileRngStream::Grazing);
+        if rng.random_bool(grazing.grazing_probability.clamp(0.0, 1.0)) {
+            *tile_kind = grazed_kind;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::spatial_index::MapTopology;
+    use crate::spatial_index::tests::world_from_rows;
+
+    #[test]
+    fn herbivores_wander_across_wrapped_edges() {
+        // The only passable tile next to the herbivore is on the far side of the map
+        let mut world = world_from_rows(&["~..", ".~.", "~.."], MapTopology::Toroidal);
+        world.init_resource::<TileRng>();
+        world.init_resource::<Grazing>();
+        let start = Position { x: 0, y: 1 };
+        let herbivore = world.spawn((Herbivore, start)).id();
+
+        for _ in 0..32 {
+            world.run_system_once(advance_tile_rng).unwrap();
+            world.run_system_once(move_herbivores).unwrap();
+            if world.get::<Position>(herbivore) != Some(&start) {
+                break;
+            }
+        }
+
+        assert_eq!(
+            world.get::<Position>(herbivore),
+            Some(&Position { x: 2, y: 1 })
+        );
+    }
+}
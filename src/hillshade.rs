@@ -0,0 +1,201 @@
+//! Shades the map by its terrain, as if lit by a low sun, so that hills and valleys stand out.
+//!
+//! Each tile's slope is worked out from the [`Elevation`] of its neighbors,
+//! then compared to the direction of the sun set in the [`Hillshade`] settings:
+//! slopes facing the sun are brightened, while those facing away are darkened.
+//! The results are stored in the [`HillshadeMap`], which tints the color of each tile as it is drawn.
+//!
+//! Only rulesets with terrain are shaded, since the elevation of other rulesets has no meaning.
+
+use bevy::prelude::*;
+
+use crate::SimState;
+use crate::map_generation::GeneratedMapSize;
+use crate::rulesets::Ruleset;
+use crate::simulation::Elevation;
+use crate::spatial_index::{GridShape, Position, Tile};
+
+pub struct HillshadePlugin;
+
+impl Plugin for HillshadePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hillshade>()
+            .register_type::<Hillshade>()
+            .init_resource::<HillshadeMap>()
+            .add_systems(OnEnter(SimState::Generate), clear_hillshade_map)
+            // Elevation is only set while the map is generated, so there's no need to check it every frame
+            .add_systems(OnExit(SimState::Generate), update_hillshade_map)
+            .add_systems(
+                Update,
+                update_hillshade_map
+                    .run_if(resource_changed::<Hillshade>)
+                    .run_if(not(in_state(SimState::Generate))),
+            );
+    }
+}
+
+/// Controls how the map is shaded by its terrain.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct Hillshade {
+    pub enabled: bool,
+    /// The compass direction that the sun shines from, in degrees clockwise from the top of the map.
+    pub sun_azimuth: f32,
+    /// How high the sun is above the horizon, in degrees.
+    ///
+    /// Lower suns cast stronger shading.
+    pub sun_altitude: f32,
+    /// How much steeper slopes are drawn than they really are.
+    ///
+    /// Elevation only spans 0.0 to 1.0 across the whole map, so its slopes need exaggerating to be visible at all.
+    pub vertical_exaggeration: f32,
+    /// How strongly the shading tints each tile, from 0.0 (not at all) to 1.0.
+    pub strength: f32,
+}
+
+impl Default for Hillshade {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            // The traditional direction for hillshading, since it matches how people expect light to fall
+            sun_azimuth: 315.0,
+            sun_altitude: 45.0,
+            vertical_exaggeration: 40.0,
+            strength: 0.6,
+        }
+    }
+}
+
+impl Hillshade {
+    /// How brightly a tile with the given slope is lit, relative to flat ground.
+    ///
+    /// The slope is the change in elevation for each tile moved along the world's x and y axes.
+    fn brightness(&self, slope: Vec2) -> f32 {
+        let (azimuth, altitude) = (
+            self.sun_azimuth.to_radians(),
+            self.sun_altitude.to_radians(),
+        );
+        let sun = Vec3::new(
+            azimuth.sin() * altitude.cos(),
+            azimuth.cos() * altitude.cos(),
+            altitude.sin(),
+        );
+        let normal = (-slope * self.vertical_exaggeration)
+            .extend(1.0)
+            .normalize();
+
+        let lit = normal.dot(sun).max(0.0) / sun.z.max(0.01);
+        1.0 + (lit - 1.0) * self.strength
+    }
+}
+
+/// How brightly each tile is lit by the [`Hillshade`], relative to flat ground.
+///
+/// This is empty while the map is generated, or if the map isn't shaded, leaving every tile unchanged.
+#[derive(Resource, Default)]
+pub struct HillshadeMap {
+    width: i32,
+    height: i32,
+    brightness: Vec<f32>,
+}
+
+impl HillshadeMap {
+    /// Returns how brightly the tile at the given position is lit.
+    pub fn brightness(&self, position: &Position) -> f32 {
+        if !(0..self.width).contains(&position.x) || !(0..self.height).contains(&position.y) {
+            return 1.0;
+        }
+
+        self.brightness
+            .get((position.y * self.width + position.x) as usize)
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Brightens or darkens a color to match how the tile at the given position is lit.
+    pub fn shade(&self, color: Color, position: &Position) -> Color {
+        let brightness = self.brightness(position);
+        if brightness == 1.0 {
+            return color;
+        }
+
+        let color = color.to_linear();
+        LinearRgba {
+            red: color.red * brightness,
+            green: color.green * brightness,
+            blue: color.blue * brightness,
+            alpha: color.alpha,
+        }
+        .into()
+    }
+}
+
+fn clear_hillshade_map(mut hillshade_map: ResMut<HillshadeMap>) {
+    *hillshade_map = HillshadeMap::default();
+}
+
+/// Works out how brightly each tile is lit from the slope of the terrain around it.
+fn update_hillshade_map(
+    tile_query: Query<(&Position, &Elevation), With<Tile>>,
+    hillshade: Res<Hillshade>,
+    ruleset: Res<Ruleset>,
+    map_size: Res<GeneratedMapSize>,
+    grid_shape: Res<GridShape>,
+    mut hillshade_map: ResMut<HillshadeMap>,
+) {
+    if !hillshade.enabled || !ruleset.has_terrain() {
+        *hillshade_map = HillshadeMap::default();
+        return;
+    }
+
+    let (width, height) = (map_size.width.max(0), map_size.height.max(0));
+    let mut elevations = vec![None; (width * height) as usize];
+    for (position, elevation) in tile_query.iter() {
+        if (0..width).contains(&position.x) && (0..height).contains(&position.y) {
+            elevations[(position.y * width + position.x) as usize] = Some(elevation.0);
+        }
+    }
+
+    let elevation_at = |x: i32, y: i32| {
+        if (0..width).contains(&x) && (0..height).contains(&y) {
+            elevations[(y * width + x) as usize]
+        } else {
+            None
+        }
+    };
+
+    // The rows of hexagonal grids are skewed, so convert the slope along each grid axis into world space
+    let x_axis = grid_shape.grid_to_world(Vec2::X) / Position::PIXELS_PER_TILE;
+    let y_axis = grid_shape.grid_to_world(Vec2::Y) / Position::PIXELS_PER_TILE;
+    let to_world = Mat2::from_cols(x_axis, y_axis).transpose().inverse();
+
+    let mut brightness = vec![1.0; elevations.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let Some(elevation) = elevation_at(x, y) else {
+                continue;
+            };
+
+            // Use whichever neighbors exist on each axis, so that the edges of the map are still shaded
+            let slope_along = |dx: i32, dy: i32| {
+                let forward = elevation_at(x + dx, y + dy);
+                let backward = elevation_at(x - dx, y - dy);
+                match (forward, backward) {
+                    (Some(forward), Some(backward)) => (forward - backward) / 2.0,
+                    (Some(forward), None) => forward - elevation,
+                    (None, Some(backward)) => elevation - backward,
+                    (None, None) => 0.0,
+                }
+            };
+
+            let grid_slope = Vec2::new(slope_along(1, 0), slope_along(0, 1));
+            brightness[(y * width + x) as usize] = hillshade.brightness(to_world * grid_slope);
+        }
+    }
+
+    *hillshade_map = HillshadeMap {
+        width,
+        height,
+        brightness,
+    };
+}
@@ -0,0 +1,149 @@
+//! Slow changes to the extent of lakes and rivers over long runs.
+//!
+//! During droughts, the shallow edges of bodies of water dry out into meadows.
+//! When rain falls, low-lying land next to water can flood, and lakes spread back out.
+
+use bevy::platform::collections::HashSet;
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+use rand::Rng;
+
+use crate::climate::Drought;
+use crate::control_flow::Simulation;
+use crate::rulesets::ForestSystems;
+use crate::simulation::{Elevation, Moisture, RainStarted, TileKind, rainfall};
+use crate::spatial_index::{Position, Tile, TileIndex};
+use crate::tile_rng::{TileRng, TileRngStream, advance_tile_rng};
+
+pub struct HydrologyPlugin;
+
+impl Plugin for HydrologyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hydrology>()
+            .register_type::<Hydrology>()
+            .add_systems(
+                Simulation,
+                (dry_out_shorelines, flood_lowlands)
+                    .chain()
+                    // Floods need to see the rain that fell this tick
+                    .after(rainfall)
+                    .after(advance_tile_rng)
+                    .in_set(ForestSystems),
+            );
+    }
+}
+
+/// Controls how quickly bodies of water shrink and grow.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct Hydrology {
+    /// The probability that a water tile bordering land dries out into a meadow on each tick of a drought.
+    drying_probability: f64,
+    /// Land tiles with an elevation below this value can flood, in the range of 0.0 to 1.0.
+    flood_elevation: f32,
+    /// The probability that a low-lying land tile bordering water floods when it is rained on.
+    ///
+    /// This is scaled by the moisture of the tile, so saturated ground floods most easily.
+    flooding_probability: f64,
+}
+
+impl Default for Hydrology {
+    fn default() -> Self {
+        Self {
+            drying_probability: 0.01,
+            flood_elevation: 0.3,
+            flooding_probability: 0.2,
+        }
+    }
+}
+
+/// During droughts, water tiles along the shore slowly dry out into meadows.
+///
+/// Only tiles with a cardinal neighbor on land can dry, so lakes shrink from their edges inwards.
+#[hot]
+fn dry_out_shorelines(
+    mut tile_query: Query<(&Position, &mut TileKind), With<Tile>>,
+    tile_index: Res<TileIndex>,
+    hydrology: Res<Hydrology>,
+    drought: Res<Drought>,
+    tile_rng: Res<TileRng>,
+) {
+    if !drought.is_active() {
+        return;
+    }
+
+    let land: HashSet<Position> = tile_query
+        .iter()
+        .filter(|(_, tile_kind)| **tile_kind != TileKind::Water)
+        .map(|(position, _)| *position)
+        .collect();
+
+    for (position, mut tile_kind) in tile_query.iter_mut() {
+        if *tile_kind != TileKind::Water {
+            continue;
+        }
+
+        let on_shore = position
+            .cardinal_neighbors()
+            .iter()
+            .filter_map(|neighbor| tile_index.resolve(neighbor))
+            .any(|neighbor| land.contains(&neighbor));
+
+        if !on_shore {
+            continue;
+        }
+
+        let mut rng = tile_rng.for_tile(position, TileRngStream::ShorelineDrying);
+        if rng.random_bool(hydrology.drying_probability.clamp(0.0, 1.0)) {
+            *tile_kind = TileKind::Meadow;
+        }
+    }
+}
+
+/// Low-lying tiles next to water can flood when they are rained on.
+///
+/// Flooded tiles become water, and are fully saturated.
+#[hot]
+fn flood_lowlands(
+    mut event_reader: EventReader<RainStarted>,
+    mut tile_query: Query<(&Position, &mut TileKind, &Elevation, &mut Moisture), With<Tile>>,
+    tile_index: Res<TileIndex>,
+    hydrology: Res<Hydrology>,
+    tile_rng: Res<TileRng>,
+) {
+    for event in event_reader.read() {
+        let water: HashSet<Position> = tile_query
+            .iter()
+            .filter(|(_, tile_kind, _, _)| **tile_kind == TileKind::Water)
+            .map(|(position, ..)| *position)
+            .collect();
+
+        for &entity in &event.tiles {
+            let Ok((position, mut tile_kind, elevation, mut moisture)) = tile_query.get_mut(entity)
+            else {
+                continue;
+            };
+
+            if *tile_kind == TileKind::Water || elevation.0 >= hydrology.flood_elevation {
+                continue;
+            }
+
+            let next_to_water = position
+                .cardinal_neighbors()
+                .iter()
+                .filter_map(|neighbor| tile_index.resolve(neighbor))
+                .any(|neighbor| water.contains(&neighbor));
+
+            let flooding_probability = hydrology.flooding_probability * moisture.0 as f64;
+            if !next_to_water {
+                continue;
+            }
+
+            let mut rng = tile_rng.for_tile(position, TileRngStream::Flooding);
+            if rng.random_bool(flooding_probability.clamp(0.0, 1.0)) {
+                *tile_kind = TileKind::Water;
+                moisture.0 = 1.0;
+            }
+        }
+    }
+}
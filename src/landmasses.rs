@@ -0,0 +1,320 @@
+//! Optional constraints on how the land of a generated map is connected.
+//!
+//! Water stops fire from spreading, so the number of separate landmasses has a big effect on how far fires can travel.
+//! These constraints make it easy to set up a map that demonstrates this,
+//! either with a single continent that fire can sweep across or a scattering of islands that it can't.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+
+use crate::SimState;
+use crate::map_generation::{GeneratedMapSize, MapSize, TerrainBatch};
+use crate::simulation::TileKind;
+use crate::spatial_index::{GridIndex, GridShape, Position, TileIndex};
+
+pub struct LandmassPlugin;
+
+impl Plugin for LandmassPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LandmassConstraint>()
+            .register_type::<LandmassConstraint>()
+            .add_systems(
+                Update,
+                regenerate_when_constraint_changes.run_if(resource_changed::<LandmassConstraint>),
+            );
+    }
+}
+
+/// Controls how many separate landmasses a generated map must have.
+///
+/// Two land tiles belong to the same landmass if you can walk from one to the other without crossing water,
+/// including diagonal steps on square grids (and across the edges of wrapping maps).
+/// This is checked once the water and rivers have been placed, and the map is repaired if needed.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Default)]
+#[reflect(Resource)]
+pub enum LandmassConstraint {
+    /// Land is left however it was generated.
+    #[default]
+    Unconstrained,
+    /// All of the land is connected.
+    ///
+    /// Every landmass except the largest is flooded.
+    SingleLandmass,
+    /// There are at least this many separate islands.
+    ///
+    /// The largest island is repeatedly cut in two by a channel of water until there are enough islands.
+    MinimumIslands(u32),
+}
+
+impl LandmassConstraint {
+    /// The largest number of channels that will be cut while trying to reach [`LandmassConstraint::MinimumIslands`].
+    const MAX_CHANNELS: u32 = 64;
+}
+
+/// How far [`enforce_landmass_constraint`] has got, kept from one frame to the next.
+#[derive(Default)]
+pub struct LandmassProgress {
+    stage: LandmassStage,
+    /// The landmass that each tile belongs to, as an index into `landmasses`,
+    /// stored column by column like the [`GridIndex`].
+    ///
+    /// Water, positions without a tile and land that hasn't been reached yet are `None`.
+    labels: Vec<Option<usize>>,
+    /// Every landmass found so far, in the order of their first tile, scanning row by row from `y = 0`.
+    landmasses: Vec<Landmass>,
+    /// The next position to check for the first tile of a new landmass, counting along each row from `y = 0`.
+    next_scan: usize,
+    /// The tiles of the landmass being filled in whose neighbors are still to be checked.
+    frontier: VecDeque<Position>,
+    /// The number of channels cut so far.
+    channels: u32,
+}
+
+/// What [`enforce_landmass_constraint`] is doing at the moment.
+#[derive(Default)]
+enum LandmassStage {
+    /// Splitting the land into landmasses.
+    #[default]
+    Labelling,
+    /// Flooding every landmass except for the one with this index.
+    Flooding(usize),
+}
+
+/// The size and extent of a single landmass.
+struct Landmass {
+    size: usize,
+    min: IVec2,
+    max: IVec2,
+}
+
+impl LandmassProgress {
+    /// Forgets every landmass, ready to split the land up again from scratch.
+    fn start_labelling(&mut self, tile_count: usize) {
+        self.stage = LandmassStage::Labelling;
+        self.labels.clear();
+        self.labels.resize(tile_count, None);
+        self.landmasses.clear();
+        self.next_scan = 0;
+        self.frontier.clear();
+    }
+
+    /// Splits the land into connected landmasses, doing up to `budget` tiles' worth of work.
+    ///
+    /// This follows the same rules as [`TileIndex::label_regions`], but can be picked up again on the next frame.
+    /// Returns `true` once every landmass has been found.
+    fn label(
+        &mut self,
+        budget: usize,
+        map_size: &MapSize,
+        tile_index: &TileIndex,
+        grid_shape: &GridShape,
+        is_land: impl Fn(&Position) -> bool,
+    ) -> bool {
+        let (width, height) = (map_size.width, map_size.height);
+
+        for _ in 0..budget {
+            // Only the most recently found landmass is ever being filled in
+            if let Some(position) = self.frontier.pop_front() {
+                let label = self.landmasses.len() - 1;
+
+                for neighbor in grid_shape.adjacent(&position) {
+                    let Some(neighbor) = tile_index.resolve(&neighbor) else {
+                        continue;
+                    };
+                    let Some(index) = GridIndex::flat_index(&neighbor, width, height) else {
+                        continue;
+                    };
+
+                    if self.labels[index].is_none() && is_land(&neighbor) {
+                        self.labels[index] = Some(label);
+                        self.frontier.push_back(neighbor);
+
+                        let landmass = &mut self.landmasses[label];
+                        landmass.size += 1;
+                        landmass.min = landmass.min.min(IVec2::new(neighbor.x, neighbor.y));
+                        landmass.max = landmass.max.max(IVec2::new(neighbor.x, neighbor.y));
+                    }
+                }
+                continue;
+            }
+
+            if self.next_scan >= self.labels.len() {
+                return true;
+            }
+
+            let position = Position {
+                x: self.next_scan as i32 % width,
+                y: self.next_scan as i32 / width,
+            };
+            self.next_scan += 1;
+
+            let Some(index) = GridIndex::flat_index(&position, width, height) else {
+                continue;
+            };
+            if self.labels[index].is_some() || tile_index.get(&position).is_none() {
+                continue;
+            }
+
+            if is_land(&position) {
+                self.labels[index] = Some(self.landmasses.len());
+                self.landmasses.push(Landmass {
+                    size: 1,
+                    min: IVec2::new(position.x, position.y),
+                    max: IVec2::new(position.x, position.y),
+                });
+                self.frontier.push_back(position);
+            }
+        }
+
+        false
+    }
+
+    /// Returns the tiles of a straight channel that cuts the given landmass in two through its middle.
+    ///
+    /// The channel runs across the landmass's narrower side, to keep it as short as possible.
+    /// A single tile of water is enough to separate landmasses on both square and hexagonal grids.
+    fn channel_across(&self, label: usize, map_size: &MapSize) -> Vec<Position> {
+        let Landmass { min, max, .. } = self.landmasses[label];
+        let middle = (min + max) / 2;
+
+        // Wide landmasses are cut from north to south, and tall ones from west to east
+        let line: Vec<Position> = if max.x - min.x >= max.y - min.y {
+            (min.y..=max.y)
+                .map(|y| Position { x: middle.x, y })
+                .collect()
+        } else {
+            (min.x..=max.x)
+                .map(|x| Position { x, y: middle.y })
+                .collect()
+        };
+
+        line.into_iter()
+            .filter(|position| {
+                GridIndex::flat_index(position, map_size.width, map_size.height)
+                    .is_some_and(|index| self.labels[index] == Some(label))
+            })
+            .collect()
+    }
+}
+
+/// Repairs the map so that it follows the [`LandmassConstraint`].
+///
+/// Finding the landmasses means searching the whole map, so this is spread over as many frames as it needs,
+/// doing a [`TerrainBatch::budget`] of tiles' worth of work each frame.
+#[hot]
+#[allow(clippy::too_many_arguments)]
+pub fn enforce_landmass_constraint(
+    mut tile_query: Query<(&Position, &mut TileKind)>,
+    landmass_constraint: Res<LandmassConstraint>,
+    tile_index: Res<TileIndex>,
+    grid_shape: Res<GridShape>,
+    map_size: Res<GeneratedMapSize>,
+    mut batch: ResMut<TerrainBatch>,
+    mut progress: Local<LandmassProgress>,
+) {
+    if *landmass_constraint == LandmassConstraint::Unconstrained {
+        return;
+    }
+
+    let (width, height) = (map_size.width, map_size.height);
+    let tile_count = (width.max(0) * height.max(0)) as usize;
+    if batch.starts_phase() {
+        progress.channels = 0;
+        progress.start_labelling(tile_count);
+    }
+
+    if let LandmassStage::Flooding(kept) = progress.stage {
+        for &entity in batch.tiles() {
+            let Ok((position, mut tile_kind)) = tile_query.get_mut(entity) else {
+                continue;
+            };
+
+            let label = GridIndex::flat_index(position, width, height)
+                .and_then(|index| progress.labels[index]);
+            if label.is_some_and(|label| label != kept) {
+                *tile_kind = TileKind::Water;
+            }
+        }
+        return;
+    }
+
+    let is_land = |position: &Position| {
+        tile_index
+            .get(position)
+            .and_then(|entity| tile_query.get(entity).ok())
+            .is_some_and(|(_, tile_kind)| *tile_kind != TileKind::Water)
+    };
+    if !progress.label(batch.budget(), &map_size, &tile_index, &grid_shape, is_land) {
+        batch.sweep_again();
+        return;
+    }
+
+    match *landmass_constraint {
+        LandmassConstraint::Unconstrained => {}
+        LandmassConstraint::SingleLandmass => {
+            // The first of the largest landmasses is kept
+            let largest = progress
+                .landmasses
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, landmass)| landmass.size)
+                .map(|(label, _)| label);
+
+            if let Some(largest) = largest
+                && progress.landmasses.len() > 1
+            {
+                progress.stage = LandmassStage::Flooding(largest);
+                batch.sweep_again();
+            }
+        }
+        LandmassConstraint::MinimumIslands(minimum) => {
+            let island_count = progress.landmasses.len();
+            if island_count >= minimum as usize {
+                return;
+            }
+
+            let Some((largest, landmass)) = progress
+                .landmasses
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, landmass)| landmass.size)
+            else {
+                warn!("There is no land to split into {minimum} islands");
+                return;
+            };
+
+            // Single tiles can't be split any further
+            if progress.channels >= LandmassConstraint::MAX_CHANNELS || landmass.size < 2 {
+                warn!("Only {island_count} of the requested {minimum} islands could be created");
+                return;
+            }
+
+            for position in progress.channel_across(largest, &map_size) {
+                if let Some(entity) = tile_index.get(&position)
+                    && let Ok((_, mut tile_kind)) = tile_query.get_mut(entity)
+                {
+                    *tile_kind = TileKind::Water;
+                }
+            }
+            progress.channels += 1;
+
+            // The islands are counted again from scratch, on the next frame
+            progress.start_labelling(tile_count);
+            batch.sweep_again();
+        }
+    }
+}
+
+fn regenerate_when_constraint_changes(
+    landmass_constraint: Res<LandmassConstraint>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    // The resource counts as changed when it is first added, but the first map is already being generated
+    if !landmass_constraint.is_added() {
+        info!("Landmass constraint changed to {landmass_constraint:?}, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+}
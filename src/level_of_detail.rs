@@ -0,0 +1,327 @@
+//! Swaps the tiles for a downsampled copy of the map when the camera is zoomed far out.
+//!
+//! Once each tile would be drawn only a few pixels across, drawing them one by one is wasted effort.
+//! Instead, every [`Chunk`] of tiles is hidden, and the whole map is drawn as a single texture,
+//! with one pixel for each [`LevelOfDetail::BLOCK_SIZE`] by [`LevelOfDetail::BLOCK_SIZE`] block of tiles,
+//! colored by the most common kind of tile in that block.
+//! Only the blocks in chunks that changed are redrawn each frame, and zooming back in restores the tiles.
+//!
+//! Any number of [`DownsampledMap`]s can exist at once, each with its own block size:
+//! the minimap keeps one of its own, drawn only by its camera.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::SimState;
+use crate::camera::MainCamera;
+use crate::chunks::Chunk;
+use crate::map_generation::{GeneratedMapSize, MapSize};
+use crate::simulation::TileKind;
+use crate::spatial_index::{GridShape, Position, Tile};
+use crate::tile_appearance::TileAppearance;
+
+pub struct LevelOfDetailPlugin;
+
+impl Plugin for LevelOfDetailPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelOfDetail>()
+            .register_type::<LevelOfDetail>()
+            .add_systems(Update, update_level_of_detail)
+            .add_systems(
+                PostUpdate,
+                (
+                    spawn_downsampled_map.run_if(level_of_detail_active),
+                    despawn_downsampled_map.run_if(not(level_of_detail_active)),
+                    redraw_downsampled_maps,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Controls when the map is drawn as a downsampled texture, rather than tile by tile.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct LevelOfDetail {
+    /// Whether the map can switch to the downsampled texture at all.
+    pub enabled: bool,
+    /// Once each tile would be drawn smaller than this many pixels across, the downsampled texture is used instead.
+    pub min_tile_pixels: f32,
+    /// Whether the downsampled texture is currently drawn in place of the tiles.
+    active: bool,
+}
+
+impl Default for LevelOfDetail {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_tile_pixels: 4.0,
+            active: false,
+        }
+    }
+}
+
+impl LevelOfDetail {
+    /// The width and height of the block of tiles covered by each pixel of the downsampled texture.
+    ///
+    /// This evenly divides [`Chunk::SIZE`], so that each block lies within a single chunk.
+    pub const BLOCK_SIZE: i32 = 4;
+
+    /// Whether the downsampled texture is currently drawn in place of the tiles.
+    pub fn active(&self) -> bool {
+        self.active
+    }
+}
+
+/// A run condition that checks whether the map is currently drawn as a downsampled texture.
+fn level_of_detail_active(level_of_detail: Res<LevelOfDetail>) -> bool {
+    level_of_detail.active
+}
+
+/// Switches between the tiles and the downsampled texture as the camera zooms in and out.
+///
+/// The tiles are always shown while the map is being generated, so that its progress can be watched.
+fn update_level_of_detail(
+    camera_projection: Single<&Projection, With<MainCamera>>,
+    sim_state: Res<State<SimState>>,
+    mut level_of_detail: ResMut<LevelOfDetail>,
+) {
+    let Projection::Orthographic(ortho) = *camera_projection else {
+        return;
+    };
+
+    let tile_pixels = Position::PIXELS_PER_TILE / ortho.scale;
+    let active = level_of_detail.enabled
+        && tile_pixels < level_of_detail.min_tile_pixels
+        && *sim_state.get() != SimState::Generate;
+
+    if level_of_detail.active != active {
+        level_of_detail.active = active;
+    }
+}
+
+/// A texture drawn over the whole map, with one pixel for each square block of tiles.
+///
+/// Each pixel is colored by the most common kind of tile in its block, and kept up to date as the tiles change.
+#[derive(Component)]
+pub struct DownsampledMap {
+    image: Handle<Image>,
+    /// The width and height of the texture, in blocks of tiles.
+    size: UVec2,
+    /// The width and height of the block of tiles covered by each pixel.
+    block_size: i32,
+}
+
+/// A marker component for the [`DownsampledMap`] drawn in place of the tiles by the [`LevelOfDetail`].
+#[derive(Component)]
+struct LevelOfDetailMap;
+
+impl DownsampledMap {
+    /// Returns a downsampled map covering the whole map, along with the mesh that draws it.
+    ///
+    /// The block size must evenly divide [`Chunk::SIZE`], so that each block lies within a single chunk.
+    /// The texture starts out transparent, and is filled in once it has been spawned.
+    pub fn bundle(
+        block_size: i32,
+        map_size: &MapSize,
+        grid_shape: &GridShape,
+        images: &mut Assets<Image>,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<ColorMaterial>,
+    ) -> impl Bundle {
+        debug_assert_eq!(Chunk::SIZE % block_size, 0);
+
+        let size = UVec2::new(
+            (map_size.width.max(1) + block_size - 1) as u32 / block_size as u32,
+            (map_size.height.max(1) + block_size - 1) as u32 / block_size as u32,
+        );
+
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        // Keep each block crisp, rather than blurring it into its neighbors
+        image.sampler = ImageSampler::nearest();
+        let image = images.add(image);
+
+        // The texture covers whole blocks, which can overhang the edge of the map
+        let texture_tiles = (size * block_size as u32).as_vec2();
+        let min = Vec2::splat(-0.5);
+        let max = Vec2::new(map_size.width as f32, map_size.height as f32) - 0.5;
+        let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+
+        // Hexagonal grids skew the map into a rhombus, so the corners are placed individually
+        let positions: Vec<[f32; 3]> = corners
+            .iter()
+            .map(|&corner| grid_shape.grid_to_world(corner).extend(0.0).to_array())
+            .collect();
+        let uvs: Vec<[f32; 2]> = corners
+            .iter()
+            .map(|&corner| {
+                let uv = (corner + 0.5) / texture_tiles;
+                [uv.x, 1.0 - uv.y]
+            })
+            .collect();
+
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; 4])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(vec![0, 1, 2, 0, 2, 3]));
+
+        (
+            Mesh2d(meshes.add(mesh)),
+            MeshMaterial2d(materials.add(ColorMaterial::from(image.clone()))),
+            Self {
+                image,
+                size,
+                block_size,
+            },
+        )
+    }
+
+    /// Returns where the pixel for the given block of tiles is stored in the image, if it is on the map.
+    fn pixel_index(&self, block: IVec2) -> Option<usize> {
+        let x = u32::try_from(block.x).ok().filter(|&x| x < self.size.x)?;
+        let y = u32::try_from(block.y).ok().filter(|&y| y < self.size.y)?;
+        // Images start from the top, while the map starts from the bottom
+        let row = self.size.y - 1 - y;
+        Some(((row * self.size.x + x) * 4) as usize)
+    }
+}
+
+/// Spawns the downsampled texture, drawn over the whole map, which is filled in when it is first redrawn.
+fn spawn_downsampled_map(
+    level_of_detail_map: Query<(), With<LevelOfDetailMap>>,
+    map_size: Res<GeneratedMapSize>,
+    grid_shape: Res<GridShape>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    if !level_of_detail_map.is_empty() {
+        return;
+    }
+
+    commands.spawn((
+        Name::new("Downsampled map"),
+        LevelOfDetailMap,
+        DownsampledMap::bundle(
+            LevelOfDetail::BLOCK_SIZE,
+            &map_size,
+            &grid_shape,
+            &mut images,
+            &mut meshes,
+            &mut materials,
+        ),
+    ));
+}
+
+/// Despawns the downsampled texture once the tiles are shown again, including whenever the map is regenerated.
+///
+/// Its image is freed along with the entity.
+fn despawn_downsampled_map(
+    level_of_detail_map: Query<Entity, With<LevelOfDetailMap>>,
+    mut commands: Commands,
+) {
+    for entity in level_of_detail_map.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Colors each block of every [`DownsampledMap`] by the most common kind of tile inside of it.
+///
+/// Newly spawned textures and changes to the [`TileAppearance`] redraw every chunk,
+/// but otherwise only chunks with tiles that changed this frame are redrawn.
+fn redraw_downsampled_maps(
+    downsampled_maps: Query<Ref<DownsampledMap>>,
+    chunk_query: Query<(&Chunk, &Children)>,
+    tile_query: Query<(&Position, &TileKind), With<Tile>>,
+    tile_appearance: Res<TileAppearance>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let any_dirty = chunk_query.iter().any(|(chunk, _)| chunk.dirty);
+    for downsampled_map in downsampled_maps.iter() {
+        let redraw_all = downsampled_map.is_added() || tile_appearance.is_changed();
+        if redraw_all || any_dirty {
+            redraw_downsampled_map(
+                &downsampled_map,
+                redraw_all,
+                &chunk_query,
+                &tile_query,
+                &tile_appearance,
+                &mut images,
+            );
+        }
+    }
+}
+
+/// Redraws the blocks of a single [`DownsampledMap`], either in every chunk or only in the dirty ones.
+fn redraw_downsampled_map(
+    downsampled_map: &DownsampledMap,
+    redraw_all: bool,
+    chunk_query: &Query<(&Chunk, &Children)>,
+    tile_query: &Query<(&Position, &TileKind), With<Tile>>,
+    tile_appearance: &TileAppearance,
+    images: &mut Assets<Image>,
+) {
+    let Some(data) = images
+        .get_mut(&downsampled_map.image)
+        .and_then(|image| image.data.as_mut())
+    else {
+        return;
+    };
+
+    let block_size = downsampled_map.block_size;
+    let blocks_per_chunk = Chunk::SIZE / block_size;
+    for (chunk, children) in chunk_query.iter() {
+        if !redraw_all && !chunk.dirty {
+            continue;
+        }
+
+        // Kinds are counted in the order they are found, so ties are always broken the same way
+        let mut block_counts: HashMap<IVec2, Vec<(TileKind, u32)>> = HashMap::default();
+        for (position, tile_kind) in tile_query.iter_many(children) {
+            let block = IVec2::new(position.x, position.y).div_euclid(IVec2::splat(block_size));
+            let counts = block_counts.entry(block).or_default();
+            match counts.iter_mut().find(|(kind, _)| kind == tile_kind) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((*tile_kind, 1)),
+            }
+        }
+
+        let first_block = chunk.coordinates * blocks_per_chunk;
+        for y in 0..blocks_per_chunk {
+            for x in 0..blocks_per_chunk {
+                let block = first_block + IVec2::new(x, y);
+                let Some(index) = downsampled_map.pixel_index(block) else {
+                    continue;
+                };
+
+                // Blocks without any tiles, such as those outside of the map mask, are left transparent
+                let color = block_counts
+                    .get(&block)
+                    .and_then(|counts| counts.iter().rev().max_by_key(|(_, count)| *count))
+                    .map_or([0; 4], |(tile_kind, _)| {
+                        tile_appearance.color(tile_kind).to_srgba().to_u8_array()
+                    });
+                data[index..index + 4].copy_from_slice(&color);
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! A demo of cellular automata in Bevy, simulating forest fires and other systems on a grid of tiles.
+//!
+//! Each module adds its own plugin, which are combined into an app by `main.rs`.
+//! The modules are also exposed here so that benchmarks can drive parts of the simulation without a window.
+
+use std::hash::Hash;
+
+use bevy::prelude::*;
+
+pub mod ants;
+pub mod camera;
+pub mod carbon;
+pub mod cell_layers;
+pub mod census;
+pub mod chunks;
+pub mod climate;
+pub mod composition;
+pub mod control_flow;
+pub mod day_night;
+pub mod dev_tools;
+pub mod disturbances;
+pub mod ecology_parameters;
+pub mod elementary;
+pub mod fire_effects;
+pub mod fire_tracking;
+pub mod firefighters;
+pub mod forestry;
+pub mod gpu_simulation;
+pub mod graphics;
+pub mod grid_overlay;
+pub mod gui;
+pub mod heightmap;
+pub mod herbivores;
+pub mod hillshade;
+pub mod hydrology;
+pub mod landmasses;
+pub mod level_of_detail;
+pub mod map_export;
+pub mod map_generation;
+pub mod map_images;
+pub mod map_mask;
+pub mod minimap;
+pub mod outbreaks;
+pub mod painting;
+pub mod population_chart;
+pub mod prescribed_burns;
+pub mod reaction_diffusion;
+pub mod recorder;
+pub mod rulesets;
+pub mod sandpile;
+pub mod screenshots;
+pub mod simulation;
+pub mod spatial_index;
+pub mod tile_appearance;
+pub mod tile_renderer;
+pub mod tile_rng;
+pub mod wave_function_collapse;
+
+#[derive(States, Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub enum SimState {
+    #[default]
+    Generate,
+    /// The map has been generated, but the simulation is frozen so that its starting conditions can be painted.
+    ///
+    /// Only entered when the [`SetupPhase`](control_flow::SetupPhase) is enabled.
+    Setup,
+    Run,
+    Paused,
+}
@@ -1,16 +1,8 @@
-use std::hash::Hash;
-
 use bevy::prelude::*;
 use bevy_prng::WyRand;
 use bevy_rand::plugin::EntropyPlugin;
 
-mod camera;
-mod control_flow;
-mod dev_tools;
-mod graphics;
-mod map_generation;
-mod simulation;
-mod spatial_index;
+use cellular_automata_demo::*;
 
 fn main() {
     App::new()
@@ -20,22 +12,60 @@ fn main() {
         .add_plugins(EntropyPlugin::<WyRand>::default())
         // Crate plugins
         .add_plugins((
+            ants::AntPlugin,
             camera::CameraPlugin,
+            climate::ClimatePlugin,
             control_flow::ControlFlowPlugin,
             dev_tools::DevToolsPlugin,
+            forestry::ForestryPlugin,
             graphics::GraphicsPlugin,
+            gui::GuiPlugin,
+            herbivores::HerbivorePlugin,
             map_generation::MapGenerationPlugin,
+            outbreaks::OutbreakPlugin,
+            painting::PaintingPlugin,
+            rulesets::RulesetPlugin,
             spatial_index::TilePlugin,
             simulation::TransitionPlugin,
         ))
+        .add_plugins((
+            carbon::CarbonPlugin,
+            cell_layers::CellLayersPlugin,
+            composition::CompositionPlugin,
+            disturbances::DisturbancePlugin,
+            ecology_parameters::EcologyParametersPlugin,
+            elementary::ElementaryPlugin,
+            fire_tracking::FireTrackingPlugin,
+            firefighters::FirefighterPlugin,
+            heightmap::HeightmapPlugin,
+            hydrology::HydrologyPlugin,
+            map_export::MapExportPlugin,
+            prescribed_burns::PrescribedBurnPlugin,
+            reaction_diffusion::ReactionDiffusionPlugin,
+            sandpile::SandpilePlugin,
+            tile_rng::TileRngPlugin,
+        ))
+        .add_plugins((
+            census::CensusPlugin,
+            chunks::ChunkPlugin,
+            day_night::DayNightPlugin,
+            fire_effects::FireEffectsPlugin,
+            gpu_simulation::GpuSimulationPlugin,
+            grid_overlay::GridOverlayPlugin,
+            hillshade::HillshadePlugin,
+            landmasses::LandmassPlugin,
+            level_of_detail::LevelOfDetailPlugin,
+            map_mask::MapMaskPlugin,
+            minimap::MinimapPlugin,
+            population_chart::PopulationChartPlugin,
+            recorder::RecorderPlugin,
+            screenshots::ScreenshotPlugin,
+            tile_appearance::TileAppearancePlugin,
+        ))
+        .add_plugins((
+            tile_renderer::TileRendererPlugin,
+            wave_function_collapse::WaveFunctionCollapsePlugin,
+        ))
         .init_state::<SimState>()
         .run();
 }
-
-#[derive(States, Debug, PartialEq, Eq, Hash, Clone, Default)]
-pub enum SimState {
-    #[default]
-    Generate,
-    Run,
-    Paused,
-}
@@ -0,0 +1,180 @@
+//! Saves the current map to disk, so that interesting landscapes can be shared and compared.
+//!
+//! Each export writes two files to the `exports` folder:
+//! a RON file holding the kind and data layers of every tile, and a PNG image with one pixel per tile.
+//! Exports are driven by [`ExportMap`] events, which are sent by the GUI and the `export_map` console command.
+
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use serde::Serialize;
+
+use crate::carbon::CarbonStock;
+use crate::map_generation::{GeneratedMapSize, MapSeed};
+use crate::simulation::{Age, Elevation, Fertility, FuelLoad, Moisture, TileKind};
+use crate::spatial_index::{Position, Tile};
+
+pub struct MapExportPlugin;
+
+impl Plugin for MapExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExportMap>()
+            .add_systems(Update, export_map.run_if(on_event::<ExportMap>));
+    }
+}
+
+/// The folder that exported maps are written to, relative to the working directory.
+const EXPORT_FOLDER: &str = "exports";
+
+/// Saves the current map to the `exports` folder, as both a RON file and a PNG image.
+#[derive(Event, Debug, Clone, Default)]
+pub struct ExportMap {
+    /// The file name to use, without an extension.
+    ///
+    /// If this is `None`, the name is based on the [`MapSeed`].
+    pub name: Option<String>,
+}
+
+/// The serialized form of an exported map.
+#[derive(Serialize, Debug)]
+struct ExportedMap {
+    /// The seed that the map was generated from.
+    ///
+    /// The simulation may have changed the map a great deal since then.
+    seed: u64,
+    width: i32,
+    height: i32,
+    /// Every tile on the map, ordered row by row from the bottom left.
+    tiles: Vec<ExportedTile>,
+}
+
+/// The serialized form of a single tile.
+///
+/// Fields for components that the active ruleset doesn't use are left at zero.
+#[derive(Serialize, Debug)]
+struct ExportedTile {
+    x: i32,
+    y: i32,
+    kind: TileKind,
+    age: u32,
+    elevation: f32,
+    moisture: f32,
+    fertility: f32,
+    fuel_load: f32,
+    carbon_stock: f32,
+}
+
+#[allow(clippy::type_complexity)]
+fn export_map(
+    mut event_reader: EventReader<ExportMap>,
+    tile_query: Query<
+        (
+            &Position,
+            &TileKind,
+            Option<&Age>,
+            Option<&Elevation>,
+            Option<&Moisture>,
+            Option<&Fertility>,
+            Option<&FuelLoad>,
+            Option<&CarbonStock>,
+        ),
+        With<Tile>,
+    >,
+    map_seed: Res<MapSeed>,
+    map_size: Res<GeneratedMapSize>,
+) {
+    // Exporting the same map several times in one frame would just overwrite the same files
+    let Some(event) = event_reader.read().last() else {
+        return;
+    };
+
+    let mut tiles: Vec<ExportedTile> = tile_query
+        .iter()
+        .map(
+            |(position, kind, age, elevation, moisture, fertility, fuel_load, carbon_stock)| {
+                ExportedTile {
+                    x: position.x,
+                    y: position.y,
+                    kind: *kind,
+                    age: age.map_or(0, |age| age.0),
+                    elevation: elevation.map_or(0.0, |elevation| elevation.0),
+                    moisture: moisture.map_or(0.0, |moisture| moisture.0),
+                    fertility: fertility.map_or(0.0, |fertility| fertility.0),
+                    fuel_load: fuel_load.map_or(0.0, |fuel_load| fuel_load.0),
+                    carbon_stock: carbon_stock.map_or(0.0, |carbon_stock| carbon_stock.0),
+                }
+            },
+        )
+        .collect();
+    tiles.sort_unstable_by_key(|tile| (tile.y, tile.x));
+
+    let exported_map = ExportedMap {
+        seed: map_seed.0,
+        width: map_size.width,
+        height: map_size.height,
+        tiles,
+    };
+
+    let name = event
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("map-{}", map_seed.0));
+    let folder = PathBuf::from(EXPORT_FOLDER);
+    if let Err(error) = fs::create_dir_all(&folder) {
+        error!("Could not create the {EXPORT_FOLDER} folder: {error}");
+        return;
+    }
+
+    let ron_path = folder.join(format!("{name}.ron"));
+    match ron::ser::to_string_pretty(&exported_map, ron::ser::PrettyConfig::default()) {
+        Ok(ron) => match fs::write(&ron_path, ron) {
+            Ok(()) => info!("Exported the map to {}", ron_path.display()),
+            Err(error) => error!("Could not write {}: {error}", ron_path.display()),
+        },
+        Err(error) => error!("Could not serialize the map: {error}"),
+    }
+
+    let png_path = folder.join(format!("{name}.png"));
+    match render_map(&exported_map).try_into_dynamic() {
+        Ok(image) => match image.save(&png_path) {
+            Ok(()) => info!("Exported the map to {}", png_path.display()),
+            Err(error) => error!("Could not write {}: {error}", png_path.display()),
+        },
+        Err(error) => error!("Could not convert the map to an image: {error}"),
+    }
+}
+
+/// Draws the map as an image, with one pixel per tile in the color of its kind.
+///
+/// Tiles missing from the map are left transparent.
+fn render_map(exported_map: &ExportedMap) -> Image {
+    let width = exported_map.width.max(0) as u32;
+    let height = exported_map.height.max(0) as u32;
+    let mut data = vec![0; (width * height * 4) as usize];
+
+    for tile in &exported_map.tiles {
+        if tile.x < 0 || tile.y < 0 || tile.x as u32 >= width || tile.y as u32 >= height {
+            continue;
+        }
+
+        // Image rows count down from the top, but map rows count up from the bottom
+        let row = height - 1 - tile.y as u32;
+        let index = ((row * width + tile.x as u32) * 4) as usize;
+        data[index..index + 4].copy_from_slice(&tile.kind.color().to_srgba().to_u8_array());
+    }
+
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD,
+    )
+}
@@ -3,54 +3,381 @@
 //! The general structure here is helpful to learn from,
 //! but unless you're building a grid-based simulation pretty much all of this can be thrown out.
 
+use std::collections::VecDeque;
+
+use bevy::ecs::bundle::NoBundleEffect;
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::platform::collections::HashSet;
 use bevy::prelude::*;
 use bevy_prng::WyRand;
-use bevy_rand::global::GlobalEntropy;
 use bevy_simple_subsecond_system::hot;
-use rand::RngCore;
+use clap::ValueEnum;
 use rand::seq::IndexedRandom;
-use strum::IntoEnumIterator;
+use rand::{Rng, RngCore, SeedableRng};
+use strum::{EnumCount, IntoEnumIterator};
+use strum_macros::{EnumCount, EnumIter};
 
 use crate::SimState;
-use crate::simulation::TileKind;
-use crate::spatial_index::{Position, Tile};
+use crate::chunks::{chunk_entity, despawn_chunks};
+use crate::control_flow::{SetupPhase, state_after_generation};
+use crate::heightmap::{Heightmap, LoadedHeightmap};
+use crate::landmasses::enforce_landmass_constraint;
+use crate::map_mask::{LoadedMaskImage, MapMask};
+use crate::rulesets::{Ruleset, apply_ruleset};
+use crate::simulation::{Elevation, Fertility, Moisture, PreviousTileKind, TileKind};
+use crate::spatial_index::{GridIndex, GridShape, Position, Tile, TileIndex};
 
-// PERF: these systems would all be faster as exclusive systems to avoid command overhead
 pub struct MapGenerationPlugin;
 
 impl Plugin for MapGenerationPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<MapSize>()
             .init_resource::<MapSize>()
+            .register_type::<GeneratedMapSize>()
+            .init_resource::<GeneratedMapSize>()
+            .register_type::<MapSeed>()
+            .init_resource::<MapSeed>()
             .register_type::<InitialWeights>()
             .init_resource::<InitialWeights>()
             .register_type::<WaterThreshold>()
             .init_resource::<WaterThreshold>()
+            .register_type::<NoisePeriod>()
+            .init_resource::<NoisePeriod>()
+            .register_type::<NoiseSettings>()
+            .init_resource::<NoiseSettings>()
+            .register_type::<DomainWarp>()
+            .init_resource::<DomainWarp>()
+            .register_type::<WaterSmoothing>()
+            .init_resource::<WaterSmoothing>()
+            .register_type::<Shoreline>()
+            .init_resource::<Shoreline>()
+            .register_type::<InitialMoisture>()
+            .init_resource::<InitialMoisture>()
+            .register_type::<ElevationNoise>()
+            .init_resource::<ElevationNoise>()
+            .register_type::<Rivers>()
+            .init_resource::<Rivers>()
+            .register_type::<FertilityNoise>()
+            .init_resource::<FertilityNoise>()
+            .register_type::<BiomeTable>()
+            .init_resource::<BiomeTable>()
+            .register_type::<ForestPatches>()
+            .init_resource::<ForestPatches>()
+            .register_type::<MapPreset>()
+            .init_resource::<MapPreset>()
+            .register_type::<TerrainGenerator>()
+            .init_resource::<TerrainGenerator>()
+            .register_type::<VoronoiSettings>()
+            .init_resource::<VoronoiSettings>()
+            .register_type::<ChunkedGeneration>()
+            .init_resource::<ChunkedGeneration>()
+            .init_resource::<GenerationCursor>()
+            .init_resource::<TerrainBatch>()
+            .add_event::<GenerationProgress>()
+            .add_event::<RegenerateMap>()
+            .add_event::<RerollMap>()
             .add_systems(
                 OnEnter(SimState::Generate),
                 (
                     clean_up_sim_state,
-                    spawn_tiles,
-                    determine_if_tiles_are_water,
-                    randomize_land_tiles,
+                    begin_generation,
+                    record_generated_map_size,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    shape_terrain,
+                    spawn_tile_chunk.in_set(SpawnTiles),
+                    // The terrain is only generated for rulesets that use it
+                    insert_tile_components::<(Elevation, Moisture, Fertility)>
+                        .run_if(ruleset_has_terrain),
+                )
+                    .chain()
+                    .run_if(in_state(SimState::Generate)),
+            )
+            .configure_sets(
+                GenerateTerrain,
+                (
+                    TerrainPhase::Elevation.run_if(shaping_terrain(TerrainPhase::Elevation)),
+                    TerrainPhase::Water.run_if(shaping_terrain(TerrainPhase::Water)),
+                    TerrainPhase::SmoothWater.run_if(shaping_terrain(TerrainPhase::SmoothWater)),
+                    TerrainPhase::WaveFunctionCollapse
+                        .run_if(shaping_terrain(TerrainPhase::WaveFunctionCollapse)),
+                    TerrainPhase::Rivers.run_if(shaping_terrain(TerrainPhase::Rivers)),
+                    TerrainPhase::Landmasses.run_if(shaping_terrain(TerrainPhase::Landmasses)),
+                    TerrainPhase::Biomes.run_if(shaping_terrain(TerrainPhase::Biomes)),
+                    TerrainPhase::Shorelines.run_if(shaping_terrain(TerrainPhase::Shorelines)),
+                    TerrainPhase::Moisture.run_if(shaping_terrain(TerrainPhase::Moisture)),
+                    TerrainPhase::Fertility.run_if(shaping_terrain(TerrainPhase::Fertility)),
                 )
                     .chain(),
             )
+            .add_systems(
+                GenerateTerrain,
+                (
+                    generate_elevation
+                        .run_if(ruleset_has_terrain)
+                        .in_set(TerrainPhase::Elevation),
+                    (
+                        determine_if_tiles_are_water
+                            .run_if(ruleset_has_terrain)
+                            .run_if(noise_generator_in_use),
+                        generate_voronoi_regions.run_if(generator_is(TerrainGenerator::Voronoi)),
+                    )
+                        .chain()
+                        .in_set(TerrainPhase::Water),
+                    smooth_water
+                        .run_if(ruleset_has_terrain)
+                        .run_if(noise_generator_in_use)
+                        .in_set(TerrainPhase::SmoothWater),
+                    trace_rivers
+                        .run_if(ruleset_has_terrain)
+                        .in_set(TerrainPhase::Rivers),
+                    enforce_landmass_constraint
+                        .run_if(ruleset_has_terrain)
+                        .in_set(TerrainPhase::Landmasses),
+                    (
+                        randomize_land_tiles
+                            .run_if(not(biome_table_in_use))
+                            .run_if(not(forest_patches_in_use))
+                            .run_if(noise_generator_in_use),
+                        assign_biomes
+                            .run_if(biome_table_in_use)
+                            .run_if(not(forest_patches_in_use))
+                            .run_if(noise_generator_in_use),
+                        place_forest_patches
+                            .run_if(forest_patches_in_use)
+                            .run_if(noise_generator_in_use),
+                    )
+                        .chain()
+                        .in_set(TerrainPhase::Biomes),
+                    create_shorelines
+                        .run_if(ruleset_has_terrain)
+                        .in_set(TerrainPhase::Shorelines),
+                    seed_moisture
+                        .run_if(ruleset_has_terrain)
+                        .in_set(TerrainPhase::Moisture),
+                    seed_fertility
+                        .run_if(ruleset_has_terrain)
+                        .in_set(TerrainPhase::Fertility),
+                ),
+            )
+            .add_systems(
+                Update,
+                apply_map_preset
+                    .after(apply_ruleset)
+                    .run_if(resource_changed::<MapPreset>.or(resource_changed::<Ruleset>)),
+            )
             .add_systems(
                 Update,
                 (
-                    regenerate_when_settings_change,
-                    finish_generation.run_if(in_state(SimState::Generate)),
+                    regenerate_when_settings_change.after(apply_map_preset),
+                    regenerate_when_generator_settings_change,
+                    regenerate_map.run_if(on_event::<RegenerateMap>),
+                    reroll_map.run_if(on_event::<RerollMap>),
                 ),
             );
     }
 }
 
-#[derive(Resource, Reflect, Debug)]
+/// The schedule containing every step of map generation that runs once all of the tiles have been spawned,
+/// such as generating elevation and placing water.
+///
+/// Each of these steps belongs to a [`TerrainPhase`]. The schedule is run once per frame by [`shape_terrain`],
+/// and only the systems in the current phase run each time, on the current [`TerrainBatch`] of tiles,
+/// so that large maps don't freeze the app.
+#[derive(ScheduleLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GenerateTerrain;
+
+/// The phases of the [`GenerateTerrain`] schedule, which run in order,
+/// each working its way across the map a [`TerrainBatch`] at a time.
+///
+/// Passes that need the results of an earlier pass for the whole map belong to a later phase.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, EnumCount)]
+pub enum TerrainPhase {
+    Elevation,
+    /// Lays out the water, or the regions of the [`TerrainGenerator::Voronoi`] generator.
+    Water,
+    /// Smooths out the water, as controlled by the [`WaterSmoothing`].
+    SmoothWater,
+    /// Lays out the tiles of the [`TerrainGenerator::WaveFunctionCollapse`] generator.
+    WaveFunctionCollapse,
+    Rivers,
+    /// Makes sure the land follows the [`LandmassConstraint`](crate::landmasses::LandmassConstraint).
+    Landmasses,
+    /// Chooses the kind of each land tile.
+    Biomes,
+    /// Lines the water with shores, once every other tile has its final kind.
+    Shorelines,
+    Moisture,
+    Fertility,
+}
+
+impl TerrainPhase {
+    /// A short description of this phase, shown while it runs.
+    pub fn description(&self) -> &'static str {
+        match self {
+            TerrainPhase::Elevation => "Raising the land",
+            TerrainPhase::Water => "Filling the lakes",
+            TerrainPhase::SmoothWater => "Smoothing the coastlines",
+            TerrainPhase::WaveFunctionCollapse => "Collapsing the wave function",
+            TerrainPhase::Rivers => "Tracing rivers",
+            TerrainPhase::Landmasses => "Shaping the landmasses",
+            TerrainPhase::Biomes => "Planting biomes",
+            TerrainPhase::Shorelines => "Lining the shores",
+            TerrainPhase::Moisture => "Watering the soil",
+            TerrainPhase::Fertility => "Fertilizing the soil",
+        }
+    }
+
+    /// The phase that runs after this one, if any.
+    fn next(&self) -> Option<TerrainPhase> {
+        TerrainPhase::iter()
+            .skip_while(|phase| phase != self)
+            .nth(1)
+    }
+}
+
+/// A run condition that checks whether the given [`TerrainPhase`] is the one being run.
+fn shaping_terrain(phase: TerrainPhase) -> impl Fn(Res<GenerationCursor>) -> bool + Clone {
+    move |cursor: Res<GenerationCursor>| cursor.phase == GenerationPhase::ShapingTerrain(phase)
+}
+
+/// Controls how map generation is spread across multiple frames.
+///
+/// Spawning or shaping every tile of a large map at once freezes the app for several seconds,
+/// so instead a limited number of tiles are worked on each frame, with a [`GenerationProgress`] event sent after each batch.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct ChunkedGeneration {
+    /// The maximum number of tiles to spawn in a single frame.
+    pub tiles_per_frame: usize,
+    /// The maximum number of tiles for each pass of a [`TerrainPhase`] to work on in a single frame.
+    ///
+    /// Shaping a tile is much cheaper than spawning it, so these batches can be larger.
+    pub terrain_tiles_per_frame: usize,
+}
+
+impl Default for ChunkedGeneration {
+    fn default() -> Self {
+        Self {
+            tiles_per_frame: 4096,
+            terrain_tiles_per_frame: 65536,
+        }
+    }
+}
+
+/// The phases that map generation moves through, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GenerationPhase {
+    /// Tiles are being spawned in batches, over several frames.
+    #[default]
+    SpawningTiles,
+    /// Every tile has been spawned, and the [`GenerateTerrain`] schedule is running the given phase.
+    ShapingTerrain(TerrainPhase),
+    /// The map is complete, and the simulation is about to start.
+    Done,
+}
+
+/// Sent each frame while the map is being generated, so that progress can be shown to the user.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GenerationProgress {
+    pub phase: GenerationPhase,
+    /// The fraction of map generation that has been completed, in the range of 0.0 to 1.0.
+    pub fraction: f32,
+}
+
+impl GenerationProgress {
+    /// The fraction of the total progress bar given to spawning tiles,
+    /// with the rest split evenly between each [`TerrainPhase`].
+    const SPAWNING_SHARE: f32 = 0.5;
+}
+
+/// Tracks how far through generating the current map we are.
+#[derive(Resource, Default)]
+struct GenerationCursor {
+    phase: GenerationPhase,
+    /// The index of the next tile to spawn or shape, counting up each column from the bottom left of the map.
+    next_tile: i32,
+    /// How many times the current [`TerrainPhase`] has already swept across the map.
+    sweep: u32,
+}
+
+/// The tiles that the passes of the current [`TerrainPhase`] work on this frame.
+///
+/// Each phase works its way across the map a batch at a time, in the same order that the tiles were spawned,
+/// and ends once it reaches the last tile, as controlled by the [`ChunkedGeneration`].
+/// Phases whose passes don't look at their first batch, because none of them are enabled, end straight away.
+///
+/// Passes that need to see the whole map before they can finish, such as smoothing or flood fills,
+/// keep their progress between frames and call [`TerrainBatch::sweep_again`] to go back over the map.
+/// Every pass in the phase sweeps again, so these passes should have a phase to themselves.
+#[derive(Resource, Debug, Default)]
+pub struct TerrainBatch {
+    tiles: Vec<Entity>,
+    /// How many times the current phase has already swept across the map.
+    sweep: u32,
+    /// Whether this batch starts from the first tile of the map.
+    first: bool,
+    /// Whether this batch reaches the last tile of the map.
+    last: bool,
+    /// The number of tiles in a full batch.
+    budget: usize,
+    /// Whether any pass has worked on this batch.
+    claimed: bool,
+    /// Whether a pass has asked to go back over the map.
+    sweep_again: bool,
+}
+
+impl TerrainBatch {
+    /// Returns the tiles to work on this frame, marking the batch as needed by the current phase.
+    pub fn tiles(&mut self) -> &[Entity] {
+        self.claimed = true;
+        &self.tiles
+    }
+
+    /// How many times the current phase has already swept across the map.
+    pub fn sweep(&self) -> u32 {
+        self.sweep
+    }
+
+    /// Whether this is the first batch of the current phase,
+    /// when any progress kept from the previous map should be thrown away.
+    pub fn starts_phase(&self) -> bool {
+        self.sweep == 0 && self.first
+    }
+
+    /// Whether this is the last batch of the current sweep across the map.
+    pub fn ends_sweep(&self) -> bool {
+        self.last
+    }
+
+    /// How many tiles' worth of work to do each frame, for passes that don't work through the batch tile by tile,
+    /// such as searches outwards from a few starting tiles.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Starts another sweep from the first tile of the map on the next frame, rather than moving on to the next phase.
+    ///
+    /// Passes that haven't finished by the end of a sweep call this to keep going.
+    /// It can be called partway through a sweep too, to start the next sweep right away.
+    pub fn sweep_again(&mut self) {
+        self.claimed = true;
+        self.sweep_again = true;
+    }
+}
+
+/// The size of the map to generate, in tiles.
+///
+/// Changes to the size only take effect when the map is regenerated:
+/// see [`GeneratedMapSize`] for the size of the map that currently exists.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
 #[reflect(Resource)]
-struct MapSize {
-    width: i32,
-    height: i32,
+pub struct MapSize {
+    pub width: i32,
+    pub height: i32,
 }
 
 impl Default for MapSize {
@@ -63,6 +390,157 @@ impl Default for MapSize {
     }
 }
 
+/// The size of the map that was most recently generated, which is the one that the tiles actually cover.
+///
+/// This is copied from the [`MapSize`] when generation begins, and doesn't change again until the map is regenerated.
+/// Anything that needs to line up with the spawned tiles, such as the spatial indexes or the GPU textures,
+/// should read this rather than the [`MapSize`], which may have been edited since.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq, Deref)]
+#[reflect(Resource)]
+pub struct GeneratedMapSize(pub MapSize);
+
+impl Default for GeneratedMapSize {
+    fn default() -> Self {
+        // No tiles exist until the first map is generated
+        Self(MapSize {
+            width: 0,
+            height: 0,
+        })
+    }
+}
+
+/// The seed used for all of the noise and random choices made while generating the map.
+///
+/// Generating a map of the same size with the same seed and settings will always produce the same map,
+/// which makes it easy to share and compare maps.
+/// The seed is chosen at random when the app starts, and changes to it take effect the next time the map is regenerated.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct MapSeed(pub u64);
+
+impl Default for MapSeed {
+    fn default() -> Self {
+        Self(rand::random())
+    }
+}
+
+impl MapSeed {
+    /// Returns the random number generator used by the given step of map generation.
+    ///
+    /// Each step gets its own generator, so that changing the settings of one step doesn't reshuffle the others.
+    pub fn rng(&self, step: GenerationStep) -> WyRand {
+        WyRand::seed_from_u64(self.0 ^ (step as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+}
+
+/// Regenerates the map from the current [`MapSeed`].
+///
+/// This produces identical terrain, which is useful for comparing the effect of simulation settings.
+#[derive(Event)]
+pub struct RegenerateMap;
+
+/// Generates a brand new map, from a fresh random [`MapSeed`].
+#[derive(Event)]
+pub struct RerollMap;
+
+fn regenerate_map(map_seed: Res<MapSeed>, mut next_state: ResMut<NextState<SimState>>) {
+    info!("Regenerating map with the same seed ({})", map_seed.0);
+    next_state.set(SimState::Generate);
+}
+
+fn reroll_map(mut map_seed: ResMut<MapSeed>, mut next_state: ResMut<NextState<SimState>>) {
+    *map_seed = MapSeed::default();
+    info!("Generating a new map with seed {}", map_seed.0);
+    next_state.set(SimState::Generate);
+}
+
+/// The steps of map generation that draw random numbers from the [`MapSeed`].
+///
+/// This includes the starting state that each ruleset sets up once the tiles are in place,
+/// so that regenerating from the same seed reproduces the whole map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationStep {
+    Elevation,
+    Water,
+    Rivers,
+    LandTiles,
+    Biomes,
+    Fertility,
+    Regions,
+    WaveFunctionCollapse,
+    ForestPatches,
+    Ants,
+    ReactionDiffusion,
+    Sandpile,
+    Herbivores,
+}
+
+/// The algorithm used to lay out the water and land tiles of a newly generated map.
+///
+/// Changing the generator regenerates the map.
+#[derive(Resource, Reflect, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum TerrainGenerator {
+    /// Water is drawn from fractal noise, and each land tile is chosen independently
+    /// (or from the [`BiomeTable`]).
+    #[default]
+    Noise,
+    /// The map is split into Voronoi regions, each of which is filled with a single kind of tile.
+    ///
+    /// See [`VoronoiSettings`] for more details.
+    Voronoi,
+    /// An experimental generator, which places tiles one at a time so that every pair of neighbors is allowed
+    /// by the [`AdjacencyRules`](crate::wave_function_collapse::AdjacencyRules).
+    ///
+    /// These rules only cover the forest tile kinds, so rulesets without terrain fall back to the noise generator.
+    WaveFunctionCollapse,
+}
+
+/// A run condition that checks whether maps are laid out by the given [`TerrainGenerator`].
+pub fn generator_is(generator: TerrainGenerator) -> impl Fn(Res<TerrainGenerator>) -> bool {
+    move |current: Res<TerrainGenerator>| *current == generator
+}
+
+/// A run condition that checks whether maps are laid out by the [`TerrainGenerator::Noise`] generator,
+/// either by choice or as a fallback.
+fn noise_generator_in_use(generator: Res<TerrainGenerator>, ruleset: Res<Ruleset>) -> bool {
+    match *generator {
+        TerrainGenerator::Noise => true,
+        TerrainGenerator::Voronoi => false,
+        TerrainGenerator::WaveFunctionCollapse => !ruleset.has_terrain(),
+    }
+}
+
+/// Controls the [`TerrainGenerator::Voronoi`] generator.
+///
+/// Seed points are scattered uniformly across the map, and each tile joins the region of its nearest seed point.
+/// Every region is then filled with a single kind of tile: water (for rulesets with terrain),
+/// with a probability equal to the [`WaterThreshold`], or else a kind drawn from the [`InitialWeights`].
+///
+/// The straight edges of Voronoi cells look very artificial,
+/// so the point used to find each tile's region is pushed around by noise, roughening the borders.
+/// The resulting large, contiguous habitat patches are great for watching fire spread from patch to patch.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct VoronoiSettings {
+    /// The number of regions to split the map into.
+    pub region_count: usize,
+    /// The furthest distance that a region's border can be pushed away from its straight edge, in tiles.
+    pub border_roughness: f32,
+    /// How quickly the borders wiggle back and forth, in cycles per tile.
+    pub roughness_frequency: f32,
+}
+
+impl Default for VoronoiSettings {
+    fn default() -> Self {
+        Self {
+            region_count: 40,
+            border_roughness: 3.0,
+            roughness_frequency: 0.1,
+        }
+    }
+}
+
 /// The initial weighting of each tile kind in the initial map generation.
 ///
 /// These weights are non-normalized and used to determine the initial distribution of tile kinds in the map.
@@ -73,8 +551,8 @@ impl Default for MapSize {
 /// or zero/omitted to indicate that the tile kind should not appear in the initial map.
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
-struct InitialWeights {
-    weights: Vec<(TileKind, f32)>,
+pub struct InitialWeights {
+    pub weights: Vec<(TileKind, f32)>,
 }
 
 impl InitialWeights {
@@ -95,6 +573,27 @@ impl InitialWeights {
             // Water tiles are generated using a different mechanism
             Water => 0.0,
             Fire => 0.0,
+            Burned => 0.0,
+            DeadForest => 0.0,
+            // A small foothold, from which the invasion can spread
+            InvasiveGrass => 0.02,
+            // Firebreaks are placed by the user
+            Firebreak => 0.0,
+            // Shores are placed along the water's edge: see `create_shorelines`
+            Shore => 0.0,
+            // These are only used by other rulesets
+            Alive => 0.0,
+            Dead => 0.0,
+            Empty => 0.0,
+            Conductor => 0.0,
+            ElectronHead => 0.0,
+            ElectronTail => 0.0,
+            White => 0.0,
+            Black => 0.0,
+            Tree => 0.0,
+            Rock => 0.0,
+            Paper => 0.0,
+            Scissors => 0.0,
         }
     }
 }
@@ -111,6 +610,97 @@ impl Default for InitialWeights {
     }
 }
 
+/// A named bundle of map generation settings, giving a good starting point for a particular kind of landscape.
+///
+/// Selecting a preset overwrites the [`WaterThreshold`], the period of the [`NoiseSettings`],
+/// and (for the forest ruleset) the [`InitialWeights`]: each of these can be tweaked further afterwards.
+#[derive(Resource, Reflect, ValueEnum, EnumIter, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum MapPreset {
+    /// Scattered small islands, covered in windswept scrub.
+    Archipelago,
+    /// A single large landmass with a few inland seas, and plenty of forest.
+    Continent,
+    /// Land dotted with medium-sized lakes.
+    ///
+    /// These are the default settings.
+    #[default]
+    LakeDistrict,
+    /// Wide open grassland, with barely any water.
+    Plains,
+}
+
+impl MapPreset {
+    /// The [`WaterThreshold`] used by this preset.
+    fn water_threshold(&self) -> f32 {
+        match self {
+            MapPreset::Archipelago => 0.6,
+            MapPreset::Continent => 0.2,
+            MapPreset::LakeDistrict => 0.4,
+            MapPreset::Plains => 0.08,
+        }
+    }
+
+    /// The period of the water noise used by this preset, in tiles.
+    ///
+    /// Longer periods produce fewer, larger bodies of water.
+    fn noise_period(&self) -> f32 {
+        match self {
+            MapPreset::Archipelago => 10.0,
+            MapPreset::Continent => 40.0,
+            MapPreset::LakeDistrict => 16.0,
+            MapPreset::Plains => 24.0,
+        }
+    }
+
+    /// The [`InitialWeights`] used by this preset, for the forest ruleset.
+    fn initial_weights(&self) -> InitialWeights {
+        let overrides: &[(TileKind, f32)] = match self {
+            MapPreset::Archipelago => &[(TileKind::Shrubland, 2.0)],
+            MapPreset::Continent => &[
+                (TileKind::ShadeIntolerantForest, 0.5),
+                (TileKind::ShadeTolerantForest, 0.25),
+            ],
+            MapPreset::LakeDistrict => &[],
+            MapPreset::Plains => &[(TileKind::Meadow, 3.0), (TileKind::Shrubland, 0.5)],
+        };
+
+        let mut initial_weights = InitialWeights::default();
+        for (tile_kind, weight) in initial_weights.weights.iter_mut() {
+            if let Some((_, new_weight)) = overrides.iter().find(|(kind, _)| kind == tile_kind) {
+                *weight = *new_weight;
+            }
+        }
+
+        initial_weights
+    }
+}
+
+/// Applies the settings of the newly selected [`MapPreset`], which regenerates the map.
+///
+/// Switching rulesets resets the [`InitialWeights`], so the preset's weights are reapplied whenever the ruleset changes.
+pub fn apply_map_preset(
+    map_preset: Res<MapPreset>,
+    ruleset: Res<Ruleset>,
+    mut water_threshold: ResMut<WaterThreshold>,
+    mut noise_period: ResMut<NoisePeriod>,
+    mut initial_weights: ResMut<InitialWeights>,
+    mut regenerate_writer: EventWriter<RegenerateMap>,
+) {
+    if map_preset.is_changed() {
+        info!("Applying the {:?} map preset.", *map_preset);
+
+        water_threshold.0 = map_preset.water_threshold();
+        noise_period.0 = map_preset.noise_period();
+        // The water threshold and noise period only take effect when the map is regenerated
+        regenerate_writer.write(RegenerateMap);
+    }
+
+    if *ruleset == Ruleset::Forest {
+        *initial_weights = map_preset.initial_weights();
+    }
+}
+
 /// The threshold below which a tile is considered water, in the range of 0.0 to 1.0.
 ///
 ///
@@ -123,7 +713,7 @@ impl Default for InitialWeights {
 /// and a threshold of 1.0 means that all tiles will be water.
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
-struct WaterThreshold(f32);
+pub struct WaterThreshold(pub f32);
 
 impl Default for WaterThreshold {
     fn default() -> Self {
@@ -131,109 +721,1432 @@ impl Default for WaterThreshold {
     }
 }
 
-impl TileKind {}
+/// The typical size of the largest features of the water noise, in tiles.
+///
+/// This is kept apart from the rest of the [`NoiseSettings`] because, like the [`MapSize`],
+/// changes to it wait for the Regenerate button rather than regenerating the map straight away.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct NoisePeriod(pub f32);
 
-#[hot]
-fn clean_up_sim_state(mut commands: Commands, query: Query<Entity, With<Tile>>) {
-    for entity in query.iter() {
-        commands.entity(entity).despawn();
+impl Default for NoisePeriod {
+    fn default() -> Self {
+        Self(16.0)
     }
 }
 
-#[hot]
-fn spawn_tiles(mut commands: Commands, map_size: Res<MapSize>) {
-    // PERF: we could speed this up by using spawn_batch
-    for x in 0..map_size.width {
-        for y in 0..map_size.height {
-            let position = Position { x, y };
-            let transform = position.to_transform();
-            let sprite = Sprite {
-                custom_size: Some(Vec2::splat(Position::PIXELS_PER_TILE)),
-                ..Default::default()
-            };
-            let name = Name::new(format!("Tile ({x}, {y})"));
+/// Controls the fractal noise used to decide which tiles are water.
+///
+/// Several octaves of perlin noise are layered on top of each other (fractal Brownian motion):
+/// the first octave sets the broad shape of the lakes, while each further octave adds finer detail to their coastlines.
+/// A single octave produces smooth, blobby lakes.
+/// The size of the broadest octave is set by the [`NoisePeriod`].
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct NoiseSettings {
+    /// The number of layers of noise to combine.
+    pub octaves: u32,
+    /// How much smaller the features of each octave are than the one before it.
+    ///
+    /// Each octave's period is divided by this value.
+    pub lacunarity: f32,
+    /// How much weaker each octave is than the one before it.
+    ///
+    /// Each octave's amplitude is multiplied by this value.
+    pub persistence: f32,
+}
 
-            commands.spawn((Tile, position, sprite, transform, TileKind::Meadow, name));
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            octaves: 4,
+            lacunarity: 2.0,
+            persistence: 0.5,
         }
     }
 }
 
-#[hot]
-fn determine_if_tiles_are_water(
-    mut tile_query: Query<(&Position, &mut TileKind)>,
-    mut rng: GlobalEntropy<WyRand>,
-    water_threshold: Res<WaterThreshold>,
-) {
-    use noiz::prelude::*;
+/// Distorts the coordinates used to sample the water noise, using a second layer of noise.
+///
+/// Rather than sampling the water noise at each tile's own position,
+/// we sample it at a nearby point, pushed in a direction chosen by the warp noise.
+/// This drags the coastlines into long, winding shapes, which look far more natural than plain fractal noise.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct DomainWarp {
+    pub enabled: bool,
+    /// The furthest distance that a sample point can be pushed, in tiles.
+    pub strength: f32,
+    /// How quickly the direction of the warp changes across the map, in cycles per tile.
+    ///
+    /// Lower values produce broad, sweeping distortions, while higher values produce ragged coastlines.
+    pub frequency: f32,
+}
 
-    // This is an example of perlin noise!
-    // noiz is an incredibly powerful library for generating noise,
-    // read its docs for more options!
-    let mut noise = Noise::<(
-        MixCellGradients<OrthoGrid, Smoothstep, QuickGradients>,
-        SNormToUNorm,
-    )>::default();
-    noise.set_period(5.0);
-    noise.set_seed(rng.next_u32());
+impl Default for DomainWarp {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strength: 4.0,
+            frequency: 0.05,
+        }
+    }
+}
 
-    for (&position, mut tile_kind) in tile_query.iter_mut() {
-        let converted_position = Vec2::new(position.x as f32, position.y as f32);
+/// Controls the smoothing pass that cleans up the water tiles, once they have been thresholded from noise.
+///
+/// Each iteration applies a majority rule, like a cellular automaton:
+/// tiles surrounded mostly by water become water, and tiles surrounded mostly by land become land.
+/// This removes isolated single-tile lakes and thin peninsulas, which otherwise make fire spread hard to follow.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct WaterSmoothing {
+    /// The number of smoothing passes to run: zero disables smoothing entirely.
+    pub iterations: u32,
+}
 
-        let noise_value: f32 = noise.sample(converted_position);
+impl Default for WaterSmoothing {
+    fn default() -> Self {
+        Self { iterations: 2 }
+    }
+}
 
-        // If the noise value is below a certain threshold, set the tile to water
-        if noise_value < water_threshold.0 {
-            *tile_kind = TileKind::Water;
+/// Starts the forest ruleset with circular patches of mature forest, scattered across a meadow.
+///
+/// The centers of the patches are placed using Poisson-disk sampling,
+/// which spreads them out evenly without falling into a regular grid, much like real stands of old-growth forest.
+/// When enabled, this takes priority over both the [`BiomeTable`] and the [`InitialWeights`].
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct ForestPatches {
+    pub enabled: bool,
+    /// The radius of each patch, in tiles.
+    pub patch_radius: f32,
+    /// How tightly the patches are packed together, in the range of 0.0 to 1.0.
+    ///
+    /// At a density of 1.0 neighboring patches can just touch,
+    /// while lower densities push the patches further apart.
+    pub density: f32,
+}
+
+impl Default for ForestPatches {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            patch_radius: 4.0,
+            density: 0.5,
         }
     }
 }
 
-// Water tiles are generated using a different mechanism, and should not be altered
-#[hot]
-fn randomize_land_tiles(
-    mut tile_query: Query<&mut TileKind>,
-    mut rng: GlobalEntropy<WyRand>,
-    initial_weights: Res<InitialWeights>,
-) {
-    // PERF: generating multiple random choices at once is significantly faster than generating them one by one.
-    for mut tile_kind in tile_query.iter_mut() {
-        if *tile_kind != TileKind::Water {
-            *tile_kind = initial_weights
-                .weights
-                .choose_weighted(&mut rng, |item| item.1)
-                .unwrap()
-                .0;
+impl ForestPatches {
+    /// The minimum distance between the centers of two patches, in tiles.
+    fn spacing(&self) -> f32 {
+        2.0 * self.patch_radius.max(0.5) / self.density.clamp(0.01, 1.0).sqrt()
+    }
+}
+
+/// Scatters points across a rectangle using Bridson's algorithm for Poisson-disk sampling.
+///
+/// No two points are closer together than `min_distance`,
+/// and the rectangle is filled until there is no room left for more points.
+fn poisson_disk_points(size: Vec2, min_distance: f32, rng: &mut impl Rng) -> Vec<Vec2> {
+    /// The number of candidates tried around each point before giving up on it.
+    const CANDIDATES_PER_POINT: usize = 30;
+
+    // Each cell of the background grid is small enough to hold at most one point
+    let cell_size = min_distance / std::f32::consts::SQRT_2;
+    let columns = (size.x / cell_size).ceil().max(1.0) as usize;
+    let rows = (size.y / cell_size).ceil().max(1.0) as usize;
+    let cell_of = |point: Vec2| {
+        let column = ((point.x / cell_size) as usize).min(columns - 1);
+        let row = ((point.y / cell_size) as usize).min(rows - 1);
+        (column, row)
+    };
+
+    let mut grid: Vec<Option<usize>> = vec![None; columns * rows];
+    let mut points = Vec::new();
+    let mut active = Vec::new();
+
+    let first_point = Vec2::new(rng.random_range(0.0..size.x), rng.random_range(0.0..size.y));
+    let (column, row) = cell_of(first_point);
+    grid[row * columns + column] = Some(0);
+    points.push(first_point);
+    active.push(0);
+
+    while !active.is_empty() {
+        let active_index = rng.random_range(0..active.len());
+        let origin = points[active[active_index]];
+
+        let candidate = (0..CANDIDATES_PER_POINT).find_map(|_| {
+            // Try a random point in the ring between one and two times the minimum distance away
+            let angle = rng.random_range(0.0..std::f32::consts::TAU);
+            let distance = rng.random_range(min_distance..2.0 * min_distance);
+            let candidate = origin + Vec2::from_angle(angle) * distance;
+
+            if candidate.x < 0.0
+                || candidate.y < 0.0
+                || candidate.x >= size.x
+                || candidate.y >= size.y
+            {
+                return None;
+            }
+
+            let (column, row) = cell_of(candidate);
+            let too_close = (row.saturating_sub(2)..(row + 3).min(rows)).any(|nearby_row| {
+                (column.saturating_sub(2)..(column + 3).min(columns)).any(|nearby_column| {
+                    grid[nearby_row * columns + nearby_column].is_some_and(|index| {
+                        points[index].distance_squared(candidate) < min_distance * min_distance
+                    })
+                })
+            });
+
+            (!too_close).then_some(candidate)
+        });
+
+        match candidate {
+            Some(candidate) => {
+                let (column, row) = cell_of(candidate);
+                grid[row * columns + column] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+            }
+            // There is no room left around this point
+            None => {
+                active.swap_remove(active_index);
+            }
         }
     }
+
+    points
 }
 
-fn finish_generation(mut next_state: ResMut<NextState<SimState>>) {
-    info!("Map generation complete, transitioning to Run state");
-    next_state.set(SimState::Run);
+/// Controls the post-processing step that lines the edges of lakes and rivers with [`TileKind::Shore`].
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Shoreline {
+    /// Whether land tiles next to water are converted into shore.
+    pub enabled: bool,
 }
 
-#[hot]
-fn regenerate_when_settings_change(
-    map_size: Res<MapSize>,
-    initial_weights: Res<InitialWeights>,
-    water_threshold: Res<WaterThreshold>,
-    mut next_state: ResMut<NextState<SimState>>,
-) {
-    if map_size.is_changed() {
-        info!("Map size changed to {:?}, regenerating map", *map_size);
-        next_state.set(SimState::Generate);
+impl Default for Shoreline {
+    fn default() -> Self {
+        Self { enabled: true }
     }
+}
 
-    if initial_weights.is_changed() {
-        info!("Initial weights changed, regenerating map");
-        next_state.set(SimState::Generate);
+/// The pair of noise functions used to offset each sample point, as controlled by the [`DomainWarp`].
+///
+/// This is also used to roughen the borders of Voronoi regions.
+struct WarpNoise {
+    x: PerlinNoise,
+    y: PerlinNoise,
+    strength: f32,
+}
+
+impl WarpNoise {
+    /// Creates warp noise that pushes points up to `strength` tiles away, changing direction `frequency` times per tile.
+    fn new(strength: f32, frequency: f32, rng: &mut impl RngCore) -> Self {
+        let period = 1.0 / frequency.max(f32::EPSILON);
+
+        let mut x = PerlinNoise::default();
+        x.set_period(period);
+        x.set_seed(rng.next_u32());
+
+        let mut y = PerlinNoise::default();
+        y.set_period(period);
+        y.set_seed(rng.next_u32());
+
+        Self { x, y, strength }
     }
 
-    if water_threshold.is_changed() {
-        info!(
-            "Water threshold changed to {:?}, regenerating map",
-            water_threshold.0
-        );
-        next_state.set(SimState::Generate);
+    /// Returns the point that should be sampled in place of the given point.
+    fn warp(&self, point: Vec2) -> Vec2 {
+        use noiz::prelude::*;
+
+        // Rescale each offset from 0.0..1.0 to -1.0..1.0, so that points can be pushed in any direction
+        let x_offset: f32 = self.x.sample(point);
+        let y_offset: f32 = self.y.sample(point);
+        let offset = Vec2::new(x_offset, y_offset) * 2.0 - 1.0;
+
+        point + offset * self.strength
+    }
+}
+
+/// Perlin noise, rescaled to the range of 0.0 to 1.0.
+type PerlinNoise = noiz::prelude::Noise<(
+    noiz::prelude::MixCellGradients<
+        noiz::prelude::OrthoGrid,
+        noiz::prelude::Smoothstep,
+        noiz::prelude::QuickGradients,
+    >,
+    noiz::prelude::SNormToUNorm,
+)>;
+
+/// Several octaves of [`PerlinNoise`], combined according to the [`NoisePeriod`] and [`NoiseSettings`].
+struct FractalNoise {
+    /// Each octave, paired with its amplitude.
+    octaves: Vec<(PerlinNoise, f32)>,
+}
+
+impl FractalNoise {
+    /// Creates the octaves of noise described by the settings, giving each its own seed.
+    fn new(period: &NoisePeriod, settings: &NoiseSettings, rng: &mut impl RngCore) -> Self {
+        let mut octaves = Vec::new();
+        let mut period = period.0;
+        let mut amplitude = 1.0;
+
+        for _ in 0..settings.octaves.max(1) {
+            let mut noise = PerlinNoise::default();
+            noise.set_period(period.max(f32::EPSILON));
+            noise.set_seed(rng.next_u32());
+            octaves.push((noise, amplitude));
+
+            period /= settings.lacunarity.max(f32::EPSILON);
+            amplitude *= settings.persistence;
+        }
+
+        Self { octaves }
+    }
+
+    /// Samples the noise at the given point, returning a value in the range of 0.0 to 1.0.
+    fn sample(&self, point: Vec2) -> f32 {
+        use noiz::prelude::*;
+
+        let mut total = 0.0;
+        let mut total_amplitude = 0.0;
+        for (noise, amplitude) in &self.octaves {
+            let value: f32 = noise.sample(point);
+            total += value * amplitude;
+            total_amplitude += amplitude;
+        }
+
+        if total_amplitude > 0.0 {
+            total / total_amplitude
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Controls how moisture is distributed across the map during initial map generation.
+///
+/// Land tiles are wettest along the shoreline, and dry out linearly with distance from the nearest water tile,
+/// reaching the inland moisture value at the falloff distance.
+/// Even the tiles right next to the water are a step drier than the shoreline value.
+/// Water tiles are always fully saturated.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct InitialMoisture {
+    /// The moisture at the water's edge, where land tiles start to dry out from, in the range of 0.0 to 1.0.
+    shoreline: f32,
+    /// The moisture of land tiles at or beyond the falloff distance from water, in the range of 0.0 to 1.0.
+    inland: f32,
+    /// The distance from water, in tiles, at which moisture reaches its inland value.
+    falloff_distance: i32,
+}
+
+impl InitialMoisture {
+    /// Returns the moisture of a land tile the given number of tiles away from the nearest water.
+    fn at_distance(&self, distance: i32) -> f32 {
+        let falloff = if self.falloff_distance > 0 {
+            (distance as f32 / self.falloff_distance as f32).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        self.shoreline.lerp(self.inland, falloff)
+    }
+}
+
+impl Default for InitialMoisture {
+    fn default() -> Self {
+        Self {
+            shoreline: 0.8,
+            inland: 0.1,
+            falloff_distance: 5,
+        }
+    }
+}
+
+/// Controls the noise layer used to generate the [`Elevation`] of each tile.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct ElevationNoise {
+    /// The typical distance between hills, in tiles.
+    ///
+    /// Larger values produce broad, gentle slopes, while smaller values produce rugged terrain.
+    period: f32,
+}
+
+impl Default for ElevationNoise {
+    fn default() -> Self {
+        Self { period: 12.0 }
+    }
+}
+
+/// Controls the rivers that are traced downhill from high ground during map generation.
+///
+/// Rivers act as long, narrow firebreaks, which blob-shaped lakes can't provide.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Rivers {
+    /// The number of rivers to generate.
+    count: u32,
+    /// The minimum [`Elevation`] of the tile that a river starts from.
+    min_source_elevation: f32,
+    /// The maximum length of each river, in tiles.
+    max_length: u32,
+}
+
+impl Default for Rivers {
+    fn default() -> Self {
+        Self {
+            count: 3,
+            min_source_elevation: 0.7,
+            max_length: 200,
+        }
+    }
+}
+
+/// Controls the noise layer used to seed the initial [`Fertility`] of each tile.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct FertilityNoise {
+    /// The typical size of patches of rich or poor soil, in tiles.
+    period: f32,
+    /// The fertility of the poorest soil on the map, in the range of 0.0 to 1.0.
+    min: f32,
+    /// The fertility of the richest soil on the map, in the range of 0.0 to 1.0.
+    max: f32,
+}
+
+impl Default for FertilityNoise {
+    fn default() -> Self {
+        Self {
+            period: 8.0,
+            min: 0.3,
+            max: 0.7,
+        }
+    }
+}
+
+impl TileKind {}
+
+/// Assigns the initial kind of each land tile based on its elevation and moisture,
+/// so that vegetation forms coherent patches rather than a uniform speckle.
+///
+/// Only rulesets with terrain use the biome table: the others, or any ruleset while it is disabled,
+/// randomly draw each tile from the [`InitialWeights`] instead.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct BiomeTable {
+    /// Whether land tiles are assigned from this table, rather than from the [`InitialWeights`].
+    pub enabled: bool,
+    /// The typical size of wet and dry patches in the moisture noise layer, in tiles.
+    ///
+    /// This layer is only used to choose biomes: the [`Moisture`] of each tile is seeded separately.
+    pub moisture_period: f32,
+    /// The tile kind of each biome.
+    ///
+    /// Each row is a band of [`Elevation`], from lowest to highest,
+    /// and each column within a row is a band of moisture, from driest to wettest.
+    /// The bands in each direction are of equal width.
+    pub biomes: Vec<Vec<TileKind>>,
+}
+
+impl BiomeTable {
+    /// Returns the tile kind of the biome with the given elevation and moisture,
+    /// both in the range of 0.0 to 1.0.
+    ///
+    /// Returns `None` if the table has no entry for that combination.
+    fn lookup(&self, elevation: f32, moisture: f32) -> Option<TileKind> {
+        let band = |value: f32, bands: usize| {
+            ((value.clamp(0.0, 1.0) * bands as f32) as usize).min(bands.saturating_sub(1))
+        };
+
+        let row = self.biomes.get(band(elevation, self.biomes.len()))?;
+        row.get(band(moisture, row.len())).copied()
+    }
+}
+
+impl Default for BiomeTable {
+    fn default() -> Self {
+        use TileKind::*;
+
+        Self {
+            enabled: true,
+            moisture_period: 10.0,
+            biomes: vec![
+                // Forests cluster in the wet lowlands...
+                vec![Shrubland, ShadeIntolerantForest, ShadeTolerantForest],
+                vec![Meadow, Shrubland, ShadeIntolerantForest],
+                // ...while the dry ridges are left to meadows
+                vec![Meadow, Meadow, Shrubland],
+            ],
+        }
+    }
+}
+
+/// Despawns every tile of the previous map.
+///
+/// Like [`spawn_tile_chunk`], this is an exclusive system to avoid the overhead of [`Commands`].
+fn clean_up_sim_state(world: &mut World) {
+    // Despawning the chunks takes almost every tile with them
+    despawn_chunks(world);
+
+    let tiles: Vec<Entity> = world
+        .query_filtered::<Entity, With<Tile>>()
+        .iter(world)
+        .collect();
+
+    for entity in tiles {
+        world.despawn(entity);
+    }
+}
+
+/// Rulesets without terrain are played out on a blank grid, without water or rivers.
+pub fn ruleset_has_terrain(ruleset: Res<Ruleset>) -> bool {
+    ruleset.has_terrain()
+}
+
+/// Starts generating a new map from scratch.
+fn begin_generation(mut cursor: ResMut<GenerationCursor>) {
+    *cursor = GenerationCursor::default();
+}
+
+/// Fixes the size of the map about to be generated, so that later edits to the [`MapSize`] wait for the next map.
+pub fn record_generated_map_size(
+    map_size: Res<MapSize>,
+    mut generated_map_size: ResMut<GeneratedMapSize>,
+) {
+    generated_map_size.0 = *map_size;
+}
+
+/// The system set that spawns the tiles of a new map, a batch at a time.
+///
+/// Tiles are spawned with only the components that every ruleset needs:
+/// rulesets with their own per-tile data add it with [`insert_tile_components`], after this set.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpawnTiles;
+
+/// Gives each newly spawned tile the default value of every component in `B`.
+///
+/// Each ruleset's plugin adds this for its own per-tile components, only while that ruleset is active,
+/// so that tiles don't carry data for rulesets that aren't running.
+pub fn insert_tile_components<B: Bundle<Effect: NoBundleEffect> + Default>(
+    tile_query: Query<Entity, Added<Tile>>,
+    mut commands: Commands,
+) {
+    let tiles: Vec<(Entity, B)> = tile_query
+        .iter()
+        .map(|entity| (entity, B::default()))
+        .collect();
+    commands.insert_batch(tiles);
+}
+
+/// Spawns the next batch of tiles, as controlled by [`ChunkedGeneration`].
+///
+/// This is an exclusive system so that each batch can be spawned all at once with [`World::spawn_batch`],
+/// which is far faster than spawning tiles one at a time with [`Commands`].
+fn spawn_tile_chunk(world: &mut World) {
+    let cursor = world.resource::<GenerationCursor>();
+    if cursor.phase != GenerationPhase::SpawningTiles {
+        return;
+    }
+
+    let map_size = world.resource::<GeneratedMapSize>();
+    let (width, height) = (map_size.width.max(0), map_size.height.max(0));
+    let total_tiles = width * height;
+    let tiles_per_frame = world
+        .resource::<ChunkedGeneration>()
+        .tiles_per_frame
+        .clamp(1, i32::MAX as usize) as i32;
+    let start = cursor.next_tile;
+    let end = start.saturating_add(tiles_per_frame).min(total_tiles);
+
+    // Precompute every tile in the batch, skipping those outside of the mask
+    let map_mask = world.resource::<MapMask>();
+    let mask_image = world.get_resource::<LoadedMaskImage>();
+    let positions: Vec<Position> = (start..end)
+        .map(|index| Position {
+            x: index / height,
+            y: index % height,
+        })
+        .filter(|position| map_mask.contains(position, map_size, mask_image))
+        .collect();
+
+    let tiles: Vec<_> = positions
+        .into_iter()
+        .map(|position| {
+            let sprite = Sprite {
+                custom_size: Some(Vec2::splat(Position::PIXELS_PER_TILE)),
+                ..Default::default()
+            };
+            let chunk = chunk_entity(world, &position);
+
+            // Tiles aren't given a `Name`: a string for each of millions of tiles adds up,
+            // and their `Position` already identifies them in the inspector
+            (
+                Tile,
+                position,
+                sprite,
+                position.to_transform(),
+                TileKind::Meadow,
+                PreviousTileKind(TileKind::Meadow),
+                ChildOf(chunk),
+            )
+        })
+        .collect();
+
+    world.spawn_batch(tiles);
+
+    let mut cursor = world.resource_mut::<GenerationCursor>();
+    cursor.next_tile = end;
+    if end >= total_tiles {
+        cursor.phase = GenerationPhase::ShapingTerrain(TerrainPhase::Elevation);
+        cursor.next_tile = 0;
+    }
+    let phase = cursor.phase;
+
+    let spawned_fraction = if total_tiles > 0 {
+        end as f32 / total_tiles as f32
+    } else {
+        1.0
+    };
+    world.send_event(GenerationProgress {
+        phase,
+        fraction: spawned_fraction * GenerationProgress::SPAWNING_SHARE,
+    });
+}
+
+/// Generates the elevation of each tile from noise, or reads it from the [`LoadedHeightmap`] if there is one.
+#[hot]
+pub fn generate_elevation(
+    mut tile_query: Query<(&Position, &mut Elevation)>,
+    map_seed: Res<MapSeed>,
+    map_size: Res<GeneratedMapSize>,
+    elevation_noise: Res<ElevationNoise>,
+    loaded_heightmap: Option<Res<LoadedHeightmap>>,
+    mut batch: ResMut<TerrainBatch>,
+) {
+    use noiz::prelude::*;
+
+    if let Some(loaded_heightmap) = loaded_heightmap {
+        for &entity in batch.tiles() {
+            if let Ok((position, mut elevation)) = tile_query.get_mut(entity) {
+                elevation.0 = loaded_heightmap.elevation_at(position, &map_size);
+            }
+        }
+        return;
+    }
+
+    let mut rng = map_seed.rng(GenerationStep::Elevation);
+
+    // This uses the same perlin noise as the water layer,
+    // but with its own seed and period so that the two layers are independent.
+    let mut noise = Noise::<(
+        MixCellGradients<OrthoGrid, Smoothstep, QuickGradients>,
+        SNormToUNorm,
+    )>::default();
+    noise.set_period(elevation_noise.period);
+    noise.set_seed(rng.next_u32());
+
+    for &entity in batch.tiles() {
+        let Ok((&position, mut elevation)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+        let converted_position = Vec2::new(position.x as f32, position.y as f32);
+
+        elevation.0 = noise.sample(converted_position);
+    }
+}
+
+/// Fills the map with lakes, drawn from fractal noise.
+///
+/// If a heightmap is in use, every tile below sea level becomes water instead.
+#[hot]
+#[allow(clippy::too_many_arguments)]
+fn determine_if_tiles_are_water(
+    mut tile_query: Query<(&Position, &Elevation, &mut TileKind)>,
+    map_seed: Res<MapSeed>,
+    water_threshold: Res<WaterThreshold>,
+    noise_period: Res<NoisePeriod>,
+    noise_settings: Res<NoiseSettings>,
+    domain_warp: Res<DomainWarp>,
+    heightmap: Res<Heightmap>,
+    loaded_heightmap: Option<Res<LoadedHeightmap>>,
+    mut batch: ResMut<TerrainBatch>,
+) {
+    if loaded_heightmap.is_some() {
+        for &entity in batch.tiles() {
+            if let Ok((_, elevation, mut tile_kind)) = tile_query.get_mut(entity)
+                && elevation.0 < heightmap.sea_level
+            {
+                *tile_kind = TileKind::Water;
+            }
+        }
+        return;
+    }
+
+    let mut rng = map_seed.rng(GenerationStep::Water);
+
+    // This is an example of fractal perlin noise!
+    // noiz is an incredibly powerful library for generating noise,
+    // read its docs for more options!
+    let noise = FractalNoise::new(&noise_period, &noise_settings, &mut rng);
+    let warp_noise = domain_warp
+        .enabled
+        .then(|| WarpNoise::new(domain_warp.strength, domain_warp.frequency, &mut rng));
+
+    for &entity in batch.tiles() {
+        let Ok((&position, _, mut tile_kind)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+        let mut converted_position = Vec2::new(position.x as f32, position.y as f32);
+        if let Some(warp_noise) = &warp_noise {
+            converted_position = warp_noise.warp(converted_position);
+        }
+
+        let noise_value = noise.sample(converted_position);
+
+        // If the noise value is below a certain threshold, set the tile to water
+        if noise_value < water_threshold.0 {
+            *tile_kind = TileKind::Water;
+        }
+    }
+}
+
+/// Whether each tile is water while [`smooth_water`] works on the map, stored column by column like the [`GridIndex`].
+///
+/// Positions without a tile are `None`.
+#[derive(Default)]
+struct SmoothingProgress {
+    water: Vec<Option<bool>>,
+    smoothed: Vec<Option<bool>>,
+}
+
+/// Smooths out the water tiles using a majority rule, as controlled by [`WaterSmoothing`].
+///
+/// Neighbors that lie off the edge of the map don't get a vote.
+/// The first sweep across the map records which tiles are water, each iteration takes another sweep,
+/// and the smoothed water is written back to the tiles on the last sweep.
+#[hot]
+fn smooth_water(
+    mut tile_query: Query<(&Position, &mut TileKind)>,
+    water_smoothing: Res<WaterSmoothing>,
+    grid_shape: Res<GridShape>,
+    map_size: Res<GeneratedMapSize>,
+    mut batch: ResMut<TerrainBatch>,
+    mut progress: Local<SmoothingProgress>,
+) {
+    if water_smoothing.iterations == 0 {
+        return;
+    }
+
+    let (width, height) = (map_size.width, map_size.height);
+    if batch.starts_phase() {
+        let tile_count = (width.max(0) * height.max(0)) as usize;
+        progress.water = vec![None; tile_count];
+        progress.smoothed = vec![None; tile_count];
+    }
+
+    let sweep = batch.sweep();
+    let progress = &mut *progress;
+    for &entity in batch.tiles() {
+        let Ok((position, mut tile_kind)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+        let Some(index) = GridIndex::flat_index(position, width, height) else {
+            continue;
+        };
+
+        if sweep == 0 {
+            progress.water[index] = Some(*tile_kind == TileKind::Water);
+        } else if sweep <= water_smoothing.iterations {
+            // Every tile is updated at once, based on the water from the previous sweep
+            let (water_neighbors, land_neighbors) = grid_shape
+                .adjacent(position)
+                .iter()
+                .filter_map(|neighbor| GridIndex::flat_index(neighbor, width, height))
+                .filter_map(|neighbor| progress.water[neighbor])
+                .fold((0, 0), |(water_count, land_count), is_water| {
+                    if is_water {
+                        (water_count + 1, land_count)
+                    } else {
+                        (water_count, land_count + 1)
+                    }
+                });
+
+            // Ties leave the tile as it was
+            progress.smoothed[index] = if water_neighbors > land_neighbors {
+                Some(true)
+            } else if land_neighbors > water_neighbors {
+                Some(false)
+            } else {
+                progress.water[index]
+            };
+        } else {
+            let is_water = progress.water[index] == Some(true);
+            if is_water && *tile_kind != TileKind::Water {
+                *tile_kind = TileKind::Water;
+            } else if !is_water && *tile_kind == TileKind::Water {
+                // Land tiles are given their final kind later on in map generation
+                *tile_kind = TileKind::Meadow;
+            }
+        }
+    }
+
+    if batch.ends_sweep() && sweep <= water_smoothing.iterations {
+        if sweep > 0 {
+            std::mem::swap(&mut progress.water, &mut progress.smoothed);
+        }
+        batch.sweep_again();
+    }
+}
+
+/// Traces rivers from random high points, flowing to the lowest neighboring tile until they reach water or the map edge.
+///
+/// The high points are gathered a batch at a time, and the rivers are traced once the whole map has been seen.
+#[hot]
+pub fn trace_rivers(
+    mut tile_query: Query<(&Position, &Elevation, &mut TileKind)>,
+    tile_index: Res<TileIndex>,
+    rivers: Res<Rivers>,
+    map_seed: Res<MapSeed>,
+    mut batch: ResMut<TerrainBatch>,
+    mut sources: Local<Vec<Position>>,
+) {
+    if batch.starts_phase() {
+        sources.clear();
+    }
+
+    for &entity in batch.tiles() {
+        if let Ok((position, elevation, tile_kind)) = tile_query.get(entity)
+            && elevation.0 >= rivers.min_source_elevation
+            && *tile_kind != TileKind::Water
+        {
+            sources.push(*position);
+        }
+    }
+
+    if !batch.ends_sweep() {
+        return;
+    }
+
+    let mut rng = map_seed.rng(GenerationStep::Rivers);
+
+    for &source in sources.choose_multiple(&mut rng, rivers.count as usize) {
+        let mut current = source;
+        let mut visited = HashSet::new();
+
+        for _ in 0..rivers.max_length {
+            visited.insert(current);
+
+            if let Some(entity) = tile_index.get(&current)
+                && let Ok((_, _, mut tile_kind)) = tile_query.get_mut(entity)
+            {
+                *tile_kind = TileKind::Water;
+            }
+
+            let neighbors = current.cardinal_neighbors();
+
+            // Rivers that reach the edge of the map flow off of it
+            if neighbors
+                .iter()
+                .any(|neighbor| tile_index.get(neighbor).is_none())
+            {
+                break;
+            }
+
+            // Water always flows downhill, carving through any pits by taking the lowest way out
+            let lowest_neighbor = neighbors
+                .iter()
+                .filter(|neighbor| !visited.contains(*neighbor))
+                .filter_map(|neighbor| {
+                    let entity = tile_index.get(neighbor)?;
+                    let (_, elevation, tile_kind) = tile_query.get(entity).ok()?;
+                    Some((*neighbor, elevation.0, *tile_kind))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+
+            match lowest_neighbor {
+                // The river has reached a lake, or another river
+                Some((_, _, TileKind::Water)) => break,
+                Some((next, _, _)) => current = next,
+                // The river has boxed itself in
+                None => break,
+            }
+        }
+    }
+}
+
+fn biome_table_in_use(ruleset: Res<Ruleset>, biome_table: Res<BiomeTable>) -> bool {
+    ruleset.has_terrain() && biome_table.enabled
+}
+
+fn forest_patches_in_use(ruleset: Res<Ruleset>, forest_patches: Res<ForestPatches>) -> bool {
+    *ruleset == Ruleset::Forest && forest_patches.enabled
+}
+
+/// Covers the land in meadow, then grows circular patches of mature forest, as controlled by the [`ForestPatches`].
+#[hot]
+fn place_forest_patches(
+    mut tile_query: Query<(&Position, &mut TileKind)>,
+    map_seed: Res<MapSeed>,
+    map_size: Res<GeneratedMapSize>,
+    forest_patches: Res<ForestPatches>,
+    grid_shape: Res<GridShape>,
+    mut batch: ResMut<TerrainBatch>,
+    mut centers: Local<Vec<Vec2>>,
+) {
+    // The patches are laid out once, then planted a batch at a time
+    if batch.starts_phase() {
+        let mut rng = map_seed.rng(GenerationStep::ForestPatches);
+
+        let size = Vec2::new(map_size.width.max(1) as f32, map_size.height.max(1) as f32);
+        // Patches are circles on screen, so distances are measured in world space
+        let to_tiles =
+            |point: Vec2| grid_shape.grid_to_world(point - 0.5) / Position::PIXELS_PER_TILE;
+        *centers = poisson_disk_points(size, forest_patches.spacing(), &mut rng)
+            .into_iter()
+            .map(to_tiles)
+            .collect();
+    }
+    let radius_squared = forest_patches.patch_radius * forest_patches.patch_radius;
+
+    for &entity in batch.tiles() {
+        let Ok((position, mut tile_kind)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+        if *tile_kind == TileKind::Water {
+            continue;
+        }
+
+        let point = grid_shape.grid_to_world(Vec2::new(position.x as f32, position.y as f32))
+            / Position::PIXELS_PER_TILE;
+        let in_patch = centers
+            .iter()
+            .any(|center| center.distance_squared(point) <= radius_squared);
+
+        *tile_kind = if in_patch {
+            TileKind::ShadeTolerantForest
+        } else {
+            TileKind::Meadow
+        };
+    }
+}
+
+/// Splits the map into noisy Voronoi regions, filling each region with a single kind of tile.
+///
+/// See [`VoronoiSettings`] for how the regions are laid out.
+#[hot]
+#[allow(clippy::too_many_arguments)]
+fn generate_voronoi_regions(
+    mut tile_query: Query<(&Position, &mut TileKind)>,
+    map_seed: Res<MapSeed>,
+    map_size: Res<GeneratedMapSize>,
+    voronoi_settings: Res<VoronoiSettings>,
+    initial_weights: Res<InitialWeights>,
+    water_threshold: Res<WaterThreshold>,
+    ruleset: Res<Ruleset>,
+    mut batch: ResMut<TerrainBatch>,
+) {
+    // Laying out the regions is cheap, so they're laid out afresh for each batch
+    let mut rng = map_seed.rng(GenerationStep::Regions);
+
+    let width = map_size.width.max(1) as f32;
+    let height = map_size.height.max(1) as f32;
+    let regions: Vec<(Vec2, TileKind)> = (0..voronoi_settings.region_count.max(1))
+        .map(|_| {
+            let seed_point = Vec2::new(rng.random_range(0.0..width), rng.random_range(0.0..height));
+            let kind = if ruleset.has_terrain() && rng.random::<f32>() < water_threshold.0 {
+                TileKind::Water
+            } else {
+                initial_weights
+                    .weights
+                    .choose_weighted(&mut rng, |item| item.1)
+                    .unwrap()
+                    .0
+            };
+
+            (seed_point, kind)
+        })
+        .collect();
+
+    let warp_noise = WarpNoise::new(
+        voronoi_settings.border_roughness,
+        voronoi_settings.roughness_frequency,
+        &mut rng,
+    );
+
+    // PERF: a spatial partition of the seed points would avoid checking every region for every tile
+    for &entity in batch.tiles() {
+        let Ok((&position, mut tile_kind)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+        let point = warp_noise.warp(Vec2::new(position.x as f32, position.y as f32));
+
+        let nearest_region = regions.iter().min_by(|(a, _), (b, _)| {
+            a.distance_squared(point)
+                .total_cmp(&b.distance_squared(point))
+        });
+
+        if let Some((_, kind)) = nearest_region {
+            *tile_kind = *kind;
+        }
+    }
+}
+
+// Water tiles are generated using a different mechanism, and should not be altered
+#[hot]
+fn randomize_land_tiles(
+    mut tile_query: Query<&mut TileKind>,
+    map_seed: Res<MapSeed>,
+    initial_weights: Res<InitialWeights>,
+    mut batch: ResMut<TerrainBatch>,
+    mut rng: Local<Option<WyRand>>,
+) {
+    // The same generator carries on from one batch to the next, so the map doesn't depend on the batch size
+    if batch.starts_phase() {
+        *rng = Some(map_seed.rng(GenerationStep::LandTiles));
+    }
+    let Some(rng) = rng.as_mut() else {
+        return;
+    };
+
+    // PERF: generating multiple random choices at once is significantly faster than generating them one by one.
+    for &entity in batch.tiles() {
+        if let Ok(mut tile_kind) = tile_query.get_mut(entity)
+            && *tile_kind != TileKind::Water
+        {
+            *tile_kind = initial_weights
+                .weights
+                .choose_weighted(&mut *rng, |item| item.1)
+                .unwrap()
+                .0;
+        }
+    }
+}
+
+/// Looks up the kind of each land tile in the [`BiomeTable`], using its elevation and a fresh moisture noise layer.
+#[hot]
+fn assign_biomes(
+    mut tile_query: Query<(&Position, &Elevation, &mut TileKind)>,
+    map_seed: Res<MapSeed>,
+    biome_table: Res<BiomeTable>,
+    mut batch: ResMut<TerrainBatch>,
+) {
+    use noiz::prelude::*;
+
+    let mut rng = map_seed.rng(GenerationStep::Biomes);
+
+    let mut noise = Noise::<(
+        MixCellGradients<OrthoGrid, Smoothstep, QuickGradients>,
+        SNormToUNorm,
+    )>::default();
+    noise.set_period(biome_table.moisture_period);
+    noise.set_seed(rng.next_u32());
+
+    for &entity in batch.tiles() {
+        let Ok((&position, elevation, mut tile_kind)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+        if *tile_kind == TileKind::Water {
+            continue;
+        }
+
+        let converted_position = Vec2::new(position.x as f32, position.y as f32);
+        let moisture: f32 = noise.sample(converted_position);
+
+        if let Some(biome) = biome_table.lookup(elevation.0, moisture) {
+            *tile_kind = biome;
+        }
+    }
+}
+
+/// Converts every land tile that touches water into [`TileKind::Shore`], as controlled by the [`Shoreline`].
+///
+/// This runs after the land tiles are assigned, so that shores line every coastline, whichever generator is used.
+#[hot]
+fn create_shorelines(
+    mut tile_query: Query<(&Position, &mut TileKind)>,
+    shoreline: Res<Shoreline>,
+    grid_shape: Res<GridShape>,
+    grid_index: Res<GridIndex>,
+    map_size: Res<GeneratedMapSize>,
+    mut batch: ResMut<TerrainBatch>,
+) {
+    if !shoreline.enabled {
+        return;
+    }
+
+    for &entity in batch.tiles() {
+        let Ok((position, tile_kind)) = tile_query.get(entity) else {
+            continue;
+        };
+        if *tile_kind == TileKind::Water {
+            continue;
+        }
+
+        // Shores never become water, so it doesn't matter which of the neighbors have been lined already.
+        // Neighbors beyond the edge of the map don't count, even on maps that wrap around
+        let touches_water = grid_shape
+            .adjacent(position)
+            .iter()
+            .filter(|neighbor| {
+                GridIndex::flat_index(neighbor, map_size.width, map_size.height).is_some()
+            })
+            .filter_map(|neighbor| grid_index.get(neighbor))
+            .any(|neighbor| {
+                tile_query
+                    .get(neighbor)
+                    .is_ok_and(|(_, tile_kind)| *tile_kind == TileKind::Water)
+            });
+
+        if touches_water && let Ok((_, mut tile_kind)) = tile_query.get_mut(entity) {
+            *tile_kind = TileKind::Shore;
+        }
+    }
+}
+
+/// How far [`seed_moisture`] has got with measuring the distance from each tile to the nearest water.
+#[derive(Default)]
+struct MoistureProgress {
+    /// The distance from each tile to the nearest water, stored column by column like the [`GridIndex`].
+    ///
+    /// Tiles that haven't been reached yet are `None`.
+    distances: Vec<Option<i32>>,
+    /// The tiles whose neighbors are still to be measured, nearest to the water first.
+    frontier: VecDeque<Position>,
+    /// Whether every tile within the falloff distance has been measured.
+    measured: bool,
+}
+
+/// Moistens each tile according to how far it is from water, as controlled by the [`InitialMoisture`].
+///
+/// Distances are measured just like [`TileIndex::distance_field`], a [`TerrainBatch::budget`] of tiles per frame:
+/// the first sweep across the map finds the water, the search outwards from it takes as many frames as it needs,
+/// and a final sweep moistens each tile.
+#[hot]
+#[allow(clippy::too_many_arguments)]
+fn seed_moisture(
+    mut tile_query: Query<(&Position, &TileKind, &mut Moisture)>,
+    tile_index: Res<TileIndex>,
+    grid_shape: Res<GridShape>,
+    map_size: Res<GeneratedMapSize>,
+    initial_moisture: Res<InitialMoisture>,
+    mut batch: ResMut<TerrainBatch>,
+    mut progress: Local<MoistureProgress>,
+) {
+    let (width, height) = (map_size.width, map_size.height);
+    if batch.starts_phase() {
+        *progress = MoistureProgress {
+            distances: vec![None; (width.max(0) * height.max(0)) as usize],
+            ..default()
+        };
+    }
+    let progress = &mut *progress;
+
+    if batch.sweep() == 0 {
+        for &entity in batch.tiles() {
+            if let Ok((position, tile_kind, _)) = tile_query.get(entity)
+                && *tile_kind == TileKind::Water
+                && let Some(index) = GridIndex::flat_index(position, width, height)
+            {
+                progress.distances[index] = Some(0);
+                progress.frontier.push_back(*position);
+            }
+        }
+
+        if batch.ends_sweep() {
+            batch.sweep_again();
+        }
+        return;
+    }
+
+    if !progress.measured {
+        for _ in 0..batch.budget() {
+            let Some(position) = progress.frontier.pop_front() else {
+                progress.measured = true;
+                break;
+            };
+
+            let Some(distance) = GridIndex::flat_index(&position, width, height)
+                .and_then(|index| progress.distances[index])
+            else {
+                continue;
+            };
+            if distance >= initial_moisture.falloff_distance {
+                continue;
+            }
+
+            for neighbor in grid_shape.adjacent(&position) {
+                let Some(neighbor) = tile_index.resolve(&neighbor) else {
+                    continue;
+                };
+
+                if let Some(index) = GridIndex::flat_index(&neighbor, width, height)
+                    && progress.distances[index].is_none()
+                {
+                    progress.distances[index] = Some(distance + 1);
+                    progress.frontier.push_back(neighbor);
+                }
+            }
+        }
+
+        // Once the search is done, the final sweep starts over from the first tile
+        batch.sweep_again();
+        return;
+    }
+
+    for &entity in batch.tiles() {
+        let Ok((position, tile_kind, mut moisture)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+        if *tile_kind == TileKind::Water {
+            moisture.0 = 1.0;
+            continue;
+        }
+
+        let distance = GridIndex::flat_index(position, width, height)
+            .and_then(|index| progress.distances[index]);
+        moisture.0 = match distance {
+            Some(distance) => initial_moisture.at_distance(distance),
+            None => initial_moisture.inland,
+        };
+    }
+}
+
+#[hot]
+fn seed_fertility(
+    mut tile_query: Query<(&Position, &mut Fertility)>,
+    map_seed: Res<MapSeed>,
+    fertility_noise: Res<FertilityNoise>,
+    mut batch: ResMut<TerrainBatch>,
+) {
+    use noiz::prelude::*;
+
+    let mut rng = map_seed.rng(GenerationStep::Fertility);
+
+    let mut noise = Noise::<(
+        MixCellGradients<OrthoGrid, Smoothstep, QuickGradients>,
+        SNormToUNorm,
+    )>::default();
+    noise.set_period(fertility_noise.period);
+    noise.set_seed(rng.next_u32());
+
+    for &entity in batch.tiles() {
+        let Ok((&position, mut fertility)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+        let converted_position = Vec2::new(position.x as f32, position.y as f32);
+
+        let noise_value: f32 = noise.sample(converted_position);
+        fertility.0 = fertility_noise.min.lerp(fertility_noise.max, noise_value);
+    }
+}
+
+/// Runs the current [`TerrainPhase`] of the [`GenerateTerrain`] schedule on the next [`TerrainBatch`] of tiles,
+/// once every tile has been spawned.
+///
+/// After the last phase, this starts the simulation (or enters [`SimState::Setup`], if the [`SetupPhase`] is enabled).
+fn shape_terrain(world: &mut World) {
+    let cursor = world.resource::<GenerationCursor>();
+    let GenerationPhase::ShapingTerrain(terrain_phase) = cursor.phase else {
+        return;
+    };
+    let (start, sweep) = (cursor.next_tile, cursor.sweep);
+
+    let map_size = world.resource::<GeneratedMapSize>();
+    let (width, height) = (map_size.width.max(0), map_size.height.max(0));
+    let total_tiles = width * height;
+    let tiles_per_frame = world
+        .resource::<ChunkedGeneration>()
+        .terrain_tiles_per_frame
+        .clamp(1, i32::MAX as usize);
+    let end = start
+        .saturating_add(tiles_per_frame as i32)
+        .min(total_tiles);
+
+    // Positions without a tile, such as those outside of the map mask, are skipped
+    let grid_index = world.resource::<GridIndex>();
+    let tiles = (start..end)
+        .filter_map(|index| grid_index.get(&GridIndex::flat_position(index as usize, height)))
+        .collect();
+    world.insert_resource(TerrainBatch {
+        tiles,
+        sweep,
+        first: start == 0,
+        last: end >= total_tiles,
+        budget: tiles_per_frame,
+        claimed: false,
+        sweep_again: false,
+    });
+
+    world.run_schedule(GenerateTerrain);
+
+    let batch = world.resource::<TerrainBatch>();
+    let (claimed, sweep_again) = (batch.claimed, batch.sweep_again);
+    let mut cursor = world.resource_mut::<GenerationCursor>();
+    let phase_done = !sweep_again && (!claimed || end >= total_tiles);
+    if sweep_again {
+        cursor.next_tile = 0;
+        cursor.sweep += 1;
+    } else if !phase_done {
+        cursor.next_tile = end;
+    } else {
+        cursor.next_tile = 0;
+        cursor.sweep = 0;
+        cursor.phase = match terrain_phase.next() {
+            Some(next_phase) => GenerationPhase::ShapingTerrain(next_phase),
+            None => GenerationPhase::Done,
+        };
+    }
+    let phase = cursor.phase;
+
+    // Later sweeps don't move the progress bar, since there's no telling how many more a phase will need
+    let phase_fraction = if phase_done || sweep > 0 || total_tiles == 0 {
+        1.0
+    } else {
+        end as f32 / total_tiles as f32
+    };
+    let phases_done = terrain_phase as usize as f32 + phase_fraction;
+    world.send_event(GenerationProgress {
+        phase,
+        fraction: GenerationProgress::SPAWNING_SHARE
+            + (1.0 - GenerationProgress::SPAWNING_SHARE) * phases_done / TerrainPhase::COUNT as f32,
+    });
+    if phase != GenerationPhase::Done {
+        return;
+    }
+
+    let next_state = state_after_generation(world.resource::<SetupPhase>());
+    info!("Map generation complete, transitioning to {next_state:?} state");
+    world.resource_mut::<NextState<SimState>>().set(next_state);
+}
+
+/// Regenerates the map whenever one of its generation settings changes, such as when it is edited in the inspector.
+///
+/// The map size, seed, water threshold and noise period are left out: those are edited through the GUI's map settings,
+/// and only take effect when the Regenerate button is pressed (or a [`RegenerateMap`] event is sent),
+/// so that editing them doesn't regenerate a large map on every change.
+#[hot]
+#[allow(clippy::too_many_arguments)]
+fn regenerate_when_settings_change(
+    initial_weights: Res<InitialWeights>,
+    noise_settings: Res<NoiseSettings>,
+    domain_warp: Res<DomainWarp>,
+    initial_moisture: Res<InitialMoisture>,
+    elevation_noise: Res<ElevationNoise>,
+    rivers: Res<Rivers>,
+    fertility_noise: Res<FertilityNoise>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    if initial_weights.is_changed() {
+        info!("Initial weights changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+
+    if noise_settings.is_changed() {
+        info!("Noise settings changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+
+    if domain_warp.is_changed() {
+        info!("Domain warp settings changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+
+    if initial_moisture.is_changed() {
+        info!("Initial moisture changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+
+    if elevation_noise.is_changed() {
+        info!("Elevation noise changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+
+    if rivers.is_changed() {
+        info!("River settings changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+
+    if fertility_noise.is_changed() {
+        info!("Fertility noise changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+}
+
+/// Like [`regenerate_when_settings_change`], but for the settings of the optional generation steps.
+///
+/// These are split into their own system to stay within Bevy's limit on the number of system parameters.
+#[hot]
+fn regenerate_when_generator_settings_change(
+    terrain_generator: Res<TerrainGenerator>,
+    voronoi_settings: Res<VoronoiSettings>,
+    water_smoothing: Res<WaterSmoothing>,
+    shoreline: Res<Shoreline>,
+    biome_table: Res<BiomeTable>,
+    forest_patches: Res<ForestPatches>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    if terrain_generator.is_changed() {
+        info!(
+            "Terrain generator changed to {:?}, regenerating map",
+            *terrain_generator
+        );
+        next_state.set(SimState::Generate);
+    }
+
+    if voronoi_settings.is_changed() {
+        info!("Voronoi settings changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+
+    if water_smoothing.is_changed() {
+        info!(
+            "Water smoothing changed to {} iterations, regenerating map",
+            water_smoothing.iterations
+        );
+        next_state.set(SimState::Generate);
+    }
+
+    if shoreline.is_changed() {
+        info!("Shoreline settings changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+
+    if biome_table.is_changed() {
+        info!("Biome table changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+
+    if forest_patches.is_changed() {
+        info!("Forest patch settings changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moisture_dries_out_with_distance_from_water() {
+        let initial_moisture = InitialMoisture {
+            shoreline: 0.8,
+            inland: 0.1,
+            falloff_distance: 5,
+        };
+
+        let expected = [(0, 0.8), (1, 0.66), (5, 0.1), (8, 0.1)];
+        for (distance, moisture) in expected {
+            assert!(
+                (initial_moisture.at_distance(distance) - moisture).abs() < 1e-6,
+                "moisture at distance {distance} should be {moisture}"
+            );
+        }
     }
 }
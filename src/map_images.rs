@@ -0,0 +1,93 @@
+//! Reads layers of the map from images, shared by the [`MapMask`](crate::map_mask::MapMask)
+//! and the [`Heightmap`](crate::heightmap::Heightmap).
+//!
+//! Images are stretched to cover the whole map, whatever its size,
+//! with the top row of the image along the northern edge of the map.
+
+use bevy::prelude::*;
+
+use crate::map_generation::MapSize;
+use crate::spatial_index::Position;
+
+/// A value read from every pixel of an image, ready to be sampled during map generation.
+#[derive(Debug)]
+pub struct ImageLayer<T> {
+    /// The width of the image, in pixels.
+    pub width: u32,
+    /// The height of the image, in pixels.
+    pub height: u32,
+    /// The value of each pixel, stored row by row from the top of the image.
+    values: Vec<T>,
+}
+
+impl<T: Copy + Default> ImageLayer<T> {
+    /// Reads every pixel of the image, converting its linear color into a value.
+    ///
+    /// Pixels whose color can't be read take the default value.
+    pub fn from_image(image: &Image, read_pixel: impl Fn(LinearRgba) -> T) -> Self {
+        let width = image.width();
+        let height = image.height();
+
+        let mut values = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let value = image
+                    .get_color_at(x, y)
+                    .map(|color| read_pixel(color.to_linear()))
+                    .unwrap_or_default();
+                values.push(value);
+            }
+        }
+
+        Self {
+            width,
+            height,
+            values,
+        }
+    }
+
+    /// Returns the value of the tile at the given position, stretching the image to cover the whole map.
+    ///
+    /// Each tile takes the value of the nearest pixel.
+    /// Returns `None` if either the image or the map is empty.
+    pub fn sample(&self, position: &Position, map_size: &MapSize) -> Option<T> {
+        if self.width == 0 || self.height == 0 || map_size.width <= 0 || map_size.height <= 0 {
+            return None;
+        }
+
+        let scale = |tile: i32, tiles: i32, pixels: u32| {
+            let pixel = (tile as f32 + 0.5) / tiles as f32 * pixels as f32;
+            (pixel.max(0.0) as u32).min(pixels - 1)
+        };
+
+        let x = scale(position.x, map_size.width, self.width);
+        // Map rows count up from the south, but image rows count down from the top
+        let y = self.height - 1 - scale(position.y, map_size.height, self.height);
+
+        self.values.get((y * self.width + x) as usize).copied()
+    }
+}
+
+/// Returns the image behind the handle once it has loaded (or been modified on disk), so that it can be read again.
+///
+/// Images that were already loaded won't send another event,
+/// so the image is also returned whenever the handle has been replaced, as reported by `handle_changed`.
+pub fn newly_loaded_image<'a>(
+    asset_events: &mut EventReader<AssetEvent<Image>>,
+    handle: &Handle<Image>,
+    handle_changed: bool,
+    images: &'a Assets<Image>,
+) -> Option<&'a Image> {
+    let loaded = asset_events.read().any(|event| {
+        matches!(
+            event,
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if *id == handle.id()
+        )
+    });
+    if !loaded && !handle_changed {
+        return None;
+    }
+
+    images.get(handle)
+}
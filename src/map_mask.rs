@@ -0,0 +1,148 @@
+//! Restricts the map to an arbitrary shape, such as a circle or a hand-drawn island outline.
+//!
+//! Tiles outside of the [`MapMask`] are never spawned, so the map is no longer a full rectangle.
+//! Lookups in the [`TileIndex`](crate::spatial_index::TileIndex) simply find nothing there,
+//! so the missing tiles behave like the edge of the map.
+
+use bevy::prelude::*;
+
+use crate::SimState;
+use crate::map_generation::MapSize;
+use crate::map_images::{ImageLayer, newly_loaded_image};
+use crate::spatial_index::Position;
+
+pub struct MapMaskPlugin;
+
+impl Plugin for MapMaskPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapMask>()
+            .register_type::<MapMask>()
+            .add_systems(
+                Update,
+                (
+                    load_mask_image.run_if(resource_changed::<MapMask>),
+                    apply_loaded_mask_image,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The shape of the map: only tiles inside of the mask are spawned.
+///
+/// Changing the mask regenerates the map (once the image has loaded, for image masks).
+#[derive(Resource, Reflect, Debug, Clone, PartialEq, Default)]
+#[reflect(Resource)]
+pub enum MapMask {
+    /// Every tile within the [`MapSize`] is spawned.
+    #[default]
+    Rectangle,
+    /// The largest ellipse that fits inside the [`MapSize`]: this is a circle for square maps.
+    Ellipse,
+    /// An image in the `assets` folder, stretched to cover the map.
+    ///
+    /// Tiles are spawned wherever the image is bright and opaque, so islands can simply be painted in white on black.
+    Image(String),
+}
+
+impl MapMask {
+    /// Checks whether the tile at the given position lies inside the mask.
+    ///
+    /// Image masks that haven't finished loading yet contain every tile.
+    pub fn contains(
+        &self,
+        position: &Position,
+        map_size: &MapSize,
+        mask_image: Option<&LoadedMaskImage>,
+    ) -> bool {
+        match self {
+            MapMask::Rectangle => true,
+            MapMask::Ellipse => {
+                let radii = Vec2::new(map_size.width as f32, map_size.height as f32) / 2.0;
+                let offset = Vec2::new(position.x as f32 + 0.5, position.y as f32 + 0.5) - radii;
+
+                (offset / radii.max(Vec2::splat(f32::EPSILON))).length_squared() <= 1.0
+            }
+            MapMask::Image(_) => {
+                mask_image.is_none_or(|mask_image| mask_image.contains(position, map_size))
+            }
+        }
+    }
+}
+
+/// The image currently being loaded (or already loaded) for a [`MapMask::Image`].
+#[derive(Resource)]
+struct MaskImageHandle(Handle<Image>);
+
+/// Which pixels of the [`MapMask::Image`] lie inside of the mask, ready to be used during map generation.
+///
+/// This resource only exists while an image mask is in use.
+#[derive(Resource, Debug)]
+pub struct LoadedMaskImage(ImageLayer<bool>);
+
+impl LoadedMaskImage {
+    /// Pixels with a brightness and opacity above this value lie inside of the mask.
+    const THRESHOLD: f32 = 0.5;
+
+    fn from_image(image: &Image) -> Self {
+        Self(ImageLayer::from_image(image, |color| {
+            let brightness = (color.red + color.green + color.blue) / 3.0;
+            brightness > Self::THRESHOLD && color.alpha > Self::THRESHOLD
+        }))
+    }
+
+    /// Checks whether the tile at the given position lies inside of the mask, stretching the image to cover the whole map.
+    fn contains(&self, position: &Position, map_size: &MapSize) -> bool {
+        self.0.sample(position, map_size).unwrap_or(false)
+    }
+}
+
+/// Starts loading the mask image if needed, or regenerates the map straight away for the built-in shapes.
+fn load_mask_image(
+    map_mask: Res<MapMask>,
+    asset_server: Res<AssetServer>,
+    mut next_state: ResMut<NextState<SimState>>,
+    mut commands: Commands,
+) {
+    let MapMask::Image(path) = &*map_mask else {
+        commands.remove_resource::<MaskImageHandle>();
+        commands.remove_resource::<LoadedMaskImage>();
+
+        // The resource counts as changed when it is first added, but the first map is already being generated
+        if !map_mask.is_added() {
+            info!("Map mask changed to {:?}, regenerating map", *map_mask);
+            next_state.set(SimState::Generate);
+        }
+        return;
+    };
+
+    commands.insert_resource(MaskImageHandle(asset_server.load(path.clone())));
+}
+
+/// Reads the mask image once it has loaded (or been modified on disk), then regenerates the map with it.
+fn apply_loaded_mask_image(
+    mut asset_events: EventReader<AssetEvent<Image>>,
+    handle: Option<Res<MaskImageHandle>>,
+    images: Res<Assets<Image>>,
+    mut next_state: ResMut<NextState<SimState>>,
+    mut commands: Commands,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    let Some(image) =
+        newly_loaded_image(&mut asset_events, &handle.0, handle.is_changed(), &images)
+    else {
+        return;
+    };
+
+    let mask_image = LoadedMaskImage::from_image(image);
+    info!(
+        "Loaded a {}x{} map mask, regenerating map",
+        mask_image.0.width, mask_image.0.height
+    );
+
+    commands.insert_resource(mask_image);
+    next_state.set(SimState::Generate);
+}
@@ -0,0 +1,251 @@
+//! A small overview of the whole map, shown in the statistics panel.
+//!
+//! A second camera draws a [`DownsampledMap`] of the whole map into the [`MinimapImage`], which the GUI displays as a widget.
+//! A rectangle on top of it outlines the part of the map that the [`MainCamera`] can currently see,
+//! and clicking (or dragging) anywhere on the minimap moves the main camera there.
+//!
+//! The minimap camera only draws its own [`RenderLayers`], so it doesn't depend on which chunks of tiles
+//! are culled to fit the main camera's view.
+
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::render::view::RenderLayers;
+use bevy::ui::RelativeCursorPosition;
+
+use crate::SimState;
+use crate::camera::MainCamera;
+use crate::chunks::Chunk;
+use crate::level_of_detail::DownsampledMap;
+use crate::map_generation::GeneratedMapSize;
+use crate::spatial_index::{GridShape, Position};
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MinimapImage>()
+            .add_systems(OnEnter(SimState::Generate), despawn_minimap_map)
+            .add_systems(OnExit(SimState::Generate), frame_minimap)
+            .add_systems(
+                Update,
+                (jump_to_minimap_click, update_minimap_view_rectangle).chain(),
+            );
+    }
+}
+
+/// The image that the minimap camera draws the whole map into.
+#[derive(Resource)]
+pub struct MinimapImage(pub Handle<Image>);
+
+impl MinimapImage {
+    /// The width and height of the minimap, in pixels.
+    const SIZE: u32 = 256;
+
+    /// The render layer that only the minimap camera draws.
+    const RENDER_LAYER: usize = 1;
+}
+
+impl FromWorld for MinimapImage {
+    fn from_world(world: &mut World) -> Self {
+        let size = Extent3d {
+            width: Self::SIZE,
+            height: Self::SIZE,
+            depth_or_array_layers: 1,
+        };
+
+        let mut image = Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Bgra8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::RENDER_ATTACHMENT;
+
+        Self(world.resource_mut::<Assets<Image>>().add(image))
+    }
+}
+
+/// A marker component for the camera that draws the minimap.
+#[derive(Component)]
+struct MinimapCamera;
+
+/// A marker component for the [`DownsampledMap`] drawn by the minimap camera.
+#[derive(Component)]
+struct MinimapMap;
+
+/// A marker component for the GUI widget that shows the [`MinimapImage`].
+#[derive(Component)]
+pub struct MinimapWidget;
+
+/// A marker component for the rectangle on the minimap that outlines what the [`MainCamera`] can see.
+#[derive(Component)]
+pub struct MinimapViewRectangle;
+
+/// Despawns the minimap's copy of the old map, before a new one is generated.
+fn despawn_minimap_map(minimap_map: Query<Entity, With<MinimapMap>>, mut commands: Commands) {
+    for entity in minimap_map.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Points the minimap camera at the newly generated map, zoomed out far enough to show all of it,
+/// and spawns the [`DownsampledMap`] that it draws.
+///
+/// The camera is only spawned once the first map has been generated,
+/// so that the main camera is always the first camera to exist.
+#[allow(clippy::too_many_arguments)]
+fn frame_minimap(
+    minimap_camera: Option<Single<(&mut Transform, &mut Projection), With<MinimapCamera>>>,
+    minimap_image: Res<MinimapImage>,
+    map_size: Res<GeneratedMapSize>,
+    grid_shape: Res<GridShape>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    // Roughly one pixel of the minimap for each block, as long as each block fits inside of a chunk
+    let tiles_across = map_size.width.max(map_size.height).max(1) as u32;
+    let block_size = tiles_across
+        .div_ceil(MinimapImage::SIZE)
+        .next_power_of_two()
+        .min(Chunk::SIZE as u32) as i32;
+    commands.spawn((
+        Name::new("Minimap map"),
+        MinimapMap,
+        DownsampledMap::bundle(
+            block_size,
+            &map_size,
+            &grid_shape,
+            &mut images,
+            &mut meshes,
+            &mut materials,
+        ),
+        RenderLayers::layer(MinimapImage::RENDER_LAYER),
+    ));
+
+    // Hexagonal grids skew the map into a rhombus, so we need to check every corner
+    let min = Vec2::splat(-0.5);
+    let max = Vec2::new(map_size.width as f32, map_size.height as f32) - 0.5;
+    let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+        .map(|corner| grid_shape.grid_to_world(corner));
+    let bounds = corners.iter().fold(
+        Rect::from_corners(corners[0], corners[0]),
+        |bounds, corner| bounds.union_point(*corner),
+    );
+
+    let extent = bounds.size().max_element().max(Position::PIXELS_PER_TILE);
+    let transform = Transform::from_translation(bounds.center().extend(0.0));
+    let projection = Projection::Orthographic(OrthographicProjection {
+        scaling_mode: ScalingMode::Fixed {
+            width: extent,
+            height: extent,
+        },
+        ..OrthographicProjection::default_2d()
+    });
+
+    match minimap_camera {
+        Some(mut minimap_camera) => {
+            let (camera_transform, camera_projection) = &mut *minimap_camera;
+            **camera_transform = transform;
+            **camera_projection = projection;
+        }
+        None => {
+            commands.spawn((
+                Name::new("Minimap camera"),
+                MinimapCamera,
+                Camera2d,
+                Camera {
+                    target: minimap_image.0.clone().into(),
+                    // Draw the minimap before the main view, which shows it in the GUI
+                    order: -1,
+                    clear_color: ClearColorConfig::Custom(Color::BLACK),
+                    ..default()
+                },
+                projection,
+                transform,
+                RenderLayers::layer(MinimapImage::RENDER_LAYER),
+            ));
+        }
+    }
+}
+
+/// Moves the main camera to wherever the minimap is being pressed.
+fn jump_to_minimap_click(
+    widget: Single<(&Interaction, &RelativeCursorPosition), With<MinimapWidget>>,
+    minimap_camera: Option<Single<(&Camera, &GlobalTransform), With<MinimapCamera>>>,
+    mut main_camera_transform: Single<&mut Transform, With<MainCamera>>,
+) {
+    let (interaction, relative_cursor_position) = *widget;
+    if *interaction != Interaction::Pressed {
+        return;
+    }
+
+    let (Some(minimap_camera), Some(cursor_position)) =
+        (minimap_camera, relative_cursor_position.normalized)
+    else {
+        return;
+    };
+
+    let (camera, camera_transform) = *minimap_camera;
+    let Some(target_size) = camera.logical_target_size() else {
+        return;
+    };
+    let Ok(point) = camera.viewport_to_world_2d(
+        camera_transform,
+        cursor_position.clamp(Vec2::ZERO, Vec2::ONE) * target_size,
+    ) else {
+        return;
+    };
+
+    main_camera_transform.translation.x = point.x;
+    main_camera_transform.translation.y = point.y;
+}
+
+/// Outlines the part of the map that the main camera can see on the minimap.
+fn update_minimap_view_rectangle(
+    main_camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    minimap_camera: Option<Single<(&Camera, &GlobalTransform), With<MinimapCamera>>>,
+    mut rectangle_node: Single<&mut Node, With<MinimapViewRectangle>>,
+) {
+    let Some(minimap_camera) = minimap_camera else {
+        return;
+    };
+
+    let (camera, camera_transform) = *main_camera;
+    let Some(viewport) = camera.logical_viewport_rect() else {
+        return;
+    };
+    let Ok(corner) = camera.viewport_to_world_2d(camera_transform, viewport.min) else {
+        return;
+    };
+    let Ok(opposite_corner) = camera.viewport_to_world_2d(camera_transform, viewport.max) else {
+        return;
+    };
+
+    let (minimap_camera, minimap_camera_transform) = *minimap_camera;
+    let Some(target_size) = minimap_camera.logical_target_size() else {
+        return;
+    };
+    let to_minimap = |point: Vec2| {
+        minimap_camera
+            .world_to_viewport(minimap_camera_transform, point.extend(0.0))
+            .map(|position| position / target_size * 100.0)
+    };
+    let (Ok(corner), Ok(opposite_corner)) = (to_minimap(corner), to_minimap(opposite_corner))
+    else {
+        return;
+    };
+
+    // Parts of the rectangle that fall outside of the minimap are clipped by the widget
+    let view = Rect::from_corners(corner, opposite_corner);
+    rectangle_node.left = Val::Percent(view.min.x);
+    rectangle_node.top = Val::Percent(view.min.y);
+    rectangle_node.width = Val::Percent(view.width());
+    rectangle_node.height = Val::Percent(view.height());
+}
@@ -0,0 +1,171 @@
+//! Insect outbreaks: a second kind of disturbance, alongside fire.
+//!
+//! Bark beetles attack mature forest, spreading from stand to stand
+//! and leaving behind dead timber that is far more flammable than the living forest it replaced.
+//! This interaction between disturbances is a great example of how simple rules can produce complex dynamics.
+
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::control_flow::Simulation;
+use crate::disturbances::{DisturbanceKind, DisturbanceStarted, schedule_disturbances};
+use crate::rulesets::ForestSystems;
+use crate::simulation::TileKind;
+use crate::spatial_index::{Position, TileIndex};
+use crate::tile_rng::{TileRng, TileRngStream, advance_tile_rng};
+
+pub struct OutbreakPlugin;
+
+impl Plugin for OutbreakPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Infested>()
+            .init_resource::<BeetleSpread>()
+            .register_type::<BeetleSpread>()
+            .init_resource::<BeetleMortality>()
+            .register_type::<BeetleMortality>()
+            .add_systems(
+                Simulation,
+                (start_outbreaks, spread_beetles, kill_infested_trees)
+                    .chain()
+                    .after(schedule_disturbances)
+                    .after(advance_tile_rng)
+                    .in_set(ForestSystems),
+            );
+    }
+}
+
+/// A marker component for tiles whose trees are infested with beetles.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Infested;
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct BeetleSpread {
+    /// The tile kinds that beetles can infest.
+    ///
+    /// Beetles prefer large, mature trees.
+    susceptible_kinds: Vec<TileKind>,
+    /// The number of susceptible tiles that are infested when a new outbreak begins.
+    ///
+    /// Outbreaks are started by the [`DisturbanceScheduler`](crate::disturbances::DisturbanceScheduler).
+    outbreak_size: usize,
+    /// The probability that an infested tile spreads beetles to each susceptible cardinal neighbor every tick.
+    spread_probability: f64,
+}
+
+impl BeetleSpread {
+    fn is_susceptible(&self, tile_kind: &TileKind) -> bool {
+        self.susceptible_kinds.contains(tile_kind)
+    }
+}
+
+impl Default for BeetleSpread {
+    fn default() -> Self {
+        Self {
+            susceptible_kinds: vec![TileKind::ShadeTolerantForest],
+            outbreak_size: 3,
+            spread_probability: 0.15,
+        }
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct BeetleMortality {
+    /// The probability that the trees in an infested tile die each tick, turning it into [`TileKind::DeadForest`].
+    mortality_probability: f64,
+}
+
+impl Default for BeetleMortality {
+    fn default() -> Self {
+        Self {
+            mortality_probability: 0.1,
+        }
+    }
+}
+
+#[hot]
+fn start_outbreaks(
+    tile_query: Query<(Entity, &Position, &TileKind), Without<Infested>>,
+    beetle_spread: Res<BeetleSpread>,
+    mut event_reader: EventReader<DisturbanceStarted>,
+    tile_rng: Res<TileRng>,
+    mut commands: Commands,
+) {
+    let mut rng = tile_rng.for_map(TileRngStream::OutbreakStart);
+    for _ in event_reader
+        .read()
+        .filter(|event| event.kind == DisturbanceKind::InsectOutbreak)
+    {
+        // Sorted by position, so that the same tiles are chosen no matter what order they are iterated in
+        let mut susceptible_tiles: Vec<(Position, Entity)> = tile_query
+            .iter()
+            .filter(|(_, _, tile_kind)| beetle_spread.is_susceptible(tile_kind))
+            .map(|(entity, position, _)| (*position, entity))
+            .collect();
+        susceptible_tiles.sort_unstable_by_key(|(position, _)| (position.y, position.x));
+        let susceptible_tiles: Vec<Entity> = susceptible_tiles
+            .into_iter()
+            .map(|(_, entity)| entity)
+            .collect();
+
+        for &entity in susceptible_tiles.choose_multiple(&mut rng, beetle_spread.outbreak_size) {
+            commands.entity(entity).insert(Infested);
+        }
+    }
+}
+
+#[hot]
+fn spread_beetles(
+    infested_query: Query<&Position, With<Infested>>,
+    uninfested_query: Query<&TileKind, Without<Infested>>,
+    beetle_spread: Res<BeetleSpread>,
+    tile_index: Res<TileIndex>,
+    tile_rng: Res<TileRng>,
+    mut commands: Commands,
+) {
+    for position in infested_query.iter() {
+        let mut rng = tile_rng.for_tile(position, TileRngStream::BeetleSpread);
+        for neighbor in position.cardinal_neighbors() {
+            let Some(neighbor_entity) = tile_index.get(&neighbor) else {
+                continue;
+            };
+
+            let Ok(neighbor_kind) = uninfested_query.get(neighbor_entity) else {
+                continue;
+            };
+
+            if beetle_spread.is_susceptible(neighbor_kind)
+                && rng.random_bool(beetle_spread.spread_probability.clamp(0.0, 1.0))
+            {
+                // Like with fire, we use `Commands` so that beetles can only spread one tile per tick
+                commands.entity(neighbor_entity).insert(Infested);
+            }
+        }
+    }
+}
+
+#[hot]
+fn kill_infested_trees(
+    mut infested_query: Query<(Entity, &Position, &mut TileKind), With<Infested>>,
+    beetle_spread: Res<BeetleSpread>,
+    beetle_mortality: Res<BeetleMortality>,
+    tile_rng: Res<TileRng>,
+    mut commands: Commands,
+) {
+    for (entity, position, mut tile_kind) in infested_query.iter_mut() {
+        // If something else (like a fire) has already destroyed the trees, the beetles have nothing left to eat
+        if !beetle_spread.is_susceptible(&tile_kind) {
+            commands.entity(entity).remove::<Infested>();
+            continue;
+        }
+
+        let mut rng = tile_rng.for_tile(position, TileRngStream::BeetleMortality);
+        if rng.random_bool(beetle_mortality.mortality_probability.clamp(0.0, 1.0)) {
+            *tile_kind = TileKind::DeadForest;
+            commands.entity(entity).remove::<Infested>();
+        }
+    }
+}
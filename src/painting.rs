@@ -0,0 +1,148 @@
+//! Lets the user paint tiles directly onto the map.
+//!
+//! Painting is driven by [`PaintTiles`] events, which can be sent by the mouse brush,
+//! console commands, or anything else that wants to overwrite a region of the map.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::input::egui_wants_any_pointer_input;
+
+use crate::camera::MainCamera;
+use crate::simulation::TileKind;
+use crate::spatial_index::{GridShape, Position, TileIndex};
+
+pub struct PaintingPlugin;
+
+impl Plugin for PaintingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Brush>()
+            .register_type::<Brush>()
+            .add_event::<PaintTiles>()
+            .add_systems(
+                Update,
+                (
+                    paint_with_brush.run_if(not(egui_wants_any_pointer_input)),
+                    paint_tiles.run_if(on_event::<PaintTiles>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The tile kind painted onto the map by holding the left mouse button.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Brush {
+    /// The kind of tile to paint, or `None` if the brush is not in use.
+    pub kind: Option<TileKind>,
+    /// The width and height of the square painted by the brush, in tiles.
+    pub size: i32,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            kind: None,
+            size: 1,
+        }
+    }
+}
+
+/// Overwrites every tile in the rectangle between `min` and `max` (inclusive) with the given kind.
+#[derive(Event, Debug)]
+pub struct PaintTiles {
+    pub min: Position,
+    pub max: Position,
+    pub kind: TileKind,
+}
+
+/// Paints with the [`Brush`] while the left mouse button is held down.
+///
+/// The cursor can skip over several tiles in a single frame,
+/// so each stroke is filled in along a straight line from where the brush was last frame.
+#[allow(clippy::too_many_arguments)]
+fn paint_with_brush(
+    mut last_painted: Local<Option<Position>>,
+    brush: Res<Brush>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_shape: Res<GridShape>,
+    ui_query: Query<&Interaction>,
+    mut event_writer: EventWriter<PaintTiles>,
+) {
+    let Some(kind) = brush.kind else {
+        *last_painted = None;
+        return;
+    };
+
+    if !mouse_input.pressed(MouseButton::Left) {
+        *last_painted = None;
+        return;
+    }
+
+    // Don't paint through the GUI
+    if ui_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        *last_painted = None;
+        return;
+    }
+
+    let Some(hovered) = hovered_position(&window, &camera, &grid_shape) else {
+        *last_painted = None;
+        return;
+    };
+
+    let stroke_start = last_painted.unwrap_or(hovered);
+    *last_painted = Some(hovered);
+
+    let half_size = (brush.size - 1).max(0) / 2;
+
+    for center in stroke_start.line_to(&hovered) {
+        event_writer.write(PaintTiles {
+            min: Position {
+                x: center.x - half_size,
+                y: center.y - half_size,
+            },
+            max: Position {
+                x: center.x - half_size + brush.size.max(1) - 1,
+                y: center.y - half_size + brush.size.max(1) - 1,
+            },
+            kind,
+        });
+    }
+}
+
+/// Returns the position of the tile under the cursor, if the cursor is inside the window.
+///
+/// Note that this position may lie outside of the map.
+pub fn hovered_position(
+    window: &Window,
+    camera: &(&Camera, &GlobalTransform),
+    grid_shape: &GridShape,
+) -> Option<Position> {
+    let cursor_position = window.cursor_position()?;
+
+    let (camera, camera_transform) = *camera;
+    let world_position = camera
+        .viewport_to_world_2d(camera_transform, cursor_position)
+        .ok()?;
+
+    Some(grid_shape.position_at(world_position))
+}
+
+fn paint_tiles(
+    mut event_reader: EventReader<PaintTiles>,
+    mut tile_query: Query<&mut TileKind>,
+    tile_index: Res<TileIndex>,
+) {
+    for event in event_reader.read() {
+        for (_, entity) in tile_index.iter_rect(event.min, event.max) {
+            if let Ok(mut tile_kind) = tile_query.get_mut(entity) {
+                tile_kind.set_if_neq(event.kind);
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Plots how many tiles of each kind there have been over the most recent ticks of the simulation.
+//!
+//! The chart is drawn into the [`PopulationChart`]'s image from the history kept by the [`TileCensus`],
+//! with one line for each kind of tile, colored to match the tiles on the map.
+//! The GUI shows the image in the statistics panel, along with buttons to pause the chart and zoom in and out.
+//! The vertical axis is scaled to fit the largest count in view, while the newest tick is always at the right edge.
+
+use std::collections::VecDeque;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use strum::IntoEnumIterator;
+
+use crate::census::TileCensus;
+use crate::simulation::TileKind;
+use crate::tile_appearance::TileAppearance;
+
+pub struct PopulationChartPlugin;
+
+impl Plugin for PopulationChartPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PopulationChart>()
+            .register_type::<PopulationChart>()
+            .add_systems(
+                Update,
+                redraw_population_chart.run_if(
+                    resource_changed::<TileCensus>
+                        .or(resource_changed::<PopulationChart>)
+                        .or(resource_changed::<TileAppearance>),
+                ),
+            );
+    }
+}
+
+/// The chart of tile counts over time, and the settings that control which ticks it shows.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct PopulationChart {
+    /// The image that the chart is drawn into.
+    #[reflect(ignore)]
+    pub image: Handle<Image>,
+    /// The number of the most recent ticks that the chart spans.
+    ///
+    /// Ticks older than the [`TileCensus::history_length`] are never shown, however large this is.
+    pub visible_ticks: usize,
+    /// The history shown while the chart is paused, copied from the [`TileCensus`] at the moment it was paused.
+    #[reflect(ignore)]
+    frozen_history: Option<VecDeque<HashMap<TileKind, u32>>>,
+}
+
+impl PopulationChart {
+    /// The width of the chart's image, in pixels.
+    pub const WIDTH: u32 = 200;
+    /// The height of the chart's image, in pixels.
+    pub const HEIGHT: u32 = 100;
+    /// The fewest ticks that the chart can be zoomed in to.
+    const MIN_VISIBLE_TICKS: usize = 10;
+    const BACKGROUND: [u8; 4] = [13, 13, 13, 255];
+
+    /// Whether the chart has stopped following the simulation.
+    pub fn paused(&self) -> bool {
+        self.frozen_history.is_some()
+    }
+
+    /// Pauses the chart on the current history, or resumes following the simulation if it was already paused.
+    pub fn toggle_paused(&mut self, tile_census: &TileCensus) {
+        self.frozen_history = match self.frozen_history {
+            Some(_) => None,
+            None => Some(tile_census.history().clone()),
+        };
+    }
+
+    /// Halves the number of ticks shown.
+    pub fn zoom_in(&mut self) {
+        self.visible_ticks = (self.visible_ticks / 2).max(Self::MIN_VISIBLE_TICKS);
+    }
+
+    /// Doubles the number of ticks shown, up to the length of the census history.
+    pub fn zoom_out(&mut self, tile_census: &TileCensus) {
+        self.visible_ticks = (self.visible_ticks * 2)
+            .min(tile_census.history_length)
+            .max(Self::MIN_VISIBLE_TICKS);
+    }
+}
+
+impl FromWorld for PopulationChart {
+    fn from_world(world: &mut World) -> Self {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: Self::WIDTH,
+                height: Self::HEIGHT,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &Self::BACKGROUND,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        // Keep the lines crisp as the chart is scaled to fit the panel
+        image.sampler = ImageSampler::nearest();
+
+        Self {
+            image: world.resource_mut::<Assets<Image>>().add(image),
+            visible_ticks: 100,
+            frozen_history: None,
+        }
+    }
+}
+
+fn redraw_population_chart(
+    population_chart: Res<PopulationChart>,
+    tile_census: Res<TileCensus>,
+    tile_appearance: Res<TileAppearance>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(data) = images
+        .get_mut(&population_chart.image)
+        .and_then(|image| image.data.as_mut())
+    else {
+        return;
+    };
+
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&PopulationChart::BACKGROUND);
+    }
+
+    let history = population_chart
+        .frozen_history
+        .as_ref()
+        .unwrap_or(tile_census.history());
+    let visible_ticks = population_chart.visible_ticks.max(2);
+    let records: Vec<&HashMap<TileKind, u32>> = history
+        .range(history.len().saturating_sub(visible_ticks)..)
+        .collect();
+    let max_count = records
+        .iter()
+        .flat_map(|record| record.values())
+        .copied()
+        .max()
+        .unwrap_or_default()
+        .max(1);
+
+    // Records are placed from the right edge, so that each zoom level always spans the same number of ticks
+    let first_tick = visible_ticks - records.len();
+    let point = |index: usize, count: u32| {
+        Vec2::new(
+            (first_tick + index) as f32 / (visible_ticks - 1) as f32
+                * (PopulationChart::WIDTH - 1) as f32,
+            count as f32 / max_count as f32 * (PopulationChart::HEIGHT - 1) as f32,
+        )
+    };
+
+    for tile_kind in TileKind::iter() {
+        // Kinds that never appear would just draw a line along the bottom, over everything else
+        if !records
+            .iter()
+            .any(|record| record.get(&tile_kind).is_some_and(|&count| count > 0))
+        {
+            continue;
+        }
+
+        let color = tile_appearance.color(&tile_kind).to_srgba().to_u8_array();
+        let points: Vec<Vec2> = records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| {
+                point(index, record.get(&tile_kind).copied().unwrap_or_default())
+            })
+            .collect();
+
+        // A single tick has no neighbors to join up to, so it is drawn as a dot instead
+        if let [only_point] = points[..] {
+            draw_line(data, only_point, only_point, color);
+        }
+        for segment in points.windows(2) {
+            draw_line(data, segment[0], segment[1], color);
+        }
+    }
+}
+
+/// Draws a straight line between two points on the chart, measured in pixels from its bottom left corner.
+fn draw_line(data: &mut [u8], from: Vec2, to: Vec2, color: [u8; 4]) {
+    let steps = (to - from).abs().max_element().ceil().max(1.0) as usize;
+    for step in 0..=steps {
+        let point = from.lerp(to, step as f32 / steps as f32).round();
+        let (x, y) = (point.x as u32, point.y as u32);
+        if x >= PopulationChart::WIDTH || y >= PopulationChart::HEIGHT {
+            continue;
+        }
+
+        // Images start from the top, while the chart starts from the bottom
+        let row = PopulationChart::HEIGHT - 1 - y;
+        let index = ((row * PopulationChart::WIDTH + x) * 4) as usize;
+        data[index..index + 4].copy_from_slice(&color);
+    }
+}
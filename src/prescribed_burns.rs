@@ -0,0 +1,222 @@
+//! Prescribed burns: fires that are deliberately lit to clear out fuel before a wildfire can burn through it.
+//!
+//! With the prescribed burn tool active, dragging across the map with the left mouse button selects a rectangle to burn.
+//! Burns can also be started from the console, and are driven by [`StartPrescribedBurn`] events.
+//! Each burn is lit at the start of the next simulation tick, and spreads far more slowly than a wildfire:
+//! see [`PrescribedFire`].
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::input::egui_wants_any_pointer_input;
+use bevy_simple_subsecond_system::hot;
+
+use crate::camera::MainCamera;
+use crate::control_flow::Simulation;
+use crate::painting::hovered_position;
+use crate::rulesets::ForestSystems;
+use crate::simulation::{PrescribedFire, TileKind, apply_cellular_rule, rainfall};
+use crate::spatial_index::{GridShape, Position, TileIndex};
+
+pub struct PrescribedBurnPlugin;
+
+impl Plugin for PrescribedBurnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PrescribedBurnTool>()
+            .register_type::<PrescribedBurnTool>()
+            .init_resource::<PrescribedBurns>()
+            .register_type::<PrescribedBurns>()
+            .add_event::<StartPrescribedBurn>()
+            .add_systems(
+                Update,
+                (
+                    select_burn_region.run_if(not(egui_wants_any_pointer_input)),
+                    draw_selected_region,
+                    queue_prescribed_burns.run_if(on_event::<StartPrescribedBurn>),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Simulation,
+                // Lighting the burns after the previous tile kinds have been stored lets the new fires
+                // remember what they are burning, and lets them spread on this same tick
+                ignite_prescribed_burns
+                    .after(rainfall)
+                    .before(apply_cellular_rule)
+                    .in_set(ForestSystems),
+            );
+    }
+}
+
+/// Lets the user select a rectangle of the map to burn by dragging with the left mouse button.
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct PrescribedBurnTool {
+    /// Whether dragging on the map selects a region to burn.
+    pub active: bool,
+    /// The tile where the current drag started, if the user is dragging.
+    drag_start: Option<Position>,
+}
+
+impl PrescribedBurnTool {
+    const SELECTION_COLOR: Color = Color::srgb(1.0, 0.5, 0.0);
+}
+
+/// Lights a prescribed burn across every tile in the rectangle between `min` and `max` (inclusive).
+#[derive(Event, Debug)]
+pub struct StartPrescribedBurn {
+    pub min: Position,
+    pub max: Position,
+}
+
+/// Controls which tiles are set alight by prescribed burns.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct PrescribedBurns {
+    /// The tile kinds that are set on fire within a burn region.
+    ///
+    /// All other tiles in the region are left alone.
+    burnable_kinds: Vec<TileKind>,
+    /// The regions waiting to be lit on the next tick, as (min, max) pairs.
+    ///
+    /// Events only live for a couple of frames, which can be shorter than a single tick.
+    pending: Vec<(Position, Position)>,
+}
+
+impl Default for PrescribedBurns {
+    fn default() -> Self {
+        Self {
+            burnable_kinds: vec![
+                TileKind::Meadow,
+                TileKind::Shrubland,
+                TileKind::ShadeIntolerantForest,
+                TileKind::ShadeTolerantForest,
+                TileKind::DeadForest,
+                TileKind::InvasiveGrass,
+                TileKind::Shore,
+            ],
+            pending: Vec::new(),
+        }
+    }
+}
+
+fn select_burn_region(
+    mut tool: ResMut<PrescribedBurnTool>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_shape: Res<GridShape>,
+    ui_query: Query<&Interaction>,
+    mut event_writer: EventWriter<StartPrescribedBurn>,
+) {
+    if !tool.active {
+        tool.drag_start = None;
+        return;
+    }
+
+    let Some(hovered) = hovered_position(&window, &camera, &grid_shape) else {
+        return;
+    };
+
+    // Don't start selecting through the GUI
+    if mouse_input.just_pressed(MouseButton::Left)
+        && ui_query
+            .iter()
+            .all(|interaction| *interaction == Interaction::None)
+    {
+        tool.drag_start = Some(hovered);
+    }
+
+    if mouse_input.just_released(MouseButton::Left)
+        && let Some(start) = tool.drag_start.take()
+    {
+        let (min, max) = selection_bounds(start, hovered);
+        event_writer.write(StartPrescribedBurn { min, max });
+    }
+}
+
+/// Returns the corners of the rectangle spanned by two tiles, as (min, max).
+fn selection_bounds(a: Position, b: Position) -> (Position, Position) {
+    (
+        Position {
+            x: a.x.min(b.x),
+            y: a.y.min(b.y),
+        },
+        Position {
+            x: a.x.max(b.x),
+            y: a.y.max(b.y),
+        },
+    )
+}
+
+/// Outlines the region that is being selected, so that the user can see what they are about to burn.
+fn draw_selected_region(
+    tool: Res<PrescribedBurnTool>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_shape: Res<GridShape>,
+    mut gizmos: Gizmos,
+) {
+    let Some(start) = tool.drag_start else {
+        return;
+    };
+
+    let Some(hovered) = hovered_position(&window, &camera, &grid_shape) else {
+        return;
+    };
+
+    // The region is a rectangle in grid coordinates, which is skewed into a rhombus on hex grids
+    let (min, max) = selection_bounds(start, hovered);
+    let min = Vec2::new(min.x as f32, min.y as f32) - 0.5;
+    let max = Vec2::new(max.x as f32, max.y as f32) + 0.5;
+    let corners = [
+        min,
+        Vec2::new(max.x, min.y),
+        max,
+        Vec2::new(min.x, max.y),
+        min,
+    ]
+    .map(|corner| grid_shape.grid_to_world(corner));
+
+    gizmos.linestrip_2d(corners, PrescribedBurnTool::SELECTION_COLOR);
+}
+
+fn queue_prescribed_burns(
+    mut event_reader: EventReader<StartPrescribedBurn>,
+    mut prescribed_burns: ResMut<PrescribedBurns>,
+) {
+    for event in event_reader.read() {
+        prescribed_burns.pending.push((event.min, event.max));
+    }
+}
+
+/// Sets every burnable tile in each pending burn region on fire, marking them as [`PrescribedFire`]s.
+#[hot]
+fn ignite_prescribed_burns(
+    mut tile_query: Query<&mut TileKind>,
+    mut prescribed_burns: ResMut<PrescribedBurns>,
+    tile_index: Res<TileIndex>,
+    mut commands: Commands,
+) {
+    let pending = std::mem::take(&mut prescribed_burns.pending);
+
+    for (min, max) in pending {
+        let mut ignited = 0;
+
+        for (_, entity) in tile_index.iter_rect(min, max) {
+            let Ok(mut tile_kind) = tile_query.get_mut(entity) else {
+                continue;
+            };
+
+            if prescribed_burns.burnable_kinds.contains(&tile_kind) {
+                *tile_kind = TileKind::Fire;
+                commands.entity(entity).insert(PrescribedFire);
+                ignited += 1;
+            }
+        }
+
+        info!(
+            "Lit a prescribed burn from ({}, {}) to ({}, {}), igniting {} tiles.",
+            min.x, min.y, max.x, max.y, ignited
+        );
+    }
+}
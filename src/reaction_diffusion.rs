@@ -0,0 +1,198 @@
+//! A Gray-Scott reaction-diffusion system: a continuous cellular automaton.
+//!
+//! Rather than holding a discrete [`TileKind`](crate::simulation::TileKind),
+//! each tile holds the concentrations of two chemicals, `u` and `v`.
+//! Both chemicals diffuse across the map, `u` is fed in from outside at a constant rate,
+//! and `v` consumes `u` to reproduce itself (`u + 2v -> 3v`) while slowly decaying.
+//! Depending on the feed and kill rates, this produces spots, stripes, mazes and even self-replicating blobs.
+
+use bevy::prelude::*;
+use bevy_simple_subsecond_system::hot;
+use rand::Rng;
+
+use crate::SimState;
+use crate::control_flow::Simulation;
+use crate::map_generation::{
+    GeneratedMapSize, GenerationStep, MapSeed, SpawnTiles, insert_tile_components,
+};
+use crate::rulesets::{Ruleset, ruleset_is};
+use crate::spatial_index::{Position, Tile};
+
+pub struct ReactionDiffusionPlugin;
+
+impl Plugin for ReactionDiffusionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Concentrations>()
+            .init_resource::<GrayScott>()
+            .register_type::<GrayScott>()
+            .add_systems(
+                Update,
+                insert_tile_components::<Concentrations>
+                    .after(SpawnTiles)
+                    .run_if(in_state(SimState::Generate))
+                    .run_if(ruleset_is(Ruleset::ReactionDiffusion)),
+            )
+            .add_systems(
+                OnExit(SimState::Generate),
+                seed_concentrations.run_if(ruleset_is(Ruleset::ReactionDiffusion)),
+            )
+            .add_systems(
+                Simulation,
+                react_and_diffuse.run_if(ruleset_is(Ruleset::ReactionDiffusion)),
+            );
+    }
+}
+
+/// The concentrations of the two chemicals in a tile, each between 0 and 1.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq)]
+pub struct Concentrations {
+    /// The chemical that is fed into the system, and consumed by `v`.
+    pub u: f32,
+    /// The chemical that reproduces by consuming `u`, and is slowly removed.
+    pub v: f32,
+}
+
+impl Default for Concentrations {
+    fn default() -> Self {
+        Self { u: 1.0, v: 0.0 }
+    }
+}
+
+/// The parameters of the Gray-Scott model.
+///
+/// See <https://www.karlsims.com/rd.html> for a lovely explanation of how these interact.
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Resource)]
+pub struct GrayScott {
+    /// The rate at which `u` is added to each tile.
+    pub feed_rate: f32,
+    /// The rate at which `v` is removed from each tile.
+    pub kill_rate: f32,
+    /// How quickly `u` spreads to neighboring tiles.
+    pub diffusion_u: f32,
+    /// How quickly `v` spreads to neighboring tiles.
+    pub diffusion_v: f32,
+    /// The number of integration steps performed each simulation tick.
+    ///
+    /// Patterns take thousands of steps to develop, so we need to take several per tick.
+    pub steps_per_tick: u32,
+    /// The number of square patches of `v` scattered across the map when it is generated.
+    pub seed_patches: u32,
+    /// The half-width of each seeded patch, in tiles.
+    pub seed_radius: i32,
+}
+
+impl Default for GrayScott {
+    fn default() -> Self {
+        // These values produce a "coral" of winding, branching stripes
+        Self {
+            feed_rate: 0.0545,
+            kill_rate: 0.062,
+            diffusion_u: 1.0,
+            diffusion_v: 0.5,
+            steps_per_tick: 10,
+            seed_patches: 5,
+            seed_radius: 2,
+        }
+    }
+}
+
+/// Scatters a few patches of `v` across the map, which patterns will grow out from.
+#[hot]
+fn seed_concentrations(
+    gray_scott: Res<GrayScott>,
+    map_size: Res<GeneratedMapSize>,
+    map_seed: Res<MapSeed>,
+    mut tile_query: Query<(&Position, &mut Concentrations), With<Tile>>,
+) {
+    if map_size.width <= 0 || map_size.height <= 0 {
+        return;
+    }
+
+    let mut rng = map_seed.rng(GenerationStep::ReactionDiffusion);
+
+    let patch_centers: Vec<Position> = (0..gray_scott.seed_patches)
+        .map(|_| Position {
+            x: rng.random_range(0..map_size.width),
+            y: rng.random_range(0..map_size.height),
+        })
+        .collect();
+
+    for (position, mut concentrations) in tile_query.iter_mut() {
+        let in_patch = patch_centers.iter().any(|center| {
+            (position.x - center.x).abs() <= gray_scott.seed_radius
+                && (position.y - center.y).abs() <= gray_scott.seed_radius
+        });
+
+        *concentrations = if in_patch {
+            Concentrations { u: 0.5, v: 0.25 }
+        } else {
+            Concentrations::default()
+        };
+    }
+}
+
+/// Integrates the Gray-Scott equations forward by [`GrayScott::steps_per_tick`] steps.
+///
+/// Diffusion is computed using a 3x3 Laplacian kernel that wraps around the edges of the map.
+/// Working on a flat grid copied out of the ECS is much faster than looking up neighbors through the
+/// [`TileIndex`](crate::spatial_index::TileIndex), since every tile is visited many times per tick.
+#[hot]
+fn react_and_diffuse(
+    gray_scott: Res<GrayScott>,
+    map_size: Res<GeneratedMapSize>,
+    mut tile_query: Query<(&Position, &mut Concentrations), With<Tile>>,
+) {
+    let width = map_size.width;
+    let height = map_size.height;
+    if width <= 0 || height <= 0 {
+        return;
+    }
+
+    let index = |x: i32, y: i32| (y.rem_euclid(height) * width + x.rem_euclid(width)) as usize;
+
+    let mut grid = vec![Concentrations::default(); (width * height) as usize];
+    for (position, concentrations) in tile_query.iter() {
+        grid[index(position.x, position.y)] = *concentrations;
+    }
+
+    let mut next_grid = grid.clone();
+    for _ in 0..gray_scott.steps_per_tick {
+        for y in 0..height {
+            for x in 0..width {
+                let mut laplacian_u = 0.0;
+                let mut laplacian_v = 0.0;
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let weight = match (dx, dy) {
+                            (0, 0) => -1.0,
+                            (0, _) | (_, 0) => 0.2,
+                            _ => 0.05,
+                        };
+                        let neighbor = grid[index(x + dx, y + dy)];
+                        laplacian_u += weight * neighbor.u;
+                        laplacian_v += weight * neighbor.v;
+                    }
+                }
+
+                let Concentrations { u, v } = grid[index(x, y)];
+                let reaction = u * v * v;
+
+                next_grid[index(x, y)] = Concentrations {
+                    u: (u + gray_scott.diffusion_u * laplacian_u - reaction
+                        + gray_scott.feed_rate * (1.0 - u))
+                        .clamp(0.0, 1.0),
+                    v: (v + gray_scott.diffusion_v * laplacian_v + reaction
+                        - (gray_scott.kill_rate + gray_scott.feed_rate) * v)
+                        .clamp(0.0, 1.0),
+                };
+            }
+        }
+
+        std::mem::swap(&mut grid, &mut next_grid);
+    }
+
+    for (position, mut concentrations) in tile_query.iter_mut() {
+        concentrations.set_if_neq(grid[index(position.x, position.y)]);
+    }
+}
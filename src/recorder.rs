@@ -0,0 +1,279 @@
+//! Records timelapses of the simulation, for sharing how a run played out.
+//!
+//! While the [`Recorder`] is recording, the simulation is captured every few ticks, without the GUI,
+//! using the same capture camera as [`screenshots`](crate::screenshots).
+//! Frames are either written straight to disk as a numbered PNG sequence,
+//! or kept in memory and assembled into an animated GIF once recording stops.
+//! Everything is written to the `recordings` folder.
+
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured, save_to_disk};
+use bevy::tasks::IoTaskPool;
+use clap::ValueEnum;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+use crate::control_flow::Simulation;
+use crate::screenshots::{CaptureSystems, SimulationCapture, take_screenshot, timestamped_name};
+
+pub struct RecorderPlugin;
+
+impl Plugin for RecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recorder>()
+            .register_type::<Recorder>()
+            .add_systems(Simulation, count_recorded_ticks)
+            .add_systems(
+                PostUpdate,
+                (
+                    update_recording.run_if(resource_changed::<Recorder>),
+                    capture_recording_frame,
+                )
+                    .chain()
+                    .in_set(CaptureSystems)
+                    // Only one capture camera should ever be spawned, even if both want it on the same frame
+                    .after(take_screenshot),
+            );
+    }
+}
+
+/// The folder that recordings are written to, relative to the working directory.
+const RECORDING_FOLDER: &str = "recordings";
+
+/// How a recording is saved.
+#[derive(Reflect, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordingFormat {
+    /// Each frame is written to its own numbered PNG file as soon as it is captured.
+    #[default]
+    PngSequence,
+    /// Frames are shrunk and kept in memory, then saved as a single looping GIF when recording stops.
+    Gif,
+}
+
+/// Controls the timelapse recorder.
+///
+/// Setting [`Recorder::recording`] starts a new recording, and clearing it stops and saves the current one.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Recorder {
+    /// Whether a recording is currently being made.
+    pub recording: bool,
+    /// The number of simulation ticks between each captured frame.
+    pub ticks_per_frame: u32,
+    /// How the recording is saved.
+    pub format: RecordingFormat,
+    /// How long each frame of a GIF is shown for, in milliseconds.
+    pub gif_frame_millis: u32,
+    /// The number of simulation ticks since the last frame was captured.
+    ticks_since_frame: u32,
+    /// Whether enough ticks have passed that a frame should be captured.
+    frame_due: bool,
+    /// The recording in progress, if any.
+    #[reflect(ignore)]
+    session: Option<RecordingSession>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self {
+            recording: false,
+            ticks_per_frame: 5,
+            format: RecordingFormat::default(),
+            gif_frame_millis: 100,
+            ticks_since_frame: 0,
+            frame_due: false,
+            session: None,
+        }
+    }
+}
+
+impl Recorder {
+    /// The most frames that a GIF can hold, to keep the memory used by long recordings in check.
+    const MAX_GIF_FRAMES: usize = 600;
+    /// The largest width or height of each frame of a GIF, in pixels.
+    const MAX_GIF_SIZE: u32 = 640;
+}
+
+/// A recording in progress.
+#[derive(Debug)]
+struct RecordingSession {
+    /// The name of the recording, used for its folder or file.
+    name: String,
+    format: RecordingFormat,
+    /// The number of frames captured so far.
+    frames_captured: u32,
+    /// The frames of a GIF that have arrived so far, along with their frame numbers.
+    ///
+    /// Captures finish a frame or so after they are requested, so these may arrive out of order.
+    gif_frames: Vec<(u32, RgbaImage)>,
+}
+
+impl RecordingSession {
+    /// The folder that the frames of a PNG sequence are written to.
+    fn folder(&self) -> PathBuf {
+        PathBuf::from(RECORDING_FOLDER).join(&self.name)
+    }
+}
+
+/// Marks the capture of a single frame of a GIF recording, with its frame number.
+#[derive(Component)]
+struct RecordedFrame(u32);
+
+fn count_recorded_ticks(mut recorder: ResMut<Recorder>) {
+    if !recorder.recording {
+        return;
+    }
+
+    recorder.ticks_since_frame += 1;
+    if recorder.ticks_since_frame >= recorder.ticks_per_frame.max(1) {
+        recorder.ticks_since_frame = 0;
+        recorder.frame_due = true;
+    }
+}
+
+/// Starts and stops recording sessions as [`Recorder::recording`] is toggled.
+fn update_recording(mut recorder: ResMut<Recorder>) {
+    if recorder.recording && recorder.session.is_none() {
+        let session = RecordingSession {
+            name: timestamped_name("recording"),
+            format: recorder.format,
+            frames_captured: 0,
+            gif_frames: Vec::new(),
+        };
+
+        let folder = match session.format {
+            RecordingFormat::PngSequence => session.folder(),
+            RecordingFormat::Gif => PathBuf::from(RECORDING_FOLDER),
+        };
+        if let Err(error) = fs::create_dir_all(&folder) {
+            error!("Could not create the {} folder: {error}", folder.display());
+            recorder.recording = false;
+            return;
+        }
+
+        info!("Started recording {}", session.name);
+        recorder.session = Some(session);
+        // Start with the state of the simulation as it is now
+        recorder.ticks_since_frame = 0;
+        recorder.frame_due = true;
+    } else if !recorder.recording
+        && let Some(session) = recorder.session.take()
+    {
+        finish_recording(session, recorder.gif_frame_millis);
+    }
+}
+
+/// Captures the next frame of the recording, once it is due.
+fn capture_recording_frame(
+    mut recorder: ResMut<Recorder>,
+    mut simulation_capture: SimulationCapture,
+    mut commands: Commands,
+) {
+    if !recorder.frame_due {
+        return;
+    }
+    recorder.frame_due = false;
+
+    let Some(session) = recorder.session.as_mut() else {
+        return;
+    };
+
+    let frame = session.frames_captured;
+    if session.format == RecordingFormat::Gif && frame as usize >= Recorder::MAX_GIF_FRAMES {
+        warn!(
+            "Stopping recording after {} frames, the most that a GIF can hold",
+            Recorder::MAX_GIF_FRAMES
+        );
+        recorder.recording = false;
+        return;
+    }
+    session.frames_captured += 1;
+
+    let image = simulation_capture.capture();
+    let mut screenshot = commands.spawn(Screenshot::image(image));
+    match session.format {
+        RecordingFormat::PngSequence => {
+            let path = session.folder().join(format!("frame-{frame:05}.png"));
+            screenshot.observe(save_to_disk(path));
+        }
+        RecordingFormat::Gif => {
+            screenshot
+                .insert(RecordedFrame(frame))
+                .observe(store_gif_frame);
+        }
+    }
+}
+
+/// Shrinks a captured frame and keeps it until the GIF is assembled.
+fn store_gif_frame(
+    trigger: Trigger<ScreenshotCaptured>,
+    frame_query: Query<&RecordedFrame>,
+    mut recorder: ResMut<Recorder>,
+) {
+    let Ok(frame) = frame_query.get(trigger.target()) else {
+        return;
+    };
+    // Frames that arrive after recording stopped are simply dropped
+    let Some(session) = recorder.session.as_mut() else {
+        return;
+    };
+
+    match trigger.event().0.clone().try_into_dynamic() {
+        Ok(image) => {
+            let image = image.thumbnail(Recorder::MAX_GIF_SIZE, Recorder::MAX_GIF_SIZE);
+            session.gif_frames.push((frame.0, image.to_rgba8()));
+        }
+        Err(error) => error!(
+            "Could not convert frame {} of the recording: {error}",
+            frame.0
+        ),
+    }
+}
+
+/// Saves a finished recording, assembling its GIF in the background so that the app doesn't freeze.
+fn finish_recording(mut session: RecordingSession, gif_frame_millis: u32) {
+    match session.format {
+        RecordingFormat::PngSequence => {
+            info!(
+                "Recorded {} frames to {}",
+                session.frames_captured,
+                session.folder().display()
+            );
+        }
+        RecordingFormat::Gif => {
+            let path = PathBuf::from(RECORDING_FOLDER).join(format!("{}.gif", session.name));
+            info!(
+                "Saving {} frames to {}",
+                session.gif_frames.len(),
+                path.display()
+            );
+
+            session.gif_frames.sort_unstable_by_key(|(frame, _)| *frame);
+            let delay = Delay::from_numer_denom_ms(gif_frame_millis, 1);
+            let frames = session
+                .gif_frames
+                .into_iter()
+                .map(move |(_, image)| Frame::from_parts(image, 0, 0, delay));
+
+            IoTaskPool::get()
+                .spawn(async move {
+                    let result = File::create(&path)
+                        .map_err(image::ImageError::IoError)
+                        .and_then(|file| {
+                            let mut encoder = GifEncoder::new(file);
+                            encoder.set_repeat(Repeat::Infinite)?;
+                            encoder.encode_frames(frames)
+                        });
+
+                    match result {
+                        Ok(()) => info!("Saved the recording to {}", path.display()),
+                        Err(error) => error!("Could not write {}: {error}", path.display()),
+                    }
+                })
+                .detach();
+        }
+    }
+}
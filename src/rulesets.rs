@@ -0,0 +1,627 @@
+//! Built-in rulesets that can be swapped between at runtime.
+//!
+//! Each ruleset pairs a [`CellularRule`] with the tile kinds, neighborhood and update mode that it expects,
+//! showing that the same grid infrastructure can run very different cellular automata.
+
+use core::fmt;
+use core::str::FromStr;
+
+use bevy::prelude::*;
+use clap::ValueEnum;
+use rand::{Rng, RngCore};
+use strum_macros::EnumIter;
+
+use crate::control_flow::Simulation;
+use crate::map_generation::InitialWeights;
+use crate::painting::Brush;
+use crate::simulation::{
+    ActiveRule, Cell, CellularRule, ForestRule, SimulationUpdateMode, TileKind,
+};
+use crate::spatial_index::{GridShape, NeighborhoodKind};
+
+pub struct RulesetPlugin;
+
+impl Plugin for RulesetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Ruleset>()
+            .register_type::<Ruleset>()
+            .init_resource::<ForestFireProbabilities>()
+            .register_type::<ForestFireProbabilities>()
+            .init_resource::<LifeRule>()
+            .register_type::<LifeRule>()
+            .configure_sets(
+                Simulation,
+                (
+                    ForestSystems.run_if(ruleset_is(Ruleset::Forest)),
+                    ForestClimateSystems.run_if(ruleset_is(Ruleset::Forest)),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    apply_ruleset
+                        .run_if(resource_changed::<Ruleset>.or(resource_changed::<GridShape>)),
+                    apply_life_rule
+                        .run_if(resource_changed::<Ruleset>.or(resource_changed::<LifeRule>))
+                        .run_if(ruleset_is(Ruleset::GameOfLife)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The ruleset that the simulation is currently running.
+///
+/// Changing this swaps out the [`ActiveRule`] and regenerates the map.
+#[derive(Resource, Reflect, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default, EnumIter)]
+#[reflect(Resource)]
+pub enum Ruleset {
+    /// Forest succession and fire, driven by the [`ForestRule`].
+    #[default]
+    Forest,
+    /// Conway's Game of Life, played out on [`TileKind::Alive`] and [`TileKind::Dead`] tiles.
+    ///
+    /// Any other life-like automaton can be run instead by changing the [`LifeRule`] resource.
+    GameOfLife,
+    /// Wireworld, a deterministic automaton that simulates electrons flowing along wires.
+    ///
+    /// The map starts out empty: paint conductors and electrons onto it to build circuits.
+    Wireworld,
+    /// Langton's ant, where ants wander the map flipping [`TileKind::White`] and [`TileKind::Black`] tiles.
+    ///
+    /// The tiles never change on their own: all of the action comes from the [`Ant`](crate::ants::Ant)s.
+    LangtonsAnt,
+    /// A one-dimensional elementary cellular automaton, drawn row by row as a space-time diagram.
+    ///
+    /// The rule number is controlled by the [`ElementaryRule`](crate::elementary::ElementaryRule) resource.
+    Elementary,
+    /// A Gray-Scott reaction-diffusion system, where each tile holds continuous chemical
+    /// [`Concentrations`](crate::reaction_diffusion::Concentrations) rather than a discrete kind.
+    ReactionDiffusion,
+    /// The Drossel-Schwabl forest-fire model: the classic, minimal forest-fire cellular automaton.
+    ///
+    /// Trees grow on empty ground and are struck by lightning at random, controlled by the [`ForestFireProbabilities`].
+    DrosselSchwabl,
+    /// Rock-paper-scissors: three species that each invade the one they beat, forming spiral waves.
+    RockPaperScissors,
+    /// The Abelian sandpile model, where each tile holds [`SandGrains`](crate::sandpile::SandGrains) that topple onto their neighbors.
+    ///
+    /// Click on the map to drop more sand.
+    Sandpile,
+}
+
+impl Ruleset {
+    /// The rule that drives the simulation under this ruleset.
+    pub fn rule(&self) -> ActiveRule {
+        match self {
+            Ruleset::Forest => ActiveRule::new(ForestRule),
+            Ruleset::GameOfLife => ActiveRule::new(LifeRule::conway()),
+            Ruleset::Wireworld => ActiveRule::new(WireworldRule),
+            Ruleset::LangtonsAnt
+            | Ruleset::Elementary
+            | Ruleset::ReactionDiffusion
+            | Ruleset::Sandpile => ActiveRule::new(StaticRule),
+            Ruleset::DrosselSchwabl => ActiveRule::new(DrosselSchwablRule),
+            Ruleset::RockPaperScissors => ActiveRule::new(CyclicRule::default()),
+        }
+    }
+
+    /// The weights used to randomly choose the kind of each land tile when the map is generated.
+    pub fn initial_weights(&self) -> InitialWeights {
+        match self {
+            Ruleset::Forest => InitialWeights::default(),
+            Ruleset::GameOfLife => InitialWeights {
+                weights: vec![(TileKind::Alive, 0.3), (TileKind::Dead, 0.7)],
+            },
+            Ruleset::Wireworld => InitialWeights {
+                weights: vec![(TileKind::Empty, 1.0)],
+            },
+            Ruleset::LangtonsAnt => InitialWeights {
+                weights: vec![(TileKind::White, 1.0)],
+            },
+            Ruleset::Elementary => InitialWeights {
+                weights: vec![(TileKind::Dead, 1.0)],
+            },
+            Ruleset::ReactionDiffusion | Ruleset::Sandpile => InitialWeights {
+                weights: vec![(TileKind::Empty, 1.0)],
+            },
+            Ruleset::DrosselSchwabl => InitialWeights {
+                weights: vec![(TileKind::Tree, 0.5), (TileKind::Empty, 0.5)],
+            },
+            Ruleset::RockPaperScissors => InitialWeights {
+                weights: vec![
+                    (TileKind::Rock, 1.0),
+                    (TileKind::Paper, 1.0),
+                    (TileKind::Scissors, 1.0),
+                ],
+            },
+        }
+    }
+
+    /// The set of tiles that count as neighbors under this ruleset.
+    pub fn neighborhood(&self) -> NeighborhoodKind {
+        match self {
+            Ruleset::Forest
+            | Ruleset::LangtonsAnt
+            | Ruleset::Elementary
+            | Ruleset::DrosselSchwabl
+            | Ruleset::Sandpile => NeighborhoodKind::VonNeumann,
+            Ruleset::GameOfLife
+            | Ruleset::Wireworld
+            | Ruleset::ReactionDiffusion
+            | Ruleset::RockPaperScissors => NeighborhoodKind::Moore,
+        }
+    }
+
+    /// How the rule should be applied to the map each tick.
+    pub fn update_mode(&self) -> SimulationUpdateMode {
+        match self {
+            Ruleset::Forest => SimulationUpdateMode::Asynchronous,
+            // Classic cellular automata expect every cell to update at once
+            Ruleset::GameOfLife
+            | Ruleset::Wireworld
+            | Ruleset::LangtonsAnt
+            | Ruleset::Elementary
+            | Ruleset::ReactionDiffusion
+            | Ruleset::DrosselSchwabl
+            | Ruleset::RockPaperScissors
+            | Ruleset::Sandpile => SimulationUpdateMode::Synchronous,
+        }
+    }
+
+    /// Whether the map should be generated with water and rivers.
+    pub fn has_terrain(&self) -> bool {
+        match self {
+            Ruleset::Forest => true,
+            Ruleset::GameOfLife
+            | Ruleset::Wireworld
+            | Ruleset::LangtonsAnt
+            | Ruleset::Elementary
+            | Ruleset::ReactionDiffusion
+            | Ruleset::DrosselSchwabl
+            | Ruleset::RockPaperScissors
+            | Ruleset::Sandpile => false,
+        }
+    }
+
+    /// The tile kinds that can be painted onto the map with the [`Brush`] under this ruleset.
+    pub fn paintable_kinds(&self) -> Vec<TileKind> {
+        match self {
+            Ruleset::Forest => vec![TileKind::Firebreak],
+            Ruleset::GameOfLife => vec![TileKind::Alive, TileKind::Dead],
+            Ruleset::Wireworld => {
+                vec![TileKind::Conductor, TileKind::ElectronHead, TileKind::Empty]
+            }
+            Ruleset::LangtonsAnt => vec![TileKind::White, TileKind::Black],
+            Ruleset::Elementary => vec![TileKind::Alive, TileKind::Dead],
+            // Concentrations are continuous, so there's nothing sensible to paint
+            Ruleset::ReactionDiffusion => Vec::new(),
+            // Sand is dropped by clicking instead
+            Ruleset::Sandpile => Vec::new(),
+            Ruleset::DrosselSchwabl => vec![TileKind::Tree, TileKind::Fire, TileKind::Empty],
+            Ruleset::RockPaperScissors => {
+                vec![TileKind::Rock, TileKind::Paper, TileKind::Scissors]
+            }
+        }
+    }
+}
+
+/// Systems that only make sense for the forest simulation, such as fire, succession and herbivores.
+///
+/// These only run while the [`Ruleset::Forest`] is active,
+/// and are paused while the grid is simulated on the GPU, since they work on the tile entities:
+/// see [`SimulationBackend`](crate::gpu_simulation::SimulationBackend).
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ForestSystems;
+
+/// Systems that drive the climate of the forest simulation, such as the seasons and droughts.
+///
+/// Like the [`ForestSystems`], these only run while the [`Ruleset::Forest`] is active,
+/// but they never touch the tiles, so they keep running while the grid is simulated on the GPU.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ForestClimateSystems;
+
+/// A run condition that returns true if the given ruleset is active.
+pub fn ruleset_is(ruleset: Ruleset) -> impl Fn(Res<Ruleset>) -> bool + Clone {
+    move |active_ruleset: Res<Ruleset>| *active_ruleset == ruleset
+}
+
+/// Swaps in the rule and settings for the newly selected ruleset.
+///
+/// Changing the [`InitialWeights`] causes the map to be regenerated.
+/// This also runs when the [`GridShape`] changes, since hexagonal grids need their own neighborhood.
+pub fn apply_ruleset(
+    ruleset: Res<Ruleset>,
+    mut active_rule: ResMut<ActiveRule>,
+    mut initial_weights: ResMut<InitialWeights>,
+    mut neighborhood: ResMut<NeighborhoodKind>,
+    grid_shape: Res<GridShape>,
+    mut update_mode: ResMut<SimulationUpdateMode>,
+    mut brush: ResMut<Brush>,
+) {
+    info!("Switching to the {:?} ruleset.", *ruleset);
+
+    *active_rule = ruleset.rule();
+    *initial_weights = ruleset.initial_weights();
+    // Hexagonal tiles only have six neighbors, regardless of the ruleset
+    *neighborhood = match *grid_shape {
+        GridShape::Square => ruleset.neighborhood(),
+        GridShape::Hexagonal => NeighborhoodKind::Hexagonal,
+    };
+    *update_mode = ruleset.update_mode();
+
+    // Don't let the user paint tiles that this ruleset doesn't understand
+    if brush
+        .kind
+        .is_some_and(|kind| !ruleset.paintable_kinds().contains(&kind))
+    {
+        brush.kind = None;
+    }
+}
+
+/// Swaps in the current [`LifeRule`], replacing the default Conway rules used by [`Ruleset::GameOfLife`].
+fn apply_life_rule(life_rule: Res<LifeRule>, mut active_rule: ResMut<ActiveRule>) {
+    info!("Running the life-like rule {}.", *life_rule);
+
+    *active_rule = ActiveRule::new(life_rule.clone());
+}
+
+/// A "life-like" cellular automaton, where dead cells are born and live cells survive
+/// based purely on how many of their neighbors are alive.
+///
+/// These are conventionally written as rule strings such as `B3/S23`,
+/// listing the neighbor counts that cause births and allow survival: see [`LifeRule::from_str`].
+#[derive(Resource, Reflect, Debug, Clone, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct LifeRule {
+    /// The numbers of live neighbors that cause a dead cell to come alive.
+    pub birth: Vec<usize>,
+    /// The numbers of live neighbors that allow a live cell to stay alive.
+    pub survival: Vec<usize>,
+}
+
+impl LifeRule {
+    /// Conway's Game of Life: B3/S23.
+    pub fn conway() -> Self {
+        Self {
+            birth: vec![3],
+            survival: vec![2, 3],
+        }
+    }
+}
+
+impl Default for LifeRule {
+    fn default() -> Self {
+        Self::conway()
+    }
+}
+
+/// The reasons that a life-like rule string could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseLifeRuleError {
+    /// The rule string was not made up of a `B` part and an `S` part.
+    MissingPart,
+    /// A character other than a digit from 0 to 8 was found in the neighbor counts.
+    InvalidCount(char),
+    /// The `B` or `S` part appeared more than once.
+    DuplicatePart(char),
+}
+
+impl fmt::Display for ParseLifeRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseLifeRuleError::MissingPart => {
+                write!(f, "expected a rule string like B3/S23")
+            }
+            ParseLifeRuleError::InvalidCount(character) => {
+                write!(f, "{character:?} is not a neighbor count from 0 to 8")
+            }
+            ParseLifeRuleError::DuplicatePart(letter) => {
+                write!(f, "the {letter} part of the rule can only be given once")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseLifeRuleError {}
+
+impl FromStr for LifeRule {
+    type Err = ParseLifeRuleError;
+
+    /// Parses a rule string in the standard `B3/S23` notation.
+    ///
+    /// The parts may come in either order, letters are case-insensitive, and the slash is optional:
+    /// `b36s23`, `S23/B36` and `B36/S23` are all the same rule (HighLife).
+    /// Either list of counts may be empty, as in `B2/S` (Seeds).
+    fn from_str(rule_string: &str) -> Result<Self, Self::Err> {
+        let mut birth: Option<Vec<usize>> = None;
+        let mut survival: Option<Vec<usize>> = None;
+        // Whether the digits currently being read belong to the birth part, rather than the survival part
+        let mut reading_birth = None;
+
+        for character in rule_string.trim().chars() {
+            match character.to_ascii_uppercase() {
+                'B' if birth.is_some() => return Err(ParseLifeRuleError::DuplicatePart('B')),
+                'S' if survival.is_some() => return Err(ParseLifeRuleError::DuplicatePart('S')),
+                'B' => {
+                    birth = Some(Vec::new());
+                    reading_birth = Some(true);
+                }
+                'S' => {
+                    survival = Some(Vec::new());
+                    reading_birth = Some(false);
+                }
+                '/' => {}
+                digit @ '0'..='8' => {
+                    let counts = match reading_birth {
+                        Some(true) => birth.as_mut(),
+                        Some(false) => survival.as_mut(),
+                        None => None,
+                    }
+                    .ok_or(ParseLifeRuleError::MissingPart)?;
+
+                    let count = digit as usize - '0' as usize;
+                    if !counts.contains(&count) {
+                        counts.push(count);
+                    }
+                }
+                _ => return Err(ParseLifeRuleError::InvalidCount(character)),
+            }
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(Self { birth, survival }),
+            _ => Err(ParseLifeRuleError::MissingPart),
+        }
+    }
+}
+
+impl fmt::Display for LifeRule {
+    /// Writes the rule in the standard `B3/S23` notation.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut birth = self.birth.clone();
+        let mut survival = self.survival.clone();
+        birth.sort_unstable();
+        survival.sort_unstable();
+
+        write!(f, "B")?;
+        for count in birth {
+            write!(f, "{count}")?;
+        }
+        write!(f, "/S")?;
+        for count in survival {
+            write!(f, "{count}")?;
+        }
+        Ok(())
+    }
+}
+
+impl CellularRule for LifeRule {
+    fn next(&self, cell: &Cell, neighbors: &[Cell], _rng: &mut dyn RngCore) -> TileKind {
+        let live_neighbors = neighbors
+            .iter()
+            .filter(|neighbor| neighbor.kind == TileKind::Alive)
+            .count();
+
+        match cell.kind {
+            TileKind::Alive if self.survival.contains(&live_neighbors) => TileKind::Alive,
+            TileKind::Alive => TileKind::Dead,
+            TileKind::Dead if self.birth.contains(&live_neighbors) => TileKind::Alive,
+            other_kind => other_kind,
+        }
+    }
+}
+
+/// Wireworld: electron heads become tails, tails become conductors,
+/// and conductors become heads if exactly one or two of their neighbors are heads.
+///
+/// This rule is entirely deterministic, and should be run with a [`NeighborhoodKind::Moore`] neighborhood.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WireworldRule;
+
+impl CellularRule for WireworldRule {
+    fn next(&self, cell: &Cell, neighbors: &[Cell], _rng: &mut dyn RngCore) -> TileKind {
+        match cell.kind {
+            TileKind::ElectronHead => TileKind::ElectronTail,
+            TileKind::ElectronTail => TileKind::Conductor,
+            TileKind::Conductor => {
+                let electron_heads = neighbors
+                    .iter()
+                    .filter(|neighbor| neighbor.kind == TileKind::ElectronHead)
+                    .count();
+
+                if electron_heads == 1 || electron_heads == 2 {
+                    TileKind::ElectronHead
+                } else {
+                    TileKind::Conductor
+                }
+            }
+            other_kind => other_kind,
+        }
+    }
+}
+
+/// Leaves every tile unchanged, for rulesets that are driven entirely by agents.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StaticRule;
+
+impl CellularRule for StaticRule {
+    fn next(&self, cell: &Cell, _neighbors: &[Cell], _rng: &mut dyn RngCore) -> TileKind {
+        cell.kind
+    }
+}
+
+/// The parameters of the [`DrosselSchwablRule`].
+///
+/// Interesting behavior emerges when lightning is much rarer than growth:
+/// forests build up into large, connected stands before being swept away by huge fires.
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Resource)]
+pub struct ForestFireProbabilities {
+    /// The probability that a tree grows on an empty tile each tick, often called `p`.
+    pub growth_probability: f32,
+    /// The probability that a tree with no burning neighbors is struck by lightning each tick, often called `f`.
+    pub lightning_probability: f32,
+}
+
+impl Default for ForestFireProbabilities {
+    fn default() -> Self {
+        Self {
+            growth_probability: 0.05,
+            lightning_probability: 0.0005,
+        }
+    }
+}
+
+/// The Drossel-Schwabl forest-fire model.
+///
+/// - Burning trees turn into empty ground.
+/// - Trees catch fire if any of their neighbors are burning, or are struck by lightning with probability `f`.
+/// - Trees grow on empty ground with probability `p`.
+///
+/// The probabilities are read from the [`ForestFireProbabilities`] resource, so they can be tweaked at runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DrosselSchwablRule;
+
+impl CellularRule for DrosselSchwablRule {
+    fn next(&self, cell: &Cell, neighbors: &[Cell], rng: &mut dyn RngCore) -> TileKind {
+        let probabilities = cell.resource::<ForestFireProbabilities>();
+
+        match cell.kind {
+            TileKind::Fire => TileKind::Empty,
+            TileKind::Tree => {
+                let neighbor_burning = neighbors
+                    .iter()
+                    .any(|neighbor| neighbor.kind == TileKind::Fire);
+
+                if neighbor_burning
+                    || rng.random_bool(probabilities.lightning_probability.clamp(0.0, 1.0) as f64)
+                {
+                    TileKind::Fire
+                } else {
+                    TileKind::Tree
+                }
+            }
+            TileKind::Empty
+                if rng.random_bool(probabilities.growth_probability.clamp(0.0, 1.0) as f64) =>
+            {
+                TileKind::Tree
+            }
+            other_kind => other_kind,
+        }
+    }
+}
+
+/// A cyclic dominance automaton, where each species invades the species that it beats.
+///
+/// A tile is taken over once enough of its neighbors belong to the species that beats it.
+/// With a [`NeighborhoodKind::Moore`] neighborhood and a random start, this produces rotating spiral waves.
+#[derive(Debug, Clone)]
+pub struct CyclicRule {
+    /// The species in the cycle: each one beats the species that follows it, wrapping around at the end.
+    pub species: Vec<TileKind>,
+    /// The minimum number of neighbors of the beating species needed to take over a tile.
+    pub threshold: usize,
+    /// A random amount, up to this value, added to the threshold for each tile.
+    ///
+    /// A little noise breaks up the otherwise perfectly straight wavefronts.
+    pub jitter: usize,
+}
+
+impl Default for CyclicRule {
+    fn default() -> Self {
+        Self {
+            species: vec![TileKind::Rock, TileKind::Scissors, TileKind::Paper],
+            threshold: 3,
+            jitter: 2,
+        }
+    }
+}
+
+impl CellularRule for CyclicRule {
+    fn next(&self, cell: &Cell, neighbors: &[Cell], rng: &mut dyn RngCore) -> TileKind {
+        let Some(index) = self.species.iter().position(|kind| *kind == cell.kind) else {
+            return cell.kind;
+        };
+
+        // Each species is beaten by the one before it in the cycle
+        let predator = self.species[(index + self.species.len() - 1) % self.species.len()];
+
+        let predator_neighbors = neighbors
+            .iter()
+            .filter(|neighbor| neighbor.kind == predator)
+            .count();
+
+        if predator_neighbors >= self.threshold + rng.random_range(0..=self.jitter) {
+            predator
+        } else {
+            cell.kind
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn life_rule(birth: &[usize], survival: &[usize]) -> LifeRule {
+        LifeRule {
+            birth: birth.to_vec(),
+            survival: survival.to_vec(),
+        }
+    }
+
+    #[test]
+    fn parses_standard_rule_strings() {
+        assert_eq!("B3/S23".parse(), Ok(LifeRule::conway()));
+        assert_eq!("B36/S23".parse(), Ok(life_rule(&[3, 6], &[2, 3])));
+    }
+
+    #[test]
+    fn parses_lowercase_and_reordered_rule_strings() {
+        assert_eq!("b3/s23".parse(), Ok(LifeRule::conway()));
+        assert_eq!("S23/B3".parse(), Ok(LifeRule::conway()));
+    }
+
+    #[test]
+    fn parses_rule_strings_without_a_slash() {
+        assert_eq!("B3S23".parse(), Ok(LifeRule::conway()));
+    }
+
+    #[test]
+    fn parses_empty_survival_counts() {
+        assert_eq!("B2/S".parse(), Ok(life_rule(&[2], &[])));
+    }
+
+    #[test]
+    fn rejects_out_of_range_counts() {
+        assert_eq!(
+            "B39/S23".parse::<LifeRule>(),
+            Err(ParseLifeRuleError::InvalidCount('9'))
+        );
+    }
+
+    #[test]
+    fn rejects_missing_parts() {
+        assert_eq!(
+            "B3".parse::<LifeRule>(),
+            Err(ParseLifeRuleError::MissingPart)
+        );
+        assert_eq!(
+            "23/3".parse::<LifeRule>(),
+            Err(ParseLifeRuleError::MissingPart)
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_parts() {
+        assert_eq!(
+            "B3/B36".parse::<LifeRule>(),
+            Err(ParseLifeRuleError::DuplicatePart('B'))
+        );
+        assert_eq!(
+            "S23/S4".parse::<LifeRule>(),
+            Err(ParseLifeRuleError::DuplicatePart('S'))
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! The Abelian sandpile model: a classic example of self-organized criticality.
+//!
+//! Each tile holds a pile of sand grains. Whenever a pile reaches four grains, it topples,
+//! sending one grain to each of its four neighbors, which may in turn cause them to topple.
+//! Grains that fall off the edge of the map are lost.
+//! Clicking on the map drops more grains onto it, setting off avalanches of every size.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::input::egui_wants_any_pointer_input;
+use bevy_simple_subsecond_system::hot;
+use rand::Rng;
+
+use crate::SimState;
+use crate::camera::MainCamera;
+use crate::control_flow::Simulation;
+use crate::map_generation::{GenerationStep, MapSeed, SpawnTiles, insert_tile_components};
+use crate::painting::hovered_position;
+use crate::rulesets::{Ruleset, ruleset_is};
+use crate::spatial_index::{GridShape, Position, Tile, TileIndex};
+
+pub struct SandpilePlugin;
+
+impl Plugin for SandpilePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SandGrains>()
+            .init_resource::<Sandpile>()
+            .register_type::<Sandpile>()
+            .add_systems(
+                Update,
+                insert_tile_components::<SandGrains>
+                    .after(SpawnTiles)
+                    .run_if(in_state(SimState::Generate))
+                    .run_if(ruleset_is(Ruleset::Sandpile)),
+            )
+            .add_systems(
+                OnExit(SimState::Generate),
+                scatter_sand.run_if(ruleset_is(Ruleset::Sandpile)),
+            )
+            .add_systems(
+                Simulation,
+                topple_sandpiles.run_if(ruleset_is(Ruleset::Sandpile)),
+            )
+            .add_systems(
+                Update,
+                drop_sand_on_click
+                    .run_if(ruleset_is(Ruleset::Sandpile))
+                    .run_if(not(egui_wants_any_pointer_input)),
+            );
+    }
+}
+
+/// The number of sand grains piled up on a tile.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SandGrains(pub u32);
+
+impl SandGrains {
+    /// Piles with at least this many grains topple, sending one grain to each of their four neighbors.
+    pub const TOPPLING_THRESHOLD: u32 = 4;
+}
+
+/// Settings for the [`Ruleset::Sandpile`] ruleset.
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Resource)]
+pub struct Sandpile {
+    /// The number of grains dropped onto a tile when it is clicked.
+    pub grains_per_click: u32,
+    /// The maximum number of grains randomly placed on each tile when the map is generated.
+    ///
+    /// Starting close to the toppling threshold means that even a single grain can set off a large avalanche.
+    pub max_initial_grains: u32,
+}
+
+impl Default for Sandpile {
+    fn default() -> Self {
+        Self {
+            grains_per_click: 8,
+            max_initial_grains: SandGrains::TOPPLING_THRESHOLD - 1,
+        }
+    }
+}
+
+fn scatter_sand(
+    sandpile: Res<Sandpile>,
+    map_seed: Res<MapSeed>,
+    mut tile_query: Query<&mut SandGrains, With<Tile>>,
+) {
+    let mut rng = map_seed.rng(GenerationStep::Sandpile);
+    for mut sand_grains in tile_query.iter_mut() {
+        sand_grains.0 = rng.random_range(0..=sandpile.max_initial_grains);
+    }
+}
+
+/// Drops grains onto the tile under the cursor when the left mouse button is clicked.
+#[allow(clippy::too_many_arguments)]
+fn drop_sand_on_click(
+    sandpile: Res<Sandpile>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_shape: Res<GridShape>,
+    ui_query: Query<&Interaction>,
+    tile_index: Res<TileIndex>,
+    mut tile_query: Query<&mut SandGrains, With<Tile>>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    // Don't drop sand through the GUI
+    if ui_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    let Some(position) = hovered_position(&window, &camera, &grid_shape) else {
+        return;
+    };
+
+    let Some(entity) = tile_index.get(&position) else {
+        return;
+    };
+
+    if let Ok(mut sand_grains) = tile_query.get_mut(entity) {
+        sand_grains.0 += sandpile.grains_per_click;
+    }
+}
+
+/// Topples every pile that has reached the [`SandGrains::TOPPLING_THRESHOLD`], all at once.
+///
+/// Only one round of toppling happens each tick, so that avalanches can be watched as they spread.
+#[hot]
+fn topple_sandpiles(
+    tile_index: Res<TileIndex>,
+    mut tile_query: Query<(&Position, &mut SandGrains), With<Tile>>,
+) {
+    let toppling_positions: Vec<Position> = tile_query
+        .iter()
+        .filter(|(_, sand_grains)| sand_grains.0 >= SandGrains::TOPPLING_THRESHOLD)
+        .map(|(position, _)| *position)
+        .collect();
+
+    for position in toppling_positions {
+        if let Some(entity) = tile_index.get(&position)
+            && let Ok((_, mut sand_grains)) = tile_query.get_mut(entity)
+        {
+            sand_grains.0 -= SandGrains::TOPPLING_THRESHOLD;
+        }
+
+        // Grains that would land off the edge of the map are simply lost
+        for neighbor in position.cardinal_neighbors() {
+            if let Some(entity) = tile_index.get(&neighbor)
+                && let Ok((_, mut sand_grains)) = tile_query.get_mut(entity)
+            {
+                sand_grains.0 += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Saves screenshots of the simulation, without the GUI drawn over it.
+//!
+//! Rather than capturing the window, a dedicated capture camera copies the [`MainCamera`]'s view
+//! and draws it into an offscreen image, which is then written to the `screenshots` folder.
+//! The capture camera is only active on the frames that something is captured, so it costs nothing the rest of the time.
+//! Screenshots are driven by [`TakeScreenshot`] events, which are sent by pressing F12 and by the `screenshot` console command.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::camera::CameraUpdateSystem;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::render::view::screenshot::{Screenshot, save_to_disk};
+use bevy::window::PrimaryWindow;
+use bevy_egui::input::egui_wants_any_keyboard_input;
+
+use crate::camera::MainCamera;
+
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TakeScreenshot>()
+            .add_systems(
+                Update,
+                screenshot_on_hotkey.run_if(not(egui_wants_any_keyboard_input)),
+            )
+            .add_systems(First, deactivate_capture_camera)
+            .add_systems(
+                PostUpdate,
+                take_screenshot
+                    .run_if(on_event::<TakeScreenshot>)
+                    .in_set(CaptureSystems),
+            )
+            .configure_sets(
+                PostUpdate,
+                CaptureSystems
+                    .before(CameraUpdateSystem)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// The folder that screenshots are written to, relative to the working directory.
+const SCREENSHOT_FOLDER: &str = "screenshots";
+
+/// Systems that capture images of the simulation using [`SimulationCapture`].
+///
+/// These run before the cameras are updated, so that the capture camera draws on the same frame that it is turned on.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CaptureSystems;
+
+/// Saves a screenshot of the simulation to the `screenshots` folder, as a PNG image.
+#[derive(Event, Debug, Clone, Default)]
+pub struct TakeScreenshot {
+    /// The file name to use, without an extension.
+    ///
+    /// If this is `None`, the name is based on the current time.
+    pub name: Option<String>,
+}
+
+/// A camera that draws the same view as the [`MainCamera`] into an offscreen image, leaving out the GUI.
+#[derive(Component)]
+struct CaptureCamera {
+    /// The image that this camera draws into.
+    image: Handle<Image>,
+}
+
+/// Captures what the [`MainCamera`] can see, without the GUI, using a separate capture camera.
+#[derive(SystemParam)]
+pub struct SimulationCapture<'w, 's> {
+    main_camera: Single<'w, (&'static Transform, &'static Projection), With<MainCamera>>,
+    capture_camera: Query<
+        'w,
+        's,
+        (
+            &'static CaptureCamera,
+            &'static mut Camera,
+            &'static mut Transform,
+            &'static mut Projection,
+        ),
+        Without<MainCamera>,
+    >,
+    window: Single<'w, &'static Window, With<PrimaryWindow>>,
+    images: ResMut<'w, Assets<Image>>,
+    commands: Commands<'w, 's>,
+}
+
+impl SimulationCapture<'_, '_> {
+    /// Points the capture camera at whatever the main camera can see, and turns it on for this frame.
+    ///
+    /// Returns the image that it will draw into, which can then be captured with a [`Screenshot`].
+    /// The capture camera is spawned the first time that it is needed, and always matches the size of the window.
+    pub fn capture(&mut self) -> Handle<Image> {
+        let size = Extent3d {
+            width: self.window.physical_width().max(1),
+            height: self.window.physical_height().max(1),
+            depth_or_array_layers: 1,
+        };
+        let (main_transform, main_projection) = *self.main_camera;
+
+        if let Ok((capture_camera, mut camera, mut transform, mut projection)) =
+            self.capture_camera.single_mut()
+        {
+            if let Some(image) = self.images.get_mut(&capture_camera.image)
+                && image.texture_descriptor.size != size
+            {
+                image.resize(size);
+            }
+
+            camera.is_active = true;
+            *transform = *main_transform;
+            *projection = main_projection.clone();
+            return capture_camera.image.clone();
+        }
+
+        let mut image = Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Bgra8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_SRC
+            | TextureUsages::COPY_DST
+            | TextureUsages::RENDER_ATTACHMENT;
+        let image = self.images.add(image);
+
+        self.commands.spawn((
+            Name::new("Capture camera"),
+            CaptureCamera {
+                image: image.clone(),
+            },
+            Camera2d,
+            Camera {
+                target: image.clone().into(),
+                // Draw before the main view, which never waits on the capture
+                order: -2,
+                ..default()
+            },
+            *main_transform,
+            main_projection.clone(),
+        ));
+
+        image
+    }
+}
+
+/// Turns off the capture camera, until the next time that something needs capturing.
+fn deactivate_capture_camera(mut camera_query: Query<&mut Camera, With<CaptureCamera>>) {
+    for mut camera in camera_query.iter_mut() {
+        camera.is_active = false;
+    }
+}
+
+fn screenshot_on_hotkey(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut event_writer: EventWriter<TakeScreenshot>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F12) {
+        event_writer.write(TakeScreenshot::default());
+    }
+}
+
+/// A file name based on the current time, such as `screenshot-1718000000123`, so that captures never overwrite each other.
+pub fn timestamped_name(prefix: &str) -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+    format!("{prefix}-{millis}")
+}
+
+pub fn take_screenshot(
+    mut event_reader: EventReader<TakeScreenshot>,
+    mut simulation_capture: SimulationCapture,
+) {
+    // Taking several screenshots in one frame would just capture the same image
+    let Some(event) = event_reader.read().last() else {
+        return;
+    };
+
+    let folder = PathBuf::from(SCREENSHOT_FOLDER);
+    if let Err(error) = fs::create_dir_all(&folder) {
+        error!("Could not create the {SCREENSHOT_FOLDER} folder: {error}");
+        return;
+    }
+
+    let name = event
+        .name
+        .clone()
+        .unwrap_or_else(|| timestamped_name("screenshot"));
+    let path = folder.join(format!("{name}.png"));
+    info!("Saving a screenshot to {}", path.display());
+
+    let image = simulation_capture.capture();
+    simulation_capture
+        .commands
+        .spawn(Screenshot::image(image))
+        .observe(save_to_disk(path));
+}
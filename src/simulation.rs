@@ -5,79 +5,753 @@
 //!
 //! All of this can be easily ripped out and replaced with your own simulation logic!
 
+use std::borrow::Cow;
+
 use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
-use bevy_prng::WyRand;
-use bevy_rand::global::GlobalEntropy;
-use bevy_rand::prelude::Entropy;
 use bevy_simple_subsecond_system::hot;
-use rand::Rng;
 use rand::seq::IndexedRandom;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
+use strum_macros::{EnumCount, EnumIter};
 
+use crate::SimState;
+use crate::climate::{ClimateConditions, Wind};
+use crate::composition::Composition;
 use crate::control_flow::Simulation;
-use crate::spatial_index::{Position, TileIndex};
+use crate::disturbances::{DisturbanceKind, DisturbanceStarted, schedule_disturbances};
+use crate::gpu_simulation::gpu_backend_active;
+use crate::map_generation::{GeneratedMapSize, SpawnTiles, insert_tile_components};
+use crate::rulesets::{ForestSystems, Ruleset, ruleset_is};
+use crate::spatial_index::{GridIndex, NeighborhoodKind, Position, TileIndex};
+use crate::tile_rng::{TileRng, TileRngStream, advance_tile_rng};
 
 pub struct TransitionPlugin;
 
 impl Plugin for TransitionPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<TileKind>()
+            .register_type::<PreviousTileKind>()
+            .init_resource::<SimulationUpdateMode>()
+            .register_type::<SimulationUpdateMode>()
+            .init_resource::<ActiveRule>()
+            .register_type::<Moisture>()
+            .register_type::<Elevation>()
+            .register_type::<Age>()
+            .register_type::<Fertility>()
+            .init_resource::<SoilFertility>()
+            .register_type::<SoilFertility>()
+            .register_type::<FireIntensity>()
+            .register_type::<FireState>()
+            .register_type::<FuelLoad>()
+            .init_resource::<FireFuel>()
+            .register_type::<FireFuel>()
+            .register_type::<PrescribedFire>()
+            .register_type::<DisturbanceContext>()
+            .register_type::<PostFirePathway>()
+            .register_type::<BurnDuration>()
+            .register_type::<BurnDurationDistribution>()
+            .init_resource::<BurnDurations>()
+            .register_type::<BurnDurations>()
             .init_resource::<FireSpread>()
             .register_type::<FireSpread>()
+            .init_resource::<EmberSpotting>()
+            .register_type::<EmberSpotting>()
             .init_resource::<FireSusceptibility>()
             .register_type::<FireSusceptibility>()
             .init_resource::<TransitionProbabilities>()
             .register_type::<TransitionProbabilities>()
+            .init_resource::<FireDrying>()
+            .register_type::<FireDrying>()
+            .init_resource::<SeedDispersal>()
+            .register_type::<SeedDispersal>()
+            .init_resource::<ShadeSuccession>()
+            .register_type::<ShadeSuccession>()
+            .init_resource::<Lightning>()
+            .register_type::<Lightning>()
+            .add_event::<LightningStruck>()
+            .init_resource::<Rainfall>()
+            .register_type::<Rainfall>()
+            .add_event::<RainStarted>()
+            .add_systems(
+                Update,
+                insert_tile_components::<(Age, FuelLoad)>
+                    .after(SpawnTiles)
+                    .run_if(in_state(SimState::Generate))
+                    .run_if(ruleset_is(Ruleset::Forest)),
+            )
             .add_systems(
                 Simulation,
                 // Using .chain() is a simple but effective way to carefully control system ordering for simulations
                 // In more complex simulations, consider using a vec of systems rather than a Schedule
-                (spread_fires, undisturbed_succession, start_fires).chain(),
+                (
+                    advance_tile_rng,
+                    store_previous_tile_kinds,
+                    age_tiles,
+                    update_fertility.in_set(ForestSystems),
+                    accumulate_fuel.in_set(ForestSystems),
+                    update_fire_intensity.in_set(ForestSystems),
+                    clear_disturbance_context.in_set(ForestSystems),
+                    rainfall.after(schedule_disturbances).in_set(ForestSystems),
+                    dry_out_near_fires.in_set(ForestSystems),
+                    apply_cellular_rule.run_if(not(gpu_backend_active)),
+                    ignite_new_fires.in_set(ForestSystems),
+                    spot_fires.in_set(ForestSystems),
+                    lightning_strikes.in_set(ForestSystems),
+                )
+                    .chain(),
             );
     }
 }
 
+/// Controls whether the simulation rules see changes made earlier in the same tick.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum SimulationUpdateMode {
+    /// Each rule reads the current state of the map, including any changes made by earlier rules this tick.
+    ///
+    /// This is cheap and makes disturbances feel responsive,
+    /// but means that the result of a tick depends on the order in which the rules run.
+    #[default]
+    Asynchronous,
+    /// Every rule reads the state of the map as it was at the start of the tick, stored in [`PreviousTileKind`],
+    /// and only writes to the current [`TileKind`].
+    ///
+    /// This is the classic double-buffered update used by most cellular automata.
+    /// Tiles that have already been disturbed this tick will not also be updated by the [`ActiveRule`].
+    /// Continuous layers such as [`Moisture`] and [`FuelLoad`] are still updated in place.
+    Synchronous,
+}
+
+impl SimulationUpdateMode {
+    /// Returns the tile kind that simulation rules should read for a tile with the given current and previous kinds.
+    pub fn read(&self, current: &TileKind, previous: &PreviousTileKind) -> TileKind {
+        match self {
+            SimulationUpdateMode::Asynchronous => *current,
+            SimulationUpdateMode::Synchronous => previous.0,
+        }
+    }
+}
+
+/// The kind of each tile at the start of the current tick.
+///
+/// This is the read buffer used by [`SimulationUpdateMode::Synchronous`].
+#[derive(Component, Reflect, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct PreviousTileKind(pub TileKind);
+
+/// Copies the kind of every tile into its [`PreviousTileKind`], before any of the rules for this tick have run.
+#[hot]
+fn store_previous_tile_kinds(mut tile_query: Query<(&TileKind, &mut PreviousTileKind)>) {
+    for (tile_kind, mut previous_tile_kind) in tile_query.iter_mut() {
+        previous_tile_kind.set_if_neq(PreviousTileKind(*tile_kind));
+    }
+}
+
+/// The rules that determine how each tile changes from one tick to the next.
+///
+/// Rules are applied to every tile at once by [`apply_cellular_rule`],
+/// and can be swapped out by inserting a new [`ActiveRule`].
+/// The forest simulation in this module is implemented by [`ForestRule`].
+pub trait CellularRule: Send + Sync + 'static {
+    /// Returns the kind that `cell` should become this tick, given its `neighbors`.
+    ///
+    /// Return the cell's current kind to leave it unchanged.
+    fn next(&self, cell: &Cell, neighbors: &[Cell], rng: &mut dyn RngCore) -> TileKind;
+}
+
+/// The [`CellularRule`] currently driving the simulation.
+///
+/// Insert this resource to replace the default [`ForestRule`] with your own rule.
+#[derive(Resource)]
+pub struct ActiveRule(pub Box<dyn CellularRule>);
+
+impl ActiveRule {
+    pub fn new(rule: impl CellularRule) -> Self {
+        Self(Box::new(rule))
+    }
+}
+
+impl Default for ActiveRule {
+    fn default() -> Self {
+        Self::new(ForestRule)
+    }
+}
+
+/// A read-only view of a single tile, passed to [`CellularRule::next`].
+pub struct Cell<'w> {
+    /// The tile entity.
+    ///
+    /// Other data about the tile, such as its [`Position`], can be read with [`Cell::get`].
+    pub entity: Entity,
+    /// The kind of the tile, as seen under the current [`SimulationUpdateMode`].
+    pub kind: TileKind,
+    world: &'w World,
+}
+
+impl<'w> Cell<'w> {
+    /// Returns the given component of this tile, if it has one.
+    pub fn get<C: Component>(&self) -> Option<&'w C> {
+        self.world.get::<C>(self.entity)
+    }
+
+    /// Returns the given resource, which rules can use for their parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resource does not exist.
+    pub fn resource<R: Resource>(&self) -> &'w R {
+        self.world.resource::<R>()
+    }
+
+    /// The world that this tile lives in.
+    pub fn world(&self) -> &'w World {
+        self.world
+    }
+}
+
+/// Computes the next kind of every tile using the [`ActiveRule`], then updates them all at once.
+///
+/// Because every tile is computed before any are changed, and each tile rolls its own random numbers from the [`TileRng`],
+/// the order in which tiles are visited does not matter.
+pub fn apply_cellular_rule(world: &mut World) {
+    let tile_rng = *world.resource::<TileRng>();
+    let mut tile_state = world.query::<(Entity, &Position, &TileKind, &PreviousTileKind)>();
+
+    let transitions = {
+        let world: &World = world;
+        let mut transitions = Vec::new();
+        let active_rule = world.resource::<ActiveRule>();
+        let grid_index = world.resource::<GridIndex>();
+        let neighborhood = world.resource::<NeighborhoodKind>();
+        let update_mode = *world.resource::<SimulationUpdateMode>();
+
+        let cell = |entity: Entity| -> Option<Cell> {
+            let current_kind = world.get::<TileKind>(entity)?;
+            let previous_kind = world.get::<PreviousTileKind>(entity)?;
+
+            Some(Cell {
+                entity,
+                kind: update_mode.read(current_kind, previous_kind),
+                world,
+            })
+        };
+
+        for (entity, position, current_kind, previous_kind) in tile_state.iter(world) {
+            // Disturbances take priority over the rule: each tile can only change once per tick
+            if update_mode == SimulationUpdateMode::Synchronous && *current_kind != previous_kind.0
+            {
+                continue;
+            }
+
+            let Some(this_cell) = cell(entity) else {
+                continue;
+            };
+
+            let neighbors: Vec<Cell> = grid_index
+                .get_many(neighborhood.neighbors(position))
+                .filter_map(cell)
+                .collect();
+
+            let mut rng = tile_rng.for_tile(position, TileRngStream::CellularRule);
+            let new_kind = active_rule.0.next(&this_cell, &neighbors, &mut rng);
+
+            // Only flag the tile as changed if it actually transitioned, so that its age is preserved
+            if new_kind != *current_kind {
+                transitions.push((entity, new_kind));
+            }
+        }
+
+        transitions
+    };
+
+    for (entity, new_kind) in transitions {
+        world.entity_mut(entity).insert(new_kind);
+    }
+}
+
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
-struct FireSpread {
+pub struct FireSpread {
     /// The ratio of fire spread probability to the base fire susceptibility.
     /// This multiplier can be adjusted to control how quickly fire spreads.
     /// Generally this value should be significantly larger than 1.
-    spread_multiplier: f64,
+    pub spread_multiplier: f64,
+    /// An additional multiplier applied when fire spreads to a tile with a higher elevation.
+    ///
+    /// Fire climbs slopes much faster than it descends them,
+    /// as the flames preheat the fuel above them.
+    pub uphill_multiplier: f64,
+    /// The multiplier applied to the spread probability of fires at each [`FireIntensity`].
+    intensity_multipliers: HashMap<FireIntensity, f64>,
+    /// The multiplier applied to the spread probability of a [`PrescribedFire`],
+    /// which is lit under carefully chosen conditions to keep it from spreading.
+    prescribed_multiplier: f64,
+}
+
+impl FireSpread {
+    /// Returns the spread multiplier for a fire of the given intensity.
+    ///
+    /// If the intensity is not found, it returns 1.0.
+    pub fn intensity_multiplier(&self, intensity: &FireIntensity) -> f64 {
+        self.intensity_multipliers
+            .get(intensity)
+            .cloned()
+            .unwrap_or(1.0)
+    }
+
+    /// Returns the spread multiplier for a burning tile, depending on whether it is a [`PrescribedFire`].
+    pub fn prescribed_multiplier(&self, prescribed: bool) -> f64 {
+        if prescribed {
+            self.prescribed_multiplier
+        } else {
+            1.0
+        }
+    }
 }
 
 impl Default for FireSpread {
     fn default() -> Self {
+        let mut intensity_multipliers = HashMap::new();
+        intensity_multipliers.insert(FireIntensity::Smoldering, 0.3);
+        intensity_multipliers.insert(FireIntensity::Burning, 1.0);
+        intensity_multipliers.insert(FireIntensity::CrownFire, 3.0);
+
         Self {
             spread_multiplier: 1e3,
+            uphill_multiplier: 2.0,
+            intensity_multipliers,
+            prescribed_multiplier: 0.1,
+        }
+    }
+}
+
+/// Controls how burning tiles throw embers downwind, igniting tiles that are not adjacent to the fire.
+///
+/// Spotting lets fires jump over rivers and firebreaks that would otherwise stop them.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct EmberSpotting {
+    /// The probability that a burning tile throws an ember each tick.
+    ///
+    /// This is scaled by the fire's intensity multiplier and the [`Wind`] speed,
+    /// so crown fires in strong winds throw by far the most embers.
+    ember_probability: f64,
+    /// The shortest distance that an ember can travel, in tiles.
+    min_distance: u32,
+    /// The longest distance that an ember can travel, in tiles.
+    max_distance: u32,
+    /// The ratio of the probability that an ember ignites the tile it lands on to the base fire susceptibility.
+    /// Like [`FireSpread::spread_multiplier`], this should generally be significantly larger than 1.
+    ignition_multiplier: f64,
+}
+
+impl Default for EmberSpotting {
+    fn default() -> Self {
+        Self {
+            ember_probability: 0.02,
+            min_distance: 2,
+            max_distance: 5,
+            ignition_multiplier: 5e2,
+        }
+    }
+}
+
+/// How fiercely a [`TileKind::Fire`] tile is burning.
+///
+/// Fires start out smoldering, intensify while they have plenty of fuel left,
+/// and die back down as their fuel is consumed.
+#[derive(Reflect, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default, EnumIter)]
+pub enum FireIntensity {
+    #[default]
+    Smoldering,
+    Burning,
+    CrownFire,
+}
+
+impl FireIntensity {
+    /// Steps this intensity one level towards the target intensity.
+    fn step_towards(&self, target: FireIntensity) -> FireIntensity {
+        use FireIntensity::*;
+
+        match (*self).cmp(&target) {
+            core::cmp::Ordering::Less => match self {
+                Smoldering => Burning,
+                Burning | CrownFire => CrownFire,
+            },
+            core::cmp::Ordering::Greater => match self {
+                CrownFire => Burning,
+                Burning | Smoldering => Smoldering,
+            },
+            core::cmp::Ordering::Equal => *self,
+        }
+    }
+}
+
+/// The state of an active fire, present only on [`TileKind::Fire`] tiles.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq)]
+pub struct FireState {
+    /// How fiercely the fire is currently burning.
+    pub intensity: FireIntensity,
+    /// The most intense that the fire has burned so far.
+    pub peak_intensity: FireIntensity,
+    /// The amount of fuel that the fire has left to consume.
+    pub fuel: f32,
+    /// The kind of the tile before it caught fire.
+    pub fueled_by: TileKind,
+}
+
+/// A marker for fires that were deliberately lit as a prescribed burn, rather than started by accident.
+///
+/// Prescribed fires spread far more slowly than wildfires: see [`FireSpread`].
+/// Any fire that they do spread to is a wildfire.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PrescribedFire;
+
+/// The most recent disturbance that a tile has experienced, which can steer how it recovers.
+///
+/// This is only kept while the tile remains in the state the disturbance left it in:
+/// it is removed as soon as the tile changes kind again.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisturbanceContext {
+    /// The tile burned, leaving behind [`TileKind::Burned`] ground.
+    Fire {
+        /// The most intense that the fire burned.
+        peak_intensity: FireIntensity,
+        /// The kind of the tile before it caught fire.
+        burned_kind: TileKind,
+    },
+}
+
+impl DisturbanceContext {
+    /// Records the aftermath of the given fire.
+    fn after_fire(fire_state: &FireState) -> Self {
+        DisturbanceContext::Fire {
+            peak_intensity: fire_state.peak_intensity,
+            burned_kind: fire_state.fueled_by,
+        }
+    }
+}
+
+/// The number of ticks that a fire has left to burn, regardless of how much fuel remains.
+///
+/// Only fires fueled by a tile kind with an entry in [`BurnDurations`] have this:
+/// all other fires burn until their fuel runs out.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BurnDuration {
+    pub remaining_ticks: u32,
+}
+
+/// A probability distribution over the number of ticks that a fire burns for.
+#[derive(Reflect, Debug, Clone, Copy, PartialEq)]
+pub enum BurnDurationDistribution {
+    /// Every fire burns for exactly this many ticks.
+    Fixed(u32),
+    /// Fires burn for a number of ticks chosen uniformly between `min` and `max` (inclusive).
+    Uniform { min: u32, max: u32 },
+    /// Each tick, the fire has the same chance of burning out, so most fires are brief but a few linger.
+    Geometric {
+        /// The probability that the fire burns out on each tick, in the range of 0.0 to 1.0.
+        burnout_probability: f64,
+    },
+}
+
+impl BurnDurationDistribution {
+    /// Draws a number of ticks from this distribution.
+    fn sample(&self, rng: &mut dyn RngCore) -> u32 {
+        match *self {
+            BurnDurationDistribution::Fixed(ticks) => ticks,
+            BurnDurationDistribution::Uniform { min, max } => rng.random_range(min..=max.max(min)),
+            BurnDurationDistribution::Geometric {
+                burnout_probability,
+            } => {
+                let burnout_probability = burnout_probability.clamp(f64::EPSILON, 1.0);
+                if burnout_probability >= 1.0 {
+                    return 1;
+                }
+
+                // Inverse transform sampling: the number of trials until the first burnout
+                let roll: f64 = rng.random_range(f64::EPSILON..1.0);
+                (roll.ln() / (1.0 - burnout_probability).ln())
+                    .ceil()
+                    .min(u32::MAX as f64) as u32
+            }
+        }
+    }
+}
+
+/// Controls how long fires burn, based on the kind of tile that they are consuming.
+///
+/// Fires get one last tick to spread on the tick that their duration runs out.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct BurnDurations {
+    /// The distribution of burn durations for fires fueled by each kind of tile.
+    ///
+    /// Missing entries burn until their fuel runs out instead: see [`FireFuel`].
+    pub distributions: HashMap<TileKind, BurnDurationDistribution>,
+}
+
+impl BurnDurations {
+    /// Draws the [`BurnDuration`] of a fire consuming a tile of the given kind,
+    /// or returns `None` if its duration is determined by its fuel.
+    fn draw(&self, fueled_by: &TileKind, rng: &mut dyn RngCore) -> Option<BurnDuration> {
+        let distribution = self.distributions.get(fueled_by)?;
+
+        Some(BurnDuration {
+            remaining_ticks: distribution.sample(rng),
+        })
+    }
+}
+
+impl Default for BurnDurations {
+    fn default() -> Self {
+        use BurnDurationDistribution::*;
+
+        let mut distributions = HashMap::new();
+        // Grass fires flash through in a single tick
+        distributions.insert(TileKind::Meadow, Fixed(1));
+        distributions.insert(TileKind::InvasiveGrass, Fixed(1));
+        distributions.insert(TileKind::Shore, Fixed(1));
+        distributions.insert(TileKind::Shrubland, Uniform { min: 1, max: 3 });
+        distributions.insert(TileKind::ShadeIntolerantForest, Uniform { min: 3, max: 6 });
+        distributions.insert(TileKind::ShadeTolerantForest, Uniform { min: 5, max: 10 });
+        // Heavy, dry logs can smolder for a very long time
+        distributions.insert(
+            TileKind::DeadForest,
+            Geometric {
+                burnout_probability: 0.1,
+            },
+        );
+
+        Self { distributions }
+    }
+}
+
+/// The amount of dead and living plant matter that a tile has accumulated, ready to burn.
+///
+/// Fuel builds up a little every tick, up to a limit set by the kind of tile,
+/// and is used up when the tile catches fire.
+/// Tiles with more fuel are more likely to ignite, and burn for longer: see [`FireFuel`].
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Default)]
+pub struct FuelLoad(pub f32);
+
+/// Controls how fuel builds up on each kind of tile, and how it feeds fires.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct FireFuel {
+    /// The amount of fuel added to tiles of each kind every tick.
+    ///
+    /// Missing entries indicate that the tile kind does not accumulate fuel.
+    pub accumulation_rates: HashMap<TileKind, f32>,
+    /// The maximum amount of fuel that tiles of each kind can hold.
+    ///
+    /// Forests can hold far more fuel than meadows, allowing them to burn much more intensely.
+    /// Missing entries indicate that the tile kind cannot hold any fuel.
+    pub max_fuel_loads: HashMap<TileKind, f32>,
+    /// The fuel load at which a tile is half as likely to ignite as a tile with unlimited fuel.
+    ///
+    /// Smaller values make ignition less sensitive to the amount of fuel present.
+    pub ignition_half_saturation: f32,
+    /// The amount of fuel consumed by a fire each tick.
+    pub consumption_rate: f32,
+    /// The amount of remaining fuel above which a fire will intensify to [`FireIntensity::Burning`].
+    pub burning_threshold: f32,
+    /// The amount of remaining fuel above which a fire will intensify to [`FireIntensity::CrownFire`].
+    pub crown_fire_threshold: f32,
+}
+
+impl FireFuel {
+    /// Creates the [`FireState`] for a newly ignited tile with the given fuel load,
+    /// which was of the kind `fueled_by` before it caught fire.
+    ///
+    /// All of the tile's accumulated fuel is handed over to the fire.
+    fn ignite(&self, fuel_load: &FuelLoad, fueled_by: TileKind) -> FireState {
+        FireState {
+            intensity: FireIntensity::Smoldering,
+            peak_intensity: FireIntensity::Smoldering,
+            fuel: fuel_load.0,
+            fueled_by,
+        }
+    }
+
+    /// Returns the multiplier applied to the ignition probability of a tile with the given fuel load.
+    ///
+    /// This rises from 0.0 for tiles with no fuel towards 1.0 for heavily loaded tiles.
+    fn ignition_multiplier(&self, fuel_load: &FuelLoad) -> f64 {
+        let fuel = fuel_load.0.max(0.0);
+        (fuel / (fuel + self.ignition_half_saturation.max(f32::EPSILON))) as f64
+    }
+
+    /// The intensity that a fire with the given amount of remaining fuel will move towards.
+    fn target_intensity(&self, fuel: f32) -> FireIntensity {
+        if fuel > self.crown_fire_threshold {
+            FireIntensity::CrownFire
+        } else if fuel > self.burning_threshold {
+            FireIntensity::Burning
+        } else {
+            FireIntensity::Smoldering
+        }
+    }
+}
+
+impl Default for FireFuel {
+    fn default() -> Self {
+        let mut accumulation_rates = HashMap::new();
+        accumulation_rates.insert(TileKind::Meadow, 0.2);
+        accumulation_rates.insert(TileKind::Shrubland, 0.3);
+        accumulation_rates.insert(TileKind::ShadeIntolerantForest, 0.4);
+        accumulation_rates.insert(TileKind::ShadeTolerantForest, 0.5);
+        // Fallen branches and needles pile up quickly under dead trees
+        accumulation_rates.insert(TileKind::DeadForest, 0.8);
+        // Invasive grass cures early, building up a thick layer of dry thatch within a few ticks
+        accumulation_rates.insert(TileKind::InvasiveGrass, 1.5);
+        accumulation_rates.insert(TileKind::Shore, 0.05);
+
+        let mut max_fuel_loads = HashMap::new();
+        max_fuel_loads.insert(TileKind::Meadow, 1.0);
+        max_fuel_loads.insert(TileKind::Shrubland, 3.0);
+        max_fuel_loads.insert(TileKind::ShadeIntolerantForest, 6.0);
+        max_fuel_loads.insert(TileKind::ShadeTolerantForest, 10.0);
+        max_fuel_loads.insert(TileKind::DeadForest, 12.0);
+        max_fuel_loads.insert(TileKind::InvasiveGrass, 7.0);
+        max_fuel_loads.insert(TileKind::Shore, 0.3);
+
+        Self {
+            accumulation_rates,
+            max_fuel_loads,
+            ignition_half_saturation: 1.0,
+            consumption_rate: 1.0,
+            burning_threshold: 2.0,
+            crown_fire_threshold: 5.0,
+        }
+    }
+}
+
+/// Adds fuel to every tile based on its kind, up to the maximum that kind of tile can hold.
+///
+/// Tiles whose kind changed to one that holds less fuel (such as logged forest) lose the excess.
+#[hot]
+fn accumulate_fuel(mut tile_query: Query<(&TileKind, &mut FuelLoad)>, fire_fuel: Res<FireFuel>) {
+    for (tile_kind, mut fuel_load) in tile_query.iter_mut() {
+        let accumulation_rate = fire_fuel
+            .accumulation_rates
+            .get(tile_kind)
+            .cloned()
+            .unwrap_or(0.0);
+        let max_fuel_load = fire_fuel
+            .max_fuel_loads
+            .get(tile_kind)
+            .cloned()
+            .unwrap_or(0.0);
+
+        let new_fuel_load = (fuel_load.0 + accumulation_rate)
+            .min(max_fuel_load)
+            .max(0.0);
+        if new_fuel_load != fuel_load.0 {
+            fuel_load.0 = new_fuel_load;
+        }
+    }
+}
+
+/// Consumes the fuel of each fire, moving its intensity one step towards the level its remaining fuel supports.
+///
+/// Fires that have reached the end of their [`BurnDuration`] (or run out of fuel, if they have none) burn out,
+/// and fire state is removed from tiles that are no longer burning.
+/// Tiles left as burned ground record the fire in their [`DisturbanceContext`].
+#[hot]
+fn update_fire_intensity(
+    mut fire_query: Query<(
+        Entity,
+        &mut TileKind,
+        &mut FireState,
+        Option<&mut BurnDuration>,
+    )>,
+    fire_fuel: Res<FireFuel>,
+    mut commands: Commands,
+) {
+    for (entity, mut tile_kind, mut fire_state, burn_duration) in fire_query.iter_mut() {
+        // Fires can also be put out by rain or firefighters
+        if *tile_kind != TileKind::Fire {
+            let mut entity_commands = commands.entity(entity);
+            entity_commands.remove::<(FireState, BurnDuration, PrescribedFire)>();
+            if *tile_kind == TileKind::Burned {
+                entity_commands.insert(DisturbanceContext::after_fire(&fire_state));
+            }
+            continue;
+        }
+
+        // Fires get one last tick to spread after consuming the last of their fuel or time
+        let burned_out = match burn_duration {
+            Some(mut burn_duration) => {
+                let burned_out = burn_duration.remaining_ticks == 0;
+                burn_duration.remaining_ticks = burn_duration.remaining_ticks.saturating_sub(1);
+                burned_out
+            }
+            None => fire_state.fuel <= 0.0,
+        };
+
+        if burned_out {
+            *tile_kind = TileKind::Burned;
+            commands
+                .entity(entity)
+                .remove::<(FireState, BurnDuration, PrescribedFire)>()
+                .insert(DisturbanceContext::after_fire(&fire_state));
+            continue;
+        }
+
+        fire_state.fuel = (fire_state.fuel - fire_fuel.consumption_rate).max(0.0);
+        let target_intensity = fire_fuel.target_intensity(fire_state.fuel);
+        let new_intensity = fire_state.intensity.step_towards(target_intensity);
+        fire_state.intensity = new_intensity;
+        fire_state.peak_intensity = fire_state.peak_intensity.max(new_intensity);
+    }
+}
+
+/// Forgets the [`DisturbanceContext`] of tiles that have changed kind since they were disturbed.
+#[hot]
+fn clear_disturbance_context(
+    tile_query: Query<(Entity, Ref<TileKind>), With<DisturbanceContext>>,
+    mut commands: Commands,
+) {
+    for (entity, tile_kind) in tile_query.iter() {
+        // Burned tiles have only just been left behind by the fire that they record
+        if tile_kind.is_changed() && *tile_kind != TileKind::Burned {
+            commands.entity(entity).remove::<DisturbanceContext>();
         }
     }
 }
 
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
-struct FireSusceptibility {
+pub struct FireSusceptibility {
     /// The base fire susceptibility of the tile.
     /// This is a multiplier applied to each tile's fire susceptibility,
     /// and will scale all fire susceptibility values at once.
-    base_susceptibility: f64,
+    pub base_susceptibility: f64,
     /// The relative, unnormalized fire susceptibility of each tile kind.
-    tile_susceptibility: HashMap<TileKind, f64>,
+    pub tile_susceptibility: HashMap<TileKind, f64>,
+    /// How strongly moisture suppresses fire, in the range of 0.0 to 1.0.
+    ///
+    /// At 0.0, moisture has no effect on fire susceptibility,
+    /// while at 1.0, fully saturated tiles cannot catch fire at all.
+    pub moisture_dampening: f64,
 }
 
 impl FireSusceptibility {
     /// Returns the fire susceptibility of a tile kind,
-    /// scaled by the base susceptibility.
+    /// scaled by the base susceptibility and reduced by the tile's moisture.
     ///
     /// If the tile kind is not found, it returns 0.0.
-    pub fn get(&self, tile_kind: &TileKind) -> f64 {
+    pub fn get(&self, tile_kind: &TileKind, moisture: &Moisture) -> f64 {
+        let dryness = (1.0 - self.moisture_dampening * moisture.0 as f64).max(0.0);
+
         self.tile_susceptibility
             .get(tile_kind)
             .cloned()
             .unwrap_or(0.0)
             * self.base_susceptibility
+            * dryness
     }
 }
 
@@ -90,15 +764,62 @@ impl Default for FireSusceptibility {
         tile_susceptibility.insert(TileKind::ShadeTolerantForest, 1.0);
         tile_susceptibility.insert(TileKind::Water, 0.0); // Water cannot catch fire
         tile_susceptibility.insert(TileKind::Fire, 0.0); // Fire is already burning
+        tile_susceptibility.insert(TileKind::Burned, 0.0); // There is nothing left to burn
+        tile_susceptibility.insert(TileKind::DeadForest, 2.0); // Dry, dead timber is a tinderbox
+        tile_susceptibility.insert(TileKind::InvasiveGrass, 0.8); // Dense, dry grass that cures early
+        tile_susceptibility.insert(TileKind::Firebreak, 0.0); // There is no fuel to burn
+        tile_susceptibility.insert(TileKind::Shore, 0.005); // Sparse grass on damp sand
 
         Self {
             base_susceptibility: 1e-3,
             tile_susceptibility,
+            moisture_dampening: 0.8,
         }
     }
 }
 
-#[derive(Component, Reflect, PartialEq, Eq, Hash, Debug, Clone, Copy, EnumIter)]
+/// The fraction of water held in a tile's soil and vegetation, in the range of 0.0 to 1.0.
+///
+/// Moisture is seeded during map generation, with tiles near water starting out wetter,
+/// and is dried out by nearby fires.
+/// Wetter tiles are less likely to catch fire: see [`FireSusceptibility::get`].
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Moisture(pub f32);
+
+/// The normalized height of a tile, in the range of 0.0 to 1.0.
+///
+/// Elevation is generated from noise during map generation,
+/// and causes fire to spread more readily uphill.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Elevation(pub f32);
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct FireDrying {
+    /// The amount of moisture removed from each neighbor of a burning tile every tick.
+    drying_rate: f32,
+}
+
+impl Default for FireDrying {
+    fn default() -> Self {
+        Self { drying_rate: 0.1 }
+    }
+}
+
+#[derive(
+    Component,
+    Reflect,
+    PartialEq,
+    Eq,
+    Hash,
+    Debug,
+    Clone,
+    Copy,
+    EnumIter,
+    EnumCount,
+    Serialize,
+    Deserialize,
+)]
 pub enum TileKind {
     Meadow,
     Shrubland,
@@ -106,64 +827,695 @@ pub enum TileKind {
     ShadeTolerantForest,
     Water,
     Fire,
+    /// Ash and charred ground left behind by a fire, which slowly recovers to meadow.
+    Burned,
+    /// A stand of trees killed by an insect outbreak, which is extremely flammable.
+    DeadForest,
+    /// An aggressive, fire-adapted invasive grass.
+    ///
+    /// It spreads quickly, burns hot, and is the first to recolonize burned ground,
+    /// creating a feedback loop where fire promotes invasion and invasion promotes fire.
+    InvasiveGrass,
+    /// A strip of cleared ground, painted by the user to stop fires in their tracks.
+    ///
+    /// Firebreaks cannot burn, and are maintained so that nothing grows back.
+    Firebreak,
+    /// Sandy ground along the edge of lakes and rivers, with only a sparse cover of dune grasses.
+    ///
+    /// Shores rarely burn, and are only very slowly stabilized by coastal scrub.
+    Shore,
+    /// A live cell in the Game of Life: see [`Ruleset::GameOfLife`](crate::rulesets::Ruleset::GameOfLife).
+    Alive,
+    /// A dead cell in the Game of Life.
+    Dead,
+    /// Bare ground in Wireworld, which never changes: see [`Ruleset::Wireworld`](crate::rulesets::Ruleset::Wireworld).
+    ///
+    /// This is also used for the empty ground that trees grow on in the Drossel-Schwabl model.
+    Empty,
+    /// A wire in Wireworld, which electrons can travel along.
+    Conductor,
+    /// The front of an electron travelling along a Wireworld wire.
+    ElectronHead,
+    /// The back of an electron travelling along a Wireworld wire, which stops it from reversing.
+    ElectronTail,
+    /// An unflipped tile for Langton's ant: see [`Ruleset::LangtonsAnt`](crate::rulesets::Ruleset::LangtonsAnt).
+    White,
+    /// A tile that has been flipped by Langton's ant.
+    Black,
+    /// A tree in the Drossel-Schwabl forest-fire model: see [`Ruleset::DrosselSchwabl`](crate::rulesets::Ruleset::DrosselSchwabl).
+    Tree,
+    /// One of the three species in the rock-paper-scissors automaton: see [`Ruleset::RockPaperScissors`](crate::rulesets::Ruleset::RockPaperScissors).
+    ///
+    /// Rock beats scissors, scissors beats paper, and paper beats rock.
+    Rock,
+    /// Paper, which beats rock.
+    Paper,
+    /// Scissors, which beat paper.
+    Scissors,
+}
+
+/// Controls how new plants spread from one tile to its neighbors.
+///
+/// Shrubs and trees can't appear out of nowhere: their seeds need to come from somewhere nearby.
+/// Transitions into a dispersal-limited kind are heavily penalized
+/// unless at least one cardinal neighbor is already of that kind,
+/// which produces patchy, realistic expansion rather than uniform noise.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct SeedDispersal {
+    /// The tile kinds that can only spread from neighboring tiles of the same kind.
+    pub dispersal_limited: Vec<TileKind>,
+    /// The multiplier applied to the probability of transitioning into a dispersal-limited kind
+    /// when no cardinal neighbor is already of that kind.
+    ///
+    /// At 0.0, these transitions are impossible without a neighboring seed source.
+    pub isolation_multiplier: f32,
+}
+
+impl SeedDispersal {
+    /// Returns the multiplier for transitioning into the target kind,
+    /// given the kinds of the tile's neighbors.
+    fn multiplier(&self, target_kind: &TileKind, neighbor_kinds: &[TileKind]) -> f32 {
+        if self.dispersal_limited.contains(target_kind) && !neighbor_kinds.contains(target_kind) {
+            self.isolation_multiplier
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Default for SeedDispersal {
+    fn default() -> Self {
+        Self {
+            dispersal_limited: vec![
+                TileKind::Shrubland,
+                TileKind::ShadeIntolerantForest,
+                TileKind::ShadeTolerantForest,
+                TileKind::InvasiveGrass,
+            ],
+            isolation_multiplier: 0.05,
+        }
+    }
+}
+
+/// Controls how the shade cast by nearby forest shapes which trees can establish.
+///
+/// Shade is measured as the fraction of a tile's eight surrounding tiles that are forested.
+/// Shade-tolerant trees can only establish under a closed canopy,
+/// while shade-intolerant pioneers struggle to regenerate beneath one.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct ShadeSuccession {
+    /// The tile kinds that cast shade on their neighbors.
+    pub canopy_kinds: Vec<TileKind>,
+    /// The minimum fraction of shaded neighbors needed for shade-tolerant forest to establish.
+    pub shade_tolerant_threshold: f32,
+    /// The multiplier applied to the probability of transitioning into shade-tolerant forest
+    /// when the tile is below the [`shade_tolerant_threshold`](Self::shade_tolerant_threshold).
+    pub exposed_tolerant_multiplier: f32,
+    /// How strongly shade suppresses shade-intolerant forest, in the range of 0.0 to 1.0.
+    ///
+    /// Under a completely closed canopy, the probability of transitioning into shade-intolerant forest
+    /// is reduced by this fraction.
+    pub intolerant_shade_penalty: f32,
+}
+
+impl ShadeSuccession {
+    /// Returns the fraction of the Moore neighbors of the given tile that are part of the canopy.
+    ///
+    /// Neighbors outside of the map count as open ground.
+    fn canopy_density(&self, cell: &Cell) -> f32 {
+        let Some(position) = cell.get::<Position>() else {
+            return 0.0;
+        };
+
+        let world = cell.world();
+        let tile_index = world.resource::<TileIndex>();
+        let update_mode = world.resource::<SimulationUpdateMode>();
+
+        let shaded_neighbors = tile_index
+            .moore_neighbors(position)
+            .filter_map(|(_, entity)| {
+                Some(update_mode.read(
+                    world.get::<TileKind>(entity)?,
+                    world.get::<PreviousTileKind>(entity)?,
+                ))
+            })
+            .filter(|neighbor_kind| self.canopy_kinds.contains(neighbor_kind))
+            .count();
+
+        shaded_neighbors as f32 / 8.0
+    }
+
+    /// Returns the multiplier for transitioning into the target kind, given the local canopy density.
+    fn multiplier(&self, target_kind: &TileKind, canopy_density: f32) -> f32 {
+        match target_kind {
+            TileKind::ShadeTolerantForest if canopy_density < self.shade_tolerant_threshold => {
+                self.exposed_tolerant_multiplier
+            }
+            TileKind::ShadeIntolerantForest => {
+                1.0 - self.intolerant_shade_penalty.clamp(0.0, 1.0) * canopy_density
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+impl Default for ShadeSuccession {
+    fn default() -> Self {
+        Self {
+            canopy_kinds: vec![
+                TileKind::ShadeIntolerantForest,
+                TileKind::ShadeTolerantForest,
+            ],
+            shade_tolerant_threshold: 0.5,
+            exposed_tolerant_multiplier: 0.0,
+            intolerant_shade_penalty: 0.8,
+        }
+    }
+}
+
+/// The built-in [`CellularRule`]: forest succession, with fire spreading between neighboring tiles.
+///
+/// Tiles catch fire from their burning neighbors based on their fire susceptibility, fuel, moisture and slope,
+/// and otherwise undergo succession according to the [`TransitionProbabilities`],
+/// shaped by soil fertility, seed dispersal and the shade cast by neighboring forest.
+/// Its parameters are read from the resources in this module, so they can be tweaked at runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ForestRule;
+
+impl CellularRule for ForestRule {
+    fn next(&self, cell: &Cell, neighbors: &[Cell], rng: &mut dyn RngCore) -> TileKind {
+        if self.catches_fire(cell, neighbors, rng) {
+            TileKind::Fire
+        } else {
+            self.undisturbed_succession(cell, neighbors, rng)
+                .unwrap_or(cell.kind)
+        }
+    }
+}
+
+impl ForestRule {
+    /// Rolls for fire spreading into this tile from each of its burning neighbors.
+    fn catches_fire(&self, cell: &Cell, neighbors: &[Cell], rng: &mut dyn RngCore) -> bool {
+        let (Some(position), Some(moisture), Some(elevation), Some(fuel_load)) = (
+            cell.get::<Position>(),
+            cell.get::<Moisture>(),
+            cell.get::<Elevation>(),
+            cell.get::<FuelLoad>(),
+        ) else {
+            return false;
+        };
+
+        let fire_susceptibility = cell.resource::<FireSusceptibility>();
+        let fire_spread = cell.resource::<FireSpread>();
+        let fire_fuel = cell.resource::<FireFuel>();
+
+        let base_probability = fire_susceptibility.get(&cell.kind, moisture)
+            * fire_fuel.ignition_multiplier(fuel_load)
+            * fire_spread.spread_multiplier
+            * ClimateConditions::fire_multiplier_in(cell.world(), position);
+
+        neighbors
+            .iter()
+            .filter(|neighbor| neighbor.kind == TileKind::Fire)
+            .any(|neighbor| {
+                let intensity = neighbor
+                    .get::<FireState>()
+                    .map(|fire_state| fire_state.intensity)
+                    .unwrap_or_default();
+
+                // Fire climbs slopes, so tiles above their burning neighbor are more at risk
+                let slope_multiplier = if neighbor
+                    .get::<Elevation>()
+                    .is_some_and(|neighbor_elevation| elevation > neighbor_elevation)
+                {
+                    fire_spread.uphill_multiplier
+                } else {
+                    1.0
+                };
+
+                // PERF: like usual, generating random numbers in batch is much faster
+                let fire_roll = rng.random_range(0.0..1.0);
+                let prescribed = neighbor.get::<PrescribedFire>().is_some();
+
+                fire_roll
+                    < base_probability
+                        * slope_multiplier
+                        * fire_spread.intensity_multiplier(&intensity)
+                        * fire_spread.prescribed_multiplier(prescribed)
+            })
+    }
+
+    /// Chooses the next kind of this tile in the absence of a disturbance.
+    ///
+    /// Returns `None` if the tile's position, age or fertility is unknown, or it has no transitions.
+    /// Tiles whose kind is set by their [`Composition`] do not undergo succession here.
+    fn undisturbed_succession(
+        &self,
+        cell: &Cell,
+        neighbors: &[Cell],
+        rng: &mut dyn RngCore,
+    ) -> Option<TileKind> {
+        if cell.get::<Composition>().is_some() && Composition::DOMINANT_KINDS.contains(&cell.kind) {
+            return None;
+        }
+
+        let position = cell.get::<Position>()?;
+        let age = cell.get::<Age>()?;
+        let fertility = cell.get::<Fertility>()?;
+
+        let transition_probabilities = cell.resource::<TransitionProbabilities>();
+        let seed_dispersal = cell.resource::<SeedDispersal>();
+        let soil_fertility = cell.resource::<SoilFertility>();
+        let shade_succession = cell.resource::<ShadeSuccession>();
+
+        let neighbor_kinds: Vec<TileKind> =
+            neighbors.iter().map(|neighbor| neighbor.kind).collect();
+        let growth_multiplier = ClimateConditions::growth_multiplier_in(cell.world(), position)
+            * soil_fertility.growth_multiplier(fertility);
+        let canopy_density = shade_succession.canopy_density(cell);
+        // Burning out isn't growth: the climate only changes how readily fires start and spread
+        let burning = cell.kind == TileKind::Fire;
+
+        transition_probabilities.choose_transition(
+            &cell.kind,
+            age,
+            cell.get::<DisturbanceContext>(),
+            |target_kind| {
+                if burning {
+                    return 1.0;
+                }
+
+                growth_multiplier
+                    * seed_dispersal.multiplier(target_kind, &neighbor_kinds)
+                    * shade_succession.multiplier(target_kind, canopy_density)
+            },
+            rng,
+        )
+    }
+}
+
+/// The nutrient richness of a tile's soil, in the range of 0.0 to 1.0.
+///
+/// Fertility is seeded from noise during map generation.
+/// Ash from fires briefly enriches the soil, while mature forests slowly deplete it.
+/// More fertile tiles undergo succession more quickly.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Fertility(pub f32);
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct SoilFertility {
+    /// The change in fertility each tick for tiles of each kind.
+    ///
+    /// Positive values enrich the soil, while negative values deplete it.
+    /// Missing entries leave the fertility of that kind unchanged.
+    pub fertility_change: HashMap<TileKind, f32>,
+    /// How strongly fertility affects the rate of succession, in the range of 0.0 to 1.0.
+    ///
+    /// Succession is slowed by this fraction on completely barren soil,
+    /// and sped up by this fraction on perfectly fertile soil.
+    pub growth_scaling: f32,
+}
+
+impl SoilFertility {
+    /// Returns the multiplier applied to succession probabilities for a tile with the given fertility.
+    fn growth_multiplier(&self, fertility: &Fertility) -> f32 {
+        1.0 + self.growth_scaling * (2.0 * fertility.0 - 1.0)
+    }
+}
+
+impl Default for SoilFertility {
+    fn default() -> Self {
+        let mut fertility_change = HashMap::new();
+        // Ash returns the nutrients locked up in the vegetation to the soil
+        fertility_change.insert(TileKind::Fire, 0.15);
+        // Grasses slowly build up the soil
+        fertility_change.insert(TileKind::Meadow, 0.002);
+        fertility_change.insert(TileKind::ShadeIntolerantForest, -0.003);
+        fertility_change.insert(TileKind::ShadeTolerantForest, -0.005);
+
+        Self {
+            fertility_change,
+            growth_scaling: 0.5,
+        }
+    }
 }
 
 #[hot]
-fn undisturbed_succession(
-    mut rng: GlobalEntropy<WyRand>,
-    transition_probabilities: Res<TransitionProbabilities>,
-    mut succession_query: Query<&mut TileKind>,
+fn update_fertility(
+    mut tile_query: Query<(&TileKind, &mut Fertility)>,
+    soil_fertility: Res<SoilFertility>,
 ) {
-    for mut tile_kind in succession_query.iter_mut() {
-        if let Some(new_kind) = transition_probabilities.choose_transition(&*tile_kind, &mut rng) {
-            *tile_kind = new_kind;
+    for (tile_kind, mut fertility) in tile_query.iter_mut() {
+        if let Some(change) = soil_fertility.fertility_change.get(tile_kind) {
+            fertility.0 = (fertility.0 + change).clamp(0.0, 1.0);
         }
     }
 }
 
+/// The number of simulation ticks that a tile has spent as its current [`TileKind`].
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Age(pub u32);
+
+/// Ages every tile by one tick, resetting the age of any tile whose kind changed since the last tick.
 #[hot]
-fn start_fires(
-    mut tile_query: Query<&mut TileKind>,
+fn age_tiles(mut tile_query: Query<(&mut Age, Ref<TileKind>)>) {
+    for (mut age, tile_kind) in tile_query.iter_mut() {
+        if tile_kind.is_changed() {
+            age.0 = 0;
+        } else {
+            age.0 += 1;
+        }
+    }
+}
+
+/// Controls how often lightning strikes the map, which is the only source of new fires.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Lightning {
+    /// The number of lightning strikes that hit random positions on the map every tick.
+    pub strikes_per_tick: u32,
+    /// The ratio of the probability that a lightning strike ignites a tile to the base fire susceptibility.
+    /// Like [`FireSpread::spread_multiplier`], this should generally be significantly larger than 1.
+    pub ignition_multiplier: f64,
+}
+
+impl Default for Lightning {
+    fn default() -> Self {
+        Self {
+            strikes_per_tick: 5,
+            ignition_multiplier: 1e3,
+        }
+    }
+}
+
+/// An event that is sent whenever lightning strikes a tile, whether or not it started a fire.
+#[derive(Event, Debug)]
+pub struct LightningStruck {
+    /// The position of the tile that was struck.
+    pub position: Position,
+    /// The tile entity that was struck.
+    pub entity: Entity,
+    /// Whether or not the strike set the tile on fire.
+    pub ignited: bool,
+}
+
+#[hot]
+#[allow(clippy::too_many_arguments)]
+fn lightning_strikes(
+    mut tile_query: Query<(&mut TileKind, &PreviousTileKind, &Moisture, &mut FuelLoad)>,
     fire_susceptibility: Res<FireSusceptibility>,
-    mut rng: GlobalEntropy<WyRand>,
+    fire_fuel: Res<FireFuel>,
+    lightning: Res<Lightning>,
+    burn_durations: Res<BurnDurations>,
+    update_mode: Res<SimulationUpdateMode>,
+    climate: ClimateConditions,
+    map_size: Res<GeneratedMapSize>,
+    tile_index: Res<TileIndex>,
+    tile_rng: Res<TileRng>,
+    mut event_writer: EventWriter<LightningStruck>,
+    mut commands: Commands,
 ) {
-    for mut tile_kind in tile_query.iter_mut() {
+    if map_size.width <= 0 || map_size.height <= 0 {
+        return;
+    }
+
+    let mut rng = tile_rng.for_map(TileRngStream::Lightning);
+
+    for _ in 0..lightning.strikes_per_tick {
+        let position = Position {
+            x: rng.random_range(0..map_size.width),
+            y: rng.random_range(0..map_size.height),
+        };
+
+        let Some(entity) = tile_index.get(&position) else {
+            continue;
+        };
+
+        let Ok((mut tile_kind, previous_kind, moisture, mut fuel_load)) =
+            tile_query.get_mut(entity)
+        else {
+            continue;
+        };
+
+        let struck_kind = update_mode.read(&tile_kind, previous_kind);
+        let ignition_probability = fire_susceptibility.get(&struck_kind, moisture)
+            * fire_fuel.ignition_multiplier(&fuel_load)
+            * lightning.ignition_multiplier
+            * climate.fire_multiplier(&position);
+
         let fire_roll = rng.random_range(0.0..1.0);
-        if fire_roll < fire_susceptibility.get(&*tile_kind) {
-            // If the tile rolled a new fire, set it to Fire state
+        let ignited = fire_roll < ignition_probability;
+        if ignited {
+            // If the strike started a new fire, set it to Fire state
+            commands
+                .entity(entity)
+                .insert(fire_fuel.ignite(&fuel_load, struck_kind));
             tile_kind.set_if_neq(TileKind::Fire);
+            fuel_load.0 = 0.0;
+
+            if let Some(burn_duration) = burn_durations.draw(&struck_kind, &mut rng) {
+                commands.entity(entity).insert(burn_duration);
+            }
         }
+
+        event_writer.write(LightningStruck {
+            position,
+            entity,
+            ignited,
+        });
     }
 }
 
+/// Controls rainstorms, which extinguish fires and refill moisture across a noise-shaped region of the map.
+///
+/// Rainstorms are started by the [`DisturbanceScheduler`](crate::disturbances::DisturbanceScheduler).
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Rainfall {
+    /// The approximate fraction of the map covered by each storm, in the range of 0.0 to 1.0.
+    coverage: f32,
+    /// The typical size of the rain clouds, in tiles.
+    cloud_period: f32,
+    /// The amount of moisture added to each tile that is rained on.
+    moisture_gain: f32,
+}
+
+impl Default for Rainfall {
+    fn default() -> Self {
+        Self {
+            coverage: 0.3,
+            cloud_period: 8.0,
+            moisture_gain: 0.3,
+        }
+    }
+}
+
+/// An event that is sent whenever a rainstorm begins.
+#[derive(Event, Debug)]
+pub struct RainStarted {
+    /// The tile entities that were rained on.
+    pub tiles: Vec<Entity>,
+}
+
+#[hot]
+pub fn rainfall(
+    mut tile_query: Query<(
+        Entity,
+        &Position,
+        &mut TileKind,
+        &PreviousTileKind,
+        &mut Moisture,
+    )>,
+    rainfall: Res<Rainfall>,
+    update_mode: Res<SimulationUpdateMode>,
+    tile_rng: Res<TileRng>,
+    mut event_reader: EventReader<DisturbanceStarted>,
+    mut event_writer: EventWriter<RainStarted>,
+) {
+    use noiz::prelude::*;
+
+    if !event_reader
+        .read()
+        .any(|event| event.kind == DisturbanceKind::HeavyRain)
+    {
+        return;
+    }
+
+    // Each storm gets its own freshly seeded noise, so that the clouds land somewhere new every time
+    let mut noise = Noise::<(
+        MixCellGradients<OrthoGrid, Smoothstep, QuickGradients>,
+        SNormToUNorm,
+    )>::default();
+    noise.set_period(rainfall.cloud_period);
+    noise.set_seed(tile_rng.for_map(TileRngStream::Rainfall).next_u32());
+
+    let mut rained_on = Vec::new();
+
+    for (entity, &position, mut tile_kind, previous_kind, mut moisture) in tile_query.iter_mut() {
+        let converted_position = Vec2::new(position.x as f32, position.y as f32);
+        let noise_value: f32 = noise.sample(converted_position);
+
+        if noise_value < rainfall.coverage {
+            // Rain puts out fires, leaving behind burned ground
+            if update_mode.read(&tile_kind, previous_kind) == TileKind::Fire {
+                *tile_kind = TileKind::Burned;
+            }
+
+            moisture.0 = (moisture.0 + rainfall.moisture_gain).min(1.0);
+            rained_on.push(entity);
+        }
+    }
+
+    info!("A rainstorm has soaked {} tiles.", rained_on.len());
+    event_writer.write(RainStarted { tiles: rained_on });
+}
+
 #[hot]
-fn spread_fires(
-    tile_query: Query<(&TileKind, &Position)>,
+fn dry_out_near_fires(
+    tile_query: Query<(&TileKind, &PreviousTileKind, &Position)>,
+    mut moisture_query: Query<&mut Moisture>,
+    fire_drying: Res<FireDrying>,
+    update_mode: Res<SimulationUpdateMode>,
+    tile_index: Res<TileIndex>,
+) {
+    for (tile, previous_kind, position) in tile_query.iter() {
+        if update_mode.read(tile, previous_kind) == TileKind::Fire {
+            for neighbor in position.cardinal_neighbors() {
+                if let Some(neighbor_entity) = tile_index.get(&neighbor)
+                    && let Ok(mut moisture) = moisture_query.get_mut(neighbor_entity)
+                {
+                    moisture.0 = (moisture.0 - fire_drying.drying_rate).max(0.0);
+                }
+            }
+        }
+    }
+}
+
+/// Throws embers downwind from burning tiles, which can ignite tiles well beyond the fire front.
+///
+/// Spread between neighboring tiles is handled by the [`ForestRule`].
+#[hot]
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn spot_fires(
+    tile_query: Query<(
+        &TileKind,
+        &PreviousTileKind,
+        &Position,
+        &Moisture,
+        &FuelLoad,
+        Option<&FireState>,
+        Has<PrescribedFire>,
+    )>,
     fire_susceptibility: Res<FireSusceptibility>,
     fire_spread: Res<FireSpread>,
-    mut rng: GlobalEntropy<WyRand>,
+    fire_fuel: Res<FireFuel>,
+    ember_spotting: Res<EmberSpotting>,
+    burn_durations: Res<BurnDurations>,
+    wind: Res<Wind>,
+    climate: ClimateConditions,
+    tile_rng: Res<TileRng>,
     tile_index: Res<TileIndex>,
+    update_mode: Res<SimulationUpdateMode>,
     mut commands: Commands,
 ) {
-    for (tile, position) in tile_query.iter() {
-        if *tile == TileKind::Fire {
-            for neighbors in position.cardinal_neighbors() {
-                if let Some(neighbor_entity) = tile_index.get(&neighbors) {
-                    if let Ok((neighbor_kind, _neighbor_position)) = tile_query.get(neighbor_entity)
-                    {
-                        // Check if the neighboring tile can catch fire
-                        // PERF: like usual, generating random numbers in batch is much faster
-                        let fire_roll = rng.random_range(0.0..1.0);
-                        if fire_roll
-                            < fire_susceptibility.get(neighbor_kind) * fire_spread.spread_multiplier
-                        {
-                            // If the roll passes, set the neighboring tile to Fire state
-                            // We use `Commands` here to avoid pain with mutable borrow rules,
-                            // but also to ensure that the iteration order of `tile_query` does not matter.
-                            commands.entity(neighbor_entity).insert(TileKind::Fire);
-                        }
-                    }
-                }
+    for (tile, previous_kind, position, _moisture, _fuel_load, fire_state, prescribed) in
+        tile_query.iter()
+    {
+        if update_mode.read(tile, previous_kind) != TileKind::Fire {
+            continue;
+        }
+
+        let mut rng = tile_rng.for_tile(position, TileRngStream::SpotFires);
+
+        let intensity = fire_state
+            .map(|fire_state| fire_state.intensity)
+            .unwrap_or_default();
+        let intensity_multiplier = fire_spread.intensity_multiplier(&intensity)
+            * fire_spread.prescribed_multiplier(prescribed);
+
+        let ember_probability =
+            ember_spotting.ember_probability * intensity_multiplier * wind.speed.max(0.0) as f64;
+        if !rng.random_bool(ember_probability.clamp(0.0, 1.0)) {
+            continue;
+        }
+
+        let max_distance = ember_spotting.max_distance.max(ember_spotting.min_distance);
+        let distance = rng.random_range(ember_spotting.min_distance..=max_distance);
+        let landing_position = wind.downwind(position, distance as f32);
+
+        let Some(landing_entity) = tile_index.get(&landing_position) else {
+            continue;
+        };
+
+        let Ok((
+            landing_kind,
+            landing_previous_kind,
+            _landing_position,
+            landing_moisture,
+            landing_fuel_load,
+            _landing_fire_state,
+            _landing_prescribed,
+        )) = tile_query.get(landing_entity)
+        else {
+            continue;
+        };
+
+        let landing_kind = update_mode.read(landing_kind, landing_previous_kind);
+        let fire_roll = rng.random_range(0.0..1.0);
+        if fire_roll
+            < fire_susceptibility.get(&landing_kind, landing_moisture)
+                * fire_fuel.ignition_multiplier(landing_fuel_load)
+                * ember_spotting.ignition_multiplier
+                * climate.fire_multiplier(&landing_position)
+        {
+            // We use `Commands` so that embers can't set off a chain of spot fires within a single tick.
+            // The tile's fuel is handed over to the fire, leaving none behind
+            commands.entity(landing_entity).insert((
+                TileKind::Fire,
+                fire_fuel.ignite(landing_fuel_load, landing_kind),
+                FuelLoad(0.0),
+            ));
+
+            if let Some(burn_duration) = burn_durations.draw(&landing_kind, &mut rng) {
+                commands.entity(landing_entity).insert(burn_duration);
+            }
+        }
+    }
+}
+
+/// Sets up the [`FireState`] of tiles that were set on fire by the [`ActiveRule`] (or painted as fire),
+/// handing their accumulated fuel over to the fire.
+#[hot]
+#[allow(clippy::type_complexity)]
+fn ignite_new_fires(
+    mut tile_query: Query<
+        (
+            Entity,
+            &Position,
+            &TileKind,
+            &PreviousTileKind,
+            &mut FuelLoad,
+        ),
+        (Changed<TileKind>, Without<FireState>),
+    >,
+    fire_fuel: Res<FireFuel>,
+    burn_durations: Res<BurnDurations>,
+    tile_rng: Res<TileRng>,
+    mut commands: Commands,
+) {
+    for (entity, position, tile_kind, previous_kind, mut fuel_load) in tile_query.iter_mut() {
+        if *tile_kind == TileKind::Fire {
+            commands
+                .entity(entity)
+                .insert(fire_fuel.ignite(&fuel_load, previous_kind.0));
+            fuel_load.0 = 0.0;
+
+            let mut rng = tile_rng.for_tile(position, TileRngStream::BurnDuration);
+            if let Some(burn_duration) = burn_durations.draw(&previous_kind.0, &mut rng) {
+                commands.entity(entity).insert(burn_duration);
             }
         }
     }
@@ -171,12 +1523,32 @@ fn spread_fires(
 
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
-struct TransitionProbabilities {
+pub struct TransitionProbabilities {
     /// The probability of transitioning to each other state from this state in the absence of another disturbance.
     ///
     /// The key is the current state, and the value is a vector of tuples,
     /// where each tuple contains a possible transition state and its associated unnormalized probability.
-    probabilities: HashMap<TileKind, Vec<(TileKind, f32)>>,
+    pub probabilities: HashMap<TileKind, Vec<(TileKind, f32)>>,
+    /// The minimum [`Age`] that a tile must reach before it can make a given transition.
+    ///
+    /// The key is a tuple of the current state and the target state.
+    /// Missing entries indicate that the transition can occur at any age.
+    pub minimum_ages: HashMap<(TileKind, TileKind), u32>,
+    /// The recovery of burned ground after fires of each peak intensity,
+    /// which replaces the undisturbed transitions of [`TileKind::Burned`].
+    ///
+    /// Missing entries use the undisturbed transitions.
+    pub post_fire_pathways: HashMap<FireIntensity, PostFirePathway>,
+}
+
+/// How burned ground recovers after a fire of a particular intensity.
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct PostFirePathway {
+    /// The unnormalized probability of transitioning to each state, as in [`TransitionProbabilities::probabilities`].
+    pub probabilities: Vec<(TileKind, f32)>,
+    /// The unnormalized probability that the vegetation survived the fire,
+    /// and the tile returns to the kind that it was before it burned.
+    pub survival: f32,
 }
 
 impl TransitionProbabilities {
@@ -184,14 +1556,64 @@ impl TransitionProbabilities {
         self.probabilities.get(tile_kind)
     }
 
+    /// Returns the minimum age required to transition from one state to another.
+    ///
+    /// If no minimum age is set, it returns 0.
+    fn minimum_age(&self, from: &TileKind, to: &TileKind) -> u32 {
+        self.minimum_ages.get(&(*from, *to)).cloned().unwrap_or(0)
+    }
+
+    /// Returns the possible transitions for a tile of the given kind, taking its most recent disturbance into account.
+    fn options(
+        &self,
+        tile_kind: &TileKind,
+        disturbance: Option<&DisturbanceContext>,
+    ) -> Option<Cow<'_, [(TileKind, f32)]>> {
+        if *tile_kind == TileKind::Burned
+            && let Some(DisturbanceContext::Fire {
+                peak_intensity,
+                burned_kind,
+            }) = disturbance
+            && let Some(pathway) = self.post_fire_pathways.get(peak_intensity)
+        {
+            let mut options = pathway.probabilities.clone();
+            // Fires painted straight onto the map don't know what they burned, so nothing can survive them
+            if *burned_kind != TileKind::Fire {
+                options.push((*burned_kind, pathway.survival));
+            }
+            return Some(Cow::Owned(options));
+        }
+
+        self.get(tile_kind)
+            .map(|options| Cow::Borrowed(options.as_slice()))
+    }
+
+    /// Randomly selects the next state for a tile of the given kind and age.
+    ///
+    /// The tile's most recent `disturbance`, if any, can replace its usual transitions:
+    /// see [`TransitionProbabilities::post_fire_pathways`].
+    /// Transitions whose minimum age has not yet been reached are excluded.
+    /// The `growth_multiplier` returns a multiplier for the weight of each transition to a different state,
+    /// allowing outside factors to speed up or slow down particular transitions.
     fn choose_transition(
         &self,
         tile_kind: &TileKind,
-        mut rng: &mut Entropy<WyRand>,
+        age: &Age,
+        disturbance: Option<&DisturbanceContext>,
+        growth_multiplier: impl Fn(&TileKind) -> f32,
+        rng: &mut dyn RngCore,
     ) -> Option<TileKind> {
-        let weighted_options = self.get(tile_kind)?;
+        let weighted_options = self.options(tile_kind, disturbance)?;
         let selection = weighted_options
-            .choose_weighted(&mut rng, |item| item.1)
+            .choose_weighted(rng, |item| {
+                if item.0 == *tile_kind {
+                    item.1
+                } else if age.0 < self.minimum_age(tile_kind, &item.0) {
+                    0.0
+                } else {
+                    item.1 * growth_multiplier(&item.0)
+                }
+            })
             .ok()?;
 
         Some(selection.0)
@@ -201,10 +1623,37 @@ impl TransitionProbabilities {
 impl Default for TransitionProbabilities {
     fn default() -> Self {
         let mut probabilities = HashMap::new();
+        let mut minimum_ages = HashMap::new();
         for tile_kind in TileKind::iter() {
             probabilities.insert(tile_kind, tile_kind.undisturbed_transition_probabilities());
+            for (target_kind, minimum_age) in tile_kind.minimum_transition_ages() {
+                minimum_ages.insert((tile_kind, target_kind), minimum_age);
+            }
+        }
+
+        let mut post_fire_pathways = HashMap::new();
+        // Light ground fires leave the roots and many of the trees alive
+        post_fire_pathways.insert(
+            FireIntensity::Smoldering,
+            PostFirePathway {
+                probabilities: vec![(TileKind::Burned, 1.0), (TileKind::Shrubland, 1.0)],
+                survival: 1.0,
+            },
+        );
+        // Crown fires kill everything, leaving the site to be recolonized by grasses
+        post_fire_pathways.insert(
+            FireIntensity::CrownFire,
+            PostFirePathway {
+                probabilities: vec![(TileKind::Burned, 1.0), (TileKind::Meadow, 0.5)],
+                survival: 0.0,
+            },
+        );
+
+        Self {
+            probabilities,
+            minimum_ages,
+            post_fire_pathways,
         }
-        Self { probabilities }
     }
 }
 
@@ -221,7 +1670,7 @@ impl TileKind {
 
         match self {
             TileKind::Meadow => {
-                vec![(Meadow, 1.0), (Shrubland, 0.5)]
+                vec![(Meadow, 1.0), (Shrubland, 0.5), (InvasiveGrass, 0.8)]
             }
             TileKind::Shrubland => {
                 vec![(Shrubland, 1.0), (ShadeIntolerantForest, 0.5)]
@@ -235,10 +1684,100 @@ impl TileKind {
             TileKind::Water => {
                 vec![(Water, 1.0)]
             }
-            // These values control how long fire will burn before transitioning to another state.
+            // Fires burn until their fuel runs out: see `update_fire_intensity`.
             TileKind::Fire => {
-                vec![(Fire, 0.5), (Meadow, 0.5), (Shrubland, 0.2)]
+                vec![(Fire, 1.0)]
+            }
+            TileKind::Burned => {
+                vec![(Burned, 1.0), (Meadow, 0.5), (InvasiveGrass, 1.0)]
+            }
+            // Dead trees slowly fall, opening the canopy up for shrubs
+            TileKind::DeadForest => {
+                vec![(DeadForest, 1.0), (Shrubland, 0.2)]
+            }
+            // Once established, invasive grass is very hard for native shrubs to displace
+            TileKind::InvasiveGrass => {
+                vec![(InvasiveGrass, 1.0), (Shrubland, 0.05)]
+            }
+            TileKind::Firebreak => {
+                vec![(Firebreak, 1.0)]
+            }
+            TileKind::Shore => {
+                vec![(Shore, 1.0), (Shrubland, 0.02)]
+            }
+            // Other rulesets use their own rules, rather than succession
+            TileKind::Alive => {
+                vec![(Alive, 1.0)]
+            }
+            TileKind::Dead => {
+                vec![(Dead, 1.0)]
+            }
+            TileKind::Empty => {
+                vec![(Empty, 1.0)]
+            }
+            TileKind::Conductor => {
+                vec![(Conductor, 1.0)]
+            }
+            TileKind::ElectronHead => {
+                vec![(ElectronHead, 1.0)]
+            }
+            TileKind::ElectronTail => {
+                vec![(ElectronTail, 1.0)]
+            }
+            TileKind::White => {
+                vec![(White, 1.0)]
             }
+            TileKind::Black => {
+                vec![(Black, 1.0)]
+            }
+            TileKind::Tree => {
+                vec![(Tree, 1.0)]
+            }
+            TileKind::Rock => {
+                vec![(Rock, 1.0)]
+            }
+            TileKind::Paper => {
+                vec![(Paper, 1.0)]
+            }
+            TileKind::Scissors => {
+                vec![(Scissors, 1.0)]
+            }
+        }
+    }
+
+    /// The minimum number of ticks that a tile must spend in this state before making each transition.
+    ///
+    /// Missing entries indicate that the transition can occur at any age.
+    fn minimum_transition_ages(&self) -> Vec<(TileKind, u32)> {
+        use TileKind::*;
+
+        match self {
+            TileKind::Meadow => vec![(Shrubland, 2)],
+            TileKind::Shrubland => vec![(ShadeIntolerantForest, 5)],
+            TileKind::ShadeIntolerantForest => vec![(ShadeTolerantForest, 10)],
+            // Burned ground must recover for a while before native plants can grow back,
+            // but invasive grass can move in almost immediately
+            TileKind::Burned => vec![(Meadow, 8), (InvasiveGrass, 1)],
+            TileKind::DeadForest => vec![(Shrubland, 10)],
+            TileKind::InvasiveGrass => vec![(Shrubland, 10)],
+            // Scrub can only take hold once the dunes have been stable for a long time
+            TileKind::Shore => vec![(Shrubland, 20)],
+            TileKind::ShadeTolerantForest
+            | TileKind::Water
+            | TileKind::Fire
+            | TileKind::Firebreak
+            | TileKind::Alive
+            | TileKind::Dead
+            | TileKind::Empty
+            | TileKind::Conductor
+            | TileKind::ElectronHead
+            | TileKind::ElectronTail
+            | TileKind::White
+            | TileKind::Black
+            | TileKind::Tree
+            | TileKind::Rock
+            | TileKind::Paper
+            | TileKind::Scissors => Vec::new(),
         }
     }
 }
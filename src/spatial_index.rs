@@ -1,9 +1,16 @@
 //! A dead simple spatial index showing off the power of immutable components + hooks.
 
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+
 use bevy::ecs::component::HookContext;
 use bevy::ecs::world::DeferredWorld;
-use bevy::platform::collections::HashMap;
+use bevy::platform::collections::{HashMap, HashSet};
 use bevy::prelude::*;
+use clap::ValueEnum;
+
+use crate::map_generation::GeneratedMapSize;
+use crate::simulation::TileKind;
 
 pub struct TilePlugin;
 
@@ -12,8 +19,35 @@ impl Plugin for TilePlugin {
         // Types need to be manually registered for bevy-inspector-egui
         app.register_type::<Tile>()
             .register_type::<Position>()
+            .register_type::<GridLayer>()
             .init_resource::<TileIndex>()
-            .register_type::<TileIndex>();
+            .register_type::<TileIndex>()
+            .init_resource::<GridIndex>()
+            .register_type::<GridIndex>()
+            .init_resource::<NeighborhoodKind>()
+            .register_type::<NeighborhoodKind>()
+            .init_resource::<MapTopology>()
+            .register_type::<MapTopology>()
+            .init_resource::<GridShape>()
+            .register_type::<GridShape>()
+            .init_resource::<MovementCosts>()
+            .register_type::<MovementCosts>()
+            .init_resource::<IndexAudit>()
+            .register_type::<IndexAudit>()
+            .add_systems(
+                PostUpdate,
+                align_transforms_to_grid.before(TransformSystem::TransformPropagate),
+            )
+            .add_systems(
+                Last,
+                audit_tile_indexes.run_if(|index_audit: Res<IndexAudit>| index_audit.enabled),
+            )
+            .add_systems(
+                PreUpdate,
+                sync_tile_indexes.run_if(
+                    resource_changed::<MapTopology>.or(resource_changed::<GeneratedMapSize>),
+                ),
+            );
     }
 }
 
@@ -21,6 +55,25 @@ impl Plugin for TilePlugin {
 #[derive(Component, Reflect, Default)]
 pub struct Tile;
 
+/// Which of the stacked grids managed by the [`TileIndex`] an entity with a [`Position`] belongs to.
+///
+/// Each layer is a separate grid, so entities on different layers can share the same position:
+/// for example, an overlay can sit on top of the tile beneath it, and each can look the other up.
+/// Entities without a layer are on the [`GridLayer::Ground`].
+///
+/// This must be inserted at the same time as the entity's [`Position`], and can't be changed afterwards.
+#[derive(Component, Reflect, Default, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[component(immutable)]
+pub enum GridLayer {
+    /// The tiles that make up the map itself.
+    ///
+    /// Only entities with the [`Tile`] component are indexed on this layer.
+    #[default]
+    Ground,
+    /// Visual effects drawn above individual tiles, such as the rain overlay.
+    Overlay,
+}
+
 #[derive(Component, Default, Reflect, PartialEq, Eq, Hash, Debug, Clone, Copy)]
 #[component(immutable, on_insert = add_position_to_index, on_replace = remove_position_from_index)]
 pub struct Position {
@@ -39,6 +92,16 @@ impl Position {
         )
     }
 
+    /// Returns the position of the tile containing the given point in world space.
+    ///
+    /// This is the inverse of [`Position::to_transform`].
+    pub fn from_world(point: Vec2) -> Position {
+        Position {
+            x: (point.x / Self::PIXELS_PER_TILE).round() as i32,
+            y: (point.y / Self::PIXELS_PER_TILE).round() as i32,
+        }
+    }
+
     /// Generates the four cardinal neighbors of this position,
     /// to the north, south, east, and west.
     pub fn cardinal_neighbors(&self) -> [Position; 4] {
@@ -61,46 +124,1558 @@ impl Position {
             },
         ]
     }
+
+    /// Generates the six neighbors of this position on a [`GridShape::Hexagonal`] grid.
+    ///
+    /// These are the four cardinal neighbors, plus the two diagonal neighbors that share an edge with this hexagon.
+    pub fn hex_neighbors(&self) -> [Position; 6] {
+        let offsets = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, -1), (-1, 1)];
+
+        offsets.map(|(dx, dy)| Position {
+            x: self.x + dx,
+            y: self.y + dy,
+        })
+    }
+
+    /// Generates the eight neighbors of this position,
+    /// including both the cardinal and the diagonal directions.
+    pub fn moore_neighbors(&self) -> [Position; 8] {
+        let mut neighbors = [*self; 8];
+        let mut i = 0;
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+
+                neighbors[i] = Position {
+                    x: self.x + dx,
+                    y: self.y + dy,
+                };
+                i += 1;
+            }
+        }
+
+        neighbors
+    }
+
+    /// Generates every position within `radius` tiles of this position, as measured by Euclidean distance.
+    ///
+    /// The position itself is not included.
+    /// A radius of 1 gives the cardinal neighbors, while a radius of 2 gives a roughly circular patch of 12 tiles.
+    pub fn neighbors_within_radius(&self, radius: i32) -> Vec<Position> {
+        let mut neighbors = Vec::new();
+
+        for dx in -radius..=radius {
+            for dy in -radius..=radius {
+                if (dx == 0 && dy == 0) || dx * dx + dy * dy > radius * radius {
+                    continue;
+                }
+
+                neighbors.push(Position {
+                    x: self.x + dx,
+                    y: self.y + dy,
+                });
+            }
+        }
+
+        neighbors
+    }
+
+    /// Generates the positions along a straight line from this position to `end`, using Bresenham's line algorithm.
+    ///
+    /// Both ends of the line are included, and each step moves to a neighboring position (possibly diagonally),
+    /// so the line never skips a tile.
+    pub fn line_to(&self, end: &Position) -> impl Iterator<Item = Position> + use<> {
+        let end = *end;
+        let (dx, dy) = ((end.x - self.x).abs(), -(end.y - self.y).abs());
+        let (step_x, step_y) = ((end.x - self.x).signum(), (end.y - self.y).signum());
+
+        let mut current = Some(*self);
+        let mut error = dx + dy;
+
+        std::iter::from_fn(move || {
+            let position = current?;
+
+            current = if position == end {
+                None
+            } else {
+                let mut next = position;
+                let doubled_error = 2 * error;
+                if doubled_error >= dy {
+                    error += dy;
+                    next.x += step_x;
+                }
+                if doubled_error <= dx {
+                    error += dx;
+                    next.y += step_y;
+                }
+                Some(next)
+            };
+
+            Some(position)
+        })
+    }
+
+    /// Generates every position exactly `radius` steps away from this position,
+    /// where diagonal steps count the same as cardinal ones.
+    ///
+    /// This forms a square ring of `8 * radius` tiles, starting from the south-west corner and running counterclockwise.
+    /// A radius of 1 gives the Moore neighbors, and a radius of 0 gives the position itself.
+    pub fn ring(&self, radius: i32) -> impl Iterator<Item = Position> + use<> {
+        let center = *self;
+        let radius = radius.max(0);
+        let side = 2 * radius;
+        let steps = if radius == 0 { 1 } else { 4 * side };
+
+        (0..steps).map(move |step| {
+            // Walk along each side in turn, stopping just short of the next corner
+            let along = step % side.max(1);
+            let (dx, dy) = match step / side.max(1) {
+                0 => (-radius + along, -radius),
+                1 => (radius, -radius + along),
+                2 => (radius - along, radius),
+                _ => (-radius, radius - along),
+            };
+
+            Position {
+                x: center.x + dx,
+                y: center.y + dy,
+            }
+        })
+    }
+}
+
+/// The set of tiles that the simulation rules treat as neighbors of each tile.
+///
+/// Many classic cellular automata rules need the full eight-tile Moore neighborhood,
+/// while the forest simulation defaults to the four cardinal directions.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum NeighborhoodKind {
+    /// The four cardinal neighbors: see [`Position::cardinal_neighbors`].
+    #[default]
+    VonNeumann,
+    /// The eight cardinal and diagonal neighbors: see [`Position::moore_neighbors`].
+    Moore,
+    /// Every tile within the given Euclidean distance: see [`Position::neighbors_within_radius`].
+    Radius(i32),
+    /// The six tiles that share an edge on a [`GridShape::Hexagonal`] grid: see [`Position::hex_neighbors`].
+    Hexagonal,
+}
+
+impl NeighborhoodKind {
+    /// Returns the neighbors of the given position under this kind of neighborhood.
+    pub fn neighbors(&self, position: &Position) -> Vec<Position> {
+        match self {
+            NeighborhoodKind::VonNeumann => position.cardinal_neighbors().to_vec(),
+            NeighborhoodKind::Moore => position.moore_neighbors().to_vec(),
+            NeighborhoodKind::Radius(radius) => position.neighbors_within_radius(*radius),
+            NeighborhoodKind::Hexagonal => position.hex_neighbors().to_vec(),
+        }
+    }
+}
+
+/// The shape of the tiles that make up the map.
+///
+/// This controls how each [`Position`] is laid out in the world:
+/// the neighbors used by the simulation rules are set by the [`NeighborhoodKind`].
+#[derive(Resource, Reflect, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum GridShape {
+    /// Square tiles, laid out in rows and columns.
+    #[default]
+    Square,
+    /// Pointy-topped hexagons, addressed by axial coordinates: `x` counts along each row, and `y` counts the rows.
+    ///
+    /// Each row is shifted half a tile to the right of the one below it, so a rectangular map is drawn as a rhombus.
+    /// Hex grids avoid the directional artifacts of square grids, since every neighbor is the same distance away.
+    Hexagonal,
+}
+
+impl GridShape {
+    /// The vertical distance between the centers of neighboring rows of hexagons, relative to their horizontal spacing.
+    const HEX_ROW_SPACING: f32 = 0.866_025_4;
+
+    /// Returns the center of the tile at the given position, in world space.
+    ///
+    /// For square grids, this matches [`Position::to_transform`].
+    pub fn world_position(&self, position: &Position) -> Vec2 {
+        self.grid_to_world(Vec2::new(position.x as f32, position.y as f32))
+    }
+
+    /// Converts a point measured in tiles (which may lie between tile centers) to world space.
+    ///
+    /// This is useful for drawing the outlines of regions, whose corners lie between tiles.
+    pub fn grid_to_world(&self, point: Vec2) -> Vec2 {
+        match self {
+            GridShape::Square => point * Position::PIXELS_PER_TILE,
+            GridShape::Hexagonal => {
+                Vec2::new(point.x + point.y / 2.0, point.y * Self::HEX_ROW_SPACING)
+                    * Position::PIXELS_PER_TILE
+            }
+        }
+    }
+
+    /// Converts a point in world space to a point measured in tiles, which may lie between tile centers.
+    ///
+    /// This is the inverse of [`GridShape::grid_to_world`].
+    pub fn world_to_grid(&self, point: Vec2) -> Vec2 {
+        match self {
+            GridShape::Square => point / Position::PIXELS_PER_TILE,
+            GridShape::Hexagonal => {
+                let y = point.y / Position::PIXELS_PER_TILE / Self::HEX_ROW_SPACING;
+                Vec2::new(point.x / Position::PIXELS_PER_TILE - y / 2.0, y)
+            }
+        }
+    }
+
+    /// Returns the position of the tile containing the given point in world space.
+    ///
+    /// This is the inverse of [`GridShape::world_position`].
+    pub fn position_at(&self, point: Vec2) -> Position {
+        match self {
+            GridShape::Square => Position::from_world(point),
+            GridShape::Hexagonal => {
+                let Vec2 { x, y } = self.world_to_grid(point);
+
+                // Round in cube coordinates, where x + y + z = 0,
+                // fixing up whichever coordinate was rounded the furthest
+                let z = -x - y;
+                let (mut rounded_x, mut rounded_y) = (x.round(), y.round());
+                let rounded_z = z.round();
+                let (error_x, error_y, error_z) = (
+                    (rounded_x - x).abs(),
+                    (rounded_y - y).abs(),
+                    (rounded_z - z).abs(),
+                );
+
+                if error_x > error_y && error_x > error_z {
+                    rounded_x = -rounded_y - rounded_z;
+                } else if error_y > error_z {
+                    rounded_y = -rounded_x - rounded_z;
+                }
+
+                Position {
+                    x: rounded_x as i32,
+                    y: rounded_y as i32,
+                }
+            }
+        }
+    }
+
+    /// Returns every tile that touches the given position, including diagonal neighbors on square grids.
+    pub fn adjacent(&self, position: &Position) -> Vec<Position> {
+        match self {
+            GridShape::Square => position.moore_neighbors().to_vec(),
+            GridShape::Hexagonal => position.hex_neighbors().to_vec(),
+        }
+    }
+
+    /// The fewest steps between adjacent tiles needed to move `dx` tiles along and `dy` tiles up.
+    ///
+    /// Diagonal steps count the same as cardinal ones on square grids.
+    pub fn steps(&self, dx: i32, dy: i32) -> i32 {
+        match self {
+            GridShape::Square => dx.abs().max(dy.abs()),
+            // Hexagonal positions are axial coordinates, with a third cube coordinate of -dx - dy
+            GridShape::Hexagonal => (dx.abs() + dy.abs() + (dx + dy).abs()) / 2,
+        }
+    }
+
+    /// The size of the sprite used to draw each tile.
+    ///
+    /// Hexagons are taller than they are wide, so that neighboring rows interlock.
+    pub fn tile_size(&self) -> Vec2 {
+        match self {
+            GridShape::Square => Vec2::splat(Position::PIXELS_PER_TILE),
+            GridShape::Hexagonal => Vec2::new(
+                Position::PIXELS_PER_TILE,
+                Position::PIXELS_PER_TILE * 2.0 / 3.0_f32.sqrt(),
+            ),
+        }
+    }
+}
+
+/// Moves everything with a [`Position`] (tiles and agents alike) to the right place for the current [`GridShape`].
+///
+/// The depth of each entity is left unchanged, so that agents stay drawn above the tiles.
+fn align_transforms_to_grid(
+    grid_shape: Res<GridShape>,
+    mut query: Query<(Ref<Position>, &mut Transform)>,
+) {
+    for (position, mut transform) in query.iter_mut() {
+        if !grid_shape.is_changed() && !position.is_changed() {
+            continue;
+        }
+
+        let world_position = grid_shape.world_position(&position);
+        transform.translation.x = world_position.x;
+        transform.translation.y = world_position.y;
+    }
+}
+
+/// What happens to neighbors that would fall off the edge of the map.
+///
+/// Looking up a position with the [`TileIndex`] follows these rules,
+/// so every system that finds its neighbors through the index respects the chosen topology.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum MapTopology {
+    /// Positions beyond the edge of the map have no tile, so tiles on the edge simply have fewer neighbors.
+    #[default]
+    Absorbing,
+    /// Positions beyond the edge are mirrored back onto the map,
+    /// so tiles on the edge see their own side of the map twice.
+    Reflecting,
+    /// Positions beyond one edge wrap around to the opposite edge, as if the map were the surface of a torus.
+    ///
+    /// This is the standard choice when studying cellular automata, since no tile is treated differently from any other.
+    Toroidal,
+}
+
+impl MapTopology {
+    /// Returns the position on a map of the given size that `position` refers to under this topology.
+    ///
+    /// Returns `None` if the position lies beyond an absorbing edge.
+    pub fn resolve(&self, position: &Position, width: i32, height: i32) -> Option<Position> {
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+
+        match self {
+            MapTopology::Absorbing => {
+                let in_bounds =
+                    (0..width).contains(&position.x) && (0..height).contains(&position.y);
+                in_bounds.then_some(*position)
+            }
+            MapTopology::Reflecting => {
+                // Mirror about the edge itself, so that -1 maps to 0 and `width` maps to `width - 1`
+                let reflect = |value: i32, size: i32| {
+                    let period = value.rem_euclid(2 * size);
+                    if period < size {
+                        period
+                    } else {
+                        2 * size - 1 - period
+                    }
+                };
+
+                Some(Position {
+                    x: reflect(position.x, width),
+                    y: reflect(position.y, height),
+                })
+            }
+            MapTopology::Toroidal => Some(Position {
+                x: position.x.rem_euclid(width),
+                y: position.y.rem_euclid(height),
+            }),
+        }
+    }
 }
 
+/// Keeps the [`TileIndex`] and [`GridIndex`] in sync with the current [`MapTopology`] and [`GeneratedMapSize`].
+fn sync_tile_indexes(
+    map_topology: Res<MapTopology>,
+    map_size: Res<GeneratedMapSize>,
+    mut tile_index: ResMut<TileIndex>,
+    mut grid_index: ResMut<GridIndex>,
+) {
+    tile_index.topology = *map_topology;
+    tile_index.width = map_size.width;
+    tile_index.height = map_size.height;
+
+    grid_index.topology = *map_topology;
+    grid_index.resize(map_size.width, map_size.height, &tile_index.tiles);
+}
+
+// Only tiles are indexed on the ground layer: other entities (such as agents) can have a `Position` too,
+// but there can be many of them on the same tile.
 fn add_position_to_index(mut deferred_world: DeferredWorld, hook_context: HookContext) {
     let entity = hook_context.entity;
     let position = deferred_world.get::<Position>(entity).unwrap().clone();
 
+    let layer = deferred_world.get::<GridLayer>(entity).copied();
+    if let Some(layer) = layer
+        && layer != GridLayer::Ground
+    {
+        deferred_world
+            .resource_mut::<TileIndex>()
+            .layers
+            .entry(layer)
+            .or_default()
+            .insert(position, entity);
+        return;
+    }
+
+    if !deferred_world.entity(entity).contains::<Tile>() {
+        return;
+    }
+
     deferred_world
         .resource_mut::<TileIndex>()
         .tiles
         .insert(position, entity);
+    deferred_world
+        .resource_mut::<GridIndex>()
+        .set(&position, Some(entity));
 }
 
 fn remove_position_from_index(mut deferred_world: DeferredWorld, hook_context: HookContext) {
     let entity = hook_context.entity;
     let position = deferred_world.get::<Position>(entity).unwrap().clone();
 
+    let layer = deferred_world.get::<GridLayer>(entity).copied();
+    if let Some(layer) = layer
+        && layer != GridLayer::Ground
+    {
+        let mut tile_index = deferred_world.resource_mut::<TileIndex>();
+        if let Some(grid) = tile_index.layers.get_mut(&layer)
+            // Another entity may have taken this spot since
+            && grid.get(&position) == Some(&entity)
+        {
+            grid.remove(&position);
+        }
+        return;
+    }
+
+    if !deferred_world.entity(entity).contains::<Tile>() {
+        return;
+    }
+
     deferred_world
         .resource_mut::<TileIndex>()
         .tiles
         .remove(&position);
+    deferred_world
+        .resource_mut::<GridIndex>()
+        .set(&position, None);
 }
 
 /// A spatial index that allows you to easily look up tiles by their position.
 ///
+/// Only entities with the [`Tile`] component are indexed on the ground,
+/// while each other [`GridLayer`] stacked above it has a grid of its own.
 /// It's kept up-to-date via lifecycle hooks on the [`Position`] component,
 /// which means that it will automatically update when tiles are added or removed.
 /// Because [`Position`] is an immutable component,
 /// these values cannot become stale, and the index will always be accurate.
+///
+/// Positions that fall off the edge of the map are handled according to the [`MapTopology`].
 // PERF: note that for most reasonable values of `n` this will still be slower than a linear-time scan,
 // because ECS is really really good at those.
-// For perf-constrained applications, you should explore other related approaches or work with Bevy itself
-// for an optimized first-party solution.
+// For perf-constrained applications, use the dense [`GridIndex`] instead,
+// or work with Bevy itself for an optimized first-party solution.
 #[derive(Resource, Default, Reflect)]
 #[reflect(Resource)]
 pub struct TileIndex {
     tiles: HashMap<Position, Entity>,
+    /// The entities on every [`GridLayer`] other than the ground, which is stored in `tiles`.
+    layers: HashMap<GridLayer, HashMap<Position, Entity>>,
+    /// A copy of the [`MapTopology`] resource, used to resolve positions beyond the edge of the map.
+    topology: MapTopology,
+    /// The width of the map, used to resolve positions beyond the edge of the map.
+    width: i32,
+    /// The height of the map, used to resolve positions beyond the edge of the map.
+    height: i32,
 }
 
 impl TileIndex {
+    /// Returns the tile at the given position, taking the [`MapTopology`] into account.
+    ///
+    /// Returns `None` if there is no tile there.
+    /// Note that maps shaped by a [`MapMask`](crate::map_mask::MapMask) can have gaps inside of their bounds.
     pub fn get(&self, position: &Position) -> Option<Entity> {
-        self.tiles.get(position).copied()
+        let position = self.resolve(position)?;
+        self.tiles.get(&position).copied()
+    }
+
+    /// Returns the entity on the given [`GridLayer`] at the given position, taking the [`MapTopology`] into account.
+    ///
+    /// Every layer covers the same area as the map, so positions beyond its edge are resolved in the same way.
+    pub fn get_in_layer(&self, layer: GridLayer, position: &Position) -> Option<Entity> {
+        if layer == GridLayer::Ground {
+            return self.get(position);
+        }
+
+        let grid = self.layers.get(&layer)?;
+        if let Some(&entity) = grid.get(position) {
+            return Some(entity);
+        }
+
+        let resolved = self.topology.resolve(position, self.width, self.height)?;
+        grid.get(&resolved).copied()
+    }
+
+    /// Returns the position of the tile that `position` refers to, taking the [`MapTopology`] into account.
+    ///
+    /// Positions on the map are returned unchanged, while positions beyond its edge may be wrapped or mirrored back onto it.
+    /// Returns `None` if there is no tile there.
+    pub fn resolve(&self, position: &Position) -> Option<Position> {
+        if self.tiles.contains_key(position) {
+            return Some(*position);
+        }
+
+        let resolved = self.topology.resolve(position, self.width, self.height)?;
+        self.tiles.contains_key(&resolved).then_some(resolved)
+    }
+
+    /// Looks up the tile at each of the given positions, taking the [`MapTopology`] into account.
+    ///
+    /// Returns the resolved position of each tile that exists, along with its entity.
+    /// Positions without a tile are skipped.
+    /// Combine this with [`Position::ring`] or [`Position::neighbors_within_radius`] to look up larger neighborhoods.
+    /// On wrapping maps smaller than the search area, the same tile may be returned more than once.
+    pub fn get_many(
+        &self,
+        positions: impl IntoIterator<Item = Position>,
+    ) -> impl Iterator<Item = (Position, Entity)> {
+        positions.into_iter().filter_map(|position| {
+            let position = self.resolve(&position)?;
+            Some((position, self.tiles[&position]))
+        })
+    }
+
+    /// Returns every tile in the rectangle between `min` and `max` (inclusive), taking the [`MapTopology`] into account.
+    ///
+    /// Parts of the rectangle without tiles are skipped.
+    pub fn iter_rect(
+        &self,
+        min: Position,
+        max: Position,
+    ) -> impl Iterator<Item = (Position, Entity)> {
+        let positions =
+            (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| Position { x, y }));
+
+        self.get_many(positions)
+    }
+
+    /// Returns the tiles among the eight Moore neighbors of the given position: see [`Position::moore_neighbors`].
+    pub fn moore_neighbors(&self, position: &Position) -> impl Iterator<Item = (Position, Entity)> {
+        self.get_many(position.moore_neighbors())
+    }
+
+    /// Returns the tiles along a straight line from `a` to `b`: see [`Position::line_to`].
+    pub fn line(&self, a: &Position, b: &Position) -> impl Iterator<Item = (Position, Entity)> {
+        self.get_many(a.line_to(b))
+    }
+
+    /// Checks whether `b` can be seen from `a`, looking along a straight line across the grid.
+    ///
+    /// The view is blocked by any tile between the two ends for which `is_blocking` returns true.
+    /// The tiles at either end never block the view, and neither do gaps in the map.
+    pub fn visible(
+        &self,
+        a: &Position,
+        b: &Position,
+        is_blocking: impl Fn(Entity) -> bool,
+    ) -> bool {
+        !self
+            .line(a, b)
+            .filter(|(position, _)| position != a && position != b)
+            .any(|(_, entity)| is_blocking(entity))
+    }
+
+    /// Finds the cheapest path from `start` to `goal` using A* search,
+    /// stepping to any adjacent tile as given by [`GridShape::adjacent`].
+    ///
+    /// `cost` returns the cost of stepping onto each tile, or `None` if the tile can't be entered at all
+    /// (see [`MovementCosts`] for a ready-made set of costs).
+    /// Every cost should be at least 1, or the path found may not be the cheapest.
+    ///
+    /// The returned path does not include `start`, and ends at `goal`.
+    /// If `start` is the goal, the path is empty.
+    /// Returns `None` if there is no tile at either end, or the goal can't be reached.
+    pub fn pathfind(
+        &self,
+        start: Position,
+        goal: Position,
+        grid_shape: &GridShape,
+        cost: impl Fn(Entity) -> Option<f32>,
+    ) -> Option<Vec<Position>> {
+        let start = self.resolve(&start)?;
+        let goal = self.resolve(&goal)?;
+
+        let mut came_from: HashMap<Position, Position> = HashMap::new();
+        let mut cheapest: HashMap<Position, f32> = HashMap::from([(start, 0.0)]);
+        let mut frontier = BinaryHeap::from([FrontierEntry {
+            estimated_cost: self.distance(&start, &goal, grid_shape) as f32,
+            cost: 0.0,
+            position: start,
+        }]);
+
+        while let Some(FrontierEntry {
+            cost: cost_so_far,
+            position,
+            ..
+        }) = frontier.pop()
+        {
+            if position == goal {
+                let mut path = Vec::new();
+                let mut step = position;
+                while step != start {
+                    path.push(step);
+                    step = came_from[&step];
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            // A cheaper route to this tile was found after this entry was added
+            if cost_so_far > cheapest[&position] {
+                continue;
+            }
+
+            for neighbor in grid_shape.adjacent(&position) {
+                let Some(neighbor) = self.resolve(&neighbor) else {
+                    continue;
+                };
+                let Some(step_cost) = cost(self.tiles[&neighbor]) else {
+                    continue;
+                };
+
+                let new_cost = cost_so_far + step_cost;
+                if cheapest
+                    .get(&neighbor)
+                    .is_none_or(|&previous_cost| new_cost < previous_cost)
+                {
+                    cheapest.insert(neighbor, new_cost);
+                    came_from.insert(neighbor, position);
+                    frontier.push(FrontierEntry {
+                        estimated_cost: new_cost
+                            + self.distance(&neighbor, &goal, grid_shape) as f32,
+                        cost: new_cost,
+                        position: neighbor,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The fewest steps between adjacent tiles needed to get from `a` to `b`, ignoring any obstacles.
+    ///
+    /// On toroidal maps, this takes the shortcut across the edge of the map when that is shorter.
+    pub fn distance(&self, a: &Position, b: &Position, grid_shape: &GridShape) -> i32 {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let (x_offsets, y_offsets) = if self.topology == MapTopology::Toroidal {
+            ([0, -self.width, self.width], [0, -self.height, self.height])
+        } else {
+            ([0; 3], [0; 3])
+        };
+
+        x_offsets
+            .iter()
+            .flat_map(|x_offset| {
+                y_offsets
+                    .iter()
+                    .map(move |y_offset| grid_shape.steps(dx + x_offset, dy + y_offset))
+            })
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// Finds every tile connected to `start` through a chain of tiles for which `is_connected` returns true,
+    /// taking the [`MapTopology`] into account.
+    ///
+    /// Tiles are connected to every tile they touch, as given by [`GridShape::adjacent`].
+    /// The returned tiles include `start` itself, and are listed in the order they were reached.
+    /// If `start` has no tile or isn't connected itself, no tiles are returned.
+    pub fn flood_fill(
+        &self,
+        start: Position,
+        grid_shape: &GridShape,
+        is_connected: impl Fn(Entity) -> bool,
+    ) -> Vec<Position> {
+        let Some(start) = self.resolve(&start) else {
+            return Vec::new();
+        };
+        if !is_connected(self.tiles[&start]) {
+            return Vec::new();
+        }
+
+        let mut region = vec![start];
+        let mut visited = HashSet::from([start]);
+        let mut frontier = VecDeque::from([start]);
+
+        while let Some(position) = frontier.pop_front() {
+            for neighbor in grid_shape.adjacent(&position) {
+                let Some(neighbor) = self.resolve(&neighbor) else {
+                    continue;
+                };
+
+                if !visited.contains(&neighbor) && is_connected(self.tiles[&neighbor]) {
+                    visited.insert(neighbor);
+                    region.push(neighbor);
+                    frontier.push_back(neighbor);
+                }
+            }
+        }
+
+        region
+    }
+
+    /// Computes how many steps each tile is from the nearest source tile, for which `is_source` returns true.
+    ///
+    /// Steps can be taken to any adjacent tile, as given by [`GridShape::adjacent`],
+    /// so on square grids diagonal steps count the same as cardinal ones.
+    /// Source tiles have a distance of 0.
+    /// Tiles more than `max_distance` steps from every source (or that can't be reached at all) are left out.
+    // PERF: this is a breadth-first search outwards from every source at once, so it visits each tile at most once.
+    // Recompute it only when it's needed, rather than every frame.
+    pub fn distance_field(
+        &self,
+        grid_shape: &GridShape,
+        max_distance: i32,
+        is_source: impl Fn(Entity) -> bool,
+    ) -> HashMap<Position, i32> {
+        let mut distances: HashMap<Position, i32> = self
+            .tiles
+            .iter()
+            .filter(|(_, entity)| is_source(**entity))
+            .map(|(position, _)| (*position, 0))
+            .collect();
+        let mut frontier: VecDeque<Position> = distances.keys().copied().collect();
+
+        while let Some(position) = frontier.pop_front() {
+            let distance = distances[&position];
+            if distance >= max_distance {
+                continue;
+            }
+
+            for neighbor in grid_shape.adjacent(&position) {
+                let Some(neighbor) = self.resolve(&neighbor) else {
+                    continue;
+                };
+
+                if !distances.contains_key(&neighbor) {
+                    distances.insert(neighbor, distance + 1);
+                    frontier.push_back(neighbor);
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Splits the map into contiguous regions of tiles that share the same label.
+    ///
+    /// `label` returns the label of each tile, such as its [`TileKind`](crate::simulation::TileKind),
+    /// or `None` to leave the tile out of every region.
+    /// Regions are found using [`TileIndex::flood_fill`], so they follow the same connectivity rules.
+    ///
+    /// Regions are listed in the order of their first tile, scanning row by row from `y = 0`,
+    /// so the index of each region is a stable label as long as the map doesn't change.
+    pub fn label_regions<L: PartialEq>(
+        &self,
+        grid_shape: &GridShape,
+        label: impl Fn(Entity) -> Option<L>,
+    ) -> Vec<Region<L>> {
+        let mut visited: HashSet<Position> = HashSet::new();
+        let mut regions = Vec::new();
+
+        let mut positions: Vec<Position> = self.tiles.keys().copied().collect();
+        positions.sort_unstable_by_key(|position| (position.y, position.x));
+
+        for position in positions {
+            if visited.contains(&position) {
+                continue;
+            }
+
+            let Some(region_label) = label(self.tiles[&position]) else {
+                continue;
+            };
+
+            let tiles = self.flood_fill(position, grid_shape, |entity| {
+                label(entity).is_some_and(|label| label == region_label)
+            });
+            visited.extend(tiles.iter().copied());
+
+            regions.push(Region {
+                label: region_label,
+                tiles,
+            });
+        }
+
+        regions
+    }
+}
+
+/// A tile waiting to be explored by [`TileIndex::pathfind`].
+///
+/// Entries are ordered so that the [`BinaryHeap`] pops the lowest estimated total cost first.
+struct FrontierEntry {
+    /// The cost of the path so far, plus an estimate of the remaining cost to the goal.
+    estimated_cost: f32,
+    /// The cost of the path so far.
+    cost: f32,
+    position: Position,
+}
+
+impl Ord for FrontierEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimated_cost.total_cmp(&self.estimated_cost)
+    }
+}
+
+impl PartialOrd for FrontierEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FrontierEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FrontierEntry {}
+
+/// How costly it is for agents to move across each kind of tile, for use with [`TileIndex::pathfind`].
+///
+/// Kinds that aren't listed can't be crossed at all.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct MovementCosts {
+    /// The cost of stepping onto each kind of tile, where open ground costs 1.
+    pub costs: Vec<(TileKind, f32)>,
+}
+
+impl Default for MovementCosts {
+    fn default() -> Self {
+        Self {
+            costs: vec![
+                (TileKind::Meadow, 1.0),
+                (TileKind::Burned, 1.0),
+                (TileKind::Firebreak, 1.0),
+                (TileKind::Shore, 1.0),
+                (TileKind::InvasiveGrass, 1.5),
+                (TileKind::Shrubland, 2.0),
+                (TileKind::ShadeIntolerantForest, 3.0),
+                (TileKind::ShadeTolerantForest, 3.0),
+                (TileKind::DeadForest, 4.0),
+            ],
+        }
+    }
+}
+
+impl MovementCosts {
+    /// Returns the cost of stepping onto a tile of the given kind, or `None` if it can't be crossed.
+    pub fn cost(&self, tile_kind: &TileKind) -> Option<f32> {
+        self.costs
+            .iter()
+            .find(|(kind, _)| kind == tile_kind)
+            .map(|(_, cost)| *cost)
+    }
+}
+
+/// A contiguous group of tiles that share the same label, as found by [`TileIndex::label_regions`].
+#[derive(Debug, Clone)]
+pub struct Region<L> {
+    /// The label shared by every tile in this region.
+    pub label: L,
+    /// The position of every tile in this region.
+    pub tiles: Vec<Position>,
+}
+
+/// A dense alternative to the [`TileIndex`], storing the tile at every position of the map in a flat grid.
+///
+/// Looking up a tile is just a little index arithmetic, making this much faster than the [`TileIndex`]
+/// for hot systems that visit the neighbors of every tile.
+/// In exchange, it takes up memory for every position within the [`GeneratedMapSize`], even those without a tile,
+/// and can't find tiles outside of the map's bounds.
+///
+/// It's kept up-to-date by the same hooks as the [`TileIndex`], and follows the same [`MapTopology`].
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct GridIndex {
+    /// The tile at each position, stored column by column.
+    tiles: Vec<Option<Entity>>,
+    /// A copy of the [`MapTopology`] resource, used to resolve positions beyond the edge of the map.
+    topology: MapTopology,
+    /// The width of the map, in tiles.
+    width: i32,
+    /// The height of the map, in tiles.
+    height: i32,
+}
+
+impl GridIndex {
+    /// Returns the tile at the given position, taking the [`MapTopology`] into account.
+    ///
+    /// Returns `None` if there is no tile there.
+    pub fn get(&self, position: &Position) -> Option<Entity> {
+        let index = match self.index(position) {
+            Some(index) => index,
+            None => {
+                let resolved = self.topology.resolve(position, self.width, self.height)?;
+                self.index(&resolved)?
+            }
+        };
+
+        self.tiles[index]
+    }
+
+    /// Looks up the tile at each of the given positions, taking the [`MapTopology`] into account.
+    ///
+    /// Positions without a tile are skipped.
+    /// This works just like [`TileIndex::get_many`], but returns only the entities.
+    pub fn get_many(
+        &self,
+        positions: impl IntoIterator<Item = Position>,
+    ) -> impl Iterator<Item = Entity> {
+        positions
+            .into_iter()
+            .filter_map(|position| self.get(&position))
+    }
+
+    /// Returns the index of the given position within the grid, or `None` if it lies outside of the map.
+    fn index(&self, position: &Position) -> Option<usize> {
+        Self::flat_index(position, self.width, self.height)
+    }
+
+    /// Returns the index of the given position within a flat grid of the given size, stored column by column.
+    ///
+    /// Returns `None` if the position lies outside of the grid.
+    /// Other dense per-tile storage, such as the [`CellLayers`](crate::cell_layers::CellLayers),
+    /// shares this layout so that the same index refers to the same tile everywhere.
+    pub fn flat_index(position: &Position, width: i32, height: i32) -> Option<usize> {
+        let in_bounds = (0..width).contains(&position.x) && (0..height).contains(&position.y);
+        in_bounds.then(|| (position.x * height + position.y) as usize)
+    }
+
+    /// Returns the position stored at the given index of a flat grid of the given height: see [`GridIndex::flat_index`].
+    pub fn flat_position(index: usize, height: i32) -> Position {
+        let height = height.max(1);
+        Position {
+            x: index as i32 / height,
+            y: index as i32 % height,
+        }
+    }
+
+    /// Records the tile at the given position, ignoring positions outside of the map.
+    fn set(&mut self, position: &Position, entity: Option<Entity>) {
+        if let Some(index) = self.index(position) {
+            self.tiles[index] = entity;
+        }
+    }
+
+    /// Resizes the grid to match a new map size, copying over every existing tile that still fits.
+    fn resize(&mut self, width: i32, height: i32, tiles: &HashMap<Position, Entity>) {
+        if width == self.width && height == self.height {
+            return;
+        }
+
+        self.width = width.max(0);
+        self.height = height.max(0);
+        self.tiles = vec![None; (self.width * self.height) as usize];
+
+        for (position, entity) in tiles {
+            self.set(position, Some(*entity));
+        }
+    }
+}
+
+/// Controls whether the [`TileIndex`] and [`GridIndex`] are checked against the world at the end of every frame.
+///
+/// This is slow, but catches bugs where tiles are spawned, moved or despawned in a way that the index hooks miss.
+/// Every discrepancy found is logged as an error.
+#[derive(Resource, Reflect, Debug, Default)]
+#[reflect(Resource)]
+pub struct IndexAudit {
+    pub enabled: bool,
+}
+
+/// Checks that every indexed entity exists with the position it was indexed at,
+/// and that every entity that should be indexed can be found there.
+fn audit_tile_indexes(
+    entity_query: Query<(Entity, &Position, Has<Tile>, Option<&GridLayer>)>,
+    tile_index: Res<TileIndex>,
+    grid_index: Res<GridIndex>,
+) {
+    let mut discrepancies = 0;
+
+    for (entity, position, is_tile, layer) in entity_query.iter() {
+        let layer = layer.copied().unwrap_or_default();
+        let indexed = match layer {
+            GridLayer::Ground if !is_tile => continue,
+            GridLayer::Ground => tile_index.tiles.get(position),
+            layer => tile_index
+                .layers
+                .get(&layer)
+                .and_then(|grid| grid.get(position)),
+        };
+
+        if indexed != Some(&entity) {
+            error!(
+                "{entity} at ({}, {}) on the {layer:?} layer is missing from the tile index, which has {indexed:?} there instead",
+                position.x, position.y
+            );
+            discrepancies += 1;
+        }
+
+        if layer == GridLayer::Ground
+            && grid_index.index(position).is_some()
+            && grid_index.get(position) != Some(entity)
+        {
+            error!(
+                "{entity} at ({}, {}) is missing from the grid index",
+                position.x, position.y
+            );
+            discrepancies += 1;
+        }
+    }
+
+    let ground = tile_index
+        .tiles
+        .iter()
+        .map(|(position, entity)| (GridLayer::Ground, position, entity));
+    let stacked = tile_index.layers.iter().flat_map(|(layer, grid)| {
+        grid.iter()
+            .map(move |(position, entity)| (*layer, position, entity))
+    });
+
+    for (layer, position, &entity) in ground.chain(stacked) {
+        match entity_query.get(entity) {
+            Ok((_, actual_position, _, actual_layer))
+                if actual_position == position
+                    && actual_layer.copied().unwrap_or_default() == layer => {}
+            Ok((_, actual_position, _, actual_layer)) => {
+                error!(
+                    "The tile index has {entity} at ({}, {}) on the {layer:?} layer, but it is at ({}, {}) on the {:?} layer",
+                    position.x,
+                    position.y,
+                    actual_position.x,
+                    actual_position.y,
+                    actual_layer.copied().unwrap_or_default()
+                );
+                discrepancies += 1;
+            }
+            Err(_) => {
+                error!(
+                    "The tile index has {entity} at ({}, {}) on the {layer:?} layer, but it no longer exists",
+                    position.x, position.y
+                );
+                discrepancies += 1;
+            }
+        }
+    }
+
+    for (index, entity) in grid_index.tiles.iter().enumerate() {
+        let Some(entity) = *entity else {
+            continue;
+        };
+
+        let expected = Position {
+            x: index as i32 / grid_index.height,
+            y: index as i32 % grid_index.height,
+        };
+        if !entity_query
+            .get(entity)
+            .is_ok_and(|(_, position, is_tile, _)| is_tile && *position == expected)
+        {
+            error!(
+                "The grid index has {entity} at ({}, {}), but there is no such tile there",
+                expected.x, expected.y
+            );
+            discrepancies += 1;
+        }
+    }
+
+    if discrepancies > 0 {
+        warn!("The index audit found {discrepancies} discrepancies this frame");
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a world containing a map drawn as text, with one row per string, starting from `y = 0`.
+    ///
+    /// `.` is meadow, `~` is water and `D` is dead forest.
+    pub(crate) fn world_from_rows(rows: &[&str], topology: MapTopology) -> World {
+        let mut world = World::new();
+        world.insert_resource(TileIndex {
+            topology,
+            width: rows[0].len() as i32,
+            height: rows.len() as i32,
+            ..default()
+        });
+        world.init_resource::<GridIndex>();
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, symbol) in row.chars().enumerate() {
+                let tile_kind = match symbol {
+                    '~' => TileKind::Water,
+                    'D' => TileKind::DeadForest,
+                    _ => TileKind::Meadow,
+                };
+                let position = Position {
+                    x: x as i32,
+                    y: y as i32,
+                };
+                world.spawn((Tile, position, tile_kind));
+            }
+        }
+
+        world
+    }
+
+    fn find_path(
+        world: &World,
+        start: Position,
+        goal: Position,
+        grid_shape: GridShape,
+    ) -> Option<Vec<Position>> {
+        let movement_costs = MovementCosts::default();
+        world
+            .resource::<TileIndex>()
+            .pathfind(start, goal, &grid_shape, |tile| {
+                movement_costs.cost(world.get::<TileKind>(tile)?)
+            })
+    }
+
+    fn tile_kind_at(world: &World, position: &Position) -> TileKind {
+        let tile = world.resource::<TileIndex>().get(position).unwrap();
+        *world.get::<TileKind>(tile).unwrap()
+    }
+
+    fn is_meadow(world: &World, tile: Entity) -> bool {
+        world.get::<TileKind>(tile) == Some(&TileKind::Meadow)
+    }
+
+    #[test]
+    fn pathfind_detours_around_water() {
+        let world = world_from_rows(
+            &["..~..", "..~..", "..~..", "..~..", "....."],
+            MapTopology::Absorbing,
+        );
+
+        let path = find_path(
+            &world,
+            Position { x: 0, y: 2 },
+            Position { x: 4, y: 2 },
+            GridShape::Square,
+        )
+        .unwrap();
+
+        assert_eq!(path.len(), 4);
+        assert!(path.contains(&Position { x: 2, y: 4 }));
+        assert_eq!(path.last(), Some(&Position { x: 4, y: 2 }));
+        assert!(
+            path.iter()
+                .all(|position| tile_kind_at(&world, position) != TileKind::Water)
+        );
+    }
+
+    #[test]
+    fn pathfind_returns_none_for_unreachable_goal() {
+        let world = world_from_rows(
+            &[".....", ".~~~.", ".~.~.", ".~~~.", "....."],
+            MapTopology::Absorbing,
+        );
+
+        let path = find_path(
+            &world,
+            Position { x: 0, y: 0 },
+            Position { x: 2, y: 2 },
+            GridShape::Square,
+        );
+
+        assert_eq!(path, None);
+    }
+
+    #[test]
+    fn pathfind_wraps_around_toroidal_maps() {
+        let rows = ["......", "......", "......"];
+        let start = Position { x: 0, y: 1 };
+        let goal = Position { x: 5, y: 1 };
+
+        let toroidal = world_from_rows(&rows, MapTopology::Toroidal);
+        assert_eq!(
+            find_path(&toroidal, start, goal, GridShape::Square),
+            Some(vec![goal])
+        );
+
+        let absorbing = world_from_rows(&rows, MapTopology::Absorbing);
+        assert_eq!(
+            find_path(&absorbing, start, goal, GridShape::Square).map(|path| path.len()),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn pathfind_prefers_cheaper_longer_route() {
+        let world = world_from_rows(
+            &[".DDD.", ".DDD.", ".DDD.", ".DDD.", "....."],
+            MapTopology::Absorbing,
+        );
+
+        let path = find_path(
+            &world,
+            Position { x: 0, y: 0 },
+            Position { x: 4, y: 0 },
+            GridShape::Square,
+        )
+        .unwrap();
+
+        // Cutting straight through the forest would take only 4 steps, but cost 13
+        assert_eq!(path.len(), 10);
+        assert!(
+            path.iter()
+                .all(|position| tile_kind_at(&world, position) == TileKind::Meadow)
+        );
+    }
+
+    #[test]
+    fn pathfind_follows_hexagonal_adjacency() {
+        let world = world_from_rows(
+            &[".....", ".....", ".....", ".....", "....."],
+            MapTopology::Absorbing,
+        );
+        let start = Position { x: 0, y: 0 };
+
+        let path = find_path(&world, start, Position { x: 4, y: 4 }, GridShape::Hexagonal).unwrap();
+
+        assert_eq!(path.len(), 8);
+        let mut previous = start;
+        for position in path {
+            assert!(previous.hex_neighbors().contains(&position));
+            previous = position;
+        }
+    }
+
+    #[test]
+    fn distance_field_counts_steps_from_the_nearest_source() {
+        let world = world_from_rows(&["~......", ".......", "......~"], MapTopology::Absorbing);
+        let is_water = |tile: Entity| world.get::<TileKind>(tile) == Some(&TileKind::Water);
+
+        let distances =
+            world
+                .resource::<TileIndex>()
+                .distance_field(&GridShape::Square, 2, is_water);
+
+        assert_eq!(distances[&Position { x: 0, y: 0 }], 0);
+        assert_eq!(distances[&Position { x: 1, y: 1 }], 1);
+        assert_eq!(distances[&Position { x: 2, y: 2 }], 2);
+        assert_eq!(distances[&Position { x: 5, y: 1 }], 1);
+        // The middle column is 3 steps from both lakes, beyond the maximum distance
+        assert!(!distances.contains_key(&Position { x: 3, y: 1 }));
+        assert_eq!(distances.len(), 18);
+    }
+
+    #[test]
+    fn flood_fill_stops_at_water() {
+        let world = world_from_rows(&["..~..", "..~..", "..~.."], MapTopology::Absorbing);
+
+        let region = world.resource::<TileIndex>().flood_fill(
+            Position { x: 0, y: 0 },
+            &GridShape::Square,
+            |tile| is_meadow(&world, tile),
+        );
+
+        assert_eq!(region.len(), 6);
+        assert!(region.iter().all(|position| position.x < 2));
+    }
+
+    #[test]
+    fn flood_fill_crosses_toroidal_seams() {
+        let rows = [".~~~.", ".~~~.", ".~~~."];
+        let start = Position { x: 0, y: 1 };
+
+        let absorbing = world_from_rows(&rows, MapTopology::Absorbing);
+        let region =
+            absorbing
+                .resource::<TileIndex>()
+                .flood_fill(start, &GridShape::Square, |tile| {
+                    is_meadow(&absorbing, tile)
+                });
+        assert_eq!(region.len(), 3);
+
+        let toroidal = world_from_rows(&rows, MapTopology::Toroidal);
+        let region =
+            toroidal
+                .resource::<TileIndex>()
+                .flood_fill(start, &GridShape::Square, |tile| is_meadow(&toroidal, tile));
+        assert_eq!(region.len(), 6);
+        assert!(region.contains(&Position { x: 4, y: 1 }));
+    }
+
+    #[test]
+    fn label_regions_separates_disjoint_lakes() {
+        let world = world_from_rows(
+            &["~~...", "~~...", ".....", "...~~"],
+            MapTopology::Absorbing,
+        );
+        let tile_index = world.resource::<TileIndex>();
+        let tile_kind = |tile: Entity| world.get::<TileKind>(tile).copied();
+
+        let regions = tile_index.label_regions(&GridShape::Square, tile_kind);
+        let lakes: Vec<&Region<TileKind>> = regions
+            .iter()
+            .filter(|region| region.label == TileKind::Water)
+            .collect();
+
+        assert_eq!(regions.len(), 3);
+        assert_eq!(lakes.len(), 2);
+        assert_eq!(lakes[0].tiles.len(), 4);
+        assert!(lakes[0].tiles.contains(&Position { x: 0, y: 0 }));
+        assert_eq!(lakes[1].tiles.len(), 2);
+        assert!(lakes[1].tiles.contains(&Position { x: 4, y: 3 }));
+
+        let relabeled = tile_index.label_regions(&GridShape::Square, tile_kind);
+        assert_eq!(relabeled.len(), regions.len());
+        for (region, relabeled) in regions.iter().zip(&relabeled) {
+            assert_eq!(region.label, relabeled.label);
+            assert_eq!(region.tiles, relabeled.tiles);
+        }
+    }
+
+    #[test]
+    fn ring_has_eight_tiles_per_step_of_radius() {
+        let center = Position { x: 3, y: 3 };
+
+        assert_eq!(center.ring(0).collect::<Vec<_>>(), vec![center]);
+        assert_eq!(center.ring(1).count(), 8);
+        assert_eq!(center.ring(2).count(), 16);
+
+        let ring: HashSet<Position> = center.ring(2).collect();
+        assert_eq!(ring.len(), 16);
+        assert!(ring.iter().all(|position| {
+            (position.x - center.x)
+                .abs()
+                .max((position.y - center.y).abs())
+                == 2
+        }));
+    }
+
+    #[test]
+    fn ring_is_clipped_at_absorbing_corners() {
+        let world = world_from_rows(&["...", "...", "..."], MapTopology::Absorbing);
+        let corner = Position { x: 0, y: 0 };
+
+        let tiles: Vec<(Position, Entity)> = world
+            .resource::<TileIndex>()
+            .get_many(corner.ring(1))
+            .collect();
+
+        assert_eq!(tiles.len(), 3);
+        assert!(
+            tiles
+                .iter()
+                .all(|(position, _)| position.x >= 0 && position.y >= 0)
+        );
+    }
+
+    #[test]
+    fn line_to_includes_both_ends_of_steep_and_shallow_lines() {
+        let start = Position { x: 1, y: 2 };
+
+        for end in [Position { x: 3, y: 9 }, Position { x: 8, y: 0 }] {
+            let line: Vec<Position> = start.line_to(&end).collect();
+            let longest_side = (end.x - start.x).abs().max((end.y - start.y).abs());
+
+            assert_eq!(line.first(), Some(&start));
+            assert_eq!(line.last(), Some(&end));
+            assert_eq!(line.len() as i32, longest_side + 1);
+            assert!(line.windows(2).all(|step| {
+                (step[1].x - step[0].x).abs() <= 1 && (step[1].y - step[0].y).abs() <= 1
+            }));
+        }
+    }
+
+    #[test]
+    fn visible_is_blocked_by_tiles_in_between() {
+        let world = world_from_rows(&["..D..", "....."], MapTopology::Absorbing);
+        let tile_index = world.resource::<TileIndex>();
+        let is_forest = |tile: Entity| world.get::<TileKind>(tile) == Some(&TileKind::DeadForest);
+        let viewer = Position { x: 0, y: 0 };
+
+        assert!(!tile_index.visible(&viewer, &Position { x: 4, y: 0 }, is_forest));
+        assert!(!tile_index.visible(&Position { x: 4, y: 0 }, &viewer, is_forest));
+        // The blocker itself can still be seen, as can the tiles in front of it and beside it
+        assert!(tile_index.visible(&viewer, &Position { x: 2, y: 0 }, is_forest));
+        assert!(tile_index.visible(&viewer, &Position { x: 1, y: 0 }, is_forest));
+        assert!(tile_index.visible(
+            &Position { x: 0, y: 1 },
+            &Position { x: 4, y: 1 },
+            is_forest
+        ));
+    }
+
+    #[test]
+    fn iter_rect_is_clipped_at_absorbing_edges() {
+        let world = world_from_rows(&["...", "...", "..."], MapTopology::Absorbing);
+        let tile_index = world.resource::<TileIndex>();
+
+        let corner: HashSet<Position> = tile_index
+            .iter_rect(Position { x: -1, y: -1 }, Position { x: 1, y: 1 })
+            .map(|(position, _)| position)
+            .collect();
+        assert_eq!(
+            corner,
+            HashSet::from([
+                Position { x: 0, y: 0 },
+                Position { x: 1, y: 0 },
+                Position { x: 0, y: 1 },
+                Position { x: 1, y: 1 },
+            ])
+        );
+
+        let beyond_the_edge =
+            tile_index.iter_rect(Position { x: 3, y: 0 }, Position { x: 5, y: 2 });
+        assert_eq!(beyond_the_edge.count(), 0);
+    }
+
+    #[test]
+    fn pathfind_stays_on_reflecting_maps() {
+        let world = world_from_rows(&["...", "...", "..."], MapTopology::Reflecting);
+
+        let path = find_path(
+            &world,
+            Position { x: 0, y: 0 },
+            Position { x: 2, y: 0 },
+            GridShape::Square,
+        )
+        .unwrap();
+
+        assert_eq!(path.len(), 2);
+        assert!(
+            path.iter()
+                .all(|position| { (0..3).contains(&position.x) && (0..3).contains(&position.y) })
+        );
+    }
+
+    #[test]
+    fn iter_rect_wraps_and_mirrors_at_the_map_edge() {
+        let rows = ["....", "....", "...."];
+        let min = Position { x: 3, y: 0 };
+        let max = Position { x: 4, y: 0 };
+
+        let toroidal = world_from_rows(&rows, MapTopology::Toroidal);
+        let wrapped: Vec<Position> = toroidal
+            .resource::<TileIndex>()
+            .iter_rect(min, max)
+            .map(|(position, _)| position)
+            .collect();
+        assert_eq!(
+            wrapped,
+            vec![Position { x: 3, y: 0 }, Position { x: 0, y: 0 }]
+        );
+
+        let reflecting = world_from_rows(&rows, MapTopology::Reflecting);
+        let mirrored: Vec<Position> = reflecting
+            .resource::<TileIndex>()
+            .iter_rect(min, max)
+            .map(|(position, _)| position)
+            .collect();
+        assert_eq!(
+            mirrored,
+            vec![Position { x: 3, y: 0 }, Position { x: 3, y: 0 }]
+        );
+    }
+
+    #[test]
+    fn ring_sees_its_own_side_at_reflecting_corners() {
+        let world = world_from_rows(&["...", "...", "..."], MapTopology::Reflecting);
+        let corner = Position { x: 0, y: 0 };
+
+        let tiles: Vec<Position> = world
+            .resource::<TileIndex>()
+            .get_many(corner.ring(1))
+            .map(|(position, _)| position)
+            .collect();
+
+        assert_eq!(tiles.len(), 8);
+        assert_eq!(
+            tiles.iter().filter(|&&position| position == corner).count(),
+            3
+        );
+    }
+
+    #[test]
+    fn line_and_visible_wrap_across_toroidal_seams() {
+        let rows = ["D...."];
+        let viewer = Position { x: 3, y: 0 };
+        let target = Position { x: 6, y: 0 };
+
+        let toroidal = world_from_rows(&rows, MapTopology::Toroidal);
+        let tile_index = toroidal.resource::<TileIndex>();
+        let is_forest =
+            |tile: Entity| toroidal.get::<TileKind>(tile) == Some(&TileKind::DeadForest);
+        let line: Vec<Position> = tile_index
+            .line(&viewer, &target)
+            .map(|(position, _)| position)
+            .collect();
+        assert_eq!(
+            line,
+            vec![
+                Position { x: 3, y: 0 },
+                Position { x: 4, y: 0 },
+                Position { x: 0, y: 0 },
+                Position { x: 1, y: 0 },
+            ]
+        );
+        assert!(!tile_index.visible(&viewer, &target, is_forest));
+
+        // Without wrapping, the line leaves the map instead, and gaps never block the view
+        let absorbing = world_from_rows(&rows, MapTopology::Absorbing);
+        let is_forest =
+            |tile: Entity| absorbing.get::<TileKind>(tile) == Some(&TileKind::DeadForest);
+        assert!(
+            absorbing
+                .resource::<TileIndex>()
+                .visible(&viewer, &target, is_forest)
+        );
+    }
+
+    #[test]
+    fn distances_wrap_across_toroidal_seams() {
+        let rows = ["~...."];
+        let is_water =
+            |world: &World, tile: Entity| world.get::<TileKind>(tile) == Some(&TileKind::Water);
+        let east_edge = Position { x: 4, y: 0 };
+
+        let toroidal = world_from_rows(&rows, MapTopology::Toroidal);
+        let tile_index = toroidal.resource::<TileIndex>();
+        let distances =
+            tile_index.distance_field(&GridShape::Square, 4, |tile| is_water(&toroidal, tile));
+        assert_eq!(distances[&east_edge], 1);
+        assert_eq!(
+            tile_index.distance(&Position { x: 0, y: 0 }, &east_edge, &GridShape::Square),
+            1
+        );
+
+        let absorbing = world_from_rows(&rows, MapTopology::Absorbing);
+        let tile_index = absorbing.resource::<TileIndex>();
+        let distances =
+            tile_index.distance_field(&GridShape::Square, 4, |tile| is_water(&absorbing, tile));
+        assert_eq!(distances[&east_edge], 4);
+        assert_eq!(
+            tile_index.distance(&Position { x: 0, y: 0 }, &east_edge, &GridShape::Square),
+            4
+        );
+    }
+
+    #[test]
+    fn label_regions_joins_tiles_across_toroidal_seams() {
+        let rows = [".~~~.", ".~~~."];
+        let tile_kind = |world: &World, tile: Entity| world.get::<TileKind>(tile).copied();
+
+        let absorbing = world_from_rows(&rows, MapTopology::Absorbing);
+        let regions = absorbing
+            .resource::<TileIndex>()
+            .label_regions(&GridShape::Square, |tile| tile_kind(&absorbing, tile));
+        assert_eq!(regions.len(), 3);
+
+        let toroidal = world_from_rows(&rows, MapTopology::Toroidal);
+        let regions = toroidal
+            .resource::<TileIndex>()
+            .label_regions(&GridShape::Square, |tile| tile_kind(&toroidal, tile));
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].label, TileKind::Meadow);
+        assert_eq!(regions[0].tiles.len(), 4);
     }
 }
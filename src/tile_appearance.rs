@@ -0,0 +1,356 @@
+//! Controls how each kind of tile looks, and loads overrides from a RON asset file.
+//!
+//! Every kind of tile has a color, which tints its texture from the tile atlas.
+//! These colors start from the selected [`Palette`], which includes presets that stay readable with color vision deficiencies.
+//! Lively kinds of tile, like fire and water, can also cycle through several frames of animation.
+//! Tweak these live in the inspector, or edit `assets/tiles.appearance.ron`:
+//! when Bevy's `file_watcher` feature is enabled (`cargo run --features bevy/file_watcher`),
+//! saving the file recolors the map straight away.
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::simulation::{FireIntensity, TileKind};
+
+pub struct TileAppearancePlugin;
+
+impl Plugin for TileAppearancePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileAppearance>()
+            .register_type::<TileAppearance>()
+            .init_resource::<Palette>()
+            .register_type::<Palette>()
+            .init_asset::<TileAppearanceOverrides>()
+            .init_asset_loader::<TileAppearanceLoader>()
+            .add_systems(Startup, load_tile_appearance)
+            .add_systems(Update, apply_tile_appearance_overrides);
+    }
+}
+
+/// How each kind of tile is drawn.
+///
+/// Changing this recolors every tile on the map.
+/// It is rebuilt from the [`Palette`] whenever that changes, with the overrides from the asset file applied on top.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct TileAppearance {
+    pub kinds: HashMap<TileKind, KindAppearance>,
+}
+
+/// How a single kind of tile is drawn: see [`TileAppearance`].
+#[derive(Reflect, Debug, Clone, Copy, PartialEq)]
+pub struct KindAppearance {
+    /// The color of the tile, which tints its texture.
+    pub color: Color,
+    /// The texture in the tile atlas used to draw this kind, if it should borrow the texture of another kind.
+    ///
+    /// By default, each kind has a texture of its own.
+    pub texture_index: Option<usize>,
+    /// How this kind cycles through the frames of its texture, if it is animated.
+    pub animation: Option<FrameAnimation>,
+}
+
+/// Cycles a kind of tile through several frames of its texture, on a timer.
+///
+/// Every tile of the same kind shows the same frame at once.
+#[derive(Reflect, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct FrameAnimation {
+    /// How many frames to cycle through, starting from the first.
+    ///
+    /// This is capped by the number of frames in the tile atlas.
+    pub frames: u32,
+    /// How long each frame is shown for, in seconds.
+    pub seconds_per_frame: f32,
+}
+
+impl FrameAnimation {
+    /// The frame that should be shown after the given number of seconds.
+    pub fn frame_at(&self, elapsed_seconds: f32) -> u32 {
+        if self.frames == 0 || self.seconds_per_frame <= 0.0 {
+            return 0;
+        }
+
+        (elapsed_seconds / self.seconds_per_frame) as u32 % self.frames
+    }
+}
+
+impl Default for TileAppearance {
+    fn default() -> Self {
+        Self::from_palette(Palette::default())
+    }
+}
+
+impl TileAppearance {
+    /// The appearance of every kind of tile, colored by the given [`Palette`].
+    pub fn from_palette(palette: Palette) -> Self {
+        let kinds = TileKind::iter()
+            .map(|kind| {
+                let animation = match kind {
+                    // Flames flicker quickly, while water ripples lazily
+                    TileKind::Fire => Some(FrameAnimation {
+                        frames: 4,
+                        seconds_per_frame: 0.12,
+                    }),
+                    TileKind::Water => Some(FrameAnimation {
+                        frames: 4,
+                        seconds_per_frame: 0.5,
+                    }),
+                    _ => None,
+                };
+                let appearance = KindAppearance {
+                    color: palette.color(&kind),
+                    texture_index: None,
+                    animation,
+                };
+                (kind, appearance)
+            })
+            .collect();
+
+        Self { kinds }
+    }
+
+    /// The color of the given kind of tile, falling back to [`TileKind::color`] if none was set.
+    pub fn color(&self, tile_kind: &TileKind) -> Color {
+        self.kinds
+            .get(tile_kind)
+            .map_or_else(|| tile_kind.color(), |appearance| appearance.color)
+    }
+
+    /// The texture in the tile atlas that should be used for the given kind of tile, if it has been overridden.
+    pub fn texture_index(&self, tile_kind: &TileKind) -> Option<usize> {
+        self.kinds.get(tile_kind)?.texture_index
+    }
+
+    /// How the given kind of tile is animated, if at all.
+    pub fn animation(&self, tile_kind: &TileKind) -> Option<FrameAnimation> {
+        self.kinds.get(tile_kind)?.animation
+    }
+}
+
+/// A preset set of colors for the map and its overlays.
+///
+/// The default colors tell meadows and fires apart by red and green alone,
+/// which a large fraction of people can't distinguish, so the other presets lean on blue, orange and lightness instead.
+#[derive(Resource, Reflect, EnumIter, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum Palette {
+    /// The colors given by [`TileKind::color`].
+    #[default]
+    Default,
+    /// Vegetation in bluish greens and fire in orange, for people who can't see green well.
+    Deuteranopia,
+    /// Like [`Palette::Deuteranopia`], but with brighter fires, since reds look dark to people who can't see red well.
+    Protanopia,
+    /// Bold colors with large differences in lightness, which stay distinct on dim or washed out screens.
+    HighContrast,
+}
+
+impl Palette {
+    /// The color of the given kind of tile under this palette.
+    ///
+    /// Kinds that are already easy to tell apart keep their [`TileKind::color`].
+    pub fn color(&self, tile_kind: &TileKind) -> Color {
+        use TileKind::*;
+
+        let color = match (self, tile_kind) {
+            (Palette::Default, _) => None,
+            (Palette::Deuteranopia | Palette::Protanopia, kind) => match kind {
+                Meadow => Some(Color::srgb_u8(0x8f, 0xd9, 0xc1)),
+                Shrubland => Some(Color::srgb_u8(0x3d, 0xb8, 0x93)),
+                ShadeIntolerantForest | Tree => Some(Color::srgb_u8(0x00, 0x9e, 0x73)),
+                ShadeTolerantForest => Some(Color::srgb_u8(0x00, 0x56, 0x3f)),
+                Water => Some(Color::srgb_u8(0x56, 0xb4, 0xe9)),
+                Fire => Some(self.fire_color(FireIntensity::Burning)),
+                Burned => Some(Color::srgb_u8(0x1a, 0x1a, 0x1a)),
+                DeadForest => Some(Color::srgb_u8(0xcc, 0x79, 0xa7)),
+                InvasiveGrass | Scissors => Some(Color::srgb_u8(0xf0, 0xe4, 0x42)),
+                Conductor => Some(Color::srgb_u8(0xe6, 0x9f, 0x00)),
+                ElectronHead => Some(Color::srgb_u8(0x56, 0xb4, 0xe9)),
+                ElectronTail => Some(Color::srgb_u8(0x00, 0x72, 0xb2)),
+                Rock => Some(Color::srgb_u8(0xd5, 0x5e, 0x00)),
+                Paper => Some(Color::srgb_u8(0x00, 0x72, 0xb2)),
+                _ => None,
+            },
+            (Palette::HighContrast, kind) => match kind {
+                Meadow => Some(Color::hsl(90., 0.8, 0.85)),
+                Shrubland => Some(Color::hsl(100., 0.7, 0.55)),
+                ShadeIntolerantForest => Some(Color::hsl(120., 0.7, 0.35)),
+                ShadeTolerantForest => Some(Color::hsl(140., 0.8, 0.15)),
+                Water => Some(Color::hsl(220., 1.0, 0.45)),
+                Fire => Some(self.fire_color(FireIntensity::Burning)),
+                Burned => Some(Color::hsl(0., 0.0, 0.02)),
+                DeadForest => Some(Color::hsl(30., 0.6, 0.3)),
+                InvasiveGrass => Some(Color::hsl(55., 1.0, 0.6)),
+                Firebreak => Some(Color::hsl(0., 0.0, 0.6)),
+                Shore => Some(Color::hsl(45., 1.0, 0.8)),
+                Alive => Some(Color::WHITE),
+                Dead | Empty => Some(Color::BLACK),
+                _ => None,
+            },
+        };
+
+        color.unwrap_or_else(|| tile_kind.color())
+    }
+
+    /// The color of a fire burning at the given intensity under this palette.
+    pub fn fire_color(&self, intensity: FireIntensity) -> Color {
+        use FireIntensity::*;
+
+        match (self, intensity) {
+            (Palette::Default, intensity) => intensity.color(),
+            (Palette::Deuteranopia, Smoldering) => Color::hsl(25., 0.85, 0.35),
+            (Palette::Deuteranopia, Burning) => Color::srgb_u8(0xd5, 0x5e, 0x00),
+            (Palette::Deuteranopia, CrownFire) => Color::hsl(50., 1.0, 0.6),
+            (Palette::Protanopia, Smoldering) => Color::hsl(30., 0.9, 0.4),
+            (Palette::Protanopia, Burning) => Color::srgb_u8(0xe6, 0x9f, 0x00),
+            (Palette::Protanopia, CrownFire) => Color::hsl(55., 1.0, 0.7),
+            (Palette::HighContrast, Smoldering) => Color::hsl(0., 1.0, 0.35),
+            (Palette::HighContrast, Burning) => Color::hsl(20., 1.0, 0.5),
+            (Palette::HighContrast, CrownFire) => Color::hsl(55., 1.0, 0.75),
+        }
+    }
+}
+
+/// Overrides for the default [`TileAppearance`], as read from a `.appearance.ron` file.
+#[derive(Asset, TypePath, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct TileAppearanceOverrides {
+    /// Replaces the appearance of each listed tile kind.
+    pub kinds: HashMap<TileKind, KindAppearanceOverride>,
+}
+
+/// Overrides for the fields of [`KindAppearance`].
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct KindAppearanceOverride {
+    /// A hex color code, such as `"#3a7d44"`.
+    pub color: Option<String>,
+    pub texture_index: Option<usize>,
+    pub animation: Option<FrameAnimation>,
+}
+
+#[derive(Default)]
+struct TileAppearanceLoader;
+
+impl AssetLoader for TileAppearanceLoader {
+    type Asset = TileAppearanceOverrides;
+    type Settings = ();
+    type Error = TileAppearanceLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["appearance.ron"]
+    }
+}
+
+/// The ways in which loading a [`TileAppearanceOverrides`] file can fail.
+#[derive(Debug)]
+enum TileAppearanceLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl std::fmt::Display for TileAppearanceLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TileAppearanceLoaderError::Io(error) => {
+                write!(f, "could not read tile appearance: {error}")
+            }
+            TileAppearanceLoaderError::Ron(error) => {
+                write!(f, "could not parse tile appearance: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TileAppearanceLoaderError {}
+
+impl From<std::io::Error> for TileAppearanceLoaderError {
+    fn from(error: std::io::Error) -> Self {
+        TileAppearanceLoaderError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for TileAppearanceLoaderError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        TileAppearanceLoaderError::Ron(error)
+    }
+}
+
+/// Keeps the tile appearance overrides loaded, so that they can be hot-reloaded.
+#[derive(Resource)]
+struct TileAppearanceHandle(Handle<TileAppearanceOverrides>);
+
+fn load_tile_appearance(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(TileAppearanceHandle(
+        asset_server.load("tiles.appearance.ron"),
+    ));
+}
+
+/// Resets the [`TileAppearance`] to the colors of the [`Palette`], then applies the overrides from the asset file.
+///
+/// This runs whenever the file is (re)loaded, or a different palette is picked.
+fn apply_tile_appearance_overrides(
+    mut asset_events: EventReader<AssetEvent<TileAppearanceOverrides>>,
+    handle: Option<Res<TileAppearanceHandle>>,
+    overrides: Res<Assets<TileAppearanceOverrides>>,
+    palette: Res<Palette>,
+    mut tile_appearance: ResMut<TileAppearance>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    let asset_changed = asset_events.read().any(|event| {
+        matches!(
+            event,
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if *id == handle.0.id()
+        )
+    });
+    if !asset_changed && !palette.is_changed() {
+        return;
+    }
+
+    info!("Applying tile appearance");
+
+    *tile_appearance = TileAppearance::from_palette(*palette);
+    // The palette still applies if the asset file is missing or hasn't loaded yet
+    let Some(overrides) = overrides.get(&handle.0) else {
+        return;
+    };
+
+    for (tile_kind, kind_override) in &overrides.kinds {
+        let Some(appearance) = tile_appearance.kinds.get_mut(tile_kind) else {
+            continue;
+        };
+
+        if let Some(hex) = &kind_override.color {
+            match Srgba::hex(hex) {
+                Ok(color) => appearance.color = color.into(),
+                Err(error) => warn!("Invalid color {hex} for {tile_kind:?}: {error}"),
+            }
+        }
+        if kind_override.texture_index.is_some() {
+            appearance.texture_index = kind_override.texture_index;
+        }
+        if kind_override.animation.is_some() {
+            appearance.animation = kind_override.animation;
+        }
+    }
+}
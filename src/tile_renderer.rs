@@ -0,0 +1,419 @@
+//! Draws every tile on the map with a single mesh, rather than a sprite per tile.
+//!
+//! Batching hundreds of thousands of sprites each frame dominates the frame time on large maps.
+//! Instead, while the [`TileRenderer::SingleMesh`] is active, the whole map is drawn as one quad,
+//! whose [`TileMapMaterial`] looks up the color and atlas texture of each tile in a storage buffer.
+//!
+//! The tile entities keep their [`Sprite`]s, which are still the source of truth for how each tile looks:
+//! the rest of the crate recolors them as usual, and any changes are copied into the buffer.
+//! Their sprites are simply moved onto a [`RenderLayers`] that no camera draws.
+//!
+//! So far, only square grids can be drawn this way: hexagonal grids always fall back to sprites.
+
+use std::ops::Range;
+
+use bevy::prelude::*;
+use bevy::render::render_asset::{RenderAssetUsages, RenderAssets};
+use bevy::render::render_resource::{AsBindGroup, BufferUsages, ShaderRef};
+use bevy::render::renderer::RenderQueue;
+use bevy::render::storage::{GpuShaderStorageBuffer, ShaderStorageBuffer};
+use bevy::render::view::RenderLayers;
+use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderSet};
+use bevy::sprite::{AlphaMode2d, Material2d, Material2dPlugin};
+
+use crate::SimState;
+use crate::level_of_detail::LevelOfDetail;
+use crate::map_generation::GeneratedMapSize;
+use crate::spatial_index::{GridShape, Position, Tile};
+
+/// The shader that draws the tile map, in the `assets` folder.
+const SHADER_ASSET_PATH: &str = "shaders/tile_map.wgsl";
+
+pub struct TileRendererPlugin;
+
+impl Plugin for TileRendererPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Material2dPlugin::<TileMapMaterial>::default())
+            .init_resource::<TileRenderer>()
+            .register_type::<TileRenderer>()
+            .add_systems(OnEnter(SimState::Generate), despawn_tile_map)
+            .add_systems(
+                Update,
+                (
+                    spawn_tile_map.run_if(single_mesh_active),
+                    despawn_tile_map.run_if(not(single_mesh_active)),
+                )
+                    .run_if(not(in_state(SimState::Generate))),
+            )
+            .add_systems(
+                PostUpdate,
+                (sync_tile_map, hide_tile_map_when_zoomed_out).run_if(resource_exists::<TileMap>),
+            );
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .add_systems(ExtractSchedule, extract_tile_map_changes)
+            .add_systems(
+                Render,
+                write_tile_map_changes
+                    .in_set(RenderSet::PrepareResources)
+                    .run_if(resource_exists::<TileMapUpload>),
+            );
+    }
+}
+
+/// How the tiles on the map are drawn.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Resource)]
+pub enum TileRenderer {
+    /// Each tile is drawn as its own sprite.
+    ///
+    /// This works for every grid shape, but gets slow on large maps.
+    Sprites,
+    /// The whole map is drawn as a single mesh, which stays fast however large the map gets.
+    ///
+    /// Only square grids are supported: hexagonal grids fall back to [`TileRenderer::Sprites`].
+    #[default]
+    SingleMesh,
+}
+
+/// A run condition that checks whether the tiles are currently drawn as a single mesh.
+fn single_mesh_active(tile_renderer: Res<TileRenderer>, grid_shape: Res<GridShape>) -> bool {
+    *tile_renderer == TileRenderer::SingleMesh && *grid_shape == GridShape::Square
+}
+
+/// The render layer that tile sprites are moved onto while the tile map is drawn instead.
+///
+/// No camera draws this layer, but the sprites stay visible as far as the rest of the crate is concerned.
+const HIDDEN_SPRITE_LAYER: usize = 31;
+
+/// Draws the whole map at once, looking up how each tile looks by its position.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+struct TileMapMaterial {
+    /// The width and height of the map in tiles, followed by the number of columns and rows in the tile atlas.
+    #[uniform(0)]
+    grid: UVec4,
+    /// The packed sRGB color and atlas texture of each tile, in rows from the bottom of the map.
+    #[storage(1, read_only)]
+    tiles: Handle<ShaderStorageBuffer>,
+    #[texture(2)]
+    #[sampler(3)]
+    atlas: Handle<Image>,
+}
+
+impl Material2d for TileMapMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode2d {
+        AlphaMode2d::Blend
+    }
+}
+
+/// The tile map that is currently being drawn, along with a copy of what is in its buffer.
+#[derive(Resource)]
+struct TileMap {
+    entity: Entity,
+    buffer: Handle<ShaderStorageBuffer>,
+    size: UVec2,
+    /// The packed color and atlas texture of each tile, as stored in the buffer.
+    tiles: Vec<UVec2>,
+    /// The tiles that changed this frame, and need copying into the buffer, as sorted runs of nearby tiles.
+    changed: Vec<Range<usize>>,
+}
+
+impl TileMap {
+    /// Returns where the tile at the given position is stored in the buffer, if it is on the map.
+    fn index(&self, position: &Position) -> Option<usize> {
+        let x = u32::try_from(position.x)
+            .ok()
+            .filter(|&x| x < self.size.x)?;
+        let y = u32::try_from(position.y)
+            .ok()
+            .filter(|&y| y < self.size.y)?;
+        Some((y * self.size.x + x) as usize)
+    }
+}
+
+/// Packs the appearance of a tile's sprite into the form used by the shader.
+fn pack_tile(sprite: &Sprite) -> UVec2 {
+    let color = u32::from_le_bytes(sprite.color.to_srgba().to_u8_array());
+    let texture = sprite
+        .texture_atlas
+        .as_ref()
+        .map_or(0, |texture_atlas| texture_atlas.index as u32);
+
+    UVec2::new(color, texture)
+}
+
+/// Tiles closer together than this are uploaded in a single write, along with the unchanged tiles between them.
+///
+/// Each write to the buffer has a fixed cost, so it's cheaper to reupload a few unchanged tiles than to split the write.
+const MERGE_DISTANCE: usize = 64;
+
+/// The most separate writes made to the buffer in one frame, beyond which the whole buffer is uploaded instead.
+const MAX_WRITES: usize = 32;
+
+/// Sorts the given ranges of tiles, merging any that overlap or lie within [`MERGE_DISTANCE`] of each other.
+///
+/// If that still leaves more than [`MAX_WRITES`] ranges, the whole map of `tile_count` tiles is covered instead.
+fn coalesce_ranges(mut ranges: Vec<Range<usize>>, tile_count: usize) -> Vec<Range<usize>> {
+    ranges.sort_unstable_by_key(|range| range.start);
+
+    let mut coalesced: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match coalesced.last_mut() {
+            Some(last) if range.start < last.end + MERGE_DISTANCE => {
+                last.end = last.end.max(range.end);
+            }
+            _ => coalesced.push(range),
+        }
+    }
+
+    if coalesced.len() > MAX_WRITES {
+        coalesced.clear();
+        coalesced.push(0..tile_count);
+    }
+
+    coalesced
+}
+
+fn tile_bytes(tiles: &[UVec2]) -> Vec<u8> {
+    tiles
+        .iter()
+        .flat_map(|tile| tile.to_array())
+        .flat_map(u32::to_le_bytes)
+        .collect()
+}
+
+/// Spawns the tile map once the map has been generated, and hides the tile sprites that it replaces.
+#[allow(clippy::too_many_arguments)]
+fn spawn_tile_map(
+    tile_map: Option<Res<TileMap>>,
+    tile_query: Query<(Entity, &Position, &Sprite), With<Tile>>,
+    map_size: Res<GeneratedMapSize>,
+    atlas_layouts: Res<Assets<TextureAtlasLayout>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<TileMapMaterial>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut commands: Commands,
+) {
+    if tile_map.is_some() {
+        return;
+    }
+
+    // Every tile shares the same atlas, which is assigned as the tiles are spawned
+    let Some((atlas, atlas_layout)) = tile_query.iter().find_map(|(_, _, sprite)| {
+        let layout = atlas_layouts.get(&sprite.texture_atlas.as_ref()?.layout)?;
+        Some((sprite.image.clone(), layout))
+    }) else {
+        return;
+    };
+    let texture_size = atlas_layout
+        .textures
+        .first()
+        .map_or(UVec2::ONE, URect::size);
+    let atlas_grid = atlas_layout.size / texture_size.max(UVec2::ONE);
+
+    let size = UVec2::new(map_size.width.max(1) as u32, map_size.height.max(1) as u32);
+    let mut tile_map = TileMap {
+        entity: Entity::PLACEHOLDER,
+        buffer: Handle::default(),
+        size,
+        // Gaps in the map, such as tiles outside of the map mask, are left transparent
+        tiles: vec![UVec2::ZERO; (size.x * size.y) as usize],
+        changed: Vec::new(),
+    };
+
+    for (entity, position, sprite) in tile_query.iter() {
+        if let Some(index) = tile_map.index(position) {
+            tile_map.tiles[index] = pack_tile(sprite);
+        }
+
+        commands
+            .entity(entity)
+            .insert(RenderLayers::layer(HIDDEN_SPRITE_LAYER));
+    }
+
+    let mut buffer = ShaderStorageBuffer::new(
+        &tile_bytes(&tile_map.tiles),
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    buffer.buffer_description.usage |= BufferUsages::COPY_DST;
+    tile_map.buffer = buffers.add(buffer);
+
+    let material = materials.add(TileMapMaterial {
+        grid: size.extend(atlas_grid.x).extend(atlas_grid.y),
+        tiles: tile_map.buffer.clone(),
+        atlas,
+    });
+
+    // The quad covers the map exactly, with the center of each tile over the center of its sprite
+    let map_pixels = size.as_vec2() * Position::PIXELS_PER_TILE;
+    let center = (map_pixels - Position::PIXELS_PER_TILE) / 2.0;
+    tile_map.entity = commands
+        .spawn((
+            Name::new("Tile map"),
+            Mesh2d(meshes.add(Rectangle::from_size(map_pixels))),
+            MeshMaterial2d(material),
+            Transform::from_translation(center.extend(0.0)),
+        ))
+        .id();
+
+    commands.insert_resource(tile_map);
+
+    info!("Drawing {}x{} tiles as a single mesh", size.x, size.y);
+}
+
+/// Copies the appearance of every tile whose sprite changed into the tile map.
+#[allow(clippy::type_complexity)]
+fn sync_tile_map(
+    tile_query: Query<(&Position, &Sprite), (With<Tile>, Changed<Sprite>)>,
+    mut tile_map: ResMut<TileMap>,
+) {
+    let tile_map = tile_map.bypass_change_detection();
+
+    let mut changed: Vec<Range<usize>> = Vec::new();
+    for (position, sprite) in tile_query.iter() {
+        let Some(index) = tile_map.index(position) else {
+            continue;
+        };
+
+        let tile = pack_tile(sprite);
+        if tile_map.tiles[index] != tile {
+            tile_map.tiles[index] = tile;
+            changed.push(index..index + 1);
+        }
+    }
+
+    tile_map.changed = coalesce_ranges(changed, tile_map.tiles.len());
+}
+
+/// Hides the tile map while the [`LevelOfDetail`] draws a downsampled copy of the map instead.
+fn hide_tile_map_when_zoomed_out(
+    tile_map: Res<TileMap>,
+    level_of_detail: Res<LevelOfDetail>,
+    mut visibility_query: Query<&mut Visibility>,
+) {
+    let Ok(mut visibility) = visibility_query.get_mut(tile_map.entity) else {
+        return;
+    };
+
+    visibility.set_if_neq(if level_of_detail.active() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    });
+}
+
+/// Despawns the tile map, showing the tile sprites again if they are still around.
+fn despawn_tile_map(
+    tile_map: Option<Res<TileMap>>,
+    tile_query: Query<Entity, With<Tile>>,
+    mut commands: Commands,
+) {
+    let Some(tile_map) = tile_map else {
+        return;
+    };
+
+    commands.entity(tile_map.entity).despawn();
+    commands.remove_resource::<TileMap>();
+
+    for entity in tile_query.iter() {
+        commands.entity(entity).remove::<RenderLayers>();
+    }
+}
+
+/// Tiles that have changed since the tile map's buffer was last written to.
+#[derive(Resource)]
+struct TileMapUpload {
+    buffer: AssetId<ShaderStorageBuffer>,
+    /// Each run of changed tiles, along with the bytes to write over it.
+    writes: Vec<(Range<usize>, Vec<u8>)>,
+}
+
+/// Copies the tiles that changed this frame into the render world.
+///
+/// Changes are merged with any that are still waiting for the buffer to be created on the GPU.
+fn extract_tile_map_changes(
+    tile_map: Extract<Option<Res<TileMap>>>,
+    pending: Option<ResMut<TileMapUpload>>,
+    mut commands: Commands,
+) {
+    let Some(tile_map) = tile_map.as_ref() else {
+        commands.remove_resource::<TileMapUpload>();
+        return;
+    };
+    if tile_map.changed.is_empty() {
+        return;
+    }
+
+    let mut ranges = tile_map.changed.clone();
+    if let Some(pending) = pending.filter(|pending| pending.buffer == tile_map.buffer.id()) {
+        ranges.extend(pending.writes.iter().map(|(range, _)| range.clone()));
+        ranges = coalesce_ranges(ranges, tile_map.tiles.len());
+    }
+
+    commands.insert_resource(TileMapUpload {
+        buffer: tile_map.buffer.id(),
+        writes: ranges
+            .into_iter()
+            .map(|range| {
+                let bytes = tile_bytes(&tile_map.tiles[range.clone()]);
+                (range, bytes)
+            })
+            .collect(),
+    });
+}
+
+/// Writes the changed tiles into the tile map's buffer, once it exists.
+fn write_tile_map_changes(
+    upload: Res<TileMapUpload>,
+    buffers: Res<RenderAssets<GpuShaderStorageBuffer>>,
+    render_queue: Res<RenderQueue>,
+    mut commands: Commands,
+) {
+    let Some(gpu_buffer) = buffers.get(upload.buffer) else {
+        return;
+    };
+
+    for (range, bytes) in &upload.writes {
+        let offset = (range.start * size_of::<UVec2>()) as u64;
+        render_queue.write_buffer(&gpu_buffer.buffer, offset, bytes);
+    }
+    commands.remove_resource::<TileMapUpload>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearby_changes_are_merged_into_one_write() {
+        let ranges = vec![10..11, 0..1, 40..41, 500..501];
+
+        assert_eq!(coalesce_ranges(ranges, 1000), vec![0..41, 500..501]);
+    }
+
+    #[test]
+    fn changes_at_both_ends_are_written_separately() {
+        let ranges = vec![0..1, 999..1000];
+
+        assert_eq!(coalesce_ranges(ranges, 1000), vec![0..1, 999..1000]);
+    }
+
+    #[test]
+    fn scattered_changes_fall_back_to_a_full_upload() {
+        let tile_count = 2 * MERGE_DISTANCE * (MAX_WRITES + 1);
+        let ranges = (0..=MAX_WRITES)
+            .map(|run| run * 2 * MERGE_DISTANCE)
+            .map(|index| index..index + 1)
+            .collect();
+
+        assert_eq!(coalesce_ranges(ranges, tile_count), vec![0..tile_count]);
+    }
+}
@@ -0,0 +1,118 @@
+//! Random number streams that belong to individual tiles, rather than to the whole simulation.
+//!
+//! Drawing every random number from a single global generator means that the outcome of a tick
+//! depends on the order in which tiles happen to be visited, which can change between runs
+//! as entities are spawned and despawned, or if the work is split across threads.
+//! Instead, each tile gets its own generator, seeded from the map seed, the current tick, and its position.
+//! The same map seed will then always produce the same simulation, no matter how the tiles are iterated.
+//! Rolls that aren't tied to a single tile, such as where lightning strikes, use a generator shared by the whole map,
+//! seeded in the same way but without a position.
+
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use rand::SeedableRng;
+
+use crate::SimState;
+use crate::map_generation::MapSeed;
+use crate::spatial_index::Position;
+
+pub struct TileRngPlugin;
+
+impl Plugin for TileRngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileRng>()
+            .register_type::<TileRng>()
+            .add_systems(OnEnter(SimState::Generate), reseed_tile_rng);
+    }
+}
+
+/// Separates the random numbers drawn by different systems for the same tile on the same tick,
+/// so that they don't end up correlated with each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileRngStream {
+    /// Rolls made by the [`ActiveRule`](crate::simulation::ActiveRule) when computing the next kind of a tile.
+    CellularRule,
+    /// Rolls made when a burning tile throws embers.
+    SpotFires,
+    /// Rolls made when deciding how long a newly ignited fire will burn for.
+    BurnDuration,
+    /// Rolls made when lightning picks where to strike, and whether the strike starts a fire.
+    Lightning,
+    /// Rolls that decide where each rainstorm falls.
+    Rainfall,
+    /// Rolls made when deciding whether shoreline water dries out during a drought.
+    ShorelineDrying,
+    /// Rolls made when deciding whether low-lying land floods after rain.
+    Flooding,
+    /// Rolls made when deciding how long a drought will last.
+    Drought,
+    /// Rolls that decide where a windstorm hits, and which trees it blows down.
+    Windstorm,
+    /// Rolls made when choosing which tiles an insect outbreak starts in.
+    OutbreakStart,
+    /// Rolls made when beetles spread from an infested tile to its neighbors.
+    BeetleSpread,
+    /// Rolls made when deciding whether infested trees die.
+    BeetleMortality,
+    /// Rolls made when deciding which disturbances begin on each tick.
+    DisturbanceSchedule,
+    /// Rolls made when choosing which patch of mature forest to log.
+    Logging,
+    /// Rolls made when herbivores choose which way to wander.
+    HerbivoreMove,
+    /// Rolls made when herbivores decide whether to eat the growth on their tile.
+    Grazing,
+}
+
+/// Hands out a deterministic random number generator for each tile on each tick.
+#[derive(Resource, Reflect, Debug, Clone, Copy, Default)]
+#[reflect(Resource)]
+pub struct TileRng {
+    /// The seed shared by every tile, taken from the [`MapSeed`] each time the map is generated.
+    map_seed: u64,
+    /// The number of simulation ticks since the map was generated.
+    tick: u64,
+}
+
+impl TileRng {
+    /// Returns the random number generator for the tile at the given position on the current tick.
+    ///
+    /// Calling this twice with the same arguments on the same tick returns generators that produce the same numbers.
+    pub fn for_tile(&self, position: &Position, stream: TileRngStream) -> WyRand {
+        let packed_position = ((position.x as u32 as u64) << 32) | position.y as u32 as u64;
+        let seed = mix(mix(mix(self.map_seed ^ stream as u64) ^ self.tick) ^ packed_position);
+
+        WyRand::seed_from_u64(seed)
+    }
+
+    /// Returns the random number generator for rolls that aren't tied to any one tile on the current tick.
+    ///
+    /// Like [`TileRng::for_tile`], calling this twice with the same stream on the same tick returns the same numbers.
+    pub fn for_map(&self, stream: TileRngStream) -> WyRand {
+        WyRand::seed_from_u64(mix(mix(self.map_seed ^ stream as u64) ^ self.tick))
+    }
+}
+
+/// Scrambles the bits of a value, using the finalizer from SplitMix64.
+///
+/// Nearby inputs (such as neighboring positions) produce wildly different outputs.
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn reseed_tile_rng(mut tile_rng: ResMut<TileRng>, map_seed: Res<MapSeed>) {
+    *tile_rng = TileRng {
+        map_seed: map_seed.0,
+        tick: 0,
+    };
+}
+
+/// Moves every tile on to a fresh set of random numbers for the next tick.
+///
+/// This should run before any system that uses the [`TileRng`] each tick.
+pub fn advance_tile_rng(mut tile_rng: ResMut<TileRng>) {
+    tile_rng.tick += 1;
+}
@@ -0,0 +1,424 @@
+//! An experimental map generator based on wave function collapse.
+//!
+//! Every tile starts out as a superposition of every tile kind that it could be.
+//! One at a time, the tile with the fewest remaining options is collapsed to a single kind,
+//! and the options of its neighbors are narrowed down to those that are allowed to sit next to it.
+//! This repeats until every tile has been decided, producing a landscape that follows the [`AdjacencyRules`].
+//!
+//! Select it by setting the [`TerrainGenerator`] to [`TerrainGenerator::WaveFunctionCollapse`]
+//! (or using the `generator` console command).
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_simple_subsecond_system::hot;
+use rand::Rng;
+
+use crate::SimState;
+use crate::map_generation::{
+    GenerateTerrain, GenerationStep, MapSeed, TerrainBatch, TerrainGenerator, TerrainPhase,
+    generator_is, ruleset_has_terrain,
+};
+use crate::simulation::TileKind;
+use crate::spatial_index::{GridShape, Position};
+
+pub struct WaveFunctionCollapsePlugin;
+
+impl Plugin for WaveFunctionCollapsePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AdjacencyRules>()
+            .register_type::<AdjacencyRules>()
+            .add_systems(
+                GenerateTerrain,
+                collapse_wave_function
+                    .in_set(TerrainPhase::WaveFunctionCollapse)
+                    .run_if(ruleset_has_terrain)
+                    .run_if(generator_is(TerrainGenerator::WaveFunctionCollapse)),
+            )
+            .add_systems(
+                Update,
+                regenerate_when_rules_change.run_if(resource_changed::<AdjacencyRules>),
+            );
+    }
+}
+
+/// A small hand-drawn landscape, used to learn the default [`AdjacencyRules`].
+///
+/// `~` is water, `.` is meadow, `s` is shrubland, `f` is shade-intolerant forest and `F` is shade-tolerant forest.
+const EXAMPLE_MAP: [&str; 8] = [
+    "~~~~..ssffFF",
+    "~~~...ssfFFF",
+    "~~....sffFFF",
+    "~.....ssfFFF",
+    "......ssffFF",
+    "...ss..sssff",
+    "..ssss....sf",
+    ".ssffss.....",
+];
+
+/// The constraints followed by the wave function collapse generator.
+///
+/// By default, these are learned from a small example map, using [`AdjacencyRules::from_example`].
+/// They can also be written by hand: every pair of kinds that isn't listed can never be neighbors.
+#[derive(Resource, Reflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct AdjacencyRules {
+    /// The tile kinds that can be placed, and how often each should be chosen relative to the others.
+    pub weights: Vec<(TileKind, f32)>,
+    /// The pairs of tile kinds that may be placed next to each other, in either order.
+    pub allowed_pairs: Vec<(TileKind, TileKind)>,
+    /// How many times to start over from scratch after reaching a tile with no valid options,
+    /// before giving up and filling the remaining tiles with the most common kind.
+    pub max_attempts: u32,
+}
+
+impl Default for AdjacencyRules {
+    fn default() -> Self {
+        let example: Vec<Vec<TileKind>> = EXAMPLE_MAP
+            .iter()
+            .map(|row| row.chars().map(example_kind).collect())
+            .collect();
+
+        Self::from_example(&example)
+    }
+}
+
+/// The tile kind represented by each character of the [`EXAMPLE_MAP`].
+fn example_kind(character: char) -> TileKind {
+    match character {
+        '~' => TileKind::Water,
+        's' => TileKind::Shrubland,
+        'f' => TileKind::ShadeIntolerantForest,
+        'F' => TileKind::ShadeTolerantForest,
+        _ => TileKind::Meadow,
+    }
+}
+
+impl AdjacencyRules {
+    /// Learns the rules from an example map, given as rows of tile kinds.
+    ///
+    /// Each kind is weighted by how often it appears,
+    /// and two kinds are allowed to be neighbors if they are ever found side by side (or one above the other).
+    pub fn from_example(example: &[Vec<TileKind>]) -> Self {
+        let mut weights: Vec<(TileKind, f32)> = Vec::new();
+        let mut allowed_pairs: Vec<(TileKind, TileKind)> = Vec::new();
+
+        let mut allow = |a: TileKind, b: TileKind| {
+            if !allowed_pairs.contains(&(a, b)) && !allowed_pairs.contains(&(b, a)) {
+                allowed_pairs.push((a, b));
+            }
+        };
+
+        for (y, row) in example.iter().enumerate() {
+            for (x, &kind) in row.iter().enumerate() {
+                match weights.iter_mut().find(|(existing, _)| *existing == kind) {
+                    Some((_, weight)) => *weight += 1.0,
+                    None => weights.push((kind, 1.0)),
+                }
+
+                // Every tile is allowed to sit next to more of itself
+                allow(kind, kind);
+                if let Some(&right) = row.get(x + 1) {
+                    allow(kind, right);
+                }
+                if let Some(&below) = example.get(y + 1).and_then(|next_row| next_row.get(x)) {
+                    allow(kind, below);
+                }
+            }
+        }
+
+        Self {
+            weights,
+            allowed_pairs,
+            max_attempts: 10,
+        }
+    }
+}
+
+/// The options remaining for each tile, stored as a bitmask over the kinds in the [`AdjacencyRules`].
+type Options = u64;
+
+/// How far [`collapse_wave_function`] has got, kept from one frame to the next.
+#[derive(Default)]
+struct WaveFunctionProgress {
+    stage: WaveFunctionStage,
+    /// The position of each cell, in the order that the tiles were spawned.
+    positions: Vec<Position>,
+    /// The cell at each position.
+    cell_lookup: HashMap<Position, usize>,
+    /// The cells that each cell is adjacent to.
+    neighbors: Vec<Vec<usize>>,
+    /// The options remaining for each cell.
+    cells: Vec<Options>,
+    /// The undecided cells, ordered by how many options they have left: see [`collapse`].
+    queue: BinaryHeap<Reverse<(u32, u32, usize)>>,
+    /// The next cell to link up or queue.
+    next_cell: usize,
+    rng: Option<WyRand>,
+    attempt: u32,
+}
+
+/// What [`collapse_wave_function`] is doing at the moment.
+#[derive(Default, Clone, Copy)]
+enum WaveFunctionStage {
+    /// Sweeping across the map to find every cell.
+    #[default]
+    Gathering,
+    /// Finding the neighbors of each cell.
+    Linking,
+    /// Queueing up every undecided cell, ready for a fresh attempt.
+    Queueing,
+    /// Collapsing one cell after another.
+    Collapsing,
+    /// Sweeping across the map to give each tile the kind that its cell collapsed to.
+    Writing,
+}
+
+/// Fills the map using wave function collapse, following the [`AdjacencyRules`].
+///
+/// Rivers and shorelines are still added afterwards, so these may break the rules in places.
+/// Collapsing the wave function means working across the whole map,
+/// so this is spread over as many frames as it needs, doing a [`TerrainBatch::budget`] of cells' worth of work each frame.
+#[hot]
+fn collapse_wave_function(
+    mut tile_query: Query<(&Position, &mut TileKind)>,
+    adjacency_rules: Res<AdjacencyRules>,
+    map_seed: Res<MapSeed>,
+    grid_shape: Res<GridShape>,
+    mut batch: ResMut<TerrainBatch>,
+    mut progress: Local<WaveFunctionProgress>,
+) {
+    let kinds: Vec<(TileKind, f32)> = adjacency_rules
+        .weights
+        .iter()
+        .copied()
+        .filter(|(_, weight)| *weight > 0.0)
+        .take(Options::BITS as usize)
+        .collect();
+
+    let Some(&(fallback_kind, _)) = kinds.iter().max_by(|(_, a), (_, b)| a.total_cmp(b)) else {
+        warn!(
+            "The adjacency rules don't allow any tile kinds, so wave function collapse was skipped"
+        );
+        return;
+    };
+
+    // For each kind, the set of kinds that may sit next to it
+    let compatible: Vec<Options> = kinds
+        .iter()
+        .map(|(kind, _)| {
+            kinds
+                .iter()
+                .enumerate()
+                .filter(|(_, (other, _))| {
+                    adjacency_rules.allowed_pairs.contains(&(*kind, *other))
+                        || adjacency_rules.allowed_pairs.contains(&(*other, *kind))
+                })
+                .fold(0, |options, (index, _)| options | 1 << index)
+        })
+        .collect();
+
+    let all_options: Options = (0..kinds.len()).fold(0, |options, index| options | 1 << index);
+
+    if batch.starts_phase() {
+        *progress = WaveFunctionProgress::default();
+    }
+    let progress = &mut *progress;
+    let cell_count = progress.positions.len();
+    let work = progress.next_cell..(progress.next_cell + batch.budget()).min(cell_count);
+
+    match progress.stage {
+        WaveFunctionStage::Gathering => {
+            for &entity in batch.tiles() {
+                if let Ok((position, _)) = tile_query.get(entity) {
+                    progress
+                        .cell_lookup
+                        .insert(*position, progress.positions.len());
+                    progress.positions.push(*position);
+                }
+            }
+
+            if batch.ends_sweep() {
+                progress.stage = WaveFunctionStage::Linking;
+                batch.sweep_again();
+            }
+        }
+        WaveFunctionStage::Linking => {
+            for cell in work.clone() {
+                let position = progress.positions[cell];
+                let adjacent = match *grid_shape {
+                    GridShape::Square => position.cardinal_neighbors().to_vec(),
+                    GridShape::Hexagonal => position.hex_neighbors().to_vec(),
+                };
+
+                let neighbors = adjacent
+                    .iter()
+                    .filter_map(|neighbor| progress.cell_lookup.get(neighbor).copied())
+                    .collect();
+                progress.neighbors.push(neighbors);
+            }
+
+            progress.next_cell = work.end;
+            if work.end >= cell_count {
+                progress.cells = vec![all_options; cell_count];
+                progress.rng = Some(map_seed.rng(GenerationStep::WaveFunctionCollapse));
+                progress.attempt = 1;
+                progress.next_cell = 0;
+                progress.stage = WaveFunctionStage::Queueing;
+            }
+            batch.sweep_again();
+        }
+        WaveFunctionStage::Queueing => {
+            let Some(rng) = progress.rng.as_mut() else {
+                return;
+            };
+
+            // Collapse the most constrained cell first, breaking ties at random to avoid directional artifacts
+            for cell in work.clone() {
+                let options = progress.cells[cell];
+                if options.count_ones() > 1 {
+                    progress
+                        .queue
+                        .push(Reverse((options.count_ones(), rng.random(), cell)));
+                }
+            }
+
+            progress.next_cell = work.end;
+            if work.end >= cell_count {
+                progress.stage = WaveFunctionStage::Collapsing;
+            }
+            batch.sweep_again();
+        }
+        WaveFunctionStage::Collapsing => {
+            let Some(rng) = progress.rng.as_mut() else {
+                return;
+            };
+
+            match collapse(
+                &mut progress.cells,
+                &mut progress.queue,
+                &kinds,
+                &compatible,
+                &progress.neighbors,
+                rng,
+                batch.budget(),
+            ) {
+                Some(true) => progress.stage = WaveFunctionStage::Writing,
+                Some(false) if progress.attempt >= adjacency_rules.max_attempts => {
+                    warn!(
+                        "Wave function collapse failed after {} attempts: filling the remaining tiles with {fallback_kind:?}",
+                        progress.attempt
+                    );
+                    progress.stage = WaveFunctionStage::Writing;
+                }
+                Some(false) => {
+                    progress.attempt += 1;
+                    progress.cells.fill(all_options);
+                    progress.queue.clear();
+                    progress.next_cell = 0;
+                    progress.stage = WaveFunctionStage::Queueing;
+                }
+                None => {}
+            }
+            batch.sweep_again();
+        }
+        WaveFunctionStage::Writing => {
+            for &entity in batch.tiles() {
+                let Ok((position, mut tile_kind)) = tile_query.get_mut(entity) else {
+                    continue;
+                };
+                let Some(&cell) = progress.cell_lookup.get(position) else {
+                    continue;
+                };
+
+                let options = progress.cells[cell];
+                *tile_kind = if options.count_ones() == 1 {
+                    kinds[options.trailing_zeros() as usize].0
+                } else {
+                    fallback_kind
+                };
+            }
+        }
+    }
+}
+
+/// Collapses up to `budget` cells from the `queue`, each down to a single option.
+///
+/// Returns `Some(true)` once every cell has been collapsed, `Some(false)` if a cell ran out of options,
+/// or `None` if there are still cells left to collapse.
+/// Undecided cells are kept in the queue ordered by how many options they have left,
+/// which is updated as their options are narrowed down.
+fn collapse(
+    cells: &mut [Options],
+    queue: &mut BinaryHeap<Reverse<(u32, u32, usize)>>,
+    kinds: &[(TileKind, f32)],
+    compatible: &[Options],
+    neighbors: &[Vec<usize>],
+    rng: &mut impl Rng,
+    budget: usize,
+) -> Option<bool> {
+    for _ in 0..budget {
+        let Some(Reverse((option_count, _, cell))) = queue.pop() else {
+            return Some(true);
+        };
+        // Narrowing a cell queues it again, leaving its old entry behind to be skipped
+        if cells[cell].count_ones() != option_count {
+            continue;
+        }
+
+        let options = cells[cell];
+        let total_weight: f32 = (0..kinds.len())
+            .filter(|index| options & 1 << index != 0)
+            .map(|index| kinds[index].1)
+            .sum();
+        let mut roll = rng.random_range(0.0..total_weight);
+        let chosen = (0..kinds.len())
+            .filter(|index| options & 1 << index != 0)
+            .find(|&index| {
+                roll -= kinds[index].1;
+                roll < 0.0
+            })
+            .unwrap_or(options.trailing_zeros() as usize);
+        cells[cell] = 1 << chosen;
+
+        // Narrow down the options of the neighbors, and their neighbors in turn
+        let mut stack = vec![cell];
+        while let Some(current) = stack.pop() {
+            let allowed = (0..kinds.len())
+                .filter(|index| cells[current] & 1 << index != 0)
+                .fold(0, |allowed, index| allowed | compatible[index]);
+
+            for &neighbor in &neighbors[current] {
+                let narrowed = cells[neighbor] & allowed;
+                if narrowed == cells[neighbor] {
+                    continue;
+                }
+
+                if narrowed == 0 {
+                    return Some(false);
+                }
+
+                cells[neighbor] = narrowed;
+                stack.push(neighbor);
+                if narrowed.count_ones() > 1 {
+                    queue.push(Reverse((narrowed.count_ones(), rng.random(), neighbor)));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn regenerate_when_rules_change(
+    generator: Res<TerrainGenerator>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    if *generator == TerrainGenerator::WaveFunctionCollapse {
+        info!("Adjacency rules changed, regenerating map");
+        next_state.set(SimState::Generate);
+    }
+}